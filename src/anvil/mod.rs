@@ -288,9 +288,9 @@ pub fn send_recv_chunks(
 
         for (pos, res) in anvil.loaded_chunks.drain() {
             let status = match res {
-                Ok(Some(ParsedChunk { chunk, timestamp })) => {
+                Ok(Some(ParsedChunk { chunk, timestamp, block_entities })) => {
                     layer.insert_chunk(pos, chunk);
-                    ChunkLoadStatus::Success { timestamp }
+                    ChunkLoadStatus::Success { timestamp, block_entities }
                 }
                 Ok(None) => ChunkLoadStatus::Empty,
                 Err(e) => ChunkLoadStatus::Failed(e),
@@ -311,9 +311,9 @@ pub fn send_recv_chunks(
                     anvil.pending_chunks.remove(&pos);
 
                     let status = match res {
-                        Ok(Some(ParsedChunk { chunk, timestamp })) => {
+                        Ok(Some(ParsedChunk { chunk, timestamp, block_entities })) => {
                             layer.insert_chunk(pos, chunk);
-                            ChunkLoadStatus::Success { timestamp }
+                            ChunkLoadStatus::Success { timestamp, block_entities }
                         }
                         Ok(None) => ChunkLoadStatus::Empty,
                         Err(e) => ChunkLoadStatus::Failed(e),
@@ -400,6 +400,8 @@ pub enum ChunkLoadStatus {
         /// The time this chunk was last modified, measured in seconds since the
         /// epoch.
         timestamp: u32,
+        /// Positions of the blocks in the chunk that have block entity data.
+        block_entities: Vec<BlockPos>,
     },
     /// The Anvil level does not have a chunk at the position. No chunk was
     /// loaded.
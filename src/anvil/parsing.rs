@@ -18,7 +18,7 @@ use valence::protocol::BlockKind;
 use valence::registry::biome::BiomeId;
 use valence::registry::BiomeRegistry;
 use valence::uuid::Uuid;
-use valence::{BlockState, ChunkPos, GameMode, Ident, ItemKind, ItemStack, UniqueId};
+use valence::{BlockPos, BlockState, ChunkPos, GameMode, Ident, ItemKind, ItemStack, UniqueId};
 
 use valence::anvil::{RegionError, RegionFolder};
 
@@ -58,10 +58,11 @@ impl DimensionFolder {
         let Some(raw_chunk) = self.region.get_chunk(pos.x, pos.z)? else {
             return Ok(None);
         };
-        let parsed = parse_chunk(raw_chunk.data, &self.biome_to_id)?;
+        let (parsed, block_entities) = parse_chunk(raw_chunk.data, &self.biome_to_id)?;
         Ok(Some(ParsedChunk {
             chunk: parsed,
             timestamp: raw_chunk.timestamp,
+            block_entities,
         }))
     }
 
@@ -361,6 +362,8 @@ fn parse_player(mut nbt: Compound, uuid: UniqueId) -> Result<PlayerData, ParsePl
 pub struct ParsedChunk {
     pub chunk: UnloadedChunk,
     pub timestamp: u32,
+    /// Positions of the blocks in the chunk that have block entity data.
+    pub block_entities: Vec<BlockPos>,
 }
 
 #[derive(Debug, Error)]
@@ -423,7 +426,7 @@ pub enum ParseChunkError {
 fn parse_chunk(
     mut nbt: Compound,
     biome_map: &BTreeMap<Ident<String>, BiomeId>, // TODO: replace with biome registry arg.
-) -> Result<UnloadedChunk, ParseChunkError> {
+) -> Result<(UnloadedChunk, Vec<BlockPos>), ParseChunkError> {
     let Some(Value::List(List::Compound(sections))) = nbt.remove("sections") else {
         return Err(ParseChunkError::MissingSections);
     };
@@ -625,6 +628,8 @@ fn parse_chunk(
         return Err(ParseChunkError::MissingBlockEntities);
     };
 
+    let mut positions = Vec::new();
+
     if let List::Compound(block_entities) = block_entities {
         for mut comp in block_entities {
             let Some(Value::String(ident)) = comp.remove("id") else {
@@ -639,12 +644,14 @@ fn parse_chunk(
                 return Err(ParseChunkError::InvalidBlockEntityPosition);
             };
 
+            let world_x = x;
             let x = x.rem_euclid(16) as u32;
 
             let Some(Value::Int(y)) = comp.remove("y") else {
                 return Err(ParseChunkError::InvalidBlockEntityPosition);
             };
 
+            let world_y = y;
             let Ok(y) = u32::try_from(y.wrapping_sub(min_sect_y * 16)) else {
                 return Err(ParseChunkError::InvalidBlockEntityPosition);
             };
@@ -657,15 +664,17 @@ fn parse_chunk(
                 return Err(ParseChunkError::InvalidBlockEntityPosition);
             };
 
+            let world_z = z;
             let z = z.rem_euclid(16) as u32;
 
             comp.remove("keepPacked");
 
             chunk.set_block_entity(x, y, z, Some(comp));
+            positions.push(BlockPos::new(world_x, world_y, world_z));
         }
     }
 
-    Ok(chunk)
+    Ok((chunk, positions))
 }
 
 const BLOCKS_PER_SECTION: usize = 16 * 16 * 16;
@@ -687,6 +696,7 @@ const fn bit_width(n: usize) -> usize {
 fn encode_chunk<C: Chunk>(pos: ChunkPos, chunk: &C) -> Compound {
     let mut blocks = Vec::new();
     let mut palette = Vec::<BlockState>::new();
+    let mut block_entities = Vec::new();
     let sections = (0..24)
         .map(|y| {
             let sect_y = y * 16;
@@ -696,6 +706,19 @@ fn encode_chunk<C: Chunk>(pos: ChunkPos, chunk: &C) -> Compound {
                 for z in 0..16 {
                     for x in 0..16 {
                         let block = chunk.block_state(x, sect_y + offset_y, z);
+                        if let (Some(kind), Some(nbt)) =
+                            (block.block_entity_kind(), chunk.block_entity(x, sect_y + offset_y, z))
+                        {
+                            let mut comp = compound! {
+                                "id" => kind.ident().to_string(),
+                                "x" => pos.x * 16 + x as i32,
+                                "y" => (sect_y + offset_y) as i32 - 64,
+                                "z" => pos.z * 16 + z as i32,
+                                "keepPacked" => false,
+                            };
+                            comp.extend(nbt.clone());
+                            block_entities.push(comp);
+                        }
                         if let Some((idx, _)) =
                             palette.iter().enumerate().find(|(_, &b)| b == block)
                         {
@@ -771,7 +794,7 @@ fn encode_chunk<C: Chunk>(pos: ChunkPos, chunk: &C) -> Compound {
         "Status" => "minecraft:full",
         "LastUpdate" => 42,
         "sections" => List::Compound(sections),
-        "block_entities" => List::Compound(vec![]),
+        "block_entities" => List::Compound(block_entities),
         "Heightmaps" => compound! {
             "MOTION_BLOCKING" => List::Long(vec![0; 37]),
             "MOTION_BLOCKING_NO_LEAVES" => List::Long(vec![0; 37]),
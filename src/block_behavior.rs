@@ -0,0 +1,256 @@
+use std::collections::HashMap;
+
+use valence::layer::chunk::IntoBlock;
+use valence::prelude::*;
+use valence::sound::Sound;
+use valence::Direction;
+
+use crate::random_tick::RandomTick;
+use crate::scheduled_tick::{ScheduledTick, ScheduledTicks, TickPriority};
+
+/// What a kind of block does when things happen to it or around it.
+///
+/// Every hook does nothing by default, so a behaviour only needs the ones it
+/// cares about. Register behaviours in [`BlockBehaviors`] while building a
+/// plugin:
+///
+/// ```ignore
+/// struct Sponge;
+///
+/// impl BlockBehavior for Sponge {
+///     fn on_place(&self, change: &mut BlockChange) {
+///         // soak up water around `change.position`
+///     }
+/// }
+///
+/// app.init_resource::<BlockBehaviors>();
+/// app.world_mut()
+///     .resource_mut::<BlockBehaviors>()
+///     .insert(BlockKind::Sponge, Sponge);
+/// ```
+pub trait BlockBehavior: Send + Sync + 'static {
+    /// A block next to this one changed. Change `update.block` to change this
+    /// block.
+    fn on_neighbor_update(&self, update: &mut NeighborUpdate) {
+        let _ = update;
+    }
+
+    /// A player placed this block.
+    fn on_place(&self, change: &mut BlockChange) {
+        let _ = change;
+    }
+
+    /// A player broke this block. It has already been replaced by the time
+    /// this runs, and `change.state` is what it was.
+    fn on_break(&self, change: &mut BlockChange) {
+        let _ = change;
+    }
+
+    /// A player right-clicked this block. Returning `true` means the block
+    /// did something, so the held item isn't placed against it.
+    fn on_use(&self, block_use: &mut BlockUse) -> bool {
+        let _ = block_use;
+        false
+    }
+
+    /// A tick scheduled for this block is due.
+    fn on_scheduled_tick(&self, tick: &mut ScheduledTick) {
+        let _ = tick;
+    }
+
+    /// Whether random ticks should pick this block at all.
+    fn has_random_ticks(&self) -> bool {
+        false
+    }
+
+    /// This block was picked for a random tick.
+    fn on_random_tick(&self, tick: &mut RandomTick) {
+        let _ = tick;
+    }
+}
+
+/// The behaviour of each kind of block that has one.
+#[derive(Resource, Default)]
+pub struct BlockBehaviors(HashMap<BlockKind, Box<dyn BlockBehavior>>);
+
+impl BlockBehaviors {
+    /// Sets the behaviour of a kind of block, replacing any it already had.
+    pub fn insert(&mut self, kind: BlockKind, behavior: impl BlockBehavior) {
+        self.0.insert(kind, Box::new(behavior));
+    }
+
+    pub fn remove(&mut self, kind: BlockKind) {
+        self.0.remove(&kind);
+    }
+
+    pub fn get(&self, kind: BlockKind) -> Option<&dyn BlockBehavior> {
+        self.0.get(&kind).map(|b| &**b)
+    }
+
+    /// Whether any behaviour wants random ticks.
+    pub fn any_random_ticks(&self) -> bool {
+        self.0.values().any(|b| b.has_random_ticks())
+    }
+}
+
+/// A behaviour that only does something on random ticks.
+pub struct OnRandomTick(pub fn(&mut RandomTick));
+
+impl BlockBehavior for OnRandomTick {
+    fn has_random_ticks(&self) -> bool {
+        true
+    }
+
+    fn on_random_tick(&self, tick: &mut RandomTick) {
+        (self.0)(tick)
+    }
+}
+
+/// A behaviour that only does something on scheduled ticks.
+pub struct OnScheduledTick(pub fn(&mut ScheduledTick));
+
+impl BlockBehavior for OnScheduledTick {
+    fn on_scheduled_tick(&self, tick: &mut ScheduledTick) {
+        (self.0)(tick)
+    }
+}
+
+/// Somewhere blocks can be looked up. Neighbour updates only read blocks
+/// through this, so they work the same on a chunk layer or a handful of
+/// blocks in a map.
+pub trait BlockView {
+    fn state_at(&self, position: BlockPos) -> Option<BlockState>;
+}
+
+impl BlockView for ChunkLayer {
+    fn state_at(&self, position: BlockPos) -> Option<BlockState> {
+        self.block(position).map(|b| b.state)
+    }
+}
+
+impl BlockView for HashMap<BlockPos, BlockState> {
+    fn state_at(&self, position: BlockPos) -> Option<BlockState> {
+        self.get(&position).copied()
+    }
+}
+
+/// A block whose neighbour changed.
+pub struct NeighborUpdate<'a> {
+    pub view: &'a dyn BlockView,
+    pub position: BlockPos,
+    /// The block as it is. Whatever it is left as when the hook returns is
+    /// put in the world.
+    pub block: Block,
+    /// Positions to update if the block changes, besides its neighbours.
+    pub next: Vec<BlockPos>,
+    scheduled: Vec<(i64, TickPriority)>,
+    sounds: Vec<(Sound, f32, f32)>,
+}
+
+impl<'a> NeighborUpdate<'a> {
+    pub fn new(view: &'a dyn BlockView, position: BlockPos, block: impl IntoBlock) -> Self {
+        Self {
+            view,
+            position,
+            block: block.into_block(),
+            next: Vec::new(),
+            scheduled: Vec::new(),
+            sounds: Vec::new(),
+        }
+    }
+
+    /// The block in a direction from this one.
+    pub fn neighbor(&self, direction: Direction) -> Option<BlockState> {
+        self.view.state_at(self.position.get_in_direction(direction))
+    }
+
+    /// Schedules a tick for this block `delay` game ticks from now.
+    pub fn schedule_tick(&mut self, delay: i64, priority: TickPriority) {
+        self.scheduled.push((delay, priority));
+    }
+
+    /// The ticks that were scheduled, as delays and priorities.
+    pub fn scheduled_ticks(&self) -> &[(i64, TickPriority)] {
+        &self.scheduled
+    }
+
+    /// Plays a sound at the block if it changes.
+    pub fn play_sound(&mut self, sound: Sound, volume: f32, pitch: f32) {
+        self.sounds.push((sound, volume, pitch));
+    }
+
+    /// The sounds to play, with their volumes and pitches.
+    pub fn sounds(&self) -> &[(Sound, f32, f32)] {
+        &self.sounds
+    }
+}
+
+/// A block that was placed or broken by a player.
+pub struct BlockChange<'a> {
+    pub layer: &'a mut ChunkLayer,
+    pub layer_id: EntityLayerId,
+    pub position: BlockPos,
+    pub state: BlockState,
+    pub client: Entity,
+    /// Positions that get a block update once the hook has run.
+    pub updates: &'a mut Vec<BlockPos>,
+}
+
+impl BlockChange<'_> {
+    /// Sets a block and lets the blocks around it know it changed.
+    pub fn set_block(&mut self, position: BlockPos, block: impl IntoBlock) {
+        self.layer.set_block(position, block);
+        self.updates.extend(with_neighbors(position));
+    }
+}
+
+/// A block a player right-clicked.
+pub struct BlockUse<'a> {
+    pub layer: &'a mut ChunkLayer,
+    pub layer_id: EntityLayerId,
+    pub position: BlockPos,
+    pub state: BlockState,
+    pub client: Entity,
+    /// Which way the player was looking.
+    pub look: Look,
+    /// The game tick it is now.
+    pub now: i64,
+    pub ticks: &'a mut ScheduledTicks,
+    /// Positions that get a block update once the hook has run.
+    pub updates: &'a mut Vec<BlockPos>,
+}
+
+impl BlockUse<'_> {
+    /// Sets a block and lets the blocks around it know it changed.
+    pub fn set_block(&mut self, position: BlockPos, block: impl IntoBlock) {
+        self.layer.set_block(position, block);
+        self.updates.extend(with_neighbors(position));
+    }
+
+    /// Schedules a tick `delay` game ticks from now.
+    pub fn schedule(&mut self, position: BlockPos, kind: BlockKind, delay: i64, priority: TickPriority) -> bool {
+        self.ticks.schedule(self.now + delay, position, kind, priority)
+    }
+}
+
+/// The block a point is in.
+pub fn block_pos(position: DVec3) -> BlockPos {
+    BlockPos {
+        x: position.x.floor() as i32,
+        y: position.y.floor() as i32,
+        z: position.z.floor() as i32,
+    }
+}
+
+/// A position and the six next to it.
+pub fn with_neighbors(position: BlockPos) -> [BlockPos; 7] {
+    [
+        position,
+        position.get_in_direction(Direction::Up),
+        position.get_in_direction(Direction::Down),
+        position.get_in_direction(Direction::North),
+        position.get_in_direction(Direction::East),
+        position.get_in_direction(Direction::South),
+        position.get_in_direction(Direction::West),
+    ]
+}
@@ -1,19 +1,92 @@
-use std::collections::VecDeque;
+use std::collections::{HashMap, HashSet, VecDeque};
 
 use valence::entity::ObjectData;
 use valence::entity::falling_block::FallingBlockEntityBundle;
 use valence::layer::chunk::IntoBlock;
 use valence::prelude::*;
+use valence::sound::SoundCategory;
 
+use crate::block_behavior::{BlockBehavior, BlockBehaviors, BlockChange, NeighborUpdate};
+use crate::building::{BlockBreakEvent, BlockPlaceEvent};
+use crate::rules::GameRules;
+use crate::scheduled_tick::{ScheduledTick, ScheduledTicks, TickPriority};
+
+/// Runs the [`BlockBehaviors`] of blocks as they are updated, placed and
+/// broken, and registers the vanilla behaviours for stairs, fences, rails,
+/// redstone wire and the like.
 pub struct BlockUpdate;
 
 impl Plugin for BlockUpdate {
     fn build(&self, app: &mut App) {
         app.insert_resource(Events::<BlockUpdateEvent>::default())
-            .add_systems(Update, handle_block_update);
+            .init_resource::<BlockBehaviors>()
+            .init_resource::<GameRules>()
+            .add_systems(Update, (handle_block_update, run_place_hooks, run_break_hooks));
+
+        let mut behaviors = app.world_mut().resource_mut::<BlockBehaviors>();
+        for kind in BlockKind::ALL {
+            let state = kind.to_state();
+            if state.get(PropName::Shape).is_some() && state.get(PropName::Half).is_some() {
+                behaviors.insert(kind, Stairs);
+            } else if kind == BlockKind::Grass {
+                behaviors.insert(kind, Grass);
+            } else if state.get(PropName::North).is_some()
+                && state.get(PropName::East).is_some()
+                && state.get(PropName::South).is_some()
+                && state.get(PropName::West).is_some()
+                && state.get(PropName::Power).is_none()
+            {
+                behaviors.insert(kind, Connecting);
+            } else if kind == BlockKind::GrassBlock || kind == BlockKind::Mycelium || kind == BlockKind::Podzol {
+                behaviors.insert(kind, Snowy);
+            } else if kind == BlockKind::Rail {
+                behaviors.insert(kind, Rail);
+            } else if kind == BlockKind::RedstoneWire {
+                behaviors.insert(kind, RedstoneWire);
+            } else if kind == BlockKind::Scaffolding {
+                behaviors.insert(kind, Scaffolding);
+            } else if FALLING_BLOCKS.contains(&kind) {
+                behaviors.insert(kind, Falling);
+            }
+            // Fence gates, stems and the other blocks that also do
+            // something when used or on random ticks get their behaviours
+            // from the plugins that handle that.
+        }
     }
 }
 
+/// Blocks that fall when there's nothing under them.
+pub const FALLING_BLOCKS: [BlockKind; 25] = [
+    BlockKind::Sand,
+    BlockKind::RedSand,
+    BlockKind::Gravel,
+    BlockKind::DragonEgg,
+    BlockKind::Anvil,
+    BlockKind::ChippedAnvil,
+    BlockKind::DamagedAnvil,
+    BlockKind::SuspiciousSand,
+    BlockKind::SuspiciousGravel,
+    BlockKind::WhiteConcretePowder,
+    BlockKind::LightGrayConcretePowder,
+    BlockKind::GrayConcretePowder,
+    BlockKind::BlackConcretePowder,
+    BlockKind::BrownConcretePowder,
+    BlockKind::RedConcretePowder,
+    BlockKind::OrangeConcretePowder,
+    BlockKind::YellowConcretePowder,
+    BlockKind::LimeConcretePowder,
+    BlockKind::GreenConcretePowder,
+    BlockKind::CyanConcretePowder,
+    BlockKind::LightBlueConcretePowder,
+    BlockKind::BlueConcretePowder,
+    BlockKind::PurpleConcretePowder,
+    BlockKind::MagentaConcretePowder,
+    BlockKind::PinkConcretePowder,
+];
+
+/// Game ticks a block waits after losing its support before it falls.
+const FALL_DELAY: i64 = 2;
+
 #[derive(Event, Debug, Copy, Clone)]
 pub struct BlockUpdateEvent {
     pub position: BlockPos,
@@ -21,736 +94,713 @@ pub struct BlockUpdateEvent {
     pub entity_layer: EntityLayerId,
 }
 
+/// How many times one block can change in a game tick before its updates are
+/// put off until the next tick.
+const MAX_CHANGES_PER_TICK: u32 = 16;
+
+/// Block updates waiting to be handled. This is kept between ticks, so an
+/// update queue that runs out of budget picks up where it left off instead
+/// of running until it's empty.
+#[derive(Debug, Default)]
+pub struct UpdateQueue {
+    queue: VecDeque<BlockUpdateEvent>,
+    /// Updates for blocks that changed too many times this tick.
+    deferred: Vec<BlockUpdateEvent>,
+    changes: HashMap<(Entity, BlockPos), u32>,
+    /// Blocks that were deferred last tick, so a loop that keeps going only
+    /// gets warned about once.
+    looping: HashSet<(Entity, BlockPos)>,
+    handled: u32,
+    over_budget: bool,
+}
+
+impl UpdateQueue {
+    /// Starts a new tick, adding the updates that were sent since the last one
+    /// after any that were left over.
+    pub fn begin(&mut self, events: impl IntoIterator<Item = BlockUpdateEvent>) {
+        let deferred = std::mem::take(&mut self.deferred);
+        self.looping = deferred.iter().map(|e| (e.layer, e.position)).collect();
+        self.queue.extend(deferred);
+        self.queue.extend(events);
+        self.changes.clear();
+        self.handled = 0;
+    }
+
+    /// The next update to handle, or `None` if there are none left or the
+    /// budget for this tick has been used up.
+    pub fn pop(&mut self, budget: u32) -> Option<BlockUpdateEvent> {
+        if self.handled >= budget {
+            return None;
+        }
+        let event = self.queue.pop_front()?;
+        self.handled += 1;
+        Some(event)
+    }
+
+    pub fn push(&mut self, event: BlockUpdateEvent) {
+        self.queue.push_back(event);
+    }
+
+    /// Queues updates for the six blocks around the one `event` is for.
+    pub fn push_neighbors(&mut self, event: BlockUpdateEvent) {
+        for direction in [
+            Direction::Up,
+            Direction::Down,
+            Direction::North,
+            Direction::East,
+            Direction::South,
+            Direction::West,
+        ] {
+            self.push(BlockUpdateEvent {
+                position: event.position.get_in_direction(direction),
+                ..event
+            });
+        }
+    }
+
+    /// Counts a change to the block `event` is for. If it has already changed
+    /// too many times this tick it is probably part of a loop, so this returns
+    /// `false` and holds the update back until the next tick.
+    pub fn record_change(&mut self, event: BlockUpdateEvent) -> bool {
+        let changes = self.changes.entry((event.layer, event.position)).or_default();
+        *changes += 1;
+        if *changes <= MAX_CHANGES_PER_TICK {
+            return true;
+        }
+        if *changes == MAX_CHANGES_PER_TICK + 1 && !self.looping.contains(&(event.layer, event.position)) {
+            tracing::warn!(
+                "block at {:?} changed more than {MAX_CHANGES_PER_TICK} times in one tick, holding its updates back",
+                event.position
+            );
+        }
+        self.deferred.push(event);
+        false
+    }
+
+    /// Ends the tick, carrying whatever is left in the queue over to the next.
+    pub fn end(&mut self, name: &str) {
+        let over_budget = !self.queue.is_empty();
+        if over_budget {
+            let mut seen = HashSet::new();
+            self.queue.retain(|e| seen.insert((e.layer, e.position)));
+            if !self.over_budget {
+                tracing::warn!(
+                    "{name} queue hit its budget of {} updates, carrying {} over to the next tick",
+                    self.handled,
+                    self.queue.len()
+                );
+            }
+        }
+        self.over_budget = over_budget;
+    }
+
+    /// How many updates were handled so far this tick.
+    pub fn handled(&self) -> u32 {
+        self.handled
+    }
+
+    /// How many updates are waiting.
+    pub fn len(&self) -> usize {
+        self.queue.len() + self.deferred.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
 pub fn handle_block_update(
+    server: Res<Server>,
+    rules: Res<GameRules>,
+    behaviors: Res<BlockBehaviors>,
     mut layers: Query<&mut ChunkLayer>,
-    mut commands: Commands,
+    mut scheduled: Query<&mut ScheduledTicks>,
     mut events: EventReader<BlockUpdateEvent>,
+    mut queue: Local<UpdateQueue>,
 ) {
-    let mut queue = VecDeque::new();
-
-    queue.extend(events.read().cloned());
+    queue.begin(events.read().cloned());
 
-    while let Some(event) = queue.pop_front() {
+    while let Some(event) = queue.pop(rules.max_block_updates) {
 
         let Ok(mut layer) = layers.get_mut(event.layer) else {
             continue;
         };
-        let Some(original_block) = layer.block(event.position) else {
+        let Some(original_block) = layer.block(event.position).map(IntoBlock::into_block) else {
+            continue;
+        };
+        let kind = original_block.state.to_kind();
+        let Some(behavior) = behaviors.get(kind) else {
             continue;
         };
-        let mut block = original_block.into_block();
-        let original_block = block.clone();
-        let (up, down, north, east, south, west) = (
-            layer
-                .block(event.position.get_in_direction(Direction::Up))
-                .map(IntoBlock::into_block),
-            layer
-                .block(event.position.get_in_direction(Direction::Down))
-                .map(IntoBlock::into_block),
-            layer
-                .block(event.position.get_in_direction(Direction::North))
-                .map(IntoBlock::into_block),
-            layer
-                .block(event.position.get_in_direction(Direction::East))
-                .map(IntoBlock::into_block),
-            layer
-                .block(event.position.get_in_direction(Direction::South))
-                .map(IntoBlock::into_block),
-            layer
-                .block(event.position.get_in_direction(Direction::West))
-                .map(IntoBlock::into_block),
-        );
 
-        let mut next = Vec::new();
-
-        if block.state.get(PropName::Shape).is_some() && block.state.get(PropName::Half).is_some() {
-            let (north, east, south, west) = (
-                north.as_ref().and_then(|b| {
-                    b.state
-                        .get(PropName::Half)
-                        .filter(|h| block.state.get(PropName::Half) == Some(*h))
-                        .and(north.as_ref().and_then(|b| b.state.get(PropName::Facing)))
-                }),
-                east.as_ref().and_then(|b| {
-                    b.state
-                        .get(PropName::Half)
-                        .filter(|h| block.state.get(PropName::Half) == Some(*h))
-                        .and(east.as_ref().and_then(|b| b.state.get(PropName::Facing)))
-                }),
-                south.as_ref().and_then(|b| {
-                    b.state
-                        .get(PropName::Half)
-                        .filter(|h| block.state.get(PropName::Half) == Some(*h))
-                        .and(south.as_ref().and_then(|b| b.state.get(PropName::Facing)))
-                }),
-                west.as_ref().and_then(|b| {
-                    b.state
-                        .get(PropName::Half)
-                        .filter(|h| block.state.get(PropName::Half) == Some(*h))
-                        .and(west.as_ref().and_then(|b| b.state.get(PropName::Facing)))
-                }),
-            );
-            block.state = block.state.set(
-                PropName::Shape,
-                match (
-                    block.state.get(PropName::Facing).unwrap(),
-                    north,
-                    east,
-                    south,
-                    west,
-                ) {
-                    // (PropValue::North, _, Some(PropValue::North), _, _) |
-                    //     (PropValue::North, _, _, _, Some(PropValue::North)) |
-                    //     (PropValue::South, _, Some(PropValue::South), _, _) |
-                    //     (PropValue::South, _, _, _, Some(PropValue::South)) |
-                    //     (PropValue::East, Some(PropValue::East), _, _, _) |
-                    //     (PropValue::East, _, _, Some(PropValue::East), _) |
-                    //     (PropValue::West, Some(PropValue::West), _, _, _) |
-                    //     (PropValue::West, _, _, Some(PropValue::West), _) => PropValue::Straight,
-                    (PropValue::North, Some(PropValue::West), _, _, _)
-                        if east != Some(PropValue::North) =>
-                    {
-                        PropValue::OuterLeft
-                    }
-                    (PropValue::East, _, Some(PropValue::North), _, _)
-                        if south != Some(PropValue::East) =>
-                    {
-                        PropValue::OuterLeft
-                    }
-                    (PropValue::South, _, _, Some(PropValue::East), _)
-                        if west != Some(PropValue::South) =>
-                    {
-                        PropValue::OuterLeft
-                    }
-                    (PropValue::West, _, _, _, Some(PropValue::South))
-                        if north != Some(PropValue::West) =>
-                    {
-                        PropValue::OuterLeft
-                    }
-                    (PropValue::North, Some(PropValue::East), _, _, _)
-                        if west != Some(PropValue::North) =>
-                    {
-                        PropValue::OuterRight
-                    }
-                    (PropValue::East, _, Some(PropValue::South), _, _)
-                        if north != Some(PropValue::East) =>
-                    {
-                        PropValue::OuterRight
-                    }
-                    (PropValue::South, _, _, Some(PropValue::West), _)
-                        if east != Some(PropValue::South) =>
-                    {
-                        PropValue::OuterRight
-                    }
-                    (PropValue::West, _, _, _, Some(PropValue::North))
-                        if south != Some(PropValue::West) =>
-                    {
-                        PropValue::OuterRight
-                    }
-                    (PropValue::North, _, _, Some(PropValue::West), _)
-                        if west != Some(PropValue::North) =>
-                    {
-                        PropValue::InnerLeft
-                    }
-                    (PropValue::East, _, _, _, Some(PropValue::North))
-                        if north != Some(PropValue::East) =>
-                    {
-                        PropValue::InnerLeft
-                    }
-                    (PropValue::South, Some(PropValue::East), _, _, _)
-                        if east != Some(PropValue::South) =>
-                    {
-                        PropValue::InnerLeft
-                    }
-                    (PropValue::West, _, Some(PropValue::South), _, _)
-                        if south != Some(PropValue::West) =>
-                    {
-                        PropValue::InnerLeft
-                    }
-                    (PropValue::North, _, _, Some(PropValue::East), _)
-                        if east != Some(PropValue::North) =>
-                    {
-                        PropValue::InnerRight
-                    }
-                    (PropValue::East, _, _, _, Some(PropValue::South))
-                        if south != Some(PropValue::East) =>
-                    {
-                        PropValue::InnerRight
-                    }
-                    (PropValue::South, Some(PropValue::West), _, _, _)
-                        if west != Some(PropValue::South) =>
-                    {
-                        PropValue::InnerRight
-                    }
-                    (PropValue::West, _, Some(PropValue::North), _, _)
-                        if north != Some(PropValue::West) =>
-                    {
-                        PropValue::InnerRight
-                    }
-                    _ => PropValue::Straight,
-                },
+        let mut update = NeighborUpdate::new(&*layer, event.position, original_block.clone());
+        behavior.on_neighbor_update(&mut update);
+
+        if !update.scheduled_ticks().is_empty() {
+            if let Ok(mut ticks) = scheduled.get_mut(event.layer) {
+                for &(delay, priority) in update.scheduled_ticks() {
+                    ticks.schedule(server.current_tick() + delay, event.position, kind, priority);
+                }
+            }
+        }
+
+        let sounds = update.sounds().to_vec();
+        let NeighborUpdate { block, next, .. } = update;
+
+        if block != original_block {
+            if !queue.record_change(event) {
+                continue;
+            }
+            for position in next {
+                queue.push(BlockUpdateEvent { position, ..event });
+            }
+            queue.push_neighbors(event);
+
+            layer.set_block(event.position, block);
+
+            let center = DVec3::new(
+                f64::from(event.position.x) + 0.5,
+                f64::from(event.position.y) + 0.5,
+                f64::from(event.position.z) + 0.5,
             );
-        } else if block.state.to_kind() == BlockKind::Grass
-            && !down.as_ref().is_some_and(|b| b.state.is_opaque())
-        {
-            block.state = BlockState::AIR;
-            block.nbt = None;
-        } else if block.state.get(PropName::North).is_some()
-            && block.state.get(PropName::East).is_some()
-            && block.state.get(PropName::South).is_some()
-            && block.state.get(PropName::West).is_some()
-            && block.state.get(PropName::Power).is_none()
-        {
-            block.state = block.state.set(
-                PropName::North,
-                if north.as_ref().is_some_and(|b| {
-                    fence_type(b.state.to_kind()) == fence_type(block.state.to_kind())
-                }) || north
-                    .as_ref()
-                    .is_some_and(|b| fence_type(b.state.to_kind()) == FenceType::Connect)
+            for (sound, volume, pitch) in sounds {
+                layer.play_sound(sound, SoundCategory::Block, center, volume, pitch);
+            }
+        }
+    }
+
+    queue.end("block update");
+}
+
+fn run_place_hooks(
+    behaviors: Res<BlockBehaviors>,
+    mut layers: Query<&mut ChunkLayer>,
+    mut events: EventReader<BlockPlaceEvent>,
+    mut block_updates: EventWriter<BlockUpdateEvent>,
+) {
+    for event in events.read() {
+        let Some(behavior) = behaviors.get(event.state.to_kind()) else {
+            continue;
+        };
+        let Ok(mut layer) = layers.get_mut(event.layer.0) else {
+            continue;
+        };
+
+        let mut updates = Vec::new();
+        behavior.on_place(&mut BlockChange {
+            layer: &mut layer,
+            layer_id: event.layer,
+            position: event.position,
+            state: event.state,
+            client: event.client,
+            updates: &mut updates,
+        });
+        send_updates(&mut block_updates, event.layer, updates);
+    }
+}
+
+fn run_break_hooks(
+    behaviors: Res<BlockBehaviors>,
+    mut layers: Query<&mut ChunkLayer>,
+    mut events: EventReader<BlockBreakEvent>,
+    mut block_updates: EventWriter<BlockUpdateEvent>,
+) {
+    for event in events.read() {
+        let Some(behavior) = behaviors.get(event.state.to_kind()) else {
+            continue;
+        };
+        let Ok(mut layer) = layers.get_mut(event.layer.0) else {
+            continue;
+        };
+
+        let mut updates = Vec::new();
+        behavior.on_break(&mut BlockChange {
+            layer: &mut layer,
+            layer_id: event.layer,
+            position: event.position,
+            state: event.state,
+            client: event.client,
+            updates: &mut updates,
+        });
+        send_updates(&mut block_updates, event.layer, updates);
+    }
+}
+
+/// Sends a block update to each of the positions, usually a block that changed
+/// and its [neighbours](crate::block_behavior::with_neighbors).
+pub fn send_updates(
+    block_updates: &mut EventWriter<BlockUpdateEvent>,
+    layer: EntityLayerId,
+    positions: impl IntoIterator<Item = BlockPos>,
+) {
+    for position in positions {
+        block_updates.send(BlockUpdateEvent {
+            position,
+            layer: layer.0,
+            entity_layer: layer,
+        });
+    }
+}
+
+/// Stairs turn into corners to line up with the stairs next to them.
+struct Stairs;
+
+impl BlockBehavior for Stairs {
+    fn on_neighbor_update(&self, update: &mut NeighborUpdate) {
+        let state = update.block.state;
+        let half = state.get(PropName::Half);
+        // Which way the stairs on each side face, if they're on the same
+        // half as these.
+        let facing = |direction| {
+            update
+                .neighbor(direction)
+                .filter(|s| s.get(PropName::Half) == half)
+                .and_then(|s| s.get(PropName::Facing))
+        };
+        let (north, east, south, west) = (
+            facing(Direction::North),
+            facing(Direction::East),
+            facing(Direction::South),
+            facing(Direction::West),
+        );
+        update.block.state = state.set(
+            PropName::Shape,
+            match (
+                state.get(PropName::Facing).unwrap(),
+                north,
+                east,
+                south,
+                west,
+            ) {
+                // (PropValue::North, _, Some(PropValue::North), _, _) |
+                //     (PropValue::North, _, _, _, Some(PropValue::North)) |
+                //     (PropValue::South, _, Some(PropValue::South), _, _) |
+                //     (PropValue::South, _, _, _, Some(PropValue::South)) |
+                //     (PropValue::East, Some(PropValue::East), _, _, _) |
+                //     (PropValue::East, _, _, Some(PropValue::East), _) |
+                //     (PropValue::West, Some(PropValue::West), _, _, _) |
+                //     (PropValue::West, _, _, Some(PropValue::West), _) => PropValue::Straight,
+                (PropValue::North, Some(PropValue::West), _, _, _)
+                    if east != Some(PropValue::North) =>
                 {
-                    PropValue::True
-                } else {
-                    PropValue::False
-                },
-            );
-            block.state = block.state.set(
-                PropName::East,
-                if east.as_ref().is_some_and(|b| {
-                    fence_type(b.state.to_kind()) == fence_type(block.state.to_kind())
-                }) || east
-                    .as_ref()
-                    .is_some_and(|b| fence_type(b.state.to_kind()) == FenceType::Connect)
+                    PropValue::OuterLeft
+                }
+                (PropValue::East, _, Some(PropValue::North), _, _)
+                    if south != Some(PropValue::East) =>
                 {
-                    PropValue::True
-                } else {
-                    PropValue::False
-                },
-            );
-            block.state = block.state.set(
-                PropName::South,
-                if south.as_ref().is_some_and(|b| {
-                    fence_type(b.state.to_kind()) == fence_type(block.state.to_kind())
-                }) || south
-                    .as_ref()
-                    .is_some_and(|b| fence_type(b.state.to_kind()) == FenceType::Connect)
+                    PropValue::OuterLeft
+                }
+                (PropValue::South, _, _, Some(PropValue::East), _)
+                    if west != Some(PropValue::South) =>
                 {
-                    PropValue::True
-                } else {
-                    PropValue::False
-                },
-            );
-            block.state = block.state.set(
-                PropName::West,
-                if west.as_ref().is_some_and(|b| {
-                    fence_type(b.state.to_kind()) == fence_type(block.state.to_kind())
-                }) || west
-                    .as_ref()
-                    .is_some_and(|b| fence_type(b.state.to_kind()) == FenceType::Connect)
+                    PropValue::OuterLeft
+                }
+                (PropValue::West, _, _, _, Some(PropValue::South))
+                    if north != Some(PropValue::West) =>
+                {
+                    PropValue::OuterLeft
+                }
+                (PropValue::North, Some(PropValue::East), _, _, _)
+                    if west != Some(PropValue::North) =>
+                {
+                    PropValue::OuterRight
+                }
+                (PropValue::East, _, Some(PropValue::South), _, _)
+                    if north != Some(PropValue::East) =>
+                {
+                    PropValue::OuterRight
+                }
+                (PropValue::South, _, _, Some(PropValue::West), _)
+                    if east != Some(PropValue::South) =>
+                {
+                    PropValue::OuterRight
+                }
+                (PropValue::West, _, _, _, Some(PropValue::North))
+                    if south != Some(PropValue::West) =>
+                {
+                    PropValue::OuterRight
+                }
+                (PropValue::North, _, _, Some(PropValue::West), _)
+                    if west != Some(PropValue::North) =>
+                {
+                    PropValue::InnerLeft
+                }
+                (PropValue::East, _, _, _, Some(PropValue::North))
+                    if north != Some(PropValue::East) =>
+                {
+                    PropValue::InnerLeft
+                }
+                (PropValue::South, Some(PropValue::East), _, _, _)
+                    if east != Some(PropValue::South) =>
+                {
+                    PropValue::InnerLeft
+                }
+                (PropValue::West, _, Some(PropValue::South), _, _)
+                    if south != Some(PropValue::West) =>
+                {
+                    PropValue::InnerLeft
+                }
+                (PropValue::North, _, _, Some(PropValue::East), _)
+                    if east != Some(PropValue::North) =>
+                {
+                    PropValue::InnerRight
+                }
+                (PropValue::East, _, _, _, Some(PropValue::South))
+                    if south != Some(PropValue::East) =>
+                {
+                    PropValue::InnerRight
+                }
+                (PropValue::South, Some(PropValue::West), _, _, _)
+                    if west != Some(PropValue::South) =>
                 {
+                    PropValue::InnerRight
+                }
+                (PropValue::West, _, Some(PropValue::North), _, _)
+                    if north != Some(PropValue::West) =>
+                {
+                    PropValue::InnerRight
+                }
+                _ => PropValue::Straight,
+            },
+        );
+    }
+}
+
+/// Grass only stays on top of something solid.
+struct Grass;
+
+impl BlockBehavior for Grass {
+    fn on_neighbor_update(&self, update: &mut NeighborUpdate) {
+        if !update.neighbor(Direction::Down).is_some_and(|s| s.is_opaque()) {
+            update.block = BlockState::AIR.into_block();
+        }
+    }
+}
+
+/// Fences, walls, panes and bars, which join up with their own kind and
+/// with solid blocks.
+struct Connecting;
+
+impl BlockBehavior for Connecting {
+    fn on_neighbor_update(&self, update: &mut NeighborUpdate) {
+        let own_type = fence_type(update.block.state.to_kind());
+        let above = update.neighbor(Direction::Up);
+        let mut state = update.block.state;
+        for (direction, prop) in [
+            (Direction::North, PropName::North),
+            (Direction::East, PropName::East),
+            (Direction::South, PropName::South),
+            (Direction::West, PropName::West),
+        ] {
+            let connects = update.neighbor(direction).is_some_and(|s| {
+                let other = fence_type(s.to_kind());
+                other == own_type || other == FenceType::Connect
+            });
+            let side = if own_type != FenceType::Wall {
+                if connects {
                     PropValue::True
                 } else {
                     PropValue::False
-                },
-            );
-        } else if block.state.get(PropName::InWall).is_some() {
-            let in_wall = match block.state.get(PropName::Facing) {
-                Some(PropValue::North) | Some(PropValue::South) => {
-                    layer
-                        .block(event.position.get_in_direction(Direction::East))
-                        .as_ref()
-                        .is_some_and(|b| b.state.get(PropName::Up).is_some())
-                        || layer
-                            .block(event.position.get_in_direction(Direction::West))
-                            .as_ref()
-                            .is_some_and(|b| b.state.get(PropName::Up).is_some())
-                }
-                _ => {
-                    layer
-                        .block(event.position.get_in_direction(Direction::North))
-                        .as_ref()
-                        .is_some_and(|b| b.state.get(PropName::Up).is_some())
-                        || layer
-                            .block(event.position.get_in_direction(Direction::South))
-                            .as_ref()
-                            .is_some_and(|b| b.state.get(PropName::Up).is_some())
                 }
+            } else if !connects {
+                PropValue::None
+            } else if above.is_some_and(|s| {
+                s.is_opaque() || (fence_type(s.to_kind()) == FenceType::Wall && s.get(prop) != Some(PropValue::None))
+            }) {
+                // Walls go all the way up to what's on top of them.
+                PropValue::Tall
+            } else {
+                PropValue::Low
             };
-            block.state = block.state.set(
-                PropName::InWall,
-                if in_wall {
+            state = state.set(prop, side);
+        }
+        if own_type == FenceType::Wall {
+            state = state.set(
+                PropName::Up,
+                if raise_post(state, above) {
                     PropValue::True
                 } else {
                     PropValue::False
                 },
             );
-        } else if block.state.to_kind() == BlockKind::GrassBlock
-            || block.state.to_kind() == BlockKind::Mycelium
-            || block.state.to_kind() == BlockKind::Podzol
-        {
-            let snowy = layer
-                .block(event.position.get_in_direction(Direction::Up))
-                .as_ref()
-                .is_some_and(|b| {
-                    b.state.to_kind() == BlockKind::Snow
-                        || b.state.to_kind() == BlockKind::SnowBlock
-                });
-            block.state = block.state.set(
-                PropName::Snowy,
-                if snowy {
+        }
+        update.block.state = state;
+    }
+}
+
+/// Whether a wall has a post in the middle. Only straight runs of wall go
+/// without one, unless there's a post or a solid block on top.
+fn raise_post(state: BlockState, above: Option<BlockState>) -> bool {
+    if above.is_some_and(|s| fence_type(s.to_kind()) == FenceType::Wall && s.get(PropName::Up) == Some(PropValue::True)) {
+        return true;
+    }
+    let side = |prop| state.get(prop).unwrap_or(PropValue::None);
+    let (north, east, south, west) = (
+        side(PropName::North),
+        side(PropName::East),
+        side(PropName::South),
+        side(PropName::West),
+    );
+    let straight = (north == PropValue::None) == (south == PropValue::None)
+        && (east == PropValue::None) == (west == PropValue::None)
+        && (north != PropValue::None || east != PropValue::None);
+    if !straight {
+        return true;
+    }
+    if (north == PropValue::Tall && south == PropValue::Tall) || (east == PropValue::Tall && west == PropValue::Tall) {
+        return false;
+    }
+    above.is_some_and(|s| s.is_opaque())
+}
+
+/// Grass blocks, mycelium and podzol look snowy with snow on top.
+struct Snowy;
+
+impl BlockBehavior for Snowy {
+    fn on_neighbor_update(&self, update: &mut NeighborUpdate) {
+        let snowy = update.neighbor(Direction::Up).is_some_and(|s| {
+            s.to_kind() == BlockKind::Snow || s.to_kind() == BlockKind::SnowBlock
+        });
+        update.block.state = update.block.state.set(
+            PropName::Snowy,
+            if snowy {
+                PropValue::True
+            } else {
+                PropValue::False
+            },
+        );
+    }
+}
+
+/// Rails curve to meet the rails around them, and pop off without a block
+/// under them.
+struct Rail;
+
+impl BlockBehavior for Rail {
+    fn on_neighbor_update(&self, update: &mut NeighborUpdate) {
+        let shape = |direction| update.neighbor(direction).and_then(|s| s.get(PropName::Shape));
+        let (north, east, south, west) = (
+            shape(Direction::North).is_some_and(|s| {
+                s == PropValue::NorthSouth
+                    || s == PropValue::EastWest
+                    || s == PropValue::SouthEast
+                    || s == PropValue::SouthWest
+            }),
+            shape(Direction::East).is_some_and(|s| {
+                s == PropValue::EastWest
+                    || s == PropValue::NorthSouth
+                    || s == PropValue::NorthWest
+                    || s == PropValue::SouthWest
+            }),
+            shape(Direction::South).is_some_and(|s| {
+                s == PropValue::NorthSouth
+                    || s == PropValue::EastWest
+                    || s == PropValue::NorthEast
+                    || s == PropValue::NorthWest
+            }),
+            shape(Direction::West).is_some_and(|s| {
+                s == PropValue::EastWest
+                    || s == PropValue::NorthSouth
+                    || s == PropValue::SouthEast
+                    || s == PropValue::NorthEast
+            }),
+        );
+        let facing = update.block.state.get(PropName::Shape).unwrap();
+        if facing != PropValue::NorthSouth && facing != PropValue::EastWest {
+            return;
+        }
+        let shape = match (north, east, south, west) {
+            (false, false, false, false) => facing,
+            (_, false, true, false) | (true, false, _, false) => PropValue::NorthSouth,
+            (false, _, false, true) | (false, true, false, _) => PropValue::EastWest,
+            (_, true, true, _) => PropValue::SouthEast,
+            (_, _, true, true) => PropValue::SouthWest,
+            (true, true, _, _) => PropValue::NorthEast,
+            _ => PropValue::NorthWest,
+        };
+        update.block.state = update.block.state.set(PropName::Shape, shape);
+        if !update.neighbor(Direction::Down).is_some_and(|s| s.is_opaque()) {
+            update.block = BlockState::AIR.into_block();
+        }
+    }
+}
+
+/// Redstone wire connects to the wire beside it, including up and down a
+/// block, and pops off without a block under it.
+struct RedstoneWire;
+
+impl BlockBehavior for RedstoneWire {
+    fn on_neighbor_update(&self, update: &mut NeighborUpdate) {
+        let is_wire = |state: Option<BlockState>| state.is_some_and(|s| s.to_kind() == BlockKind::RedstoneWire);
+        let is_opaque = |state: Option<BlockState>| state.is_some_and(|s| s.is_opaque());
+        let position = update.position;
+        let up = update.neighbor(Direction::Up);
+        let mut state = update.block.state;
+
+        for (direction, prop) in [
+            (Direction::North, PropName::North),
+            (Direction::East, PropName::East),
+            (Direction::South, PropName::South),
+            (Direction::West, PropName::West),
+        ] {
+            let side = update.neighbor(direction);
+            let above = position.get_in_direction(Direction::Up).get_in_direction(direction);
+            let below = position.get_in_direction(Direction::Down).get_in_direction(direction);
+            state = if is_wire(side) {
+                state.set(prop, PropValue::Side)
+            } else if is_wire(update.view.state_at(above)) && !is_opaque(up) {
+                update.next.push(above);
+                state.set(prop, PropValue::Up)
+            } else if is_wire(update.view.state_at(below)) && !is_opaque(side) {
+                update.next.push(below);
+                state.set(prop, PropValue::Side)
+            } else {
+                state.set(prop, PropValue::None)
+            };
+        }
+
+        let (connected_north, connected_east, connected_south, connected_west) = (
+            state.get(PropName::North) != Some(PropValue::None),
+            state.get(PropName::East) != Some(PropValue::None),
+            state.get(PropName::South) != Some(PropValue::None),
+            state.get(PropName::West) != Some(PropValue::None),
+        );
+        if !connected_east && !connected_south && !connected_west {
+            state = state.set(PropName::South, PropValue::Side);
+        }
+        if !connected_north && !connected_south && !connected_west {
+            state = state.set(PropName::West, PropValue::Side);
+        }
+        if !connected_north && !connected_east && !connected_west {
+            state = state.set(PropName::North, PropValue::Side);
+        }
+        if !connected_north && !connected_east && !connected_south {
+            state = state.set(PropName::East, PropValue::Side);
+        }
+        update.block.state = state;
+
+        if !is_opaque(update.neighbor(Direction::Down)) {
+            update.block = BlockState::AIR.into_block();
+        }
+    }
+}
+
+/// Scaffolding counts how far it is from scaffolding that stands on
+/// something.
+struct Scaffolding;
+
+impl BlockBehavior for Scaffolding {
+    fn on_neighbor_update(&self, update: &mut NeighborUpdate) {
+        let down = update.neighbor(Direction::Down);
+        let bottom = !(down.is_some_and(|s| s.to_kind() == BlockKind::Scaffolding)
+            || down.is_some_and(|s| s.is_opaque()));
+        let distance = if bottom {
+            [Direction::North, Direction::East, Direction::South, Direction::West]
+                .into_iter()
+                .filter_map(|d| update.neighbor(d).and_then(|s| s.get(PropName::Distance)))
+                .map(|d| match d {
+                    PropValue::_0 => 0,
+                    PropValue::_1 => 1,
+                    PropValue::_2 => 2,
+                    PropValue::_3 => 3,
+                    PropValue::_4 => 4,
+                    PropValue::_5 => 5,
+                    PropValue::_6 => 6,
+                    _ => 7,
+                })
+                .min()
+                .unwrap_or(7)
+        } else {
+            0
+        };
+        update.block.state = update
+            .block
+            .state
+            .set(
+                PropName::Bottom,
+                if bottom {
                     PropValue::True
                 } else {
                     PropValue::False
                 },
+            )
+            .set(
+                PropName::Distance,
+                match distance {
+                    0 => PropValue::_0,
+                    1 => PropValue::_1,
+                    2 => PropValue::_2,
+                    3 => PropValue::_3,
+                    4 => PropValue::_4,
+                    5 => PropValue::_5,
+                    6 => PropValue::_6,
+                    _ => PropValue::_7,
+                },
             );
-        } else if block.state.to_kind() == BlockKind::MelonStem {
-            if east
-                .as_ref()
-                .is_some_and(|b| b.state.to_kind() == BlockKind::Melon)
-            {
-                block.state =
-                    BlockState::ATTACHED_MELON_STEM.set(PropName::Facing, PropValue::East);
-            } else if west
-                .as_ref()
-                .is_some_and(|b| b.state.to_kind() == BlockKind::Melon)
-            {
-                block.state =
-                    BlockState::ATTACHED_MELON_STEM.set(PropName::Facing, PropValue::West);
-            } else if north
-                .as_ref()
-                .is_some_and(|b| b.state.to_kind() == BlockKind::Melon)
-            {
-                block.state =
-                    BlockState::ATTACHED_MELON_STEM.set(PropName::Facing, PropValue::North);
-            } else if south
-                .as_ref()
-                .is_some_and(|b| b.state.to_kind() == BlockKind::Melon)
-            {
-                block.state =
-                    BlockState::ATTACHED_MELON_STEM.set(PropName::Facing, PropValue::South);
-            }
-        } else if block.state.to_kind() == BlockKind::AttachedMelonStem {
-            let dir = match block.state.get(PropName::Facing) {
-                Some(PropValue::North) => Direction::North,
-                Some(PropValue::East) => Direction::East,
-                Some(PropValue::South) => Direction::South,
-                _ => Direction::West,
-            };
-            if layer
-                .block(event.position.get_in_direction(dir))
-                .filter(|b| b.state.to_kind() == BlockKind::Melon)
-                .is_none()
-            {
-                block.state = BlockState::MELON_STEM;
-            }
-        } else if block.state.to_kind() == BlockKind::Rail {
-            let (north, east, south, west) = (
-                layer
-                    .block(event.position.get_in_direction(Direction::North))
-                    .as_ref()
-                    .and_then(|b| b.state.get(PropName::Shape))
-                    .as_ref()
-                    .is_some_and(|s| {
-                        *s == PropValue::NorthSouth
-                            || *s == PropValue::EastWest
-                            || *s == PropValue::SouthEast
-                            || *s == PropValue::SouthWest
-                    }),
-                layer
-                    .block(event.position.get_in_direction(Direction::East))
-                    .as_ref()
-                    .and_then(|b| b.state.get(PropName::Shape))
-                    .as_ref()
-                    .is_some_and(|s| {
-                        *s == PropValue::EastWest
-                            || *s == PropValue::NorthSouth
-                            || *s == PropValue::NorthWest
-                            || *s == PropValue::SouthWest
-                    }),
-                layer
-                    .block(event.position.get_in_direction(Direction::South))
-                    .as_ref()
-                    .and_then(|b| b.state.get(PropName::Shape))
-                    .as_ref()
-                    .is_some_and(|s| {
-                        *s == PropValue::NorthSouth
-                            || *s == PropValue::EastWest
-                            || *s == PropValue::NorthEast
-                            || *s == PropValue::NorthWest
-                    }),
-                layer
-                    .block(event.position.get_in_direction(Direction::West))
-                    .as_ref()
-                    .and_then(|b| b.state.get(PropName::Shape))
-                    .as_ref()
-                    .is_some_and(|s| {
-                        *s == PropValue::EastWest
-                            || *s == PropValue::NorthSouth
-                            || *s == PropValue::SouthEast
-                            || *s == PropValue::NorthEast
-                    }),
-            );
-            let facing = block.state.get(PropName::Shape).unwrap();
-            if facing != PropValue::NorthSouth && facing != PropValue::EastWest {
-                continue;
-            }
-            let shape = match (north, east, south, west) {
-                (false, false, false, false) => facing,
-                (_, false, true, false) | (true, false, _, false) => PropValue::NorthSouth,
-                (false, _, false, true) | (false, true, false, _) => PropValue::EastWest,
-                (_, true, true, _) => PropValue::SouthEast,
-                (_, _, true, true) => PropValue::SouthWest,
-                (true, true, _, _) => PropValue::NorthEast,
-                _ => PropValue::NorthWest,
-            };
-            block.state = block.state.set(PropName::Shape, shape);
-            if !down.as_ref().is_some_and(|b| b.state.is_opaque()) {
-                block.state = BlockState::AIR;
-                block.nbt = None;
-            }
-        } else if block.state.to_kind() == BlockKind::RedstoneWire {
-            let (up_north, up_east, up_south, up_west) = (
-                layer
-                    .block(
-                        event
-                            .position
-                            .get_in_direction(Direction::Up)
-                            .get_in_direction(Direction::North),
-                    )
-                    .map(IntoBlock::into_block),
-                layer
-                    .block(
-                        event
-                            .position
-                            .get_in_direction(Direction::Up)
-                            .get_in_direction(Direction::East),
-                    )
-                    .map(IntoBlock::into_block),
-                layer
-                    .block(
-                        event
-                            .position
-                            .get_in_direction(Direction::Up)
-                            .get_in_direction(Direction::South),
-                    )
-                    .map(IntoBlock::into_block),
-                layer
-                    .block(
-                        event
-                            .position
-                            .get_in_direction(Direction::Up)
-                            .get_in_direction(Direction::West),
-                    )
-                    .map(IntoBlock::into_block),
-            );
-            let (down_north, down_east, down_south, down_west) = (
-                layer
-                    .block(
-                        event
-                            .position
-                            .get_in_direction(Direction::Down)
-                            .get_in_direction(Direction::North),
-                    )
-                    .map(IntoBlock::into_block),
-                layer
-                    .block(
-                        event
-                            .position
-                            .get_in_direction(Direction::Down)
-                            .get_in_direction(Direction::East),
-                    )
-                    .map(IntoBlock::into_block),
-                layer
-                    .block(
-                        event
-                            .position
-                            .get_in_direction(Direction::Down)
-                            .get_in_direction(Direction::South),
-                    )
-                    .map(IntoBlock::into_block),
-                layer
-                    .block(
-                        event
-                            .position
-                            .get_in_direction(Direction::Down)
-                            .get_in_direction(Direction::West),
-                    )
-                    .map(IntoBlock::into_block),
-            );
-            if north
-                .as_ref()
-                .is_some_and(|b| b.state.to_kind() == BlockKind::RedstoneWire)
-            {
-                block.state = block.state.set(PropName::North, PropValue::Side);
-            } else if up_north
-                .as_ref()
-                .is_some_and(|b| b.state.to_kind() == BlockKind::RedstoneWire)
-                && !up.as_ref().is_some_and(|b| b.state.is_opaque())
-            {
-                block.state = block.state.set(PropName::North, PropValue::Up);
-                next.push(
-                    event
-                        .position
-                        .get_in_direction(Direction::Up)
-                        .get_in_direction(Direction::North),
-                );
-            } else if down_north
-                .clone()
-                .as_ref()
-                .is_some_and(|b| b.state.to_kind() == BlockKind::RedstoneWire)
-                && !north.as_ref().is_some_and(|b| b.state.is_opaque())
-            {
-                block.state = block.state.set(PropName::North, PropValue::Side);
-                next.push(
-                    event
-                        .position
-                        .get_in_direction(Direction::Down)
-                        .get_in_direction(Direction::North),
-                );
-            } else {
-                block.state = block.state.set(PropName::North, PropValue::None);
-            }
-            if east
-                .as_ref()
-                .is_some_and(|b| b.state.to_kind() == BlockKind::RedstoneWire)
-            {
-                block.state = block.state.set(PropName::East, PropValue::Side);
-            } else if up_east
-                .as_ref()
-                .is_some_and(|b| b.state.to_kind() == BlockKind::RedstoneWire)
-                && !up.as_ref().is_some_and(|b| b.state.is_opaque())
-            {
-                block.state = block.state.set(PropName::East, PropValue::Up);
-                next.push(
-                    event
-                        .position
-                        .get_in_direction(Direction::Up)
-                        .get_in_direction(Direction::East),
-                );
-            } else if down_east
-                .clone()
-                .as_ref()
-                .is_some_and(|b| b.state.to_kind() == BlockKind::RedstoneWire)
-                && !east.as_ref().is_some_and(|b| b.state.is_opaque())
-            {
-                block.state = block.state.set(PropName::East, PropValue::Side);
-                next.push(
-                    event
-                        .position
-                        .get_in_direction(Direction::Down)
-                        .get_in_direction(Direction::East),
-                );
-            } else {
-                block.state = block.state.set(PropName::East, PropValue::None);
-            }
-            if south
-                .as_ref()
-                .is_some_and(|b| b.state.to_kind() == BlockKind::RedstoneWire)
-            {
-                block.state = block.state.set(PropName::South, PropValue::Side);
-            } else if up_south
-                .as_ref()
-                .is_some_and(|b| b.state.to_kind() == BlockKind::RedstoneWire)
-                && !up.as_ref().is_some_and(|b| b.state.is_opaque())
-            {
-                block.state = block.state.set(PropName::South, PropValue::Up);
-                next.push(
-                    event
-                        .position
-                        .get_in_direction(Direction::Up)
-                        .get_in_direction(Direction::South),
-                );
-            } else if down_south
-                .clone()
-                .as_ref()
-                .is_some_and(|b| b.state.to_kind() == BlockKind::RedstoneWire)
-                && !south.as_ref().is_some_and(|b| b.state.is_opaque())
-            {
-                block.state = block.state.set(PropName::South, PropValue::Side);
-                next.push(
-                    event
-                        .position
-                        .get_in_direction(Direction::Down)
-                        .get_in_direction(Direction::South),
-                );
-            } else {
-                block.state = block.state.set(PropName::South, PropValue::None);
-            }
-            if west
-                .as_ref()
-                .is_some_and(|b| b.state.to_kind() == BlockKind::RedstoneWire)
-            {
-                block.state = block.state.set(PropName::West, PropValue::Side);
-            } else if up_west
-                .as_ref()
-                .is_some_and(|b| b.state.to_kind() == BlockKind::RedstoneWire)
-                && !up.as_ref().is_some_and(|b| b.state.is_opaque())
-            {
-                block.state = block.state.set(PropName::West, PropValue::Up);
-                next.push(
-                    event
-                        .position
-                        .get_in_direction(Direction::Up)
-                        .get_in_direction(Direction::West),
-                );
-            } else if down_west
-                .clone()
-                .as_ref()
-                .is_some_and(|b| b.state.to_kind() == BlockKind::RedstoneWire)
-                && !west.as_ref().is_some_and(|b| b.state.is_opaque())
-            {
-                block.state = block.state.set(PropName::West, PropValue::Side);
-                next.push(
-                    event
-                        .position
-                        .get_in_direction(Direction::Down)
-                        .get_in_direction(Direction::West),
-                );
-            } else {
-                block.state = block.state.set(PropName::West, PropValue::None);
-            }
-            let (connected_north, connected_east, connected_south, connected_west) = (
-                block.state.get(PropName::North) != Some(PropValue::None),
-                block.state.get(PropName::East) != Some(PropValue::None),
-                block.state.get(PropName::South) != Some(PropValue::None),
-                block.state.get(PropName::West) != Some(PropValue::None),
-            );
-            if !connected_east && !connected_south && !connected_west {
-                block.state = block.state.set(PropName::South, PropValue::Side);
-            }
-            if !connected_north && !connected_south && !connected_west {
-                block.state = block.state.set(PropName::West, PropValue::Side);
-            }
-            if !connected_north && !connected_east && !connected_west {
-                block.state = block.state.set(PropName::North, PropValue::Side);
-            }
-            if !connected_north && !connected_east && !connected_south {
-                block.state = block.state.set(PropName::East, PropValue::Side);
-            }
+    }
+}
 
-            if !down.as_ref().is_some_and(|b| b.state.is_opaque()) {
-                block.state = BlockState::AIR;
-                block.nbt = None;
-            }
-        } else if block.state.to_kind() == BlockKind::Scaffolding {
-            let bottom = !(down
-                .as_ref()
-                .is_some_and(|b| b.state.to_kind() == BlockKind::Scaffolding)
-                || down.is_some_and(|b| b.state.is_opaque()));
-            let distance = if bottom {
-                north
-                    .as_ref()
-                    .and_then(|b| b.state.get(PropName::Distance))
-                    .into_iter()
-                    .chain(east.as_ref().and_then(|b| b.state.get(PropName::Distance)))
-                    .chain(south.as_ref().and_then(|b| b.state.get(PropName::Distance)))
-                    .chain(west.as_ref().and_then(|b| b.state.get(PropName::Distance)))
-                    .map(|d| match d {
-                        PropValue::_0 => 0,
-                        PropValue::_1 => 1,
-                        PropValue::_2 => 2,
-                        PropValue::_3 => 3,
-                        PropValue::_4 => 4,
-                        PropValue::_5 => 5,
-                        PropValue::_6 => 6,
-                        _ => 7,
-                    })
-                    .min()
-                    .unwrap_or(7)
-            } else {
-                0
-            };
-            block.state = block
-                .state
-                .set(
-                    PropName::Bottom,
-                    if bottom {
-                        PropValue::True
-                    } else {
-                        PropValue::False
-                    },
-                )
-                .set(
-                    PropName::Distance,
-                    match distance {
-                        0 => PropValue::_0,
-                        1 => PropValue::_1,
-                        2 => PropValue::_2,
-                        3 => PropValue::_3,
-                        4 => PropValue::_4,
-                        5 => PropValue::_5,
-                        6 => PropValue::_6,
-                        _ => PropValue::_7,
-                    },
-                );
-        } else if (block.state.to_kind() == BlockKind::Sand
-            || block.state.to_kind() == BlockKind::RedSand
-            || block.state.to_kind() == BlockKind::Gravel
-            || block.state.to_kind() == BlockKind::DragonEgg
-            || block.state.to_kind() == BlockKind::Anvil
-            || block.state.to_kind() == BlockKind::ChippedAnvil
-            || block.state.to_kind() == BlockKind::DamagedAnvil
-            || block.state.to_kind() == BlockKind::SuspiciousSand
-            || block.state.to_kind() == BlockKind::SuspiciousGravel
-            || block.state.to_kind() == BlockKind::WhiteConcretePowder
-            || block.state.to_kind() == BlockKind::LightGrayConcretePowder
-            || block.state.to_kind() == BlockKind::GrayConcretePowder
-            || block.state.to_kind() == BlockKind::BlackConcretePowder
-            || block.state.to_kind() == BlockKind::BrownConcretePowder
-            || block.state.to_kind() == BlockKind::RedConcretePowder
-            || block.state.to_kind() == BlockKind::OrangeConcretePowder
-            || block.state.to_kind() == BlockKind::YellowConcretePowder
-            || block.state.to_kind() == BlockKind::LimeConcretePowder
-            || block.state.to_kind() == BlockKind::GreenConcretePowder
-            || block.state.to_kind() == BlockKind::CyanConcretePowder
-            || block.state.to_kind() == BlockKind::LightBlueConcretePowder
-            || block.state.to_kind() == BlockKind::BlueConcretePowder
-            || block.state.to_kind() == BlockKind::PurpleConcretePowder
-            || block.state.to_kind() == BlockKind::MagentaConcretePowder
-            || block.state.to_kind() == BlockKind::PinkConcretePowder)
-            && !down.is_some_and(|b| !b.state.is_air() && !b.state.is_liquid())
-        {
-            block.state = BlockState::AIR;
-            block.nbt = None;
-            let position = Position(DVec3::new(f64::from(event.position.x) + 0.5, event.position.y.into(), f64::from(event.position.z) + 0.5));
-            commands.spawn(FallingBlockEntityBundle {
-                position,
-                layer: event.entity_layer,
-                object_data: ObjectData(original_block.state.to_raw() as i32),
-                ..Default::default()
+/// Sand, gravel, anvils and the like fall shortly after losing what's under
+/// them.
+struct Falling;
+
+/// The concrete that concrete powder hardens into.
+fn concrete(kind: BlockKind) -> Option<BlockKind> {
+    kind.to_str().strip_suffix("_powder").and_then(BlockKind::from_str)
+}
+
+fn unsupported(below: Option<BlockState>) -> bool {
+    !below.is_some_and(|s| !s.is_air() && !s.is_liquid())
+}
+
+impl BlockBehavior for Falling {
+    fn on_neighbor_update(&self, update: &mut NeighborUpdate) {
+        // Concrete powder sets as soon as water touches it.
+        if let Some(concrete) = concrete(update.block.state.to_kind()) {
+            let wet = [
+                Direction::Up,
+                Direction::North,
+                Direction::East,
+                Direction::South,
+                Direction::West,
+            ]
+            .into_iter()
+            .any(|d| {
+                update.neighbor(d).is_some_and(|s| {
+                    s.to_kind() == BlockKind::Water || s.get(PropName::Waterlogged) == Some(PropValue::True)
+                })
             });
+            if wet {
+                update.block.state = concrete.to_state();
+                return;
+            }
         }
 
-        if block != original_block {
-            queue.extend(next.into_iter().map(|p| BlockUpdateEvent { position: p, ..event }));
+        if unsupported(update.neighbor(Direction::Down)) {
+            update.schedule_tick(FALL_DELAY, TickPriority::Normal);
+        }
+    }
 
-            queue.push_back(BlockUpdateEvent {
-                position: event.position.get_in_direction(Direction::Up),
-                ..event
-            });
-            queue.push_back(BlockUpdateEvent {
-                position: event.position.get_in_direction(Direction::Down),
-                ..event
-            });
-            queue.push_back(BlockUpdateEvent {
-                position: event.position.get_in_direction(Direction::North),
-                ..event
-            });
-            queue.push_back(BlockUpdateEvent {
-                position: event.position.get_in_direction(Direction::East),
-                ..event
-            });
-            queue.push_back(BlockUpdateEvent {
-                position: event.position.get_in_direction(Direction::South),
-                ..event
-            });
-            queue.push_back(BlockUpdateEvent {
-                position: event.position.get_in_direction(Direction::West),
-                ..event
-            });
+    /// Turns the block into a falling block entity, if it still has nothing
+    /// under it.
+    fn on_scheduled_tick(&self, tick: &mut ScheduledTick) {
+        let below = tick.layer.block(tick.position.get_in_direction(Direction::Down)).map(|b| b.state);
+        if !FALLING_BLOCKS.contains(&tick.state.to_kind()) || !unsupported(below) {
+            return;
         }
 
-        layer.set_block(event.position, block);
+        tick.set_block(tick.position, BlockState::AIR);
+        let position = Position(DVec3::new(
+            f64::from(tick.position.x) + 0.5,
+            tick.position.y.into(),
+            f64::from(tick.position.z) + 0.5,
+        ));
+        tick.commands.spawn(FallingBlockEntityBundle {
+            position,
+            layer: tick.layer_id,
+            object_data: ObjectData(tick.state.to_raw() as i32),
+            ..Default::default()
+        });
     }
 }
 
@@ -822,3 +872,284 @@ fn fence_type(block_kind: BlockKind) -> FenceType {
         _ => FenceType::None,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ORIGIN: BlockPos = BlockPos { x: 0, y: 0, z: 0 };
+
+    /// Runs the behaviour's neighbour update on the block at the origin of
+    /// `blocks`, which is laid out as positions relative to it.
+    fn neighbor_update<'a>(
+        behavior: &impl BlockBehavior,
+        blocks: &'a HashMap<BlockPos, BlockState>,
+    ) -> NeighborUpdate<'a> {
+        let mut update = NeighborUpdate::new(blocks, ORIGIN, blocks[&ORIGIN]);
+        behavior.on_neighbor_update(&mut update);
+        update
+    }
+
+    fn at(direction: Direction) -> BlockPos {
+        ORIGIN.get_in_direction(direction)
+    }
+
+    fn stairs(facing: PropValue, half: PropValue) -> BlockState {
+        BlockState::OAK_STAIRS.set(PropName::Facing, facing).set(PropName::Half, half)
+    }
+
+    fn stair_shape(neighbors: &[(Direction, BlockState)]) -> PropValue {
+        let mut blocks = HashMap::from([(ORIGIN, stairs(PropValue::North, PropValue::Bottom))]);
+        blocks.extend(neighbors.iter().map(|&(direction, state)| (at(direction), state)));
+        neighbor_update(&Stairs, &blocks).block.state.get(PropName::Shape).unwrap()
+    }
+
+    #[test]
+    fn stairs_turn_into_corners() {
+        let bottom = |facing| stairs(facing, PropValue::Bottom);
+
+        assert_eq!(stair_shape(&[]), PropValue::Straight);
+        assert_eq!(stair_shape(&[(Direction::North, bottom(PropValue::West))]), PropValue::OuterLeft);
+        assert_eq!(stair_shape(&[(Direction::North, bottom(PropValue::East))]), PropValue::OuterRight);
+        assert_eq!(stair_shape(&[(Direction::South, bottom(PropValue::West))]), PropValue::InnerLeft);
+        assert_eq!(stair_shape(&[(Direction::South, bottom(PropValue::East))]), PropValue::InnerRight);
+
+        // Stairs on the other half don't count.
+        assert_eq!(
+            stair_shape(&[(Direction::North, stairs(PropValue::West, PropValue::Top))]),
+            PropValue::Straight
+        );
+        // Nor do corners that would break up a straight run beside them.
+        assert_eq!(
+            stair_shape(&[
+                (Direction::South, bottom(PropValue::East)),
+                (Direction::East, bottom(PropValue::North)),
+            ]),
+            PropValue::Straight
+        );
+    }
+
+    fn sides(state: BlockState) -> [PropValue; 4] {
+        [PropName::North, PropName::East, PropName::South, PropName::West].map(|prop| state.get(prop).unwrap())
+    }
+
+    fn connect(center: BlockState, neighbors: &[(Direction, BlockState)]) -> BlockState {
+        let mut blocks = HashMap::from([(ORIGIN, center)]);
+        blocks.extend(neighbors.iter().map(|&(direction, state)| (at(direction), state)));
+        neighbor_update(&Connecting, &blocks).block.state
+    }
+
+    #[test]
+    fn fences_and_panes_connect() {
+        let fence = connect(
+            BlockState::OAK_FENCE,
+            &[
+                (Direction::North, BlockState::GLASS_PANE),
+                (Direction::East, BlockState::SPRUCE_FENCE),
+                (Direction::South, BlockState::NETHER_BRICK_FENCE),
+                (Direction::West, BlockState::STONE),
+            ],
+        );
+        assert_eq!(sides(fence), [PropValue::False, PropValue::True, PropValue::False, PropValue::True]);
+
+        let pane = connect(
+            BlockState::GLASS_PANE,
+            &[
+                (Direction::North, BlockState::IRON_BARS),
+                (Direction::East, BlockState::OAK_FENCE),
+                (Direction::South, BlockState::RED_STAINED_GLASS_PANE),
+            ],
+        );
+        assert_eq!(sides(pane), [PropValue::True, PropValue::False, PropValue::True, PropValue::False]);
+    }
+
+    #[test]
+    fn walls_connect() {
+        let up = |state: BlockState| state.get(PropName::Up).unwrap();
+
+        let alone = connect(BlockState::COBBLESTONE_WALL, &[]);
+        assert_eq!(sides(alone), [PropValue::None; 4]);
+        assert_eq!(up(alone), PropValue::True);
+
+        let straight = connect(
+            BlockState::COBBLESTONE_WALL,
+            &[
+                (Direction::North, BlockState::STONE_BRICK_WALL),
+                (Direction::South, BlockState::STONE),
+                (Direction::East, BlockState::OAK_FENCE),
+            ],
+        );
+        assert_eq!(sides(straight), [PropValue::Low, PropValue::None, PropValue::Low, PropValue::None]);
+        assert_eq!(up(straight), PropValue::False);
+
+        let corner = connect(
+            BlockState::COBBLESTONE_WALL,
+            &[
+                (Direction::North, BlockState::COBBLESTONE_WALL),
+                (Direction::East, BlockState::COBBLESTONE_WALL),
+            ],
+        );
+        assert_eq!(sides(corner), [PropValue::Low, PropValue::Low, PropValue::None, PropValue::None]);
+        assert_eq!(up(corner), PropValue::True);
+
+        // A solid block on top makes the wall reach up to it.
+        let covered = connect(
+            BlockState::COBBLESTONE_WALL,
+            &[
+                (Direction::North, BlockState::COBBLESTONE_WALL),
+                (Direction::South, BlockState::COBBLESTONE_WALL),
+                (Direction::Up, BlockState::STONE),
+            ],
+        );
+        assert_eq!(sides(covered), [PropValue::Tall, PropValue::None, PropValue::Tall, PropValue::None]);
+        assert_eq!(up(covered), PropValue::False);
+    }
+
+    #[test]
+    fn snow_makes_grass_blocks_snowy() {
+        let snowy = |above: Option<BlockState>| {
+            let mut blocks = HashMap::from([(ORIGIN, BlockState::GRASS_BLOCK)]);
+            blocks.extend(above.map(|state| (at(Direction::Up), state)));
+            neighbor_update(&Snowy, &blocks).block.state.get(PropName::Snowy).unwrap()
+        };
+
+        assert_eq!(snowy(Some(BlockState::SNOW)), PropValue::True);
+        assert_eq!(snowy(Some(BlockState::SNOW_BLOCK)), PropValue::True);
+        assert_eq!(snowy(Some(BlockState::AIR)), PropValue::False);
+        assert_eq!(snowy(None), PropValue::False);
+    }
+
+    #[test]
+    fn falling_blocks_schedule_a_tick_without_support() {
+        let fall = |block: BlockState, neighbors: &[(Direction, BlockState)]| {
+            let mut blocks = HashMap::from([(ORIGIN, block)]);
+            blocks.extend(neighbors.iter().map(|&(direction, state)| (at(direction), state)));
+            let update = neighbor_update(&Falling, &blocks);
+            (update.block.state, update.scheduled_ticks().to_vec())
+        };
+        let scheduled = vec![(FALL_DELAY, TickPriority::Normal)];
+
+        assert_eq!(fall(BlockState::SAND, &[(Direction::Down, BlockState::AIR)]).1, scheduled);
+        assert_eq!(fall(BlockState::GRAVEL, &[(Direction::Down, BlockState::WATER)]).1, scheduled);
+        assert_eq!(fall(BlockState::SAND, &[]).1, scheduled);
+        assert!(fall(BlockState::SAND, &[(Direction::Down, BlockState::STONE)]).1.is_empty());
+
+        // Concrete powder touching water hardens instead of falling.
+        let (state, ticks) = fall(
+            BlockState::RED_CONCRETE_POWDER,
+            &[(Direction::East, BlockState::WATER), (Direction::Down, BlockState::AIR)],
+        );
+        assert_eq!(state, BlockState::RED_CONCRETE);
+        assert!(ticks.is_empty());
+    }
+
+    #[test]
+    fn grass_breaks_without_support() {
+        let grass = |below: Option<BlockState>| {
+            let mut blocks = HashMap::from([(ORIGIN, BlockState::GRASS)]);
+            blocks.extend(below.map(|state| (at(Direction::Down), state)));
+            neighbor_update(&Grass, &blocks).block.state
+        };
+
+        assert_eq!(grass(Some(BlockState::DIRT)), BlockState::GRASS);
+        assert_eq!(grass(Some(BlockState::AIR)), BlockState::AIR);
+        assert_eq!(grass(None), BlockState::AIR);
+    }
+
+    fn update(x: i32) -> BlockUpdateEvent {
+        let layer = Entity::from_raw(1);
+        BlockUpdateEvent {
+            position: BlockPos::new(x, 0, 0),
+            layer,
+            entity_layer: EntityLayerId(layer),
+        }
+    }
+
+    fn drain(queue: &mut UpdateQueue, budget: u32) -> Vec<i32> {
+        std::iter::from_fn(|| queue.pop(budget)).map(|e| e.position.x).collect()
+    }
+
+    #[test]
+    fn updates_over_budget_carry_over() {
+        let mut queue = UpdateQueue::default();
+        queue.begin((0..5).map(update));
+        assert_eq!(drain(&mut queue, 3), [0, 1, 2]);
+        assert_eq!(queue.handled(), 3);
+        queue.end("test");
+        assert!(queue.over_budget);
+        assert_eq!(queue.len(), 2);
+
+        // What was left goes before the new updates, and the budget starts
+        // over.
+        queue.begin([update(5)]);
+        assert_eq!(queue.handled(), 0);
+        assert_eq!(drain(&mut queue, 3), [3, 4, 5]);
+        queue.end("test");
+        assert!(!queue.over_budget);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn carried_over_updates_are_deduplicated() {
+        let mut queue = UpdateQueue::default();
+        queue.begin([0, 1, 0, 2, 1].map(update));
+        assert_eq!(drain(&mut queue, 1), [0]);
+        queue.end("test");
+
+        queue.begin([]);
+        assert_eq!(drain(&mut queue, 10), [1, 0, 2]);
+    }
+
+    #[test]
+    fn blocks_changing_too_often_are_deferred() {
+        let mut queue = UpdateQueue::default();
+        queue.begin([]);
+        for _ in 0..MAX_CHANGES_PER_TICK {
+            assert!(queue.record_change(update(0)));
+        }
+        assert!(!queue.record_change(update(0)));
+        assert!(!queue.record_change(update(0)));
+        // Other blocks aren't held back.
+        assert!(queue.record_change(update(1)));
+        assert_eq!(queue.len(), 2);
+        queue.end("test");
+
+        // The held back updates run next tick, when the block can change
+        // again.
+        queue.begin([]);
+        assert_eq!(drain(&mut queue, 10), [0, 0]);
+        assert!(queue.record_change(update(0)));
+    }
+
+    #[test]
+    fn looping_blocks_are_remembered_between_ticks() {
+        let mut queue = UpdateQueue::default();
+        let overflow = |queue: &mut UpdateQueue| {
+            for _ in 0..=MAX_CHANGES_PER_TICK {
+                queue.record_change(update(0));
+            }
+        };
+
+        queue.begin([]);
+        assert!(queue.looping.is_empty());
+        overflow(&mut queue);
+        queue.end("test");
+
+        // A block that keeps looping is known about, so it isn't warned
+        // about again.
+        queue.begin([]);
+        assert!(queue.looping.contains(&(update(0).layer, update(0).position)));
+        drain(&mut queue, 10);
+        overflow(&mut queue);
+        queue.end("test");
+
+        queue.begin([]);
+        assert!(queue.looping.contains(&(update(0).layer, update(0).position)));
+        drain(&mut queue, 10);
+        queue.end("test");
+
+        // Once it settles down, it would be warned about again.
+        queue.begin([]);
+        assert!(queue.looping.is_empty());
+    }
+}
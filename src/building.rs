@@ -1,6 +1,8 @@
 use valence::entity::entity::Flags;
+use valence::entity::living::Health;
 use valence::interact_block::InteractBlockEvent;
 use valence::inventory::HeldItem;
+use valence::nbt::Compound;
 use valence::{prelude::*, Direction};
 
 use valence::entity::{
@@ -84,6 +86,7 @@ use valence::entity::{
 };
 
 use crate::block_update::{handle_block_update, BlockUpdate, BlockUpdateEvent};
+use crate::mining::DiggingProgress;
 
 pub struct Building;
 
@@ -91,6 +94,9 @@ impl Plugin for Building {
     fn build(&self, app: &mut App) {
         app.insert_resource(Events::<BlockUpdateEvent>::default())
             .add_event::<CancelDiggingEvent>()
+            .add_event::<BlockBreakEvent>()
+            .add_event::<CancelPlacingEvent>()
+            .add_event::<BlockPlaceEvent>()
             .add_systems(Update, (digging, building, summoning).before(handle_block_update))
             .add_plugins(BlockUpdate);
     }
@@ -101,12 +107,42 @@ pub struct CancelDiggingEvent {
     pub client: Entity,
 }
 
+/// Stops the `building` system from placing a block for this client's
+/// interaction this tick.
+#[derive(Event, Debug, Clone)]
+pub struct CancelPlacingEvent {
+    pub client: Entity,
+}
+
+/// Sent when a player has placed a block.
+#[derive(Event, Debug, Copy, Clone)]
+pub struct BlockPlaceEvent {
+    pub client: Entity,
+    pub position: BlockPos,
+    /// The block that was placed.
+    pub state: BlockState,
+    pub layer: EntityLayerId,
+}
+
+/// Sent when a player has broken a block, after it has been replaced with air.
+#[derive(Event, Debug, Clone)]
+pub struct BlockBreakEvent {
+    pub client: Entity,
+    pub position: BlockPos,
+    /// The block that was broken.
+    pub state: BlockState,
+    /// The block entity data of the broken block, if it had any.
+    pub nbt: Option<Compound>,
+    pub layer: EntityLayerId,
+}
+
 pub fn digging(
-    clients: Query<(&GameMode, &EntityLayerId)>,
+    clients: Query<(&GameMode, &EntityLayerId, Option<&DiggingProgress>)>,
     mut layers: Query<&mut ChunkLayer>,
     mut events: EventReader<DiggingEvent>,
     mut cancelled: EventReader<CancelDiggingEvent>,
     mut block_updates: EventWriter<BlockUpdateEvent>,
+    mut breaks: EventWriter<BlockBreakEvent>,
 ) {
     let cancelled = cancelled.read().map(|e| e.client).collect::<Vec<_>>();
 
@@ -115,16 +151,60 @@ pub fn digging(
             continue;
         }
 
-        let Ok((game_mode, &layer_id)) = clients.get(event.client) else {
+        let Ok((game_mode, &layer_id, progress)) = clients.get(event.client) else {
             continue;
         };
         let Ok(mut layer) = layers.get_mut(layer_id.0) else {
             continue;
         };
+        // Survival clients don't send `Stop` for blocks that break instantly.
+        let instant = progress.is_some_and(|p| p.is_instant(event.position));
         if (*game_mode == GameMode::Creative && event.state == DiggingState::Start)
             || (*game_mode == GameMode::Survival && event.state == DiggingState::Stop)
+            || (*game_mode == GameMode::Survival && event.state == DiggingState::Start && instant)
         {
-            layer.set_block(event.position, BlockState::AIR);
+            let Some(Block { state, nbt }) = layer.set_block(event.position, BlockState::AIR) else {
+                continue;
+            };
+            // The water a waterlogged block was holding stays where it was.
+            if state.get(PropName::Waterlogged) == Some(PropValue::True) {
+                layer.set_block(event.position, BlockState::WATER);
+            }
+
+            breaks.send(BlockBreakEvent {
+                client: event.client,
+                position: event.position,
+                state,
+                nbt,
+                layer: layer_id,
+            });
+
+            // Doors, beds and tall plants don't stay around without their
+            // other half. Only the broken half drops anything.
+            if let Some((other, other_state)) = linked_half(state, event.position)
+                .and_then(|pos| layer.block(pos).map(|b| (pos, b.state)))
+                .filter(|(_, other_state)| other_state.to_kind() == state.to_kind())
+            {
+                let replacement = if state.to_kind() == BlockKind::TallSeagrass
+                    || other_state.get(PropName::Waterlogged) == Some(PropValue::True)
+                {
+                    BlockState::WATER
+                } else {
+                    BlockState::AIR
+                };
+                layer.set_block(other, replacement);
+                for position in [
+                    other,
+                    other.get_in_direction(Direction::Up),
+                    other.get_in_direction(Direction::Down),
+                    other.get_in_direction(Direction::North),
+                    other.get_in_direction(Direction::East),
+                    other.get_in_direction(Direction::South),
+                    other.get_in_direction(Direction::West),
+                ] {
+                    block_updates.send(BlockUpdateEvent { position, layer: layer_id.0, entity_layer: layer_id });
+                }
+            }
 
             block_updates.send(BlockUpdateEvent {
                 position: event.position,
@@ -165,14 +245,55 @@ pub fn digging(
     }
 }
 
-fn building(
-    mut clients: Query<(&mut Inventory, &GameMode, &HeldItem, &Look, &Hitbox, &Flags, &EntityLayerId)>,
+/// Returns where the other half of a two-block-tall block or a bed is.
+pub fn linked_half(state: BlockState, position: BlockPos) -> Option<BlockPos> {
+    match (state.get(PropName::Half), state.get(PropName::Part)) {
+        (Some(PropValue::Lower), _) => Some(position.get_in_direction(Direction::Up)),
+        (Some(PropValue::Upper), _) => Some(position.get_in_direction(Direction::Down)),
+        (_, Some(part)) => {
+            // Beds face from the foot towards the head.
+            let (to_head, to_foot) = match state.get(PropName::Facing)? {
+                PropValue::North => (Direction::North, Direction::South),
+                PropValue::East => (Direction::East, Direction::West),
+                PropValue::South => (Direction::South, Direction::North),
+                _ => (Direction::West, Direction::East),
+            };
+            Some(position.get_in_direction(if part == PropValue::Foot { to_head } else { to_foot }))
+        }
+        _ => None,
+    }
+}
+
+/// Whether a block placed at `position` would overlap a living entity in the
+/// layer. Items, experience orbs and other non-living entities don't get in
+/// the way of placing blocks.
+fn obstructed(state: BlockState, position: BlockPos, layer: EntityLayerId, entities: &Query<(&Hitbox, &EntityLayerId), (With<Health>, Without<Despawned>)>) -> bool {
+    let offset = DVec3::new(position.x as f64, position.y as f64, position.z as f64);
+    state.collision_shapes().any(|c| {
+        entities
+            .iter()
+            .filter(|(_, &entity_layer)| entity_layer == layer)
+            .any(|(hitbox, _)| (c + offset).intersects(hitbox.get()))
+    })
+}
+
+pub fn building(
+    mut clients: Query<(&mut Inventory, &GameMode, &HeldItem, &Look, &Flags, &EntityLayerId)>,
+    entities: Query<(&Hitbox, &EntityLayerId), (With<Health>, Without<Despawned>)>,
     mut layers: Query<&mut ChunkLayer>,
     mut events: EventReader<InteractBlockEvent>,
+    mut cancelled: EventReader<CancelPlacingEvent>,
     mut block_updates: EventWriter<BlockUpdateEvent>,
+    mut places: EventWriter<BlockPlaceEvent>,
 ) {
+    let cancelled = cancelled.read().map(|e| e.client).collect::<Vec<_>>();
+
     for event in events.read() {
-        let Ok((mut inventory, game_mode, held, look, hitbox, flags, &layer_id)) = clients.get_mut(event.client)
+        if cancelled.contains(&event.client) {
+            continue;
+        }
+
+        let Ok((mut inventory, game_mode, held, look, flags, &layer_id)) = clients.get_mut(event.client)
         else {
             continue;
         };
@@ -207,6 +328,9 @@ fn building(
             (event.position, true)
         };
         let mut force_replace = false;
+        // The other half of two-block-tall blocks and beds, placed together
+        // with the main block once nothing is in the way.
+        let mut other_half: Option<(BlockPos, BlockState)> = None;
         let mut state = block_kind.to_state();
         if state.get(PropName::Axis).is_some() {
             state = state.set(
@@ -438,13 +562,8 @@ fn building(
             {
                 continue;
             }
-            if state.set(PropName::Part, PropValue::Head).collision_shapes().any(|c| (c + DVec3::new(real_pos.get_in_direction(dir).x as f64, real_pos.get_in_direction(dir).y as f64, real_pos.get_in_direction(dir).z as f64)).intersects(hitbox.get() + 0.5 * DVec3::Y)) {
-                continue;
-            }
-            layer.set_block(
-                real_pos.get_in_direction(dir),
-                state.set(PropName::Part, PropValue::Head),
-            );
+            state = state.set(PropName::Part, PropValue::Foot);
+            other_half = Some((real_pos.get_in_direction(dir), state.set(PropName::Part, PropValue::Head)));
         } else if state.to_kind() == BlockKind::Beehive
             || state.to_kind() == BlockKind::BeeNest
             || state.to_kind() == BlockKind::BigDripleaf
@@ -705,78 +824,51 @@ fn building(
                 _ => PropValue::South,
             };
             state = state.set(PropName::Facing, facing);
-            let (left, right) = match facing {
+            let (clockwise, counter_clockwise) = match facing {
                 PropValue::North => (Direction::East, Direction::West),
                 PropValue::East => (Direction::South, Direction::North),
                 PropValue::South => (Direction::West, Direction::East),
                 _ => (Direction::North, Direction::South),
             };
-            let matching_left = layer
-                .block(real_pos.get_in_direction(left))
-                .is_some_and(|b| {
+            let is_door = |pos: BlockPos| {
+                layer.block(pos).is_some_and(|b| {
                     b.state.to_kind() == state.to_kind()
-                        && b.state.get(PropName::Hinge) == Some(PropValue::Right)
-                });
-            let matching_right = layer
-                .block(real_pos.get_in_direction(right))
-                .is_some_and(|b| {
-                    b.state.to_kind() == state.to_kind()
-                        && b.state.get(PropName::Hinge) == Some(PropValue::Left)
-                });
-            if matching_left && !matching_right {
-                state = state.set(PropName::Hinge, PropValue::Left);
-            } else if matching_right && !matching_left {
-                state = state.set(PropName::Hinge, PropValue::Right);
+                        && b.state.get(PropName::Half) == Some(PropValue::Lower)
+                })
+            };
+            let solid = |pos: BlockPos| layer.block(pos).is_some_and(|b| b.state.is_opaque()) as i32;
+            let door_clockwise = is_door(real_pos.get_in_direction(clockwise));
+            let door_counter_clockwise = is_door(real_pos.get_in_direction(counter_clockwise));
+            // Positive when there are more solid blocks on the clockwise side.
+            let walls = solid(real_pos.get_in_direction(clockwise))
+                + solid(real_pos.get_in_direction(clockwise).get_in_direction(Direction::Up))
+                - solid(real_pos.get_in_direction(counter_clockwise))
+                - solid(real_pos.get_in_direction(counter_clockwise).get_in_direction(Direction::Up));
+            // Like vanilla, a door next to another one becomes the other half
+            // of a double door, otherwise the hinge goes against the wall or
+            // on the side of the block that was clicked.
+            let aim = match facing {
+                PropValue::North => event.cursor_pos.x,
+                PropValue::East => event.cursor_pos.z,
+                PropValue::South => 1. - event.cursor_pos.x,
+                _ => 1. - event.cursor_pos.z,
+            };
+            let hinge = if (door_counter_clockwise && !door_clockwise) || walls > 0 {
+                PropValue::Right
+            } else if (door_clockwise && !door_counter_clockwise) || walls < 0 {
+                PropValue::Left
+            } else if aim > 0.5 {
+                PropValue::Right
             } else {
-                let left_blocks = layer
-                    .block(real_pos.get_in_direction(left))
-                    .is_some_and(|b| b.state.is_opaque()) as u8
-                    + layer
-                        .block(
-                            real_pos
-                                .get_in_direction(left)
-                                .get_in_direction(Direction::Up),
-                        )
-                        .is_some_and(|b| b.state.is_opaque()) as u8;
-                let right_blocks = layer
-                    .block(real_pos.get_in_direction(right))
-                    .is_some_and(|b| b.state.is_opaque()) as u8
-                    + layer
-                        .block(
-                            real_pos
-                                .get_in_direction(right)
-                                .get_in_direction(Direction::Up),
-                        )
-                        .is_some_and(|b| b.state.is_opaque()) as u8;
-                let aim = match facing {
-                    PropValue::North => event.cursor_pos.x,
-                    PropValue::East => event.cursor_pos.z,
-                    PropValue::South => 1. - event.cursor_pos.x,
-                    _ => 1. - event.cursor_pos.z,
-                };
-                state = state.set(
-                    PropName::Hinge,
-                    if left_blocks > right_blocks {
-                        PropValue::Left
-                    } else if right_blocks > left_blocks || aim > 0.5 {
-                        PropValue::Right
-                    } else {
-                        PropValue::Left
-                    },
-                );
-            }
+                PropValue::Left
+            };
+            state = state.set(PropName::Hinge, hinge).set(PropName::Half, PropValue::Lower);
             if layer
                 .block(real_pos.get_in_direction(Direction::Up))
                 .filter(|b| !b.state.is_replaceable())
                 .is_none()
             {
-                if state.set(PropName::Half, PropValue::Upper).collision_shapes().any(|c| (c + DVec3::new(real_pos.x as f64, (real_pos.y + 1) as f64, real_pos.z as f64)).intersects(hitbox.get() + 0.5 * DVec3::Y)) {
-                    continue;
-                }
-                layer.set_block(
-                    real_pos.get_in_direction(Direction::Up),
-                    state.set(PropName::Half, PropValue::Upper),
-                );
+                other_half = Some((real_pos.get_in_direction(Direction::Up), state.set(PropName::Half, PropValue::Upper)));
             } else {
                 continue;
             }
@@ -799,10 +891,8 @@ fn building(
                 .filter(|b| !b.state.is_replaceable())
                 .is_none()
             {
-                layer.set_block(
-                    real_pos.get_in_direction(Direction::Up),
-                    state.set(PropName::Half, PropValue::Upper),
-                );
+                state = state.set(PropName::Half, PropValue::Lower);
+                other_half = Some((real_pos.get_in_direction(Direction::Up), state.set(PropName::Half, PropValue::Upper)));
             } else {
                 continue;
             }
@@ -935,10 +1025,8 @@ fn building(
                 .filter(|b| !b.state.is_replaceable())
                 .is_none()
             {
-                layer.set_block(
-                    real_pos.get_in_direction(Direction::Up),
-                    state.set(PropName::Half, PropValue::Upper),
-                );
+                state = state.set(PropName::Half, PropValue::Lower);
+                other_half = Some((real_pos.get_in_direction(Direction::Up), state.set(PropName::Half, PropValue::Upper)));
             } else {
                 continue;
             }
@@ -1003,10 +1091,8 @@ fn building(
             {
                 continue;
             }
-            layer.set_block(
-                real_pos.get_in_direction(Direction::Up),
-                state.set(PropName::Half, PropValue::Upper),
-            );
+            state = state.set(PropName::Half, PropValue::Lower);
+            other_half = Some((real_pos.get_in_direction(Direction::Up), state.set(PropName::Half, PropValue::Upper)));
         } else if state.to_kind() == BlockKind::OakTrapdoor
             || state.to_kind() == BlockKind::SpruceTrapdoor
             || state.to_kind() == BlockKind::BirchTrapdoor
@@ -1115,21 +1201,12 @@ fn building(
             }
         }
 
-        if state.collision_shapes().any(|c| (c + DVec3::new(real_pos.x as f64, real_pos.y as f64, real_pos.z as f64)).intersects(hitbox.get() + 0.5 * DVec3::Y)) {
+        if obstructed(state, real_pos, layer_id, &entities)
+            || other_half.is_some_and(|(pos, half)| obstructed(half, pos, layer_id, &entities))
+        {
             continue;
         }
 
-        if *game_mode == GameMode::Survival {
-            // check if the player has the item in their inventory and remove
-            // it.
-            if stack.count > 1 {
-                let amount = stack.count - 1;
-                inventory.set_slot_amount(slot_id, amount);
-            } else {
-                inventory.set_slot(slot_id, ItemStack::EMPTY);
-            }
-        }
-
         if layer
             .block(real_pos)
             .is_some_and(|b| b.state.to_kind() == BlockKind::Water)
@@ -1145,9 +1222,50 @@ fn building(
         {
             continue;
         }
+
+        if *game_mode == GameMode::Survival {
+            // check if the player has the item in their inventory and remove
+            // it.
+            if stack.count > 1 {
+                let amount = stack.count - 1;
+                inventory.set_slot_amount(slot_id, amount);
+            } else {
+                inventory.set_slot(slot_id, ItemStack::EMPTY);
+            }
+        }
+
         // client.send_chat_message(format!("{:?}", state));
         layer.set_block(real_pos, state);
 
+        if let Some((pos, mut half)) = other_half {
+            if layer
+                .block(pos)
+                .is_some_and(|b| b.state.to_kind() == BlockKind::Water)
+                && half.get(PropName::Waterlogged).is_some()
+            {
+                half = half.set(PropName::Waterlogged, PropValue::True);
+            }
+            layer.set_block(pos, half);
+            for position in [
+                pos,
+                pos.get_in_direction(Direction::Up),
+                pos.get_in_direction(Direction::Down),
+                pos.get_in_direction(Direction::North),
+                pos.get_in_direction(Direction::East),
+                pos.get_in_direction(Direction::South),
+                pos.get_in_direction(Direction::West),
+            ] {
+                block_updates.send(BlockUpdateEvent { position, layer: layer_id.0, entity_layer: layer_id });
+            }
+        }
+
+        places.send(BlockPlaceEvent {
+            client: event.client,
+            position: real_pos,
+            state,
+            layer: layer_id,
+        });
+
         block_updates.send(BlockUpdateEvent { position: real_pos, layer: layer_id.0, entity_layer: layer_id });
         block_updates.send(BlockUpdateEvent {
             position: real_pos.get_in_direction(Direction::Up),
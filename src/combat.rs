@@ -4,7 +4,7 @@ use valence::inventory::HeldItem;
 use valence::math::Vec3Swizzles;
 use valence::prelude::*;
 
-use crate::death::DeathEvent;
+use crate::damage::{DamageCause, DamageEvent, DamageSource};
 
 pub struct Combat;
 
@@ -35,16 +35,15 @@ fn handle_combat_event(
         &Velocity,
         &mut CombatState,
         &mut EntityStatuses,
-        &mut Health,
         &mut Inventory,
         &HeldItem,
         &GameMode,
         &Username,
     )>,
-    mut entities: Query<(Entity, &Position, &mut Velocity, &mut Health, &mut CombatState), Without<Client>>,
+    mut entities: Query<(Entity, &Position, &mut Velocity, &mut CombatState), (With<Health>, Without<Client>)>,
     mut sprinting: EventReader<SprintEvent>,
     mut interact_entity: EventReader<InteractEntityEvent>,
-    mut death: EventWriter<DeathEvent>,
+    mut damage_events: EventWriter<DamageEvent>,
 ) {
     for &SprintEvent { client, state } in sprinting.read() {
         if let Ok((_, _, _, _, mut combat_state, ..)) = clients.get_mut(client) {
@@ -123,7 +122,7 @@ fn handle_combat_event(
             _ => (1.0, 4.0),
         };
 
-        if let Ok((entity, mut client, pos, velocity, mut state, mut statuses, mut health, .., game_mode, _)) = clients.get_mut(victim_entity) {
+        if let Ok((entity, mut client, pos, velocity, mut state, mut statuses, .., game_mode, _)) = clients.get_mut(victim_entity) {
             if *game_mode == GameMode::Creative || *game_mode == GameMode::Spectator {
                 continue;
             }
@@ -155,16 +154,12 @@ fn handle_combat_event(
             client.trigger_status(EntityStatus::PlayAttackSound);
             statuses.trigger(EntityStatus::PlayAttackSound);
 
-            if health.0 > damage {
-                health.0 -= damage;
-            } else {
-                health.0 = 0.0;
-                death.send(DeathEvent {
-                    entity,
-                    killed_by: Some(attacker_entity),
-                });
-            }
-        } else if let Ok((entity, pos, mut velocity, mut health, mut state)) = entities.get_mut(victim_entity) {
+            damage_events.send(DamageEvent {
+                target: entity,
+                amount: damage,
+                source: DamageSource::attack(DamageCause::PlayerAttack, attacker_entity),
+            });
+        } else if let Ok((entity, pos, mut velocity, mut state)) = entities.get_mut(victim_entity) {
             if server.current_tick() - state.last_attacked_tick < 10 {
                 continue;
             }
@@ -189,15 +184,11 @@ fn handle_combat_event(
 
             velocity.0 += Vec3::new(dir.x * knockback_xz, knockback_y, dir.y * knockback_xz);
 
-            if health.0 > damage {
-                health.0 -= damage;
-            } else {
-                health.0 = 0.0;
-                death.send(DeathEvent {
-                    entity,
-                    killed_by: Some(attacker_entity),
-                });
-            }
+            damage_events.send(DamageEvent {
+                target: entity,
+                amount: damage,
+                source: DamageSource::attack(DamageCause::PlayerAttack, attacker_entity),
+            });
         }
 
         let Ok(
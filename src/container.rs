@@ -0,0 +1,396 @@
+use std::ops::Range;
+
+use valence::entity::entity::Flags;
+use valence::interact_block::InteractBlockEvent;
+use valence::inventory::{HeldItem, OpenInventory};
+use valence::nbt::{compound, Compound, List, Value};
+use valence::prelude::*;
+use valence::protocol::Hand;
+use valence::rand::{thread_rng, Rng};
+use valence::sound::{Sound, SoundCategory};
+use valence::Direction;
+
+use crate::building::{building, digging, BlockBreakEvent, CancelPlacingEvent};
+use crate::interact::sneak_bypasses;
+use crate::item_entity::{spawn_item, DEFAULT_PICKUP_DELAY};
+
+/// Blocks that hold items: chests, barrels, shulker boxes, hoppers,
+/// dispensers, droppers and furnaces.
+pub struct Containers;
+
+impl Plugin for Containers {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                (close_containers, open_containers.before(building)).chain(),
+                save_containers,
+                drop_contents.after(digging),
+            ),
+        );
+    }
+}
+
+/// The inventory of a container block while players have it open. The rest of
+/// the time the items are only kept in the block entity data of the chunk,
+/// which is where they are saved from.
+#[derive(Component, Debug, Clone)]
+pub struct Container {
+    pub layer: EntityLayerId,
+    /// The blocks the inventory belongs to. Large chests have two, and the
+    /// first one holds the top half of the inventory.
+    pub blocks: Vec<BlockPos>,
+}
+
+/// Slots in a single chest.
+const CHEST_SLOTS: u16 = 27;
+
+/// Returns the inventory kind and title of a container block.
+pub fn container_kind(kind: BlockKind) -> Option<(InventoryKind, &'static str)> {
+    let name = kind.to_str();
+
+    Some(match kind {
+        BlockKind::Chest | BlockKind::TrappedChest => (InventoryKind::Generic9x3, "container.chest"),
+        BlockKind::Barrel => (InventoryKind::Generic9x3, "container.barrel"),
+        BlockKind::Hopper => (InventoryKind::Hopper, "container.hopper"),
+        BlockKind::Dispenser => (InventoryKind::Generic3x3, "container.dispenser"),
+        BlockKind::Dropper => (InventoryKind::Generic3x3, "container.dropper"),
+        BlockKind::Furnace => (InventoryKind::Furnace, "container.furnace"),
+        BlockKind::BlastFurnace => (InventoryKind::BlastFurnace, "container.blast_furnace"),
+        BlockKind::Smoker => (InventoryKind::Smoker, "container.smoker"),
+        _ if name == "shulker_box" || name.ends_with("_shulker_box") => (InventoryKind::ShulkerBox, "container.shulkerBox"),
+        _ => return None,
+    })
+}
+
+/// Returns the other half of a large chest.
+pub fn chest_neighbour(state: BlockState, position: BlockPos) -> Option<BlockPos> {
+    let (clockwise, counter_clockwise) = match state.get(PropName::Facing)? {
+        PropValue::North => (Direction::East, Direction::West),
+        PropValue::East => (Direction::South, Direction::North),
+        PropValue::South => (Direction::West, Direction::East),
+        _ => (Direction::North, Direction::South),
+    };
+    match state.get(PropName::Type)? {
+        PropValue::Left => Some(position.get_in_direction(clockwise)),
+        PropValue::Right => Some(position.get_in_direction(counter_clockwise)),
+        _ => None,
+    }
+}
+
+/// Returns the blocks that make up the container at `position`, in inventory
+/// order.
+fn container_blocks(layer: &ChunkLayer, state: BlockState, position: BlockPos) -> Vec<BlockPos> {
+    let Some(other) = chest_neighbour(state, position)
+        .filter(|&other| layer.block(other).is_some_and(|b| b.state.to_kind() == state.to_kind()))
+    else {
+        return vec![position];
+    };
+
+    // The right half of a large chest is the top of the inventory.
+    if state.get(PropName::Type) == Some(PropValue::Right) {
+        vec![position, other]
+    } else {
+        vec![other, position]
+    }
+}
+
+/// Reads the `Items` of a block entity into `inventory`, starting at slot
+/// `offset`.
+pub fn read_items(nbt: Option<&Compound>, inventory: &mut Inventory, offset: u16) {
+    let Some(Value::List(List::Compound(items))) = nbt.and_then(|nbt| nbt.get("Items")) else {
+        return;
+    };
+
+    for item in items {
+        let Some(&Value::Byte(slot)) = item.get("Slot") else {
+            continue;
+        };
+        let Some(Value::String(id)) = item.get("id") else {
+            continue;
+        };
+        let Some(kind) = ItemKind::from_str(id.trim_start_matches("minecraft:")) else {
+            continue;
+        };
+        let count = match item.get("Count") {
+            Some(&Value::Byte(count)) => count,
+            Some(&Value::Int(count)) => count as i8,
+            _ => 1,
+        };
+        let nbt = match item.get("tag") {
+            Some(Value::Compound(tag)) => Some(tag.clone()),
+            _ => None,
+        };
+
+        let slot = offset + slot as u16;
+        if slot < inventory.slot_count() {
+            inventory.set_slot(slot, ItemStack::new(kind, count, nbt));
+        }
+    }
+}
+
+/// Writes the non-empty slots in `slots` as a block entity `Items` list,
+/// numbered from zero.
+pub fn write_items(inventory: &Inventory, slots: Range<u16>) -> List {
+    let start = slots.start;
+
+    List::Compound(
+        slots
+            .filter(|&slot| !inventory.slot(slot).is_empty())
+            .map(|slot| {
+                let stack = inventory.slot(slot);
+                let mut item = compound! {
+                    "Slot" => (slot - start) as i8,
+                    "id" => format!("minecraft:{}", stack.item.to_str()),
+                    "Count" => stack.count,
+                };
+                if let Some(nbt) = &stack.nbt {
+                    item.insert("tag", Value::Compound(nbt.clone()));
+                }
+                item
+            })
+            .collect(),
+    )
+}
+
+/// Slots of a container's inventory that belong to its `index`th block.
+fn block_slots(container: &Container, inventory: &Inventory, index: usize) -> Range<u16> {
+    if container.blocks.len() > 1 {
+        index as u16 * CHEST_SLOTS..(index as u16 + 1) * CHEST_SLOTS
+    } else {
+        0..inventory.slot_count()
+    }
+}
+
+fn play_container_sound(layer: &mut ChunkLayer, kind: BlockKind, position: BlockPos, open: bool) {
+    let name = kind.to_str();
+    let sound = match (kind, open) {
+        (BlockKind::Chest | BlockKind::TrappedChest, true) => Sound::BlockChestOpen,
+        (BlockKind::Chest | BlockKind::TrappedChest, false) => Sound::BlockChestClose,
+        (BlockKind::Barrel, true) => Sound::BlockBarrelOpen,
+        (BlockKind::Barrel, false) => Sound::BlockBarrelClose,
+        _ if name.ends_with("shulker_box") && open => Sound::BlockShulkerBoxOpen,
+        _ if name.ends_with("shulker_box") => Sound::BlockShulkerBoxClose,
+        _ => return,
+    };
+    let center = DVec3::new(
+        f64::from(position.x) + 0.5,
+        f64::from(position.y) + 0.5,
+        f64::from(position.z) + 0.5,
+    );
+    layer.play_sound(sound, SoundCategory::Block, center, 0.5, 1.0);
+}
+
+fn open_containers(
+    clients: Query<(&Inventory, &HeldItem, &Flags, &EntityLayerId)>,
+    containers: Query<(Entity, &Container)>,
+    mut layers: Query<&mut ChunkLayer>,
+    mut events: EventReader<InteractBlockEvent>,
+    mut cancel: EventWriter<CancelPlacingEvent>,
+    mut commands: Commands,
+) {
+    for event in events.read() {
+        if event.hand != Hand::Main {
+            continue;
+        }
+        let Ok((inventory, held, flags, &layer_id)) = clients.get(event.client) else {
+            continue;
+        };
+        if sneak_bypasses(flags, inventory, held) {
+            continue;
+        }
+        let Ok(mut layer) = layers.get_mut(layer_id.0) else {
+            continue;
+        };
+        let Some(state) = layer.block(event.position).map(|b| b.state) else {
+            continue;
+        };
+        let Some((kind, title)) = container_kind(state.to_kind()) else {
+            continue;
+        };
+
+        cancel.send(CancelPlacingEvent { client: event.client });
+
+        let blocks = container_blocks(&layer, state, event.position);
+
+        // Players looking into the same container share its inventory.
+        let existing = containers
+            .iter()
+            .find(|(_, c)| c.layer == layer_id && c.blocks.contains(&event.position))
+            .map(|(entity, _)| entity);
+
+        let entity = match existing {
+            Some(entity) => entity,
+            None => {
+                let (kind, title) = if blocks.len() > 1 {
+                    (InventoryKind::Generic9x6, "container.chestDouble")
+                } else {
+                    (kind, title)
+                };
+                let mut items = Inventory::with_title(kind, Text::translate(title, []));
+                for (i, &position) in blocks.iter().enumerate() {
+                    let nbt = layer.block(position).and_then(|b| b.nbt);
+                    read_items(nbt, &mut items, i as u16 * CHEST_SLOTS);
+                }
+
+                play_container_sound(&mut layer, state.to_kind(), event.position, true);
+
+                commands
+                    .spawn((
+                        items,
+                        Container {
+                            layer: layer_id,
+                            blocks,
+                        },
+                    ))
+                    .id()
+            }
+        };
+
+        commands.entity(event.client).insert(OpenInventory::new(entity));
+    }
+}
+
+/// Writes changes to open containers back into the block entities, so they
+/// are saved with the chunk.
+fn save_containers(
+    containers: Query<(&Inventory, &Container), Changed<Inventory>>,
+    mut layers: Query<&mut ChunkLayer>,
+) {
+    for (inventory, container) in &containers {
+        let Ok(mut layer) = layers.get_mut(container.layer.0) else {
+            continue;
+        };
+
+        for (i, &position) in container.blocks.iter().enumerate() {
+            let items = write_items(inventory, block_slots(container, inventory, i));
+
+            if let Some(nbt) = layer.block_entity_mut(position) {
+                nbt.insert("Items", items);
+                continue;
+            }
+            // Freshly placed containers don't have any block entity data yet.
+            let Some(state) = layer.block(position).map(|b| b.state) else {
+                continue;
+            };
+            if container_kind(state.to_kind()).is_none() {
+                continue;
+            }
+            layer.set_block(
+                position,
+                Block {
+                    state,
+                    nbt: Some(compound! { "Items" => items }),
+                },
+            );
+        }
+    }
+}
+
+/// Gets rid of container inventories that no one is looking at anymore.
+fn close_containers(
+    viewers: Query<&OpenInventory>,
+    containers: Query<(Entity, &Container)>,
+    mut layers: Query<&mut ChunkLayer>,
+    mut commands: Commands,
+) {
+    for (entity, container) in &containers {
+        if viewers.iter().any(|open| open.entity == entity) {
+            continue;
+        }
+
+        if let Ok(mut layer) = layers.get_mut(container.layer.0) {
+            let position = container.blocks[0];
+            if let Some(kind) = layer.block(position).map(|b| b.state.to_kind()) {
+                play_container_sound(&mut layer, kind, position, false);
+            }
+        }
+        commands.entity(entity).despawn();
+    }
+}
+
+/// Spills the items of broken containers. A large chest that loses one half
+/// turns back into a single chest with the other half's items.
+fn drop_contents(
+    viewers: Query<(Entity, &OpenInventory)>,
+    containers: Query<(Entity, &Inventory, &Container)>,
+    mut layers: Query<&mut ChunkLayer>,
+    mut breaks: EventReader<BlockBreakEvent>,
+    mut commands: Commands,
+) {
+    let mut rng = thread_rng();
+
+    for event in breaks.read() {
+        if container_kind(event.state.to_kind()).is_none() {
+            continue;
+        }
+        let Ok(mut layer) = layers.get_mut(event.layer.0) else {
+            continue;
+        };
+
+        let mut items = Inventory::new(InventoryKind::Generic9x6);
+        let open = containers
+            .iter()
+            .find(|(_, _, c)| c.layer == event.layer && c.blocks.contains(&event.position));
+
+        if let Some((entity, inventory, container)) = open {
+            // The open inventory is more up to date than the block entity.
+            for (i, &position) in container.blocks.iter().enumerate() {
+                let slots = block_slots(container, inventory, i);
+                if position == event.position {
+                    let start = slots.start;
+                    for slot in slots {
+                        items.set_slot(slot - start, inventory.slot(slot).clone());
+                    }
+                } else if let Some(nbt) = layer.block_entity_mut(position) {
+                    nbt.insert("Items", write_items(inventory, slots));
+                }
+            }
+
+            for (viewer, open) in &viewers {
+                if open.entity == entity {
+                    commands.entity(viewer).remove::<OpenInventory>();
+                }
+            }
+            commands.entity(entity).despawn();
+        } else {
+            read_items(event.nbt.as_ref(), &mut items, 0);
+        }
+
+        if let Some(other) = chest_neighbour(event.state, event.position) {
+            let half = layer
+                .block(other)
+                .filter(|b| b.state.to_kind() == event.state.to_kind())
+                .map(|b| (b.state, b.nbt.cloned()));
+            if let Some((state, nbt)) = half {
+                layer.set_block(
+                    other,
+                    Block {
+                        state: state.set(PropName::Type, PropValue::Single),
+                        nbt,
+                    },
+                );
+            }
+        }
+
+        for slot in 0..items.slot_count() {
+            let stack = items.slot(slot);
+            if stack.is_empty() {
+                continue;
+            }
+
+            let position = DVec3::new(
+                f64::from(event.position.x) + rng.gen_range(0.1..0.9),
+                f64::from(event.position.y) + rng.gen_range(0.1..0.9),
+                f64::from(event.position.z) + rng.gen_range(0.1..0.9),
+            );
+            let velocity = Vec3::new(
+                rng.gen_range(-0.05..0.05) * 20.0,
+                0.2 * 20.0,
+                rng.gen_range(-0.05..0.05) * 20.0,
+            );
+
+            spawn_item(&mut commands, event.layer, position, stack.clone(), velocity, DEFAULT_PICKUP_DELAY);
+        }
+    }
+}
@@ -0,0 +1,1148 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::ops::Range;
+use std::path::Path;
+
+use serde::Deserialize;
+use thiserror::Error;
+use valence::entity::entity::Flags;
+use valence::event_loop::PacketEvent;
+use valence::ident::Ident;
+use valence::interact_block::InteractBlockEvent;
+use valence::inventory::{CursorItem, HeldItem, OpenInventory};
+use valence::prelude::*;
+use valence::protocol::packets::play::click_slot_c2s::ClickMode;
+use valence::protocol::packets::play::unlock_recipes_s2c::UpdateRecipeBookAction;
+use valence::protocol::packets::play::{
+    ClickSlotC2s, CloseHandledScreenC2s, SynchronizeRecipesS2c, UnlockRecipesS2c,
+};
+use valence::protocol::{Encode, Hand, RawBytes, VarInt, WritePacket};
+
+use crate::building::{building, CancelPlacingEvent};
+use crate::interact::sneak_bypasses;
+use crate::item_entity::{insert_into_inventory, throw_item};
+use crate::smelting::{smelting_recipe, smelting_recipe_by_id, smelting_recipe_id, FurnaceKind, SmeltingRecipe};
+
+/// Crafting in the player's inventory and at crafting tables, with recipes
+/// from the [`Recipes`] resource.
+pub struct Crafting;
+
+impl Plugin for Crafting {
+    fn build(&self, app: &mut App) {
+        if !app.world().contains_resource::<Recipes>() {
+            let recipes = match Recipes::load(RECIPES_PATH, ITEM_TAGS_PATH) {
+                Ok(recipes) => recipes,
+                Err(LoadRecipesError::Io(err)) if err.kind() == ErrorKind::NotFound => {
+                    tracing::warn!("`{RECIPES_PATH}` doesn't exist, so there is nothing to craft");
+                    Recipes::default()
+                }
+                Err(err) => {
+                    tracing::warn!("failed to load recipes, so there is nothing to craft: {err}");
+                    Recipes::default()
+                }
+            };
+            app.insert_resource(recipes);
+        }
+
+        app.add_systems(
+            Update,
+            (
+                open_crafting_tables.before(building),
+                (close_crafting_grids, craft, update_results).chain(),
+                send_recipe_book,
+            ),
+        );
+    }
+}
+
+/// The directory recipes are read from when the [`Crafting`] plugin is added,
+/// relative to the directory the server is run in. This is where they are in
+/// the vanilla server jar, so its `data` directory can be copied out as is.
+pub const RECIPES_PATH: &str = "data/minecraft/recipes";
+
+/// The directory of item tags that recipe ingredients can refer to.
+pub const ITEM_TAGS_PATH: &str = "data/minecraft/tags/items";
+
+/// Every recipe on the server, by namespaced id.
+///
+/// Insert this resource before adding the [`Crafting`] plugin to set the
+/// recipes in code, otherwise they are loaded from [`RECIPES_PATH`].
+#[derive(Resource, Debug, Clone, Default)]
+pub struct Recipes {
+    recipes: Vec<(String, Recipe)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Recipe {
+    Shaped(ShapedRecipe),
+    Shapeless(ShapelessRecipe),
+    Cooking(CookingRecipe),
+    Stonecutting(StonecuttingRecipe),
+    Smithing(SmithingRecipe),
+}
+
+/// The items that can go in one place of a recipe. An ingredient without any
+/// items only matches an empty slot.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Ingredient(pub Vec<ItemKind>);
+
+impl Ingredient {
+    pub fn matches(&self, stack: &ItemStack) -> bool {
+        if self.0.is_empty() {
+            stack.is_empty()
+        } else {
+            !stack.is_empty() && self.0.contains(&stack.item)
+        }
+    }
+}
+
+/// Which tab of the recipe book a crafting recipe is shown in.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CraftingCategory {
+    Building,
+    Redstone,
+    Equipment,
+    Misc,
+}
+
+/// Which tab of the recipe book a furnace recipe is shown in.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CookingCategory {
+    Food,
+    Blocks,
+    Misc,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CookingKind {
+    Smelting,
+    Blasting,
+    Smoking,
+    CampfireCooking,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShapedRecipe {
+    pub group: String,
+    pub category: CraftingCategory,
+    pub width: usize,
+    pub height: usize,
+    /// Row by row, `width * height` of them.
+    pub ingredients: Vec<Ingredient>,
+    pub result: ItemStack,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShapelessRecipe {
+    pub group: String,
+    pub category: CraftingCategory,
+    pub ingredients: Vec<Ingredient>,
+    pub result: ItemStack,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CookingRecipe {
+    pub kind: CookingKind,
+    pub group: String,
+    pub category: CookingCategory,
+    pub ingredient: Ingredient,
+    pub result: ItemStack,
+    pub experience: f32,
+    pub cooking_time: i32,
+}
+
+impl From<&CookingRecipe> for SmeltingRecipe {
+    fn from(recipe: &CookingRecipe) -> Self {
+        Self {
+            output: recipe.result.item,
+            experience: recipe.experience,
+            cook_time: recipe.cooking_time,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StonecuttingRecipe {
+    pub group: String,
+    pub ingredient: Ingredient,
+    pub result: ItemStack,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SmithingRecipe {
+    pub template: Ingredient,
+    pub base: Ingredient,
+    pub addition: Ingredient,
+    /// The item the base turns into. Armor trims don't have one, since they
+    /// keep the base item and only add the trim to it.
+    pub result: Option<ItemStack>,
+}
+
+impl Recipes {
+    /// Reads every recipe in a directory of vanilla recipe JSON files, with
+    /// ingredient tags from a directory of item tag files. Recipes for items
+    /// the server doesn't know about, and special recipes like banner
+    /// patterns, are skipped.
+    pub fn load<P: AsRef<Path>, Q: AsRef<Path>>(recipes_dir: P, tags_dir: Q) -> Result<Self, LoadRecipesError> {
+        let mut tags = HashMap::new();
+        match read_json_files::<RawTag>(tags_dir.as_ref()) {
+            Ok(files) => {
+                for (name, tag) in files {
+                    tags.insert(format!("minecraft:{name}"), tag.values);
+                }
+            }
+            // Recipes can do without tags, as long as they don't use any.
+            Err(LoadRecipesError::Io(err)) if err.kind() == ErrorKind::NotFound => {}
+            Err(err) => return Err(err),
+        }
+
+        let mut recipes = Self::default();
+        for (name, raw) in read_json_files::<RawRecipe>(recipes_dir.as_ref())? {
+            if let Some(recipe) = raw.resolve(&tags) {
+                recipes.insert(format!("minecraft:{name}"), recipe);
+            }
+        }
+        Ok(recipes)
+    }
+
+    /// Adds a recipe, replacing any recipe with the same id.
+    pub fn insert(&mut self, id: impl Into<String>, recipe: Recipe) {
+        let id = id.into();
+        self.recipes.retain(|(existing, _)| *existing != id);
+        self.recipes.push((id, recipe));
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Recipe> {
+        self.recipes.iter().find(|(existing, _)| existing == id).map(|(_, recipe)| recipe)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Recipe)> {
+        self.recipes.iter().map(|(id, recipe)| (id.as_str(), recipe))
+    }
+
+    /// Returns what the items in a crafting grid `width` slots wide make, or
+    /// an empty stack if they don't make anything.
+    pub fn craft(&self, grid: &[ItemStack], width: usize) -> ItemStack {
+        self.recipes
+            .iter()
+            .find_map(|(_, recipe)| match recipe {
+                Recipe::Shaped(shaped) if shaped.matches(grid, width) => Some(shaped.result.clone()),
+                Recipe::Shapeless(shapeless) if shapeless.matches(grid) => Some(shapeless.result.clone()),
+                _ => None,
+            })
+            .unwrap_or(ItemStack::EMPTY)
+    }
+
+    /// Returns what `furnace` turns `input` into. The built-in furnace
+    /// recipes are used when no furnace recipes have been loaded.
+    pub fn cooking(&self, furnace: FurnaceKind, input: ItemKind) -> Option<SmeltingRecipe> {
+        if !self.has_cooking() {
+            return smelting_recipe(furnace, input);
+        }
+        self.find_cooking(furnace, input).map(|(_, recipe)| recipe.into())
+    }
+
+    /// The id of the recipe [`cooking`](Self::cooking) returns, which is what
+    /// furnaces keep track of the recipes they've used by.
+    pub fn cooking_id(&self, furnace: FurnaceKind, input: ItemKind) -> Option<String> {
+        if !self.has_cooking() {
+            return smelting_recipe_id(furnace, input);
+        }
+        self.find_cooking(furnace, input).map(|(id, _)| id.to_owned())
+    }
+
+    /// Looks a furnace recipe up by the id [`cooking_id`](Self::cooking_id)
+    /// gave it.
+    pub fn cooking_by_id(&self, id: &str) -> Option<SmeltingRecipe> {
+        if !self.has_cooking() {
+            return smelting_recipe_by_id(id);
+        }
+        match self.get(id)? {
+            Recipe::Cooking(recipe) => Some(recipe.into()),
+            _ => None,
+        }
+    }
+
+    fn has_cooking(&self) -> bool {
+        self.recipes.iter().any(|(_, recipe)| matches!(recipe, Recipe::Cooking(_)))
+    }
+
+    fn find_cooking(&self, furnace: FurnaceKind, input: ItemKind) -> Option<(&str, &CookingRecipe)> {
+        let kind = match furnace {
+            FurnaceKind::Furnace => CookingKind::Smelting,
+            FurnaceKind::BlastFurnace => CookingKind::Blasting,
+            FurnaceKind::Smoker => CookingKind::Smoking,
+        };
+
+        self.recipes.iter().find_map(|(id, recipe)| match recipe {
+            Recipe::Cooking(cooking) if cooking.kind == kind && cooking.ingredient.0.contains(&input) => {
+                Some((id.as_str(), cooking))
+            }
+            _ => None,
+        })
+    }
+
+    /// Everything a stonecutter can make out of `input`.
+    pub fn stonecutting(&self, input: &ItemStack) -> impl Iterator<Item = &StonecuttingRecipe> + '_ {
+        let input = input.clone();
+        self.recipes.iter().filter_map(move |(_, recipe)| match recipe {
+            Recipe::Stonecutting(stonecutting) if stonecutting.ingredient.matches(&input) => Some(stonecutting),
+            _ => None,
+        })
+    }
+
+    /// The smithing recipe for the items in a smithing table, if there is one.
+    pub fn smithing(&self, template: &ItemStack, base: &ItemStack, addition: &ItemStack) -> Option<&SmithingRecipe> {
+        self.recipes.iter().find_map(|(_, recipe)| match recipe {
+            Recipe::Smithing(smithing)
+                if smithing.template.matches(template)
+                    && smithing.base.matches(base)
+                    && smithing.addition.matches(addition) =>
+            {
+                Some(smithing)
+            }
+            _ => None,
+        })
+    }
+}
+
+impl ShapedRecipe {
+    /// Whether the grid holds the recipe's pattern anywhere in it, either way
+    /// round, with nothing else around it.
+    pub fn matches(&self, grid: &[ItemStack], width: usize) -> bool {
+        let height = grid.len() / width;
+        if self.width > width || self.height > height {
+            return false;
+        }
+
+        for x in 0..=width - self.width {
+            for y in 0..=height - self.height {
+                if self.matches_at(grid, width, height, x, y, false) || self.matches_at(grid, width, height, x, y, true)
+                {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    fn matches_at(&self, grid: &[ItemStack], width: usize, height: usize, x: usize, y: usize, mirrored: bool) -> bool {
+        for gx in 0..width {
+            for gy in 0..height {
+                let stack = &grid[gy * width + gx];
+                let inside = gx >= x && gx < x + self.width && gy >= y && gy < y + self.height;
+                if !inside {
+                    if !stack.is_empty() {
+                        return false;
+                    }
+                    continue;
+                }
+
+                let mut rx = gx - x;
+                if mirrored {
+                    rx = self.width - 1 - rx;
+                }
+                if !self.ingredients[(gy - y) * self.width + rx].matches(stack) {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+}
+
+impl ShapelessRecipe {
+    /// Whether the grid holds exactly the recipe's ingredients, in any order.
+    pub fn matches(&self, grid: &[ItemStack]) -> bool {
+        let stacks = grid.iter().filter(|stack| !stack.is_empty()).collect::<Vec<_>>();
+        if stacks.len() != self.ingredients.len() {
+            return false;
+        }
+
+        let mut used = vec![false; self.ingredients.len()];
+        assign_ingredients(&stacks, &self.ingredients, &mut used)
+    }
+}
+
+/// Tries to give every stack its own ingredient, backtracking when an
+/// ingredient that accepts several of the stacks was given to the wrong one.
+fn assign_ingredients(stacks: &[&ItemStack], ingredients: &[Ingredient], used: &mut [bool]) -> bool {
+    let Some((stack, rest)) = stacks.split_first() else {
+        return true;
+    };
+
+    for i in 0..ingredients.len() {
+        if used[i] || !ingredients[i].matches(stack) {
+            continue;
+        }
+        used[i] = true;
+        if assign_ingredients(rest, ingredients, used) {
+            return true;
+        }
+        used[i] = false;
+    }
+    false
+}
+
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum LoadRecipesError {
+    #[error("Error reading recipes: {0}")]
+    Io(#[from] io::Error),
+    #[error("Invalid recipe file `{0}`: {1}")]
+    Json(String, serde_json::Error),
+}
+
+/// Reads every JSON file under `dir`, named by their path relative to it
+/// without the extension, as in `"trim_templates"` or `"oak_planks"`.
+fn read_json_files<T: for<'de> Deserialize<'de>>(dir: &Path) -> Result<Vec<(String, T)>, LoadRecipesError> {
+    let mut files = Vec::new();
+    let mut dirs = vec![dir.to_path_buf()];
+
+    while let Some(current) = dirs.pop() {
+        for entry in fs::read_dir(&current)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                dirs.push(path);
+                continue;
+            }
+            if path.extension() != Some("json".as_ref()) {
+                continue;
+            }
+
+            let name = path
+                .strip_prefix(dir)
+                .unwrap_or(&path)
+                .with_extension("")
+                .to_string_lossy()
+                .replace('\\', "/");
+            let contents = fs::read_to_string(&path)?;
+            let value = serde_json::from_str(&contents).map_err(|err| LoadRecipesError::Json(name.clone(), err))?;
+            files.push((name, value));
+        }
+    }
+
+    Ok(files)
+}
+
+#[derive(Deserialize)]
+struct RawTag {
+    values: Vec<RawTagEntry>,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(untagged)]
+enum RawTagEntry {
+    Id(String),
+    Optional { id: String },
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type")]
+enum RawRecipe {
+    #[serde(rename = "minecraft:crafting_shaped")]
+    Shaped {
+        #[serde(default)]
+        group: String,
+        category: Option<String>,
+        pattern: Vec<String>,
+        key: HashMap<char, RawIngredient>,
+        result: RawResult,
+    },
+    #[serde(rename = "minecraft:crafting_shapeless")]
+    Shapeless {
+        #[serde(default)]
+        group: String,
+        category: Option<String>,
+        ingredients: Vec<RawIngredient>,
+        result: RawResult,
+    },
+    #[serde(rename = "minecraft:smelting")]
+    Smelting(RawCooking),
+    #[serde(rename = "minecraft:blasting")]
+    Blasting(RawCooking),
+    #[serde(rename = "minecraft:smoking")]
+    Smoking(RawCooking),
+    #[serde(rename = "minecraft:campfire_cooking")]
+    CampfireCooking(RawCooking),
+    #[serde(rename = "minecraft:stonecutting")]
+    Stonecutting {
+        #[serde(default)]
+        group: String,
+        ingredient: RawIngredient,
+        result: String,
+        #[serde(default = "default_count")]
+        count: i8,
+    },
+    #[serde(rename = "minecraft:smithing_transform")]
+    SmithingTransform {
+        template: RawIngredient,
+        base: RawIngredient,
+        addition: RawIngredient,
+        result: RawResult,
+    },
+    #[serde(rename = "minecraft:smithing_trim")]
+    SmithingTrim {
+        template: RawIngredient,
+        base: RawIngredient,
+        addition: RawIngredient,
+    },
+    /// Special recipes, like dyeing armor or copying books, which have no
+    /// fixed ingredients.
+    #[serde(other)]
+    Special,
+}
+
+#[derive(Deserialize)]
+struct RawCooking {
+    #[serde(default)]
+    group: String,
+    category: Option<String>,
+    ingredient: RawIngredient,
+    result: String,
+    #[serde(default)]
+    experience: f32,
+    cookingtime: Option<i32>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawIngredient {
+    One(RawIngredientEntry),
+    AnyOf(Vec<RawIngredientEntry>),
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawIngredientEntry {
+    Item { item: String },
+    Tag { tag: String },
+}
+
+#[derive(Deserialize)]
+struct RawResult {
+    item: String,
+    #[serde(default = "default_count")]
+    count: i8,
+}
+
+fn default_count() -> i8 {
+    1
+}
+
+fn parse_item(id: &str) -> Option<ItemKind> {
+    ItemKind::from_str(id.trim_start_matches("minecraft:"))
+}
+
+/// Returns every item in a tag, following the tags it includes.
+fn tag_items(tag: &str, tags: &HashMap<String, Vec<RawTagEntry>>, depth: usize) -> Vec<ItemKind> {
+    let Some(entries) = tags.get(tag) else {
+        return Vec::new();
+    };
+    // Tags that include each other would otherwise never finish.
+    if depth > 16 {
+        return Vec::new();
+    }
+
+    let mut items = Vec::new();
+    for entry in entries {
+        let (RawTagEntry::Id(id) | RawTagEntry::Optional { id }) = entry;
+        match id.strip_prefix('#') {
+            Some(inner) => items.extend(tag_items(inner, tags, depth + 1)),
+            None => items.extend(parse_item(id)),
+        }
+    }
+    items
+}
+
+impl RawIngredient {
+    fn resolve(&self, tags: &HashMap<String, Vec<RawTagEntry>>) -> Option<Ingredient> {
+        let entries = match self {
+            Self::One(entry) => std::slice::from_ref(entry),
+            Self::AnyOf(entries) => entries.as_slice(),
+        };
+
+        let mut items = Vec::new();
+        for entry in entries {
+            match entry {
+                RawIngredientEntry::Item { item } => items.extend(parse_item(item)),
+                RawIngredientEntry::Tag { tag } => items.extend(tag_items(tag, tags, 0)),
+            }
+        }
+
+        // An ingredient nothing can fill would make the recipe match empty
+        // slots instead.
+        (!items.is_empty()).then_some(Ingredient(items))
+    }
+}
+
+impl RawResult {
+    fn resolve(&self) -> Option<ItemStack> {
+        Some(ItemStack::new(parse_item(&self.item)?, self.count, None))
+    }
+}
+
+fn crafting_category(name: Option<&str>) -> CraftingCategory {
+    match name {
+        Some("building") => CraftingCategory::Building,
+        Some("redstone") => CraftingCategory::Redstone,
+        Some("equipment") => CraftingCategory::Equipment,
+        _ => CraftingCategory::Misc,
+    }
+}
+
+fn cooking_category(name: Option<&str>) -> CookingCategory {
+    match name {
+        Some("food") => CookingCategory::Food,
+        Some("blocks") => CookingCategory::Blocks,
+        _ => CookingCategory::Misc,
+    }
+}
+
+impl RawRecipe {
+    fn resolve(self, tags: &HashMap<String, Vec<RawTagEntry>>) -> Option<Recipe> {
+        let (kind, raw) = match self {
+            Self::Shaped {
+                group,
+                category,
+                pattern,
+                key,
+                result,
+            } => {
+                // Rows and columns that are empty all the way across aren't
+                // part of the pattern, so it can go anywhere in the grid.
+                let rows = pattern
+                    .iter()
+                    .map(|row| row.chars().collect::<Vec<_>>())
+                    .collect::<Vec<_>>();
+                let used_row = |row: &Vec<char>| row.iter().any(|&c| c != ' ');
+                let first_row = rows.iter().position(used_row)?;
+                let last_row = rows.iter().rposition(used_row)?;
+                let columns = rows.iter().map(Vec::len).max()?;
+                let used_column = |x: &usize| rows.iter().any(|row| row.get(*x).is_some_and(|&c| c != ' '));
+                let first_column = (0..columns).find(used_column)?;
+                let last_column = (0..columns).rev().find(used_column)?;
+
+                let mut ingredients = Vec::new();
+                for row in &rows[first_row..=last_row] {
+                    for x in first_column..=last_column {
+                        match row.get(x).copied().unwrap_or(' ') {
+                            ' ' => ingredients.push(Ingredient::default()),
+                            c => ingredients.push(key.get(&c)?.resolve(tags)?),
+                        }
+                    }
+                }
+
+                return Some(Recipe::Shaped(ShapedRecipe {
+                    group,
+                    category: crafting_category(category.as_deref()),
+                    width: last_column - first_column + 1,
+                    height: last_row - first_row + 1,
+                    ingredients,
+                    result: result.resolve()?,
+                }));
+            }
+            Self::Shapeless {
+                group,
+                category,
+                ingredients,
+                result,
+            } => {
+                return Some(Recipe::Shapeless(ShapelessRecipe {
+                    group,
+                    category: crafting_category(category.as_deref()),
+                    ingredients: ingredients
+                        .iter()
+                        .map(|ingredient| ingredient.resolve(tags))
+                        .collect::<Option<_>>()?,
+                    result: result.resolve()?,
+                }));
+            }
+            Self::Stonecutting {
+                group,
+                ingredient,
+                result,
+                count,
+            } => {
+                return Some(Recipe::Stonecutting(StonecuttingRecipe {
+                    group,
+                    ingredient: ingredient.resolve(tags)?,
+                    result: ItemStack::new(parse_item(&result)?, count, None),
+                }));
+            }
+            Self::SmithingTransform {
+                template,
+                base,
+                addition,
+                result,
+            } => {
+                return Some(Recipe::Smithing(SmithingRecipe {
+                    template: template.resolve(tags)?,
+                    base: base.resolve(tags)?,
+                    addition: addition.resolve(tags)?,
+                    result: Some(result.resolve()?),
+                }));
+            }
+            Self::SmithingTrim {
+                template,
+                base,
+                addition,
+            } => {
+                return Some(Recipe::Smithing(SmithingRecipe {
+                    template: template.resolve(tags)?,
+                    base: base.resolve(tags)?,
+                    addition: addition.resolve(tags)?,
+                    result: None,
+                }));
+            }
+            Self::Special => return None,
+            Self::Smelting(raw) => (CookingKind::Smelting, raw),
+            Self::Blasting(raw) => (CookingKind::Blasting, raw),
+            Self::Smoking(raw) => (CookingKind::Smoking, raw),
+            Self::CampfireCooking(raw) => (CookingKind::CampfireCooking, raw),
+        };
+
+        let default_time = match kind {
+            CookingKind::Smelting => 200,
+            CookingKind::Blasting | CookingKind::Smoking => 100,
+            CookingKind::CampfireCooking => 600,
+        };
+
+        Some(Recipe::Cooking(CookingRecipe {
+            kind,
+            group: raw.group,
+            category: cooking_category(raw.category.as_deref()),
+            ingredient: raw.ingredient.resolve(tags)?,
+            result: ItemStack::new(parse_item(&raw.result)?, 1, None),
+            experience: raw.experience,
+            cooking_time: raw.cookingtime.unwrap_or(default_time),
+        }))
+    }
+}
+
+/// A crafting table a player has open. Each player gets their own grid, and
+/// whatever is left in it goes back to them when they close it.
+#[derive(Component, Debug, Copy, Clone)]
+pub struct CraftingTable {
+    pub owner: Entity,
+}
+
+/// The result slot of both the player's and the crafting table's grid.
+const RESULT_SLOT: u16 = 0;
+
+/// Slots of the 2x2 grid in the player's own inventory.
+const PLAYER_GRID: Range<u16> = 1..5;
+
+/// Slots of the 3x3 grid of a crafting table.
+const TABLE_GRID: Range<u16> = 1..10;
+
+fn grid_width(grid: &Range<u16>) -> usize {
+    if grid.len() == 4 {
+        2
+    } else {
+        3
+    }
+}
+
+fn grid_items(inventory: &Inventory, grid: &Range<u16>) -> Vec<ItemStack> {
+    grid.clone().map(|slot| inventory.slot(slot).clone()).collect()
+}
+
+/// What an item leaves behind in the grid after it is used up in a recipe.
+fn remainder(item: ItemKind) -> Option<ItemKind> {
+    match item {
+        ItemKind::WaterBucket | ItemKind::LavaBucket | ItemKind::MilkBucket | ItemKind::PowderSnowBucket => {
+            Some(ItemKind::Bucket)
+        }
+        ItemKind::HoneyBottle | ItemKind::DragonBreath => Some(ItemKind::GlassBottle),
+        _ => None,
+    }
+}
+
+/// Takes one of each ingredient out of the grid. Remainders go where their
+/// ingredient was, or are returned when that slot isn't empty.
+fn consume_ingredients(inventory: &mut Inventory, grid: &Range<u16>) -> Vec<ItemStack> {
+    let mut leftover = Vec::new();
+
+    for slot in grid.clone() {
+        let stack = inventory.slot(slot).clone();
+        if stack.is_empty() {
+            continue;
+        }
+        inventory.set_slot_amount(slot, stack.count - 1);
+
+        if let Some(item) = remainder(stack.item) {
+            let remainder = ItemStack::new(item, 1, None);
+            if inventory.slot(slot).is_empty() {
+                inventory.set_slot(slot, remainder);
+            } else {
+                leftover.push(remainder);
+            }
+        }
+    }
+
+    leftover
+}
+
+/// Whether all of `stack` fits in the player's inventory at once.
+fn fits_in_inventory(inventory: &Inventory, stack: &ItemStack) -> bool {
+    let max_stack = i32::from(stack.item.max_stack());
+    let room = (9..45)
+        .map(|slot| inventory.slot(slot))
+        .map(|existing| {
+            if existing.is_empty() {
+                max_stack
+            } else if existing.item == stack.item && existing.nbt == stack.nbt {
+                max_stack - i32::from(existing.count)
+            } else {
+                0
+            }
+        })
+        .sum::<i32>();
+
+    room >= i32::from(stack.count)
+}
+
+/// Puts items back in the player's inventory, and throws out whatever
+/// doesn't fit.
+fn give_back(
+    commands: &mut Commands,
+    inventory: &mut Inventory,
+    stack: ItemStack,
+    position: DVec3,
+    look: Look,
+    layer: EntityLayerId,
+) {
+    let taken = insert_into_inventory(inventory, &stack);
+    if taken < stack.count {
+        let rest = ItemStack::new(stack.item, stack.count - taken, stack.nbt);
+        throw_item(commands, position, look, layer, rest);
+    }
+}
+
+fn open_crafting_tables(
+    clients: Query<(&Inventory, &HeldItem, &Flags, &GameMode, &EntityLayerId)>,
+    layers: Query<&ChunkLayer>,
+    mut events: EventReader<InteractBlockEvent>,
+    mut cancel: EventWriter<CancelPlacingEvent>,
+    mut commands: Commands,
+) {
+    for event in events.read() {
+        if event.hand != Hand::Main {
+            continue;
+        }
+        let Ok((inventory, held, flags, game_mode, layer_id)) = clients.get(event.client) else {
+            continue;
+        };
+        if *game_mode == GameMode::Spectator || sneak_bypasses(flags, inventory, held) {
+            continue;
+        }
+        let Ok(layer) = layers.get(layer_id.0) else {
+            continue;
+        };
+        if !layer
+            .block(event.position)
+            .is_some_and(|b| b.state.to_kind() == BlockKind::CraftingTable)
+        {
+            continue;
+        }
+
+        cancel.send(CancelPlacingEvent { client: event.client });
+
+        let table = commands
+            .spawn((
+                Inventory::with_title(InventoryKind::Crafting, Text::translate("container.crafting", [])),
+                CraftingTable { owner: event.client },
+            ))
+            .id();
+        commands.entity(event.client).insert(OpenInventory::new(table));
+    }
+}
+
+/// Gives back what players left in a crafting grid when they close it,
+/// dropping anything that doesn't fit in their inventory.
+fn close_crafting_grids(
+    mut clients: Query<(&mut Inventory, Option<&OpenInventory>, &Position, &Look, &EntityLayerId), With<Client>>,
+    mut tables: Query<(Entity, &mut Inventory, &CraftingTable), Without<Client>>,
+    mut packets: EventReader<PacketEvent>,
+    mut commands: Commands,
+) {
+    for (entity, mut table, crafting_table) in &mut tables {
+        let owner = clients.get_mut(crafting_table.owner);
+        if owner.as_ref().is_ok_and(|(_, open, ..)| open.is_some_and(|open| open.entity == entity)) {
+            continue;
+        }
+
+        if let Ok((mut inventory, _, position, look, &layer)) = owner {
+            for slot in TABLE_GRID {
+                let stack = table.replace_slot(slot, ItemStack::EMPTY);
+                if !stack.is_empty() {
+                    give_back(&mut commands, &mut inventory, stack, position.0, *look, layer);
+                }
+            }
+        }
+        commands.entity(entity).insert(Despawned);
+    }
+
+    // The player's own grid empties when they close their inventory screen.
+    for packet in packets.read() {
+        let Some(pkt) = packet.decode::<CloseHandledScreenC2s>() else {
+            continue;
+        };
+        if pkt.window_id != 0 {
+            continue;
+        }
+        let Ok((mut inventory, _, position, look, &layer)) = clients.get_mut(packet.client) else {
+            continue;
+        };
+
+        for slot in PLAYER_GRID {
+            let stack = inventory.replace_slot(slot, ItemStack::EMPTY);
+            if !stack.is_empty() {
+                give_back(&mut commands, &mut inventory, stack, position.0, *look, layer);
+            }
+        }
+    }
+}
+
+/// Crafts when a player takes from a result slot. Clicking puts one craft on
+/// the cursor and shift-clicking crafts as many as fit in the inventory.
+///
+/// Valence doesn't accept these clicks itself, since items appear out of
+/// nowhere, so they are read straight from the packets and the client is
+/// brought back in line with what was really crafted.
+fn craft(
+    recipes: Res<Recipes>,
+    mut clients: Query<(&mut Inventory, &mut CursorItem, Option<&OpenInventory>, &Position, &Look, &EntityLayerId), With<Client>>,
+    mut tables: Query<&mut Inventory, (With<CraftingTable>, Without<Client>)>,
+    mut packets: EventReader<PacketEvent>,
+    mut commands: Commands,
+) {
+    for packet in packets.read() {
+        let Some(pkt) = packet.decode::<ClickSlotC2s>() else {
+            continue;
+        };
+        if pkt.slot_idx != RESULT_SLOT as i16 {
+            continue;
+        }
+        let Ok((mut inventory, mut cursor, open, position, look, &layer)) = clients.get_mut(packet.client) else {
+            continue;
+        };
+
+        let (mut table, grid) = if pkt.window_id == 0 {
+            (None, PLAYER_GRID)
+        } else {
+            let Some(table) = open.and_then(|open| tables.get_mut(open.entity).ok()) else {
+                continue;
+            };
+            (Some(table), TABLE_GRID)
+        };
+        let width = grid_width(&grid);
+
+        let mut leftover = Vec::new();
+        match pkt.mode {
+            ClickMode::Click => {
+                let grid_inventory = table.as_deref_mut().unwrap_or(&mut *inventory);
+                let result = recipes.craft(&grid_items(grid_inventory, &grid), width);
+                if result.is_empty() {
+                    continue;
+                }
+                if !cursor.0.is_empty()
+                    && (cursor.0.item != result.item
+                        || cursor.0.nbt != result.nbt
+                        || i32::from(cursor.0.count) + i32::from(result.count) > i32::from(result.item.max_stack()))
+                {
+                    continue;
+                }
+
+                leftover.extend(consume_ingredients(grid_inventory, &grid));
+                if cursor.0.is_empty() {
+                    cursor.0 = result;
+                } else {
+                    cursor.0.count += result.count;
+                }
+            }
+            ClickMode::ShiftClick => {
+                let mut first = None;
+                // A full grid can't be crafted more than a stack's worth of
+                // times.
+                for _ in 0..64 {
+                    let grid_inventory = table.as_deref_mut().unwrap_or(&mut *inventory);
+                    let result = recipes.craft(&grid_items(grid_inventory, &grid), width);
+                    // Stop once the ingredients left make something else.
+                    if result.is_empty() || first.get_or_insert(result.item) != &result.item {
+                        break;
+                    }
+                    if !fits_in_inventory(&inventory, &result) {
+                        break;
+                    }
+
+                    let grid_inventory = table.as_deref_mut().unwrap_or(&mut *inventory);
+                    leftover.extend(consume_ingredients(grid_inventory, &grid));
+                    insert_into_inventory(&mut inventory, &result);
+                }
+            }
+            _ => continue,
+        }
+
+        for stack in leftover {
+            give_back(&mut commands, &mut inventory, stack, position.0, *look, layer);
+        }
+    }
+}
+
+/// Shows what the items in a crafting grid make in its result slot.
+fn update_results(
+    recipes: Res<Recipes>,
+    mut clients: Query<&mut Inventory, (With<Client>, Changed<Inventory>)>,
+    mut tables: Query<&mut Inventory, (With<CraftingTable>, Without<Client>, Changed<Inventory>)>,
+) {
+    let grids = clients
+        .iter_mut()
+        .map(|inventory| (inventory, PLAYER_GRID))
+        .chain(tables.iter_mut().map(|inventory| (inventory, TABLE_GRID)));
+
+    for (mut inventory, grid) in grids {
+        let result = recipes.craft(&grid_items(&inventory, &grid), grid_width(&grid));
+        if *inventory.slot(RESULT_SLOT) != result {
+            inventory.set_slot(RESULT_SLOT, result);
+        }
+    }
+}
+
+fn encode_ingredient(buf: &mut Vec<u8>, ingredient: &Ingredient) -> anyhow::Result<()> {
+    VarInt(ingredient.0.len() as i32).encode(&mut *buf)?;
+    for &item in &ingredient.0 {
+        ItemStack::new(item, 1, None).encode(&mut *buf)?;
+    }
+    Ok(())
+}
+
+fn encode_crafting_category(buf: &mut Vec<u8>, category: CraftingCategory) -> anyhow::Result<()> {
+    let id = match category {
+        CraftingCategory::Building => 0,
+        CraftingCategory::Redstone => 1,
+        CraftingCategory::Equipment => 2,
+        CraftingCategory::Misc => 3,
+    };
+    VarInt(id).encode(buf)
+}
+
+/// Writes every recipe the way the recipe book packet lists them.
+fn encode_recipes(recipes: &Recipes) -> anyhow::Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    VarInt(recipes.recipes.len() as i32).encode(&mut buf)?;
+
+    for (id, recipe) in &recipes.recipes {
+        let kind = match recipe {
+            Recipe::Shaped(_) => "minecraft:crafting_shaped",
+            Recipe::Shapeless(_) => "minecraft:crafting_shapeless",
+            Recipe::Cooking(cooking) => match cooking.kind {
+                CookingKind::Smelting => "minecraft:smelting",
+                CookingKind::Blasting => "minecraft:blasting",
+                CookingKind::Smoking => "minecraft:smoking",
+                CookingKind::CampfireCooking => "minecraft:campfire_cooking",
+            },
+            Recipe::Stonecutting(_) => "minecraft:stonecutting",
+            Recipe::Smithing(smithing) if smithing.result.is_some() => "minecraft:smithing_transform",
+            Recipe::Smithing(_) => "minecraft:smithing_trim",
+        };
+        kind.encode(&mut buf)?;
+        id.as_str().encode(&mut buf)?;
+
+        match recipe {
+            Recipe::Shaped(shaped) => {
+                VarInt(shaped.width as i32).encode(&mut buf)?;
+                VarInt(shaped.height as i32).encode(&mut buf)?;
+                shaped.group.as_str().encode(&mut buf)?;
+                encode_crafting_category(&mut buf, shaped.category)?;
+                for ingredient in &shaped.ingredients {
+                    encode_ingredient(&mut buf, ingredient)?;
+                }
+                shaped.result.encode(&mut buf)?;
+                // Whether unlocking it shows a toast.
+                true.encode(&mut buf)?;
+            }
+            Recipe::Shapeless(shapeless) => {
+                shapeless.group.as_str().encode(&mut buf)?;
+                encode_crafting_category(&mut buf, shapeless.category)?;
+                VarInt(shapeless.ingredients.len() as i32).encode(&mut buf)?;
+                for ingredient in &shapeless.ingredients {
+                    encode_ingredient(&mut buf, ingredient)?;
+                }
+                shapeless.result.encode(&mut buf)?;
+            }
+            Recipe::Cooking(cooking) => {
+                cooking.group.as_str().encode(&mut buf)?;
+                let category = match cooking.category {
+                    CookingCategory::Food => 0,
+                    CookingCategory::Blocks => 1,
+                    CookingCategory::Misc => 2,
+                };
+                VarInt(category).encode(&mut buf)?;
+                encode_ingredient(&mut buf, &cooking.ingredient)?;
+                cooking.result.encode(&mut buf)?;
+                cooking.experience.encode(&mut buf)?;
+                VarInt(cooking.cooking_time).encode(&mut buf)?;
+            }
+            Recipe::Stonecutting(stonecutting) => {
+                stonecutting.group.as_str().encode(&mut buf)?;
+                encode_ingredient(&mut buf, &stonecutting.ingredient)?;
+                stonecutting.result.encode(&mut buf)?;
+            }
+            Recipe::Smithing(smithing) => {
+                encode_ingredient(&mut buf, &smithing.template)?;
+                encode_ingredient(&mut buf, &smithing.base)?;
+                encode_ingredient(&mut buf, &smithing.addition)?;
+                if let Some(result) = &smithing.result {
+                    result.encode(&mut buf)?;
+                }
+            }
+        }
+    }
+
+    Ok(buf)
+}
+
+/// Tells joining players about every recipe, and unlocks all of them in their
+/// recipe book.
+fn send_recipe_book(recipes: Res<Recipes>, mut clients: Query<&mut Client, Added<Client>>) {
+    if clients.is_empty() {
+        return;
+    }
+
+    let encoded = match encode_recipes(&recipes) {
+        Ok(encoded) => encoded,
+        Err(err) => {
+            tracing::warn!("failed to encode recipes: {err}");
+            return;
+        }
+    };
+    let ids = recipes
+        .recipes
+        .iter()
+        .filter_map(|(id, _)| Ident::new(Cow::Borrowed(id.as_str())).ok())
+        .collect::<Vec<_>>();
+
+    for mut client in &mut clients {
+        client.write_packet(&SynchronizeRecipesS2c {
+            recipes: RawBytes(&encoded),
+        });
+        client.write_packet(&UnlockRecipesS2c {
+            action: UpdateRecipeBookAction::Init {
+                recipe_ids: ids.clone(),
+            },
+            crafting_recipe_book_open: false,
+            crafting_recipe_book_filter_active: false,
+            smelting_recipe_book_open: false,
+            smelting_recipe_book_filter_active: false,
+            blast_furnace_recipe_book_open: false,
+            blast_furnace_recipe_book_filter_active: false,
+            smoker_recipe_book_open: false,
+            smoker_recipe_book_filter_active: false,
+            recipe_ids: ids.clone(),
+        });
+    }
+}
@@ -0,0 +1,411 @@
+use valence::entity::active_status_effects::ActiveStatusEffects;
+use valence::entity::living::Health;
+use valence::entity::player::AbsorptionAmount;
+use valence::prelude::*;
+use valence::protocol::status_effects::StatusEffect;
+
+use crate::death::DeathEvent;
+use crate::item::enchantment_level;
+
+/// Runs every [`DamageEvent`] through invulnerability frames, armour,
+/// enchantments, resistance and absorption before taking it off the target's
+/// [`Health`].
+///
+/// Other plugins can change or cancel damage by editing [`PendingDamage`] from
+/// a system placed between two [`DamageStage`]s, for example
+/// `.after(DamageStage::Armor).before(DamageStage::Enchantments)`.
+pub struct Damage;
+
+impl Plugin for Damage {
+    fn build(&self, app: &mut App) {
+        app.add_event::<DamageEvent>()
+            .init_resource::<PendingDamage>()
+            .configure_sets(
+                Update,
+                (
+                    DamageStage::Collect,
+                    DamageStage::Invulnerability,
+                    DamageStage::Armor,
+                    DamageStage::Enchantments,
+                    DamageStage::Resistance,
+                    DamageStage::Absorption,
+                    DamageStage::Apply,
+                )
+                    .chain(),
+            )
+            .add_systems(
+                Update,
+                (
+                    init_entities,
+                    collect.in_set(DamageStage::Collect),
+                    invulnerability.in_set(DamageStage::Invulnerability),
+                    armor.in_set(DamageStage::Armor),
+                    enchantments.in_set(DamageStage::Enchantments),
+                    resistance.in_set(DamageStage::Resistance),
+                    absorption.in_set(DamageStage::Absorption),
+                    apply.in_set(DamageStage::Apply),
+                ),
+            );
+    }
+}
+
+/// Deals damage to an entity with [`Health`].
+#[derive(Event, Debug, Copy, Clone)]
+pub struct DamageEvent {
+    pub target: Entity,
+    pub amount: f32,
+    pub source: DamageSource,
+}
+
+/// Where a piece of damage came from.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct DamageSource {
+    pub cause: DamageCause,
+    /// The entity responsible for the damage, credited with the kill if it is
+    /// fatal.
+    pub attacker: Option<Entity>,
+}
+
+impl DamageSource {
+    pub fn new(cause: DamageCause) -> Self {
+        Self { cause, attacker: None }
+    }
+
+    pub fn attack(cause: DamageCause, attacker: Entity) -> Self {
+        Self { cause, attacker: Some(attacker) }
+    }
+}
+
+impl From<DamageCause> for DamageSource {
+    fn from(cause: DamageCause) -> Self {
+        Self::new(cause)
+    }
+}
+
+/// What kind of thing dealt damage.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DamageCause {
+    PlayerAttack,
+    MobAttack,
+    Explosion,
+    Fall,
+    Drowning,
+    Suffocation,
+    /// Squashed by too many entities in one place.
+    Cramming,
+    Lava,
+    /// Standing in a fire block.
+    InFire,
+    /// Burning after leaving fire or lava.
+    OnFire,
+    Cactus,
+    SweetBerryBush,
+    Void,
+    Starvation,
+    Magic,
+    Generic,
+}
+
+impl DamageCause {
+    /// Whether worn armour does nothing against this damage.
+    pub fn bypasses_armor(self) -> bool {
+        matches!(
+            self,
+            DamageCause::Fall
+                | DamageCause::Drowning
+                | DamageCause::Suffocation
+                | DamageCause::Cramming
+                | DamageCause::OnFire
+                | DamageCause::Void
+                | DamageCause::Starvation
+                | DamageCause::Magic
+        )
+    }
+
+    /// Whether the Resistance effect does nothing against this damage.
+    pub fn bypasses_resistance(self) -> bool {
+        self == DamageCause::Void
+    }
+
+    /// Whether protection enchantments do nothing against this damage.
+    pub fn bypasses_enchantments(self) -> bool {
+        self == DamageCause::Void
+    }
+
+    /// Whether this damage still hurts players in creative mode.
+    pub fn bypasses_creative(self) -> bool {
+        self == DamageCause::Void
+    }
+
+    pub fn is_fire(self) -> bool {
+        matches!(self, DamageCause::Lava | DamageCause::InFire | DamageCause::OnFire)
+    }
+}
+
+/// The stages damage goes through, in the order they run.
+#[derive(SystemSet, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum DamageStage {
+    /// Damage events are moved into [`PendingDamage`].
+    Collect,
+    /// Damage during the invulnerability frames after a hit is cancelled,
+    /// unless it is stronger than that hit.
+    Invulnerability,
+    Armor,
+    /// Protection enchantments on worn armour.
+    Enchantments,
+    /// The Resistance status effect.
+    Resistance,
+    /// Absorption hearts are used up before health.
+    Absorption,
+    /// Whatever is left is taken off the target's health.
+    Apply,
+}
+
+/// Damage that is on its way through the [`DamageStage`]s this tick.
+#[derive(Resource, Debug, Default)]
+pub struct PendingDamage(pub Vec<PendingHit>);
+
+#[derive(Debug, Copy, Clone)]
+pub struct PendingHit {
+    pub target: Entity,
+    pub amount: f32,
+    pub source: DamageSource,
+    /// Cancelled hits are skipped by every later stage and never applied.
+    pub cancelled: bool,
+}
+
+impl PendingDamage {
+    /// Iterates over the hits that haven't been cancelled.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut PendingHit> {
+        self.0.iter_mut().filter(|hit| !hit.cancelled && hit.amount > 0.0)
+    }
+}
+
+/// Number of ticks after being hurt during which weaker hits are ignored.
+const INVULNERABILITY_TICKS: i64 = 10;
+
+#[derive(Component, Debug, Default, Copy, Clone)]
+struct Invulnerability {
+    last_hurt_tick: i64,
+    last_amount: f32,
+}
+
+fn init_entities(entities: Query<Entity, Added<Health>>, mut commands: Commands) {
+    for entity in &entities {
+        commands.entity(entity).insert(Invulnerability {
+            last_hurt_tick: i64::MIN / 2,
+            last_amount: 0.0,
+        });
+    }
+}
+
+fn collect(mut events: EventReader<DamageEvent>, mut pending: ResMut<PendingDamage>) {
+    pending.0.extend(events.read().map(|event| PendingHit {
+        target: event.target,
+        amount: event.amount,
+        source: event.source,
+        cancelled: false,
+    }));
+}
+
+fn invulnerability(
+    server: Res<Server>,
+    mut pending: ResMut<PendingDamage>,
+    mut targets: Query<(&mut Invulnerability, &Health, Option<&GameMode>)>,
+) {
+    let tick = server.current_tick();
+
+    for hit in pending.iter_mut() {
+        let Ok((mut invulnerability, health, game_mode)) = targets.get_mut(hit.target) else {
+            hit.cancelled = true;
+            continue;
+        };
+
+        if health.0 <= 0.0 {
+            hit.cancelled = true;
+            continue;
+        }
+
+        match game_mode {
+            Some(GameMode::Spectator) => {
+                hit.cancelled = true;
+                continue;
+            }
+            Some(GameMode::Creative) if !hit.source.cause.bypasses_creative() => {
+                hit.cancelled = true;
+                continue;
+            }
+            _ => {}
+        }
+
+        // A stronger hit during the invulnerability frames only deals the
+        // difference.
+        if tick - invulnerability.last_hurt_tick < INVULNERABILITY_TICKS {
+            if hit.amount <= invulnerability.last_amount {
+                hit.cancelled = true;
+                continue;
+            }
+            let amount = hit.amount;
+            hit.amount -= invulnerability.last_amount;
+            invulnerability.last_amount = amount;
+        } else {
+            invulnerability.last_hurt_tick = tick;
+            invulnerability.last_amount = hit.amount;
+        }
+    }
+}
+
+/// Armour points and toughness given by a piece of armour.
+fn armor_stats(item: ItemKind) -> (f32, f32) {
+    match item {
+        ItemKind::LeatherHelmet => (1.0, 0.0),
+        ItemKind::LeatherChestplate => (3.0, 0.0),
+        ItemKind::LeatherLeggings => (2.0, 0.0),
+        ItemKind::LeatherBoots => (1.0, 0.0),
+
+        ItemKind::ChainmailHelmet => (2.0, 0.0),
+        ItemKind::ChainmailChestplate => (5.0, 0.0),
+        ItemKind::ChainmailLeggings => (4.0, 0.0),
+        ItemKind::ChainmailBoots => (1.0, 0.0),
+
+        ItemKind::GoldenHelmet => (2.0, 0.0),
+        ItemKind::GoldenChestplate => (5.0, 0.0),
+        ItemKind::GoldenLeggings => (3.0, 0.0),
+        ItemKind::GoldenBoots => (1.0, 0.0),
+
+        ItemKind::IronHelmet => (2.0, 0.0),
+        ItemKind::IronChestplate => (6.0, 0.0),
+        ItemKind::IronLeggings => (5.0, 0.0),
+        ItemKind::IronBoots => (2.0, 0.0),
+
+        ItemKind::DiamondHelmet => (3.0, 2.0),
+        ItemKind::DiamondChestplate => (8.0, 2.0),
+        ItemKind::DiamondLeggings => (6.0, 2.0),
+        ItemKind::DiamondBoots => (3.0, 2.0),
+
+        ItemKind::NetheriteHelmet => (3.0, 3.0),
+        ItemKind::NetheriteChestplate => (8.0, 3.0),
+        ItemKind::NetheriteLeggings => (6.0, 3.0),
+        ItemKind::NetheriteBoots => (3.0, 3.0),
+
+        ItemKind::TurtleHelmet => (2.0, 0.0),
+
+        _ => (0.0, 0.0),
+    }
+}
+
+/// Player inventory slots for the helmet, chestplate, leggings and boots.
+const ARMOR_SLOTS: std::ops::RangeInclusive<u16> = 5..=8;
+
+fn armor(mut pending: ResMut<PendingDamage>, targets: Query<&Inventory>) {
+    for hit in pending.iter_mut() {
+        if hit.source.cause.bypasses_armor() {
+            continue;
+        }
+        let Ok(inventory) = targets.get(hit.target) else {
+            continue;
+        };
+
+        let (points, toughness) = ARMOR_SLOTS
+            .map(|slot| armor_stats(inventory.slot(slot).item))
+            .fold((0.0, 0.0), |(points, toughness), (p, t)| (points + p, toughness + t));
+
+        let effective = (points - 4.0 * hit.amount / (toughness + 2.0))
+            .max(points / 5.0)
+            .min(20.0);
+        hit.amount *= 1.0 - effective / 25.0;
+    }
+}
+
+fn enchantments(mut pending: ResMut<PendingDamage>, targets: Query<&Inventory>) {
+    for hit in pending.iter_mut() {
+        if hit.source.cause.bypasses_enchantments() {
+            continue;
+        }
+        let Ok(inventory) = targets.get(hit.target) else {
+            continue;
+        };
+
+        let cause = hit.source.cause;
+        let protection: i32 = ARMOR_SLOTS
+            .map(|slot| {
+                let stack = inventory.slot(slot);
+                let mut protection = enchantment_level(stack, "minecraft:protection");
+                if cause.is_fire() {
+                    protection += 2 * enchantment_level(stack, "minecraft:fire_protection");
+                }
+                if cause == DamageCause::Explosion {
+                    protection += 2 * enchantment_level(stack, "minecraft:blast_protection");
+                }
+                if cause == DamageCause::Fall {
+                    protection += 3 * enchantment_level(stack, "minecraft:feather_falling");
+                }
+                protection
+            })
+            .sum();
+
+        hit.amount *= 1.0 - protection.min(20) as f32 / 25.0;
+    }
+}
+
+fn resistance(mut pending: ResMut<PendingDamage>, targets: Query<&ActiveStatusEffects>) {
+    for hit in pending.iter_mut() {
+        if hit.source.cause.bypasses_resistance() {
+            continue;
+        }
+        let Some(effect) = targets
+            .get(hit.target)
+            .ok()
+            .and_then(|effects| effects.get_current_effect(StatusEffect::Resistance))
+        else {
+            continue;
+        };
+
+        let level = f32::from(effect.amplifier()) + 1.0;
+        hit.amount *= (1.0 - 0.2 * level).max(0.0);
+    }
+}
+
+fn absorption(mut pending: ResMut<PendingDamage>, mut targets: Query<&mut AbsorptionAmount>) {
+    for hit in pending.iter_mut() {
+        let Ok(mut absorption) = targets.get_mut(hit.target) else {
+            continue;
+        };
+        if absorption.0 <= 0.0 {
+            continue;
+        }
+
+        let absorbed = absorption.0.min(hit.amount);
+        absorption.0 -= absorbed;
+        hit.amount -= absorbed;
+    }
+}
+
+fn apply(
+    mut pending: ResMut<PendingDamage>,
+    mut targets: Query<&mut Health>,
+    mut death: EventWriter<DeathEvent>,
+) {
+    for hit in pending.0.drain(..) {
+        if hit.cancelled || hit.amount <= 0.0 {
+            continue;
+        }
+        let Ok(mut health) = targets.get_mut(hit.target) else {
+            continue;
+        };
+        if health.0 <= 0.0 {
+            continue;
+        }
+
+        if health.0 > hit.amount {
+            health.0 -= hit.amount;
+        } else {
+            health.0 = 0.0;
+            death.send(DeathEvent {
+                entity: hit.target,
+                killed_by: hit.source.attacker,
+                cause: hit.source.cause,
+            });
+        }
+    }
+}
@@ -1,10 +1,65 @@
 
-use valence::{entity::living::Health, message::SendMessage, prelude::*};
+use valence::entity::entity::CustomName;
+use valence::entity::living::Health;
+use valence::entity::EntityStatuses;
+use valence::inventory::HeldItem;
+use valence::message::SendMessage;
+use valence::prelude::*;
+
+use crate::damage::DamageCause;
+use crate::item;
+use crate::rules::GameRules;
 
 #[derive(Event, Debug, Copy, Clone)]
 pub struct DeathEvent {
     pub entity: Entity,
     pub killed_by: Option<Entity>,
+    pub cause: DamageCause,
+}
+
+/// Builds the translation key vanilla uses for a death message, such as
+/// `death.attack.lava.player`.
+fn translation_key(cause: DamageCause, has_killer: bool, has_weapon: bool) -> String {
+    let base = match cause {
+        DamageCause::PlayerAttack if has_killer => "death.attack.player",
+        DamageCause::MobAttack if has_killer => "death.attack.mob",
+        DamageCause::PlayerAttack | DamageCause::MobAttack | DamageCause::Generic => "death.attack.generic",
+        DamageCause::Fall if !has_killer => return "death.fell.accident.generic".into(),
+        DamageCause::Fall => "death.attack.fall",
+        DamageCause::Explosion => "death.attack.explosion",
+        DamageCause::Drowning => "death.attack.drown",
+        DamageCause::Suffocation => "death.attack.inWall",
+        DamageCause::Cramming => "death.attack.cramming",
+        DamageCause::Lava => "death.attack.lava",
+        DamageCause::InFire => "death.attack.inFire",
+        DamageCause::OnFire => "death.attack.onFire",
+        DamageCause::Cactus => "death.attack.cactus",
+        DamageCause::SweetBerryBush => "death.attack.sweetBerryBush",
+        DamageCause::Void => "death.attack.outOfWorld",
+        DamageCause::Starvation => "death.attack.starve",
+        DamageCause::Magic => "death.attack.magic",
+    };
+
+    match cause {
+        DamageCause::PlayerAttack | DamageCause::MobAttack if has_weapon => format!("{base}.item"),
+        DamageCause::PlayerAttack | DamageCause::MobAttack => base.into(),
+        _ if has_killer => format!("{base}.player"),
+        _ => base.into(),
+    }
+}
+
+/// Builds the death message for an entity killed by `cause`. The killer's
+/// weapon is only mentioned if it has been renamed, like in vanilla.
+pub fn death_message(cause: DamageCause, victim: Text, killer: Option<Text>, weapon: Option<Text>) -> Text {
+    let key = translation_key(cause, killer.is_some(), killer.is_some() && weapon.is_some());
+
+    let mut with = vec![victim];
+    if let Some(killer) = killer {
+        with.push(killer);
+        with.extend(weapon);
+    }
+
+    Text::translate(key, with)
 }
 
 pub struct Death;
@@ -12,33 +67,72 @@ pub struct Death;
 impl Plugin for Death {
     fn build(&self, app: &mut App) {
         app.add_event::<DeathEvent>()
-            .add_systems(Update, handle_death_event);
+            .init_resource::<GameRules>()
+            .add_systems(Update, (handle_death_event, despawn_dead_entities));
     }
 }
 
+/// Ticks a dead entity stays around for so its death animation can play.
+const DEATH_ANIMATION_TICKS: i64 = 20;
+
+/// An entity that has died and is playing its death animation.
+#[derive(Component, Debug, Copy, Clone)]
+pub struct Dying {
+    pub despawn_tick: i64,
+}
+
 fn handle_death_event(
+    server: Res<Server>,
+    rules: Res<GameRules>,
     mut events: EventReader<DeathEvent>,
     mut clients: Query<(&mut Client, &Username, &EntityLayerId)>,
-    entities: Query<&EntityLayerId, (With<Health>, Without<Client>)>,
+    killers: Query<(Option<&Username>, Option<&CustomName>, &EntityKind, Option<&Inventory>, Option<&HeldItem>)>,
+    mut entities: Query<&mut EntityStatuses, (With<Health>, Without<Client>, Without<Dying>)>,
     mut layers: Query<&mut EntityLayer>,
     mut commands: Commands,
 ) {
     for event in events.read() {
-        if let Some([(mut client, killed_name, killed_layer_id), (_, killer_name, killer_layer_id)]) = event.killed_by.and_then(|k| clients.get_many_mut([event.entity, k]).ok()) {
-            if killer_layer_id != killed_layer_id {
-                continue;
-            }
-            client.kill(format!("You were killed by {}", killer_name.0));
-            if let Ok(mut layer) = layers.get_mut(killer_layer_id.0) {
-                layer.send_chat_message(format!("{} was killed by {}", killed_name.0, killer_name.0).color(Color::DARK_AQUA));
+        if let Ok(mut statuses) = entities.get_mut(event.entity) {
+            statuses.trigger(EntityStatus::PlayDeathSoundOrAddProjectileHitParticles);
+            commands.entity(event.entity).insert(Dying {
+                despawn_tick: server.current_tick() + DEATH_ANIMATION_TICKS,
+            });
+            continue;
+        }
+
+        let (killer, weapon) = match event.killed_by.and_then(|killer| killers.get(killer).ok()) {
+            Some((username, custom_name, kind, inventory, held)) => {
+                let name = match (username, custom_name.and_then(|name| name.0.clone())) {
+                    (Some(username), _) => Text::text(username.0.clone()),
+                    (None, Some(name)) => name,
+                    (None, None) => Text::translate(kind.translation_key().unwrap_or("entity.notFound"), []),
+                };
+                let weapon = inventory.zip(held).and_then(|(inventory, held)| item::custom_name(inventory.slot(held.slot())));
+                (Some(name), weapon)
             }
-        } else if let Ok((mut client, killed_name, layer_id)) = clients.get_mut(event.entity) {
-            client.kill("You were killed by an unknown entity");
+            None => (None, None),
+        };
+
+        let Ok((mut client, killed_name, layer_id)) = clients.get_mut(event.entity) else {
+            continue;
+        };
+
+        let message = death_message(event.cause, Text::text(killed_name.0.clone()), killer, weapon);
+
+        client.kill(message.clone());
+
+        if rules.show_death_messages {
             if let Ok(mut layer) = layers.get_mut(layer_id.0) {
-                layer.send_chat_message(format!("{} was killed", killed_name.0).color(Color::DARK_AQUA));
+                layer.send_chat_message(message);
             }
-        } else if let Ok(_) = entities.get(event.entity) {
-            commands.entity(event.entity).insert(Despawned);
+        }
+    }
+}
+
+fn despawn_dead_entities(server: Res<Server>, entities: Query<(Entity, &Dying)>, mut commands: Commands) {
+    for (entity, dying) in &entities {
+        if server.current_tick() >= dying.despawn_tick {
+            commands.entity(entity).insert(Despawned);
         }
     }
 }
@@ -0,0 +1,422 @@
+use valence::abilities::PlayerAbilitiesFlags;
+use valence::entity::entity::{Air, Flags};
+use valence::math::Aabb;
+use valence::movement::MovementEvent;
+use valence::prelude::*;
+
+use crate::block_behavior::block_pos;
+use crate::damage::{DamageCause, DamageEvent};
+use crate::players::EYE_HEIGHT;
+
+/// Damage dealt to players by the world around them: falling, drowning,
+/// suffocating, burning, cacti and berry bushes, and the void.
+pub struct Environment;
+
+impl Plugin for Environment {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                init_clients,
+                track_falling,
+                (breathe, suffocate, touch_blocks, burn, void).after(track_falling),
+            ),
+        );
+    }
+}
+
+/// Maximum air supply of a player, in ticks.
+pub const MAX_AIR: i32 = 300;
+
+/// Distance a player has fallen since they were last on the ground.
+#[derive(Component, Debug, Default, Copy, Clone)]
+pub struct FallDistance(pub f64);
+
+/// The number of ticks an entity keeps burning for after leaving fire or lava.
+#[derive(Component, Debug, Default, Copy, Clone)]
+pub struct FireTicks(pub i32);
+
+fn init_clients(clients: Query<Entity, Added<Client>>, mut commands: Commands) {
+    for client in &clients {
+        commands.entity(client).insert((
+            FallDistance::default(),
+            FireTicks::default(),
+        ));
+    }
+}
+
+/// Whether a player in this game mode can be hurt by the environment.
+fn vulnerable(game_mode: GameMode) -> bool {
+    game_mode == GameMode::Survival || game_mode == GameMode::Adventure
+}
+
+pub fn track_falling(
+    mut clients: Query<(
+        &mut FallDistance,
+        &GameMode,
+        &PlayerAbilitiesFlags,
+        &EntityLayerId,
+    )>,
+    layers: Query<&ChunkLayer>,
+    mut movements: EventReader<MovementEvent>,
+    mut damage_events: EventWriter<DamageEvent>,
+) {
+    for movement in movements.read() {
+        let Ok((mut fall, game_mode, abilities, layer_id)) =
+            clients.get_mut(movement.client)
+        else {
+            continue;
+        };
+        let Ok(layer) = layers.get(layer_id.0) else {
+            continue;
+        };
+
+        let feet = layer.block(block_pos(movement.position)).map(|b| b.state.to_kind());
+
+        // Water, cobwebs, ladders and the like all stop a fall without damage.
+        if abilities.flying()
+            || matches!(
+                feet,
+                Some(
+                    BlockKind::Water
+                        | BlockKind::BubbleColumn
+                        | BlockKind::Cobweb
+                        | BlockKind::Ladder
+                        | BlockKind::Vine
+                        | BlockKind::Scaffolding
+                        | BlockKind::PowderSnow
+                        | BlockKind::TwistingVines
+                        | BlockKind::TwistingVinesPlant
+                        | BlockKind::WeepingVines
+                        | BlockKind::WeepingVinesPlant
+                )
+            )
+            || layer
+                .block(block_pos(movement.position))
+                .is_some_and(|b| b.state.get(PropName::Waterlogged) == Some(PropValue::True))
+        {
+            fall.0 = 0.0;
+            continue;
+        }
+
+        let dy = movement.position.y - movement.old_position.y;
+        if !movement.on_ground {
+            if dy < 0.0 {
+                fall.0 -= dy;
+            }
+            continue;
+        }
+
+        let distance = fall.0;
+        fall.0 = 0.0;
+
+        if !vulnerable(*game_mode) {
+            continue;
+        }
+
+        // The block that was landed on is the one just below the player's feet.
+        let landed_on = layer
+            .block(block_pos(movement.position - DVec3::new(0.0, 0.2, 0.0)))
+            .map(|b| b.state.to_kind());
+
+        let multiplier = match landed_on {
+            Some(BlockKind::SlimeBlock) => 0.0,
+            Some(BlockKind::HayBlock | BlockKind::HoneyBlock) => 0.2,
+            Some(
+                BlockKind::WhiteBed
+                | BlockKind::LightGrayBed
+                | BlockKind::GrayBed
+                | BlockKind::BlackBed
+                | BlockKind::BrownBed
+                | BlockKind::RedBed
+                | BlockKind::OrangeBed
+                | BlockKind::YellowBed
+                | BlockKind::LimeBed
+                | BlockKind::GreenBed
+                | BlockKind::CyanBed
+                | BlockKind::LightBlueBed
+                | BlockKind::BlueBed
+                | BlockKind::PurpleBed
+                | BlockKind::MagentaBed
+                | BlockKind::PinkBed,
+            ) => 0.5,
+            _ => 1.0,
+        };
+
+        let damage = ((distance - 3.0).ceil() * multiplier).floor() as f32;
+        if damage > 0.0 {
+            damage_events.send(DamageEvent {
+                target: movement.client,
+                amount: damage,
+                source: DamageCause::Fall.into(),
+            });
+        }
+    }
+}
+
+fn breathe(
+    mut clients: Query<(
+        Entity,
+        &Position,
+        &mut Air,
+        &GameMode,
+        &EntityLayerId,
+    )>,
+    layers: Query<&ChunkLayer>,
+    mut damage_events: EventWriter<DamageEvent>,
+) {
+    for (entity, position, mut air, game_mode, layer_id) in &mut clients {
+        let Ok(layer) = layers.get(layer_id.0) else {
+            continue;
+        };
+
+        let underwater = layer
+            .block(block_pos(position.0 + DVec3::new(0.0, EYE_HEIGHT, 0.0)))
+            .is_some_and(|b| {
+                b.state.to_kind() == BlockKind::Water
+                    || b.state.to_kind() == BlockKind::BubbleColumn
+                    || b.state.get(PropName::Waterlogged) == Some(PropValue::True)
+            });
+
+        if !underwater {
+            if air.0 < MAX_AIR {
+                air.0 = (air.0 + 4).min(MAX_AIR);
+            }
+            continue;
+        }
+
+        if !vulnerable(*game_mode) {
+            continue;
+        }
+
+        air.0 -= 1;
+        if air.0 <= -20 {
+            air.0 = 0;
+            damage_events.send(DamageEvent {
+                target: entity,
+                amount: 2.0,
+                source: DamageCause::Drowning.into(),
+            });
+        }
+    }
+}
+
+fn suffocate(
+    clients: Query<(
+        Entity,
+        &Position,
+        &GameMode,
+        &EntityLayerId,
+    )>,
+    layers: Query<&ChunkLayer>,
+    mut damage_events: EventWriter<DamageEvent>,
+) {
+    for (entity, position, game_mode, layer_id) in &clients {
+        if !vulnerable(*game_mode) {
+            continue;
+        }
+        let Ok(layer) = layers.get(layer_id.0) else {
+            continue;
+        };
+
+        let head = block_pos(position.0 + DVec3::new(0.0, EYE_HEIGHT, 0.0));
+        let suffocating = layer.block(head).is_some_and(|b| {
+            b.state.is_opaque() && b.state.collision_shapes().any(|shape| {
+                shape.max().y - shape.min().y >= 1.0
+                    && shape.max().x - shape.min().x >= 1.0
+                    && shape.max().z - shape.min().z >= 1.0
+            })
+        });
+
+        if suffocating {
+            damage_events.send(DamageEvent {
+                target: entity,
+                amount: 1.0,
+                source: DamageCause::Suffocation.into(),
+            });
+        }
+    }
+}
+
+/// Damage from blocks the player is inside of or touching: lava, fire, cacti
+/// and sweet berry bushes.
+fn touch_blocks(
+    mut clients: Query<(
+        Entity,
+        &Position,
+        &OldPosition,
+        &Hitbox,
+        &mut FireTicks,
+        &GameMode,
+        &EntityLayerId,
+    )>,
+    layers: Query<&ChunkLayer>,
+    mut damage_events: EventWriter<DamageEvent>,
+) {
+    for (
+        entity,
+        position,
+        old_position,
+        hitbox,
+        mut fire,
+        game_mode,
+        layer_id,
+    ) in &mut clients {
+        if !vulnerable(*game_mode) {
+            continue;
+        }
+        let Ok(layer) = layers.get(layer_id.0) else {
+            continue;
+        };
+
+        // Cacti are slightly smaller than a full block, so grow the hitbox a
+        // little to catch players standing right next to them.
+        let hitbox = hitbox.get();
+        let touching = Aabb::new(
+            hitbox.min() - DVec3::splat(0.01),
+            hitbox.max() + DVec3::splat(0.01),
+        );
+
+        let min = block_pos(touching.min());
+        let max = block_pos(touching.max());
+
+        let mut worst: Option<(f32, DamageCause)> = None;
+
+        for y in min.y..=max.y {
+            for z in min.z..=max.z {
+                for x in min.x..=max.x {
+                    let pos = BlockPos { x, y, z };
+                    let Some(block) = layer.block(pos) else {
+                        continue;
+                    };
+
+                    let damage = match block.state.to_kind() {
+                        BlockKind::Lava => {
+                            fire.0 = fire.0.max(300);
+                            Some((4.0, DamageCause::Lava))
+                        }
+                        BlockKind::Fire => {
+                            fire.0 = fire.0.max(160);
+                            Some((1.0, DamageCause::InFire))
+                        }
+                        BlockKind::SoulFire => {
+                            fire.0 = fire.0.max(160);
+                            Some((2.0, DamageCause::InFire))
+                        }
+                        BlockKind::Campfire
+                            if block.state.get(PropName::Lit) == Some(PropValue::True) =>
+                        {
+                            Some((1.0, DamageCause::InFire))
+                        }
+                        BlockKind::SoulCampfire
+                            if block.state.get(PropName::Lit) == Some(PropValue::True) =>
+                        {
+                            Some((2.0, DamageCause::InFire))
+                        }
+                        BlockKind::MagmaBlock if y == min.y => Some((1.0, DamageCause::InFire)),
+                        BlockKind::Cactus => Some((1.0, DamageCause::Cactus)),
+                        BlockKind::SweetBerryBush
+                            if block.state.get(PropName::Age) != Some(PropValue::_0)
+                                && position.0 != old_position.get() =>
+                        {
+                            Some((1.0, DamageCause::SweetBerryBush))
+                        }
+                        _ => None,
+                    };
+
+                    if let Some((amount, cause)) = damage {
+                        let shapes_touch = block.state.collision_shapes().next().is_none()
+                            || block.state.collision_shapes().any(|shape| {
+                                (shape + DVec3::new(x as f64, y as f64, z as f64)).intersects(touching)
+                            });
+                        if shapes_touch && worst.map_or(true, |(worst, _)| amount > worst) {
+                            worst = Some((amount, cause));
+                        }
+                    }
+                }
+            }
+        }
+
+        if let Some((amount, cause)) = worst {
+            damage_events.send(DamageEvent {
+                target: entity,
+                amount,
+                source: cause.into(),
+            });
+        }
+    }
+}
+
+fn burn(
+    mut clients: Query<(
+        Entity,
+        &Position,
+        &mut FireTicks,
+        &mut Flags,
+        &GameMode,
+        &EntityLayerId,
+    )>,
+    layers: Query<&ChunkLayer>,
+    mut damage_events: EventWriter<DamageEvent>,
+) {
+    for (entity, position, mut fire, mut flags, game_mode, layer_id) in &mut clients {
+        let in_water = layers.get(layer_id.0).is_ok_and(|layer| {
+            layer.block(block_pos(position.0)).is_some_and(|b| {
+                b.state.to_kind() == BlockKind::Water
+                    || b.state.get(PropName::Waterlogged) == Some(PropValue::True)
+            })
+        });
+
+        if in_water || !vulnerable(*game_mode) {
+            fire.0 = 0;
+        }
+
+        if fire.0 > 0 {
+            fire.0 -= 1;
+            if fire.0 % 20 == 0 {
+                damage_events.send(DamageEvent {
+                    target: entity,
+                    amount: 1.0,
+                    source: DamageCause::OnFire.into(),
+                });
+            }
+        }
+
+        let on_fire = fire.0 > 0;
+        if flags.on_fire() != on_fire {
+            flags.set_on_fire(on_fire);
+        }
+    }
+}
+
+/// Distance below the bottom of the world at which players start taking void
+/// damage.
+const VOID_DEPTH: f64 = 64.0;
+
+fn void(
+    clients: Query<(
+        Entity,
+        &Position,
+        &GameMode,
+        &EntityLayerId,
+    )>,
+    layers: Query<&ChunkLayer>,
+    mut damage_events: EventWriter<DamageEvent>,
+) {
+    for (entity, position, game_mode, layer_id) in &clients {
+        if *game_mode == GameMode::Spectator {
+            continue;
+        }
+        let Ok(layer) = layers.get(layer_id.0) else {
+            continue;
+        };
+
+        if position.0.y < f64::from(layer.min_y()) - VOID_DEPTH {
+            damage_events.send(DamageEvent {
+                target: entity,
+                amount: 4.0,
+                source: DamageCause::Void.into(),
+            });
+        }
+    }
+}
@@ -0,0 +1,703 @@
+use valence::interact_block::InteractBlockEvent;
+use valence::inventory::HeldItem;
+use valence::movement::MovementEvent;
+use valence::prelude::*;
+use valence::protocol::Hand;
+use valence::rand::{thread_rng, Rng};
+use valence::sound::{Sound, SoundCategory};
+use valence::Direction;
+
+use crate::block_behavior::{block_pos, with_neighbors, BlockBehavior, BlockBehaviors, NeighborUpdate, OnRandomTick};
+use crate::block_update::{send_updates, BlockUpdateEvent};
+use crate::environment::{track_falling, FallDistance};
+use crate::item_entity::{spawn_item, DEFAULT_PICKUP_DELAY};
+use crate::loot::{block_drops, LootTables};
+use crate::placement::is_soil;
+use crate::random_tick::RandomTick;
+
+pub mod trees;
+
+use trees::tree_template;
+
+/// Crops, stems, sugar cane, cactus, bamboo, kelp and saplings growing on
+/// random ticks, farmland drying out and being trampled, and bone meal.
+pub struct Farming;
+
+impl Plugin for Farming {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BlockBehaviors>().init_resource::<LootTables>();
+
+        let mut behaviors = app.world_mut().resource_mut::<BlockBehaviors>();
+        for kind in [BlockKind::Wheat, BlockKind::Carrots, BlockKind::Potatoes, BlockKind::Beetroots] {
+            behaviors.insert(kind, OnRandomTick(grow_crop));
+        }
+        behaviors.insert(BlockKind::MelonStem, Stem);
+        behaviors.insert(BlockKind::PumpkinStem, Stem);
+        behaviors.insert(BlockKind::AttachedMelonStem, AttachedStem);
+        behaviors.insert(BlockKind::AttachedPumpkinStem, AttachedStem);
+        behaviors.insert(BlockKind::SugarCane, OnRandomTick(grow_tall_plant));
+        behaviors.insert(BlockKind::Cactus, OnRandomTick(grow_tall_plant));
+        behaviors.insert(BlockKind::Bamboo, OnRandomTick(grow_bamboo));
+        behaviors.insert(BlockKind::BambooSapling, OnRandomTick(grow_bamboo_sapling));
+        behaviors.insert(BlockKind::Kelp, OnRandomTick(grow_kelp));
+        for kind in [
+            BlockKind::OakSapling,
+            BlockKind::SpruceSapling,
+            BlockKind::BirchSapling,
+            BlockKind::JungleSapling,
+            BlockKind::AcaciaSapling,
+            BlockKind::CherrySapling,
+        ] {
+            behaviors.insert(kind, OnRandomTick(grow_sapling));
+        }
+        behaviors.insert(BlockKind::Farmland, OnRandomTick(hydrate_farmland));
+
+        app.add_systems(Update, (use_bone_meal, trample_farmland.before(track_falling)));
+    }
+}
+
+/// The light level plants need to grow.
+const GROWTH_LIGHT: u8 = 9;
+
+/// Slot of the off hand in the player's inventory.
+const OFF_HAND_SLOT: u16 = 45;
+
+/// Returns the value of a numbered property like `age` or `moisture`.
+pub fn number(n: u8) -> PropValue {
+    match n {
+        0 => PropValue::_0,
+        1 => PropValue::_1,
+        2 => PropValue::_2,
+        3 => PropValue::_3,
+        4 => PropValue::_4,
+        5 => PropValue::_5,
+        6 => PropValue::_6,
+        7 => PropValue::_7,
+        8 => PropValue::_8,
+        9 => PropValue::_9,
+        10 => PropValue::_10,
+        11 => PropValue::_11,
+        12 => PropValue::_12,
+        13 => PropValue::_13,
+        14 => PropValue::_14,
+        15 => PropValue::_15,
+        16 => PropValue::_16,
+        17 => PropValue::_17,
+        18 => PropValue::_18,
+        19 => PropValue::_19,
+        20 => PropValue::_20,
+        21 => PropValue::_21,
+        22 => PropValue::_22,
+        23 => PropValue::_23,
+        24 => PropValue::_24,
+        _ => PropValue::_25,
+    }
+}
+
+/// The number a numbered property is set to, if the block has it.
+pub fn number_of(state: BlockState, name: PropName) -> Option<u8> {
+    Some(match state.get(name)? {
+        PropValue::_0 => 0,
+        PropValue::_1 => 1,
+        PropValue::_2 => 2,
+        PropValue::_3 => 3,
+        PropValue::_4 => 4,
+        PropValue::_5 => 5,
+        PropValue::_6 => 6,
+        PropValue::_7 => 7,
+        PropValue::_8 => 8,
+        PropValue::_9 => 9,
+        PropValue::_10 => 10,
+        PropValue::_11 => 11,
+        PropValue::_12 => 12,
+        PropValue::_13 => 13,
+        PropValue::_14 => 14,
+        PropValue::_15 => 15,
+        PropValue::_16 => 16,
+        PropValue::_17 => 17,
+        PropValue::_18 => 18,
+        PropValue::_19 => 19,
+        PropValue::_20 => 20,
+        PropValue::_21 => 21,
+        PropValue::_22 => 22,
+        PropValue::_23 => 23,
+        PropValue::_24 => 24,
+        PropValue::_25 => 25,
+        _ => return None,
+    })
+}
+
+/// How much light a block gives off.
+fn light_emission(state: BlockState) -> u8 {
+    let kind = state.to_kind();
+    let lit = state.get(PropName::Lit) != Some(PropValue::False);
+
+    match kind {
+        BlockKind::Glowstone
+        | BlockKind::SeaLantern
+        | BlockKind::Lantern
+        | BlockKind::JackOLantern
+        | BlockKind::Shroomlight
+        | BlockKind::Beacon
+        | BlockKind::Conduit
+        | BlockKind::Lava
+        | BlockKind::Fire
+        | BlockKind::EndGateway
+        | BlockKind::EndPortal
+        | BlockKind::OchreFroglight
+        | BlockKind::VerdantFroglight
+        | BlockKind::PearlescentFroglight => 15,
+        BlockKind::Campfire | BlockKind::RedstoneLamp if lit => 15,
+        BlockKind::Torch | BlockKind::WallTorch | BlockKind::EndRod => 14,
+        BlockKind::Furnace | BlockKind::BlastFurnace | BlockKind::Smoker if lit => 13,
+        BlockKind::SoulTorch
+        | BlockKind::SoulWallTorch
+        | BlockKind::SoulLantern
+        | BlockKind::SoulFire
+        | BlockKind::CryingObsidian => 10,
+        BlockKind::SoulCampfire if lit => 10,
+        _ => 0,
+    }
+}
+
+/// Whether the light at `position` is at least `level`, either from the sky
+/// or from blocks that give off light.
+///
+/// The sky counts as fully lit whatever the time of day, as it does for
+/// plants in vanilla. Light from blocks spreads through anything, since the
+/// server doesn't keep track of how light is blocked.
+pub fn light_at_least(layer: &ChunkLayer, position: BlockPos, level: u8) -> bool {
+    let top = layer.min_y() + layer.height() as i32;
+    let open_sky = (position.y + 1..top).all(|y| {
+        layer
+            .block(BlockPos::new(position.x, y, position.z))
+            .map_or(true, |b| !b.state.is_opaque())
+    });
+    if open_sky {
+        return true;
+    }
+
+    // Light drops by one for every block it travels, so nothing further away
+    // than this can make it bright enough.
+    let radius = 15 - i32::from(level);
+    for dx in -radius..=radius {
+        for dy in -radius..=radius {
+            for dz in -radius..=radius {
+                let distance = dx.abs() + dy.abs() + dz.abs();
+                if distance > radius {
+                    continue;
+                }
+                let pos = BlockPos::new(position.x + dx, position.y + dy, position.z + dz);
+                let emitted = layer.block(pos).map_or(0, |b| light_emission(b.state));
+                if i32::from(emitted) - distance >= i32::from(level) {
+                    return true;
+                }
+            }
+        }
+    }
+    false
+}
+
+fn max_age(kind: BlockKind) -> u8 {
+    if kind == BlockKind::Beetroots {
+        3
+    } else {
+        7
+    }
+}
+
+fn horizontal_neighbours(position: BlockPos) -> [BlockPos; 4] {
+    [
+        position.get_in_direction(Direction::North),
+        position.get_in_direction(Direction::East),
+        position.get_in_direction(Direction::South),
+        position.get_in_direction(Direction::West),
+    ]
+}
+
+/// How quickly a crop grows, from the farmland under and around it. Crops
+/// planted in rows grow faster than ones crowded in with their own kind.
+fn growth_speed(layer: &ChunkLayer, position: BlockPos, kind: BlockKind) -> f32 {
+    let below = position.get_in_direction(Direction::Down);
+    let mut speed = 1.0;
+
+    for dx in -1..=1 {
+        for dz in -1..=1 {
+            let state = layer.block(BlockPos::new(below.x + dx, below.y, below.z + dz)).map(|b| b.state);
+            let mut bonus = match state {
+                Some(s) if s.to_kind() == BlockKind::Farmland && number_of(s, PropName::Moisture) > Some(0) => 3.0,
+                Some(s) if s.to_kind() == BlockKind::Farmland => 1.0,
+                _ => 0.0,
+            };
+            if dx != 0 || dz != 0 {
+                bonus /= 4.0;
+            }
+            speed += bonus;
+        }
+    }
+
+    let same = |pos: BlockPos| layer.block(pos).is_some_and(|b| b.state.to_kind() == kind);
+    let [north, east, south, west] = horizontal_neighbours(position);
+    let along_z = same(north) || same(south);
+    let along_x = same(east) || same(west);
+    let diagonal = same(BlockPos::new(position.x - 1, position.y, position.z - 1))
+        || same(BlockPos::new(position.x + 1, position.y, position.z - 1))
+        || same(BlockPos::new(position.x + 1, position.y, position.z + 1))
+        || same(BlockPos::new(position.x - 1, position.y, position.z + 1));
+
+    if (along_z && along_x) || diagonal {
+        speed /= 2.0;
+    }
+
+    speed
+}
+
+/// Whether a crop or stem grows a stage this random tick.
+fn grows(tick: &mut RandomTick) -> bool {
+    if !light_at_least(tick.layer, tick.position, GROWTH_LIGHT) {
+        return false;
+    }
+    let speed = growth_speed(tick.layer, tick.position, tick.state.to_kind());
+    tick.rng.gen_range(0..(25.0 / speed) as i32 + 1) == 0
+}
+
+fn grow_crop(tick: &mut RandomTick) {
+    let kind = tick.state.to_kind();
+    let Some(age) = number_of(tick.state, PropName::Age) else {
+        return;
+    };
+    if age >= max_age(kind) {
+        return;
+    }
+    // Beetroots grow at two thirds of the speed of other crops.
+    if kind == BlockKind::Beetroots && tick.rng.gen_range(0..3) == 0 {
+        return;
+    }
+
+    if grows(tick) {
+        tick.set_block(tick.position, tick.state.set(PropName::Age, number(age + 1)));
+    }
+}
+
+/// Stems grow up, and once fully grown put out a melon or pumpkin next to
+/// them.
+fn grow_stem(tick: &mut RandomTick) {
+    let Some(age) = number_of(tick.state, PropName::Age) else {
+        return;
+    };
+    if !grows(tick) {
+        return;
+    }
+
+    if age < 7 {
+        tick.set_block(tick.position, tick.state.set(PropName::Age, number(age + 1)));
+        return;
+    }
+
+    let (fruit, attached) = if tick.state.to_kind() == BlockKind::MelonStem {
+        (BlockState::MELON, BlockState::ATTACHED_MELON_STEM)
+    } else {
+        (BlockState::PUMPKIN, BlockState::ATTACHED_PUMPKIN_STEM)
+    };
+    let (direction, facing) = match tick.rng.gen_range(0..4) {
+        0 => (Direction::North, PropValue::North),
+        1 => (Direction::East, PropValue::East),
+        2 => (Direction::South, PropValue::South),
+        _ => (Direction::West, PropValue::West),
+    };
+    let target = tick.position.get_in_direction(direction);
+    let below = target.get_in_direction(Direction::Down);
+
+    if tick.layer.block(target).is_some_and(|b| b.state.is_air())
+        && tick.layer.block(below).is_some_and(|b| is_soil(b.state.to_kind()))
+    {
+        tick.set_block(target, fruit);
+        tick.set_block(tick.position, attached.set(PropName::Facing, facing));
+    }
+}
+
+/// Melon and pumpkin stems grow on random ticks, and join up with a fruit
+/// that is put next to them.
+struct Stem;
+
+impl BlockBehavior for Stem {
+    fn on_neighbor_update(&self, update: &mut NeighborUpdate) {
+        let (fruit, attached) = if update.block.state.to_kind() == BlockKind::MelonStem {
+            (BlockKind::Melon, BlockState::ATTACHED_MELON_STEM)
+        } else {
+            (BlockKind::Pumpkin, BlockState::ATTACHED_PUMPKIN_STEM)
+        };
+        for (direction, facing) in [
+            (Direction::East, PropValue::East),
+            (Direction::West, PropValue::West),
+            (Direction::North, PropValue::North),
+            (Direction::South, PropValue::South),
+        ] {
+            if update.neighbor(direction).is_some_and(|s| s.to_kind() == fruit) {
+                update.block.state = attached.set(PropName::Facing, facing);
+                return;
+            }
+        }
+    }
+
+    fn has_random_ticks(&self) -> bool {
+        true
+    }
+
+    fn on_random_tick(&self, tick: &mut RandomTick) {
+        grow_stem(tick);
+    }
+}
+
+struct AttachedStem;
+
+impl BlockBehavior for AttachedStem {
+    fn on_neighbor_update(&self, update: &mut NeighborUpdate) {
+        let (fruit, stem) = if update.block.state.to_kind() == BlockKind::AttachedMelonStem {
+            (BlockKind::Melon, BlockState::MELON_STEM)
+        } else {
+            (BlockKind::Pumpkin, BlockState::PUMPKIN_STEM)
+        };
+        let direction = match update.block.state.get(PropName::Facing) {
+            Some(PropValue::North) => Direction::North,
+            Some(PropValue::East) => Direction::East,
+            Some(PropValue::South) => Direction::South,
+            _ => Direction::West,
+        };
+        // A stem that loses its fruit is still fully grown, and can grow
+        // another one.
+        if !update.neighbor(direction).is_some_and(|s| s.to_kind() == fruit) {
+            update.block.state = stem.set(PropName::Age, PropValue::_7);
+        }
+    }
+}
+
+/// Sugar cane and cactus grow a block taller every 16 random ticks, up to
+/// three blocks.
+fn grow_tall_plant(tick: &mut RandomTick) {
+    let kind = tick.state.to_kind();
+    let above = tick.position.get_in_direction(Direction::Up);
+    if !tick.layer.block(above).is_some_and(|b| b.state.is_air()) {
+        return;
+    }
+
+    let mut height = 1;
+    while height < 3
+        && tick
+            .layer
+            .block(BlockPos::new(tick.position.x, tick.position.y - height, tick.position.z))
+            .is_some_and(|b| b.state.to_kind() == kind)
+    {
+        height += 1;
+    }
+    if height >= 3 {
+        return;
+    }
+
+    let Some(age) = number_of(tick.state, PropName::Age) else {
+        return;
+    };
+    if age == 15 {
+        tick.set_block(above, BlockState::from_kind(kind));
+        tick.set_block(tick.position, tick.state.set(PropName::Age, PropValue::_0));
+    } else {
+        tick.set_block(tick.position, tick.state.set(PropName::Age, number(age + 1)));
+    }
+}
+
+fn is_bamboo(layer: &ChunkLayer, position: BlockPos) -> bool {
+    layer.block(position).is_some_and(|b| b.state.to_kind() == BlockKind::Bamboo)
+}
+
+fn grow_bamboo(tick: &mut RandomTick) {
+    if tick.state.get(PropName::Stage) != Some(PropValue::_0) || tick.rng.gen_range(0..3) != 0 {
+        return;
+    }
+    let above = tick.position.get_in_direction(Direction::Up);
+    if !tick.layer.block(above).is_some_and(|b| b.state.is_air())
+        || !light_at_least(tick.layer, tick.position, GROWTH_LIGHT)
+    {
+        return;
+    }
+
+    let mut height = 1;
+    while height < 16 && is_bamboo(tick.layer, BlockPos::new(tick.position.x, tick.position.y - height, tick.position.z)) {
+        height += 1;
+    }
+    if height >= 16 {
+        return;
+    }
+
+    // The top few blocks of a stalk carry leaves, bigger ones at the very
+    // top.
+    let below = tick.position.get_in_direction(Direction::Down);
+    let below_two = below.get_in_direction(Direction::Down);
+    let below_state = tick.layer.block(below).map(|b| b.state);
+    let mut leaves = PropValue::None;
+    if height >= 1 {
+        match below_state {
+            Some(state) if state.to_kind() == BlockKind::Bamboo && state.get(PropName::Leaves) != Some(PropValue::None) => {
+                leaves = PropValue::Large;
+                if let Some(two) = tick.layer.block(below_two).map(|b| b.state).filter(|s| s.to_kind() == BlockKind::Bamboo) {
+                    tick.set_block(below, state.set(PropName::Leaves, PropValue::Small));
+                    tick.set_block(below_two, two.set(PropName::Leaves, PropValue::None));
+                }
+            }
+            _ => leaves = PropValue::Small,
+        }
+    }
+
+    let thick = tick.state.get(PropName::Age) == Some(PropValue::_1) || is_bamboo(tick.layer, below_two);
+    let done = (height >= 11 && tick.rng.gen::<f32>() < 0.25) || height == 15;
+
+    tick.set_block(
+        above,
+        BlockState::BAMBOO
+            .set(PropName::Age, if thick { PropValue::_1 } else { PropValue::_0 })
+            .set(PropName::Leaves, leaves)
+            .set(PropName::Stage, if done { PropValue::_1 } else { PropValue::_0 }),
+    );
+}
+
+fn grow_bamboo_sapling(tick: &mut RandomTick) {
+    let above = tick.position.get_in_direction(Direction::Up);
+    if tick.rng.gen_range(0..3) != 0
+        || !tick.layer.block(above).is_some_and(|b| b.state.is_air())
+        || !light_at_least(tick.layer, tick.position, GROWTH_LIGHT)
+    {
+        return;
+    }
+
+    tick.set_block(tick.position, BlockState::BAMBOO);
+    tick.set_block(above, BlockState::BAMBOO.set(PropName::Leaves, PropValue::Small));
+}
+
+/// Kelp grows up through water, a block at a time, until it reaches its
+/// randomly chosen age limit.
+fn grow_kelp(tick: &mut RandomTick) {
+    let Some(age) = number_of(tick.state, PropName::Age) else {
+        return;
+    };
+    if age >= 25 || tick.rng.gen::<f64>() >= 0.14 {
+        return;
+    }
+    let above = tick.position.get_in_direction(Direction::Up);
+    if !tick.layer.block(above).is_some_and(|b| {
+        b.state.to_kind() == BlockKind::Water && b.state.get(PropName::Level) == Some(PropValue::_0)
+    }) {
+        return;
+    }
+
+    tick.set_block(above, BlockState::KELP.set(PropName::Age, number(age + 1)));
+    tick.set_block(tick.position, BlockState::KELP_PLANT);
+}
+
+/// Takes a sapling a step closer to being a tree. Saplings grow in two
+/// stages, and only turn into a tree if there is room for one.
+fn advance_sapling(layer: &ChunkLayer, position: BlockPos, state: BlockState, rng: &mut impl Rng) -> Vec<(BlockPos, BlockState)> {
+    if state.get(PropName::Stage) == Some(PropValue::_0) {
+        return vec![(position, state.set(PropName::Stage, PropValue::_1))];
+    }
+    let Some(template) = tree_template(state.to_kind()) else {
+        return Vec::new();
+    };
+    template.generate(layer, position, rng).unwrap_or_default()
+}
+
+fn grow_sapling(tick: &mut RandomTick) {
+    let above = tick.position.get_in_direction(Direction::Up);
+    if !light_at_least(tick.layer, above, GROWTH_LIGHT) || tick.rng.gen_range(0..7) != 0 {
+        return;
+    }
+
+    for (position, state) in advance_sapling(tick.layer, tick.position, tick.state, tick.rng) {
+        tick.set_block(position, state);
+    }
+}
+
+/// Whether farmland holds on to its moisture because something is planted in
+/// it.
+fn keeps_farmland(kind: BlockKind) -> bool {
+    kind == BlockKind::Wheat
+        || kind == BlockKind::Carrots
+        || kind == BlockKind::Potatoes
+        || kind == BlockKind::Beetroots
+        || kind == BlockKind::MelonStem
+        || kind == BlockKind::PumpkinStem
+        || kind == BlockKind::AttachedMelonStem
+        || kind == BlockKind::AttachedPumpkinStem
+        || kind == BlockKind::TorchflowerCrop
+        || kind == BlockKind::PitcherCrop
+}
+
+/// Farmland is kept wet by water up to four blocks away. Without it, it dries
+/// out and eventually turns back into dirt if nothing is planted in it.
+fn hydrate_farmland(tick: &mut RandomTick) {
+    let Some(moisture) = number_of(tick.state, PropName::Moisture) else {
+        return;
+    };
+
+    let mut near_water = false;
+    'search: for dx in -4..=4 {
+        for dy in 0..=1 {
+            for dz in -4..=4 {
+                let pos = BlockPos::new(tick.position.x + dx, tick.position.y + dy, tick.position.z + dz);
+                if tick.layer.block(pos).is_some_and(|b| {
+                    b.state.to_kind() == BlockKind::Water || b.state.get(PropName::Waterlogged) == Some(PropValue::True)
+                }) {
+                    near_water = true;
+                    break 'search;
+                }
+            }
+        }
+    }
+
+    if near_water {
+        if moisture < 7 {
+            tick.set_block(tick.position, tick.state.set(PropName::Moisture, PropValue::_7));
+        }
+    } else if moisture > 0 {
+        tick.set_block(tick.position, tick.state.set(PropName::Moisture, number(moisture - 1)));
+    } else if !tick
+        .layer
+        .block(tick.position.get_in_direction(Direction::Up))
+        .is_some_and(|b| keeps_farmland(b.state.to_kind()))
+    {
+        tick.set_block(tick.position, BlockState::DIRT);
+    }
+}
+
+/// Returns the blocks bone meal changes when used on `state`, or `None` if it
+/// can't be used on it at all.
+fn bone_meal(layer: &ChunkLayer, position: BlockPos, state: BlockState, rng: &mut impl Rng) -> Option<Vec<(BlockPos, BlockState)>> {
+    let kind = state.to_kind();
+
+    if kind == BlockKind::Wheat || kind == BlockKind::Carrots || kind == BlockKind::Potatoes || kind == BlockKind::Beetroots {
+        let age = number_of(state, PropName::Age)?;
+        let max = max_age(kind);
+        if age >= max {
+            return None;
+        }
+        let age = (age + rng.gen_range(2..=5)).min(max);
+        Some(vec![(position, state.set(PropName::Age, number(age)))])
+    } else if kind == BlockKind::MelonStem || kind == BlockKind::PumpkinStem {
+        let age = number_of(state, PropName::Age)?;
+        if age >= 7 {
+            return None;
+        }
+        let age = (age + rng.gen_range(2..=5)).min(7);
+        Some(vec![(position, state.set(PropName::Age, number(age)))])
+    } else if kind == BlockKind::SweetBerryBush {
+        let age = number_of(state, PropName::Age)?;
+        if age >= 3 {
+            return None;
+        }
+        Some(vec![(position, state.set(PropName::Age, number(age + 1)))])
+    } else if tree_template(kind).is_some() {
+        // Bone meal is used up even when the sapling doesn't grow.
+        if rng.gen::<f32>() >= 0.45 {
+            return Some(Vec::new());
+        }
+        Some(advance_sapling(layer, position, state, rng))
+    } else {
+        None
+    }
+}
+
+fn use_bone_meal(
+    mut clients: Query<(&mut Inventory, &HeldItem, &GameMode, &EntityLayerId)>,
+    mut layers: Query<&mut ChunkLayer>,
+    mut events: EventReader<InteractBlockEvent>,
+    mut block_updates: EventWriter<BlockUpdateEvent>,
+) {
+    let mut rng = thread_rng();
+
+    for event in events.read() {
+        let Ok((mut inventory, held, game_mode, &layer_id)) = clients.get_mut(event.client) else {
+            continue;
+        };
+        if *game_mode == GameMode::Spectator {
+            continue;
+        }
+        let slot = match event.hand {
+            Hand::Main => held.slot(),
+            Hand::Off => OFF_HAND_SLOT,
+        };
+        let stack = inventory.slot(slot).clone();
+        if stack.item != ItemKind::BoneMeal {
+            continue;
+        }
+        let Ok(mut layer) = layers.get_mut(layer_id.0) else {
+            continue;
+        };
+        let Some(state) = layer.block(event.position).map(|b| b.state) else {
+            continue;
+        };
+        let Some(changes) = bone_meal(&layer, event.position, state, &mut rng) else {
+            continue;
+        };
+
+        for (position, state) in changes {
+            layer.set_block(position, state);
+            send_updates(&mut block_updates, layer_id, with_neighbors(position));
+        }
+
+        let center = DVec3::new(
+            f64::from(event.position.x) + 0.5,
+            f64::from(event.position.y) + 0.5,
+            f64::from(event.position.z) + 0.5,
+        );
+        layer.play_sound(Sound::ItemBoneMealUse, SoundCategory::Block, center, 1.0, 1.0);
+
+        if *game_mode != GameMode::Creative {
+            inventory.set_slot_amount(slot, stack.count - 1);
+        }
+    }
+}
+
+/// Landing on farmland from high enough can turn it back into dirt, knocking
+/// off whatever was planted in it.
+fn trample_farmland(
+    clients: Query<(&FallDistance, &GameMode, &EntityLayerId)>,
+    mut layers: Query<&mut ChunkLayer>,
+    tables: Res<LootTables>,
+    mut movements: EventReader<MovementEvent>,
+    mut block_updates: EventWriter<BlockUpdateEvent>,
+    mut commands: Commands,
+) {
+    let mut rng = thread_rng();
+
+    for movement in movements.read() {
+        if !movement.on_ground {
+            continue;
+        }
+        let Ok((fall, game_mode, &layer_id)) = clients.get(movement.client) else {
+            continue;
+        };
+        if *game_mode == GameMode::Spectator || rng.gen::<f64>() >= fall.0 - 0.5 {
+            continue;
+        }
+        let Ok(mut layer) = layers.get_mut(layer_id.0) else {
+            continue;
+        };
+
+        // Farmland is a little lower than a full block.
+        let position = block_pos(movement.position - DVec3::new(0.0, 0.2, 0.0));
+        if !layer.block(position).is_some_and(|b| b.state.to_kind() == BlockKind::Farmland) {
+            continue;
+        }
+
+        let above = position.get_in_direction(Direction::Up);
+        if let Some(crop) = layer.block(above).map(|b| b.state).filter(|s| keeps_farmland(s.to_kind())) {
+            layer.set_block(above, BlockState::AIR);
+            let center = DVec3::new(f64::from(above.x) + 0.5, f64::from(above.y) + 0.25, f64::from(above.z) + 0.5);
+            for stack in block_drops(&tables.get(crop.to_kind()), crop, &ItemStack::EMPTY, &mut rng) {
+                spawn_item(&mut commands, layer_id, center, stack, Vec3::new(0.0, 4.0, 0.0), DEFAULT_PICKUP_DELAY);
+            }
+            send_updates(&mut block_updates, layer_id, with_neighbors(above));
+        }
+
+        layer.set_block(position, BlockState::DIRT);
+        send_updates(&mut block_updates, layer_id, with_neighbors(position));
+    }
+}
@@ -0,0 +1,145 @@
+use valence::prelude::*;
+use valence::rand::Rng;
+use valence::Direction;
+
+use super::number;
+
+/// The shape of a tree's leaves.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Foliage {
+    /// A rounded blob around the top of the trunk, like oak and birch.
+    Blob,
+    /// Rings that get wider further down, like spruce.
+    Cone,
+    /// A wide, flat layer on top, like acacia.
+    Flat,
+}
+
+/// What a sapling grows into.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TreeTemplate {
+    pub log: BlockState,
+    pub leaves: BlockState,
+    /// The shortest and tallest the trunk can be.
+    pub height: (i32, i32),
+    pub foliage: Foliage,
+}
+
+/// Returns the tree a sapling grows into. Dark oak saplings need four of
+/// them in a square, so they don't grow on their own.
+pub fn tree_template(sapling: BlockKind) -> Option<TreeTemplate> {
+    let (log, leaves, height, foliage) = match sapling {
+        BlockKind::OakSapling => (BlockState::OAK_LOG, BlockState::OAK_LEAVES, (4, 6), Foliage::Blob),
+        BlockKind::BirchSapling => (BlockState::BIRCH_LOG, BlockState::BIRCH_LEAVES, (5, 7), Foliage::Blob),
+        BlockKind::JungleSapling => (BlockState::JUNGLE_LOG, BlockState::JUNGLE_LEAVES, (4, 10), Foliage::Blob),
+        BlockKind::CherrySapling => (BlockState::CHERRY_LOG, BlockState::CHERRY_LEAVES, (4, 7), Foliage::Blob),
+        BlockKind::SpruceSapling => (BlockState::SPRUCE_LOG, BlockState::SPRUCE_LEAVES, (6, 9), Foliage::Cone),
+        BlockKind::AcaciaSapling => (BlockState::ACACIA_LOG, BlockState::ACACIA_LEAVES, (5, 8), Foliage::Flat),
+        _ => return None,
+    };
+
+    Some(TreeTemplate {
+        log,
+        leaves,
+        height,
+        foliage,
+    })
+}
+
+/// Whether a tree can grow through this block.
+fn can_grow_into(state: BlockState) -> bool {
+    let kind = state.to_kind();
+    state.is_air() || state.is_replaceable() || kind.to_str().ends_with("_leaves") || kind.to_str().ends_with("_sapling")
+}
+
+impl TreeTemplate {
+    /// Works out every block of a tree growing from `base`. Returns `None` if
+    /// something is in the way of its trunk.
+    pub fn generate(&self, layer: &ChunkLayer, base: BlockPos, rng: &mut impl Rng) -> Option<Vec<(BlockPos, BlockState)>> {
+        let height = rng.gen_range(self.height.0..=self.height.1);
+
+        let trunk = (0..height)
+            .map(|y| BlockPos::new(base.x, base.y + y, base.z))
+            .collect::<Vec<_>>();
+        if !trunk
+            .iter()
+            .all(|&pos| layer.block(pos).is_some_and(|b| can_grow_into(b.state)))
+        {
+            return None;
+        }
+
+        let top = base.y + height;
+        let mut leaves = Vec::new();
+
+        match self.foliage {
+            Foliage::Blob => {
+                for y in top - 3..=top {
+                    let dy = y - top;
+                    let radius = 1 - dy / 2;
+                    for dx in -radius..=radius {
+                        for dz in -radius..=radius {
+                            // The corners are cut off, always at the very top
+                            // and at random further down.
+                            if dx.abs() == radius && dz.abs() == radius && (dy == 0 || rng.gen_bool(0.5)) {
+                                continue;
+                            }
+                            leaves.push(BlockPos::new(base.x + dx, y, base.z + dz));
+                        }
+                    }
+                }
+            }
+            Foliage::Cone => {
+                for (i, y) in (base.y + 2..=top).rev().enumerate() {
+                    let radius = if i == 0 { 0 } else { (1 + i as i32 % 2 + i as i32 / 4).min(3) };
+                    for dx in -radius..=radius {
+                        for dz in -radius..=radius {
+                            if radius > 0 && dx.abs() == radius && dz.abs() == radius {
+                                continue;
+                            }
+                            leaves.push(BlockPos::new(base.x + dx, y, base.z + dz));
+                        }
+                    }
+                }
+            }
+            Foliage::Flat => {
+                for (y, radius) in [(top - 1, 2), (top, 1)] {
+                    for dx in -radius..=radius {
+                        for dz in -radius..=radius {
+                            if dx.abs() == radius && dz.abs() == radius {
+                                continue;
+                            }
+                            leaves.push(BlockPos::new(base.x + dx, y, base.z + dz));
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut blocks = trunk.iter().map(|&pos| (pos, self.log)).collect::<Vec<_>>();
+
+        for pos in leaves {
+            if trunk.contains(&pos) || !layer.block(pos).is_some_and(|b| can_grow_into(b.state)) {
+                continue;
+            }
+            // Leaves that are too far from a log to be held up decay.
+            let distance = trunk
+                .iter()
+                .map(|log| (log.x - pos.x).abs() + (log.y - pos.y).abs() + (log.z - pos.z).abs())
+                .min()
+                .unwrap_or(7)
+                .clamp(1, 7);
+            blocks.push((pos, self.leaves.set(PropName::Distance, number(distance as u8))));
+        }
+
+        // Grass under the trunk is covered up and turns to dirt.
+        let soil = base.get_in_direction(Direction::Down);
+        if layer
+            .block(soil)
+            .is_some_and(|b| b.state.to_kind() == BlockKind::GrassBlock || b.state.to_kind() == BlockKind::Podzol)
+        {
+            blocks.push((soil, BlockState::DIRT));
+        }
+
+        Some(blocks)
+    }
+}
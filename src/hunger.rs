@@ -0,0 +1,534 @@
+use valence::entity::entity::Flags;
+use valence::entity::living::Health;
+use valence::entity::player::{Food, Saturation};
+use valence::event_loop::PacketEvent;
+use valence::interact_item::InteractItemEvent;
+use valence::inventory::HeldItem;
+use valence::movement::MovementEvent;
+use valence::protocol::packets::play::player_action_c2s::PlayerAction;
+use valence::protocol::packets::play::{HealthUpdateS2c, PlayerActionC2s};
+use valence::protocol::{Hand, VarInt, WritePacket};
+use valence::prelude::*;
+
+use crate::damage::{DamageCause, DamageEvent};
+use crate::rules::{Difficulty, GameRules};
+
+pub struct Hunger;
+
+impl Plugin for Hunger {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameRules>()
+            .add_systems(
+                Update,
+                (
+                    init_clients,
+                    (
+                        movement_exhaustion,
+                        action_exhaustion,
+                        damage_exhaustion,
+                        block_low_food_sprinting,
+                    ),
+                    (start_eating, stop_eating, finish_eating).chain(),
+                    apply_exhaustion,
+                    regenerate,
+                    starve,
+                    sync_health,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Food level at or below which players can no longer sprint.
+pub const SPRINT_FOOD_LEVEL: i32 = 6;
+
+/// Exhaustion at which a point of saturation, or food if there is no
+/// saturation left, is used up.
+const EXHAUSTION_PER_FOOD: f32 = 4.0;
+
+/// Exhaustion values taken from the vanilla server.
+pub mod exhaustion {
+    pub const SPRINT_PER_METER: f32 = 0.1;
+    pub const SWIM_PER_METER: f32 = 0.01;
+    pub const JUMP: f32 = 0.05;
+    pub const SPRINT_JUMP: f32 = 0.2;
+    pub const ATTACK: f32 = 0.1;
+    pub const DAMAGE: f32 = 0.1;
+    pub const MINE: f32 = 0.005;
+    pub const REGENERATE: f32 = 6.0;
+}
+
+#[derive(Component, Debug, Copy, Clone)]
+pub struct HungerState {
+    /// Exhaustion accumulated since food or saturation was last used up.
+    pub exhaustion: f32,
+    /// Ticks since starvation damage was last applied.
+    starvation_timer: i32,
+    /// Ticks since the player last regenerated health.
+    regeneration_timer: i32,
+    /// Health last tick, used to work out how much damage has been taken.
+    last_health: f32,
+}
+
+impl HungerState {
+    pub fn add_exhaustion(&mut self, amount: f32) {
+        self.exhaustion = (self.exhaustion + amount).min(40.0);
+    }
+}
+
+impl Default for HungerState {
+    fn default() -> Self {
+        Self {
+            exhaustion: 0.0,
+            starvation_timer: 0,
+            regeneration_timer: 0,
+            last_health: 20.0,
+        }
+    }
+}
+
+/// A player that is in the middle of eating the item in their held slot.
+#[derive(Component, Debug, Copy, Clone)]
+pub struct Eating {
+    slot: u16,
+    item: ItemKind,
+    finish_tick: i64,
+}
+
+/// The nutritional value of an item that can be eaten.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct FoodProperties {
+    pub nutrition: i32,
+    pub saturation_modifier: f32,
+    /// Whether the item can be eaten when the player is not hungry.
+    pub always_edible: bool,
+    /// The number of ticks it takes to eat the item.
+    pub eat_ticks: i64,
+    /// The item left behind after eating, such as the bowl of a stew.
+    pub remainder: Option<ItemKind>,
+}
+
+impl FoodProperties {
+    const fn new(nutrition: i32, saturation_modifier: f32) -> Self {
+        Self {
+            nutrition,
+            saturation_modifier,
+            always_edible: false,
+            eat_ticks: 32,
+            remainder: None,
+        }
+    }
+
+    const fn always_edible(mut self) -> Self {
+        self.always_edible = true;
+        self
+    }
+
+    const fn eat_ticks(mut self, ticks: i64) -> Self {
+        self.eat_ticks = ticks;
+        self
+    }
+
+    const fn remainder(mut self, item: ItemKind) -> Self {
+        self.remainder = Some(item);
+        self
+    }
+
+    /// The saturation restored by eating the item.
+    pub fn saturation(&self) -> f32 {
+        self.nutrition as f32 * self.saturation_modifier * 2.0
+    }
+}
+
+/// Returns the food properties of an item, or `None` if it can't be eaten.
+pub fn food_properties(item: ItemKind) -> Option<FoodProperties> {
+    Some(match item {
+        ItemKind::Apple => FoodProperties::new(4, 0.3),
+        ItemKind::BakedPotato => FoodProperties::new(5, 0.6),
+        ItemKind::Beef => FoodProperties::new(3, 0.3),
+        ItemKind::Beetroot => FoodProperties::new(1, 0.6),
+        ItemKind::BeetrootSoup => FoodProperties::new(6, 0.6).remainder(ItemKind::Bowl),
+        ItemKind::Bread => FoodProperties::new(5, 0.6),
+        ItemKind::Carrot => FoodProperties::new(3, 0.6),
+        ItemKind::Chicken => FoodProperties::new(2, 0.3),
+        ItemKind::ChorusFruit => FoodProperties::new(4, 0.3).always_edible(),
+        ItemKind::Cod => FoodProperties::new(2, 0.1),
+        ItemKind::CookedBeef => FoodProperties::new(8, 0.8),
+        ItemKind::CookedChicken => FoodProperties::new(6, 0.6),
+        ItemKind::CookedCod => FoodProperties::new(5, 0.6),
+        ItemKind::CookedMutton => FoodProperties::new(6, 0.8),
+        ItemKind::CookedPorkchop => FoodProperties::new(8, 0.8),
+        ItemKind::CookedRabbit => FoodProperties::new(5, 0.6),
+        ItemKind::CookedSalmon => FoodProperties::new(6, 0.8),
+        ItemKind::Cookie => FoodProperties::new(2, 0.1),
+        ItemKind::DriedKelp => FoodProperties::new(1, 0.3).eat_ticks(16),
+        ItemKind::EnchantedGoldenApple => FoodProperties::new(4, 1.2).always_edible(),
+        ItemKind::GlowBerries => FoodProperties::new(2, 0.1),
+        ItemKind::GoldenApple => FoodProperties::new(4, 1.2).always_edible(),
+        ItemKind::GoldenCarrot => FoodProperties::new(6, 1.2),
+        ItemKind::HoneyBottle => FoodProperties::new(6, 0.1)
+            .eat_ticks(40)
+            .remainder(ItemKind::GlassBottle),
+        ItemKind::MelonSlice => FoodProperties::new(2, 0.3),
+        ItemKind::MushroomStew => FoodProperties::new(6, 0.6).remainder(ItemKind::Bowl),
+        ItemKind::Mutton => FoodProperties::new(2, 0.3),
+        ItemKind::PoisonousPotato => FoodProperties::new(2, 0.3),
+        ItemKind::Porkchop => FoodProperties::new(3, 0.3),
+        ItemKind::Potato => FoodProperties::new(1, 0.3),
+        ItemKind::Pufferfish => FoodProperties::new(1, 0.1),
+        ItemKind::PumpkinPie => FoodProperties::new(8, 0.3),
+        ItemKind::Rabbit => FoodProperties::new(3, 0.3),
+        ItemKind::RabbitStew => FoodProperties::new(10, 0.6).remainder(ItemKind::Bowl),
+        ItemKind::RottenFlesh => FoodProperties::new(4, 0.1),
+        ItemKind::Salmon => FoodProperties::new(2, 0.1),
+        ItemKind::SpiderEye => FoodProperties::new(2, 0.8),
+        ItemKind::SuspiciousStew => FoodProperties::new(6, 0.6)
+            .always_edible()
+            .remainder(ItemKind::Bowl),
+        ItemKind::SweetBerries => FoodProperties::new(2, 0.1),
+        ItemKind::TropicalFish => FoodProperties::new(1, 0.1),
+        _ => {
+            return None;
+        }
+    })
+}
+
+/// Whether a player in this game mode uses up food.
+fn uses_food(game_mode: GameMode) -> bool {
+    game_mode == GameMode::Survival || game_mode == GameMode::Adventure
+}
+
+fn init_clients(
+    clients: Query<(Entity, &Health), Added<Client>>,
+    mut commands: Commands,
+) {
+    for (client, health) in &clients {
+        commands.entity(client).insert(HungerState {
+            last_health: health.0,
+            ..Default::default()
+        });
+    }
+}
+
+fn movement_exhaustion(
+    mut clients: Query<(&mut HungerState, &Flags, &GameMode, &EntityLayerId)>,
+    layers: Query<&ChunkLayer>,
+    mut movements: EventReader<MovementEvent>,
+) {
+    for movement in movements.read() {
+        let Ok((mut hunger, flags, game_mode, layer_id)) = clients.get_mut(movement.client) else {
+            continue;
+        };
+
+        if !uses_food(*game_mode) {
+            continue;
+        }
+
+        let delta = movement.position - movement.old_position;
+        let horizontal = (delta.x * delta.x + delta.z * delta.z).sqrt() as f32;
+
+        let in_water = layers.get(layer_id.0).is_ok_and(|layer| {
+            layer
+                .block(BlockPos {
+                    x: movement.position.x.floor() as i32,
+                    y: movement.position.y.floor() as i32,
+                    z: movement.position.z.floor() as i32,
+                })
+                .is_some_and(|b| b.state.to_kind() == BlockKind::Water)
+        });
+
+        if in_water {
+            hunger.add_exhaustion(exhaustion::SWIM_PER_METER * delta.length() as f32);
+        } else if flags.sprinting() {
+            hunger.add_exhaustion(exhaustion::SPRINT_PER_METER * horizontal);
+        }
+
+        if movement.old_on_ground && !movement.on_ground && delta.y > 0.0 && !in_water {
+            hunger.add_exhaustion(if flags.sprinting() {
+                exhaustion::SPRINT_JUMP
+            } else {
+                exhaustion::JUMP
+            });
+        }
+    }
+}
+
+fn action_exhaustion(
+    mut clients: Query<(&mut HungerState, &GameMode)>,
+    mut interact_entity: EventReader<InteractEntityEvent>,
+    mut digging: EventReader<DiggingEvent>,
+) {
+    for event in interact_entity.read() {
+        if event.interact != EntityInteraction::Attack {
+            continue;
+        }
+        if let Ok((mut hunger, game_mode)) = clients.get_mut(event.client) {
+            if uses_food(*game_mode) {
+                hunger.add_exhaustion(exhaustion::ATTACK);
+            }
+        }
+    }
+
+    for event in digging.read() {
+        if event.state != DiggingState::Stop {
+            continue;
+        }
+        if let Ok((mut hunger, game_mode)) = clients.get_mut(event.client) {
+            if uses_food(*game_mode) {
+                hunger.add_exhaustion(exhaustion::MINE);
+            }
+        }
+    }
+}
+
+fn damage_exhaustion(mut clients: Query<(&mut HungerState, &Health, &GameMode)>) {
+    for (mut hunger, health, game_mode) in &mut clients {
+        if health.0 < hunger.last_health && uses_food(*game_mode) {
+            hunger.add_exhaustion(exhaustion::DAMAGE);
+        }
+        if hunger.last_health != health.0 {
+            hunger.last_health = health.0;
+        }
+    }
+}
+
+/// The client stops sprinting by itself once its food level is too low, but
+/// a modified client could carry on, so the flag is cleared here as well.
+fn block_low_food_sprinting(
+    mut clients: Query<(&mut Flags, &Food, &GameMode)>,
+    mut sprinting: EventReader<SprintEvent>,
+) {
+    for event in sprinting.read() {
+        if event.state != SprintState::Start {
+            continue;
+        }
+        let Ok((mut flags, food, game_mode)) = clients.get_mut(event.client) else {
+            continue;
+        };
+        if uses_food(*game_mode) && food.0 <= SPRINT_FOOD_LEVEL {
+            flags.set_sprinting(false);
+        }
+    }
+}
+
+fn start_eating(
+    server: Res<Server>,
+    clients: Query<(&Inventory, &HeldItem, &Food, &GameMode), Without<Eating>>,
+    mut events: EventReader<InteractItemEvent>,
+    mut commands: Commands,
+) {
+    for event in events.read() {
+        if event.hand != Hand::Main {
+            continue;
+        }
+
+        let Ok((inventory, held, food, game_mode)) = clients.get(event.client) else {
+            continue;
+        };
+
+        let stack = inventory.slot(held.slot());
+        let Some(properties) = food_properties(stack.item) else {
+            continue;
+        };
+
+        if food.0 >= 20 && !properties.always_edible && *game_mode != GameMode::Creative {
+            continue;
+        }
+
+        commands.entity(event.client).insert(Eating {
+            slot: held.slot(),
+            item: stack.item,
+            finish_tick: server.current_tick() + properties.eat_ticks,
+        });
+    }
+}
+
+/// Cancels eating when the player lets go of the use button or switches to
+/// another item.
+fn stop_eating(
+    eating: Query<(Entity, &Eating, &Inventory, &HeldItem)>,
+    mut packets: EventReader<PacketEvent>,
+    mut commands: Commands,
+) {
+    for packet in packets.read() {
+        let Some(pkt) = packet.decode::<PlayerActionC2s>() else {
+            continue;
+        };
+        if pkt.action == PlayerAction::ReleaseUseItem && eating.contains(packet.client) {
+            commands.entity(packet.client).remove::<Eating>();
+        }
+    }
+
+    for (client, state, inventory, held) in &eating {
+        if held.slot() != state.slot || inventory.slot(state.slot).item != state.item {
+            commands.entity(client).remove::<Eating>();
+        }
+    }
+}
+
+fn finish_eating(
+    server: Res<Server>,
+    mut clients: Query<(
+        Entity,
+        &mut Client,
+        &Eating,
+        &mut Inventory,
+        &mut Food,
+        &mut Saturation,
+        &GameMode,
+    )>,
+    mut commands: Commands,
+) {
+    for (entity, mut client, state, mut inventory, mut food, mut saturation, game_mode) in &mut clients {
+        if server.current_tick() < state.finish_tick {
+            continue;
+        }
+
+        commands.entity(entity).remove::<Eating>();
+
+        let Some(properties) = food_properties(state.item) else {
+            continue;
+        };
+
+        food.0 = (food.0 + properties.nutrition).min(20);
+        saturation.0 = (saturation.0 + properties.saturation()).min(food.0 as f32);
+
+        client.trigger_status(EntityStatus::ConsumeItem);
+
+        if *game_mode == GameMode::Creative {
+            continue;
+        }
+
+        let stack = inventory.slot(state.slot);
+        if stack.count > 1 {
+            let amount = stack.count - 1;
+            inventory.set_slot_amount(state.slot, amount);
+            if let Some(remainder) = properties.remainder {
+                if let Some(slot) = inventory.first_empty_slot_in(9..45) {
+                    inventory.set_slot(slot, ItemStack::new(remainder, 1, None));
+                }
+            }
+        } else {
+            inventory.set_slot(
+                state.slot,
+                properties
+                    .remainder
+                    .map(|remainder| ItemStack::new(remainder, 1, None))
+                    .unwrap_or(ItemStack::EMPTY),
+            );
+        }
+    }
+}
+
+fn apply_exhaustion(
+    rules: Res<GameRules>,
+    mut clients: Query<(&mut HungerState, &mut Food, &mut Saturation)>,
+) {
+    for (mut hunger, mut food, mut saturation) in &mut clients {
+        if hunger.exhaustion < EXHAUSTION_PER_FOOD {
+            continue;
+        }
+
+        hunger.exhaustion -= EXHAUSTION_PER_FOOD;
+
+        if saturation.0 > 0.0 {
+            saturation.0 = (saturation.0 - 1.0).max(0.0);
+        } else if rules.difficulty != Difficulty::Peaceful {
+            food.0 = (food.0 - 1).max(0);
+        }
+    }
+}
+
+/// Maximum health of a player.
+const MAX_HEALTH: f32 = 20.0;
+
+/// Heals players over time, using up their saturation and food.
+///
+/// Players with full food and some saturation left heal quickly, while
+/// players who are only nearly full heal slowly.
+fn regenerate(
+    rules: Res<GameRules>,
+    mut clients: Query<(&mut HungerState, &Food, &Saturation, &mut Health)>,
+) {
+    for (mut hunger, food, saturation, mut health) in &mut clients {
+        if !rules.natural_regeneration || health.0 <= 0.0 || health.0 >= MAX_HEALTH {
+            hunger.regeneration_timer = 0;
+            continue;
+        }
+
+        if saturation.0 > 0.0 && food.0 >= 20 {
+            hunger.regeneration_timer += 1;
+            if hunger.regeneration_timer >= 10 {
+                let used = saturation.0.min(6.0);
+                health.0 = (health.0 + used / 6.0).min(MAX_HEALTH);
+                hunger.add_exhaustion(used);
+                hunger.regeneration_timer = 0;
+            }
+        } else if food.0 >= 18 {
+            hunger.regeneration_timer += 1;
+            if hunger.regeneration_timer >= 80 {
+                health.0 = (health.0 + 1.0).min(MAX_HEALTH);
+                hunger.add_exhaustion(exhaustion::REGENERATE);
+                hunger.regeneration_timer = 0;
+            }
+        } else {
+            hunger.regeneration_timer = 0;
+        }
+    }
+}
+
+/// Ticks between each point of starvation damage.
+const STARVATION_TICKS: i32 = 80;
+
+fn starve(
+    rules: Res<GameRules>,
+    mut clients: Query<(Entity, &mut HungerState, &Food, &Health, &GameMode)>,
+    mut damage_events: EventWriter<DamageEvent>,
+) {
+    for (entity, mut hunger, food, health, game_mode) in &mut clients {
+        if food.0 > 0 || !uses_food(*game_mode) || health.0 <= 0.0 {
+            hunger.starvation_timer = 0;
+            continue;
+        }
+
+        hunger.starvation_timer += 1;
+        if hunger.starvation_timer < STARVATION_TICKS {
+            continue;
+        }
+        hunger.starvation_timer = 0;
+
+        let min_health = match rules.difficulty {
+            Difficulty::Peaceful => {
+                continue;
+            }
+            Difficulty::Easy => 10.0,
+            Difficulty::Normal => 1.0,
+            Difficulty::Hard => 0.0,
+        };
+
+        if health.0 <= min_health {
+            continue;
+        }
+
+        damage_events.send(DamageEvent {
+            target: entity,
+            amount: 1.0,
+            source: DamageCause::Starvation.into(),
+        });
+    }
+}
+
+fn sync_health(
+    mut clients: Query<
+        (&mut Client, &Health, &Food, &Saturation),
+        Or<(Changed<Health>, Changed<Food>, Changed<Saturation>)>,
+    >,
+) {
+    for (mut client, health, food, saturation) in &mut clients {
+        client.write_packet(&HealthUpdateS2c {
+            health: health.0,
+            food: VarInt(food.0),
+            food_saturation: saturation.0,
+        });
+    }
+}
@@ -0,0 +1,273 @@
+use valence::entity::entity::Flags;
+use valence::interact_block::InteractBlockEvent;
+use valence::inventory::HeldItem;
+use valence::prelude::*;
+use valence::protocol::Hand;
+use valence::sound::{Sound, SoundCategory};
+use valence::Direction;
+
+use crate::block_behavior::{BlockBehavior, BlockBehaviors, BlockUse, NeighborUpdate};
+use crate::block_update::BlockUpdateEvent;
+use crate::building::{building, linked_half, CancelPlacingEvent};
+use crate::scheduled_tick::{ScheduledTick, ScheduledTicks, TickPriority};
+
+/// Right-clicking blocks that do something when used, like opening doors and
+/// flipping levers, instead of placing the held item against them.
+pub struct Interact;
+
+impl Plugin for Interact {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BlockBehaviors>()
+            .add_systems(Update, interact.before(building));
+
+        let mut behaviors = app.world_mut().resource_mut::<BlockBehaviors>();
+        for kind in BlockKind::ALL {
+            let name = kind.to_str();
+            if name.ends_with("_door") && kind != BlockKind::IronDoor {
+                behaviors.insert(kind, Door);
+            } else if name.ends_with("_trapdoor") && kind != BlockKind::IronTrapdoor {
+                behaviors.insert(kind, Trapdoor);
+            } else if name.ends_with("_fence_gate") {
+                behaviors.insert(kind, FenceGate);
+            } else if kind == BlockKind::Lever {
+                behaviors.insert(kind, Lever);
+            } else if name.ends_with("_button") {
+                behaviors.insert(kind, Button);
+            }
+        }
+    }
+}
+
+/// Slot of the off hand in the player's inventory.
+const OFF_HAND_SLOT: u16 = 45;
+
+/// Ticks a wooden button stays pressed for. Stone buttons are released sooner.
+const WOODEN_BUTTON_TICKS: i64 = 30;
+const STONE_BUTTON_TICKS: i64 = 20;
+
+/// Sneaking while holding something uses the item instead of the block that
+/// was clicked.
+pub fn sneak_bypasses(flags: &Flags, inventory: &Inventory, held: &HeldItem) -> bool {
+    flags.sneaking() && !(inventory.slot(held.slot()).is_empty() && inventory.slot(OFF_HAND_SLOT).is_empty())
+}
+
+fn block_center(position: BlockPos) -> DVec3 {
+    DVec3::new(
+        f64::from(position.x) + 0.5,
+        f64::from(position.y) + 0.5,
+        f64::from(position.z) + 0.5,
+    )
+}
+
+fn is_stone_button(kind: BlockKind) -> bool {
+    kind == BlockKind::StoneButton || kind == BlockKind::PolishedBlackstoneButton
+}
+
+fn toggle(state: BlockState, prop: PropName) -> BlockState {
+    if state.get(prop) == Some(PropValue::True) {
+        state.set(prop, PropValue::False)
+    } else {
+        state.set(prop, PropValue::True)
+    }
+}
+
+fn interact(
+    server: Res<Server>,
+    behaviors: Res<BlockBehaviors>,
+    clients: Query<(&Inventory, &HeldItem, &Flags, &Look, &GameMode, &EntityLayerId)>,
+    mut layers: Query<(&mut ChunkLayer, &mut ScheduledTicks)>,
+    mut events: EventReader<InteractBlockEvent>,
+    mut cancel: EventWriter<CancelPlacingEvent>,
+    mut block_updates: EventWriter<BlockUpdateEvent>,
+) {
+    for event in events.read() {
+        if event.hand != Hand::Main {
+            continue;
+        }
+        let Ok((inventory, held, flags, look, game_mode, &layer_id)) = clients.get(event.client) else {
+            continue;
+        };
+        if *game_mode == GameMode::Spectator {
+            continue;
+        }
+        if sneak_bypasses(flags, inventory, held) {
+            continue;
+        }
+        let Ok((mut layer, mut ticks)) = layers.get_mut(layer_id.0) else {
+            continue;
+        };
+        let Some(state) = layer.block(event.position).map(|b| b.state) else {
+            continue;
+        };
+        let Some(behavior) = behaviors.get(state.to_kind()) else {
+            continue;
+        };
+
+        let mut updates = Vec::new();
+        let used = behavior.on_use(&mut BlockUse {
+            layer: &mut layer,
+            layer_id,
+            position: event.position,
+            state,
+            client: event.client,
+            look: *look,
+            now: server.current_tick(),
+            ticks: &mut ticks,
+            updates: &mut updates,
+        });
+
+        for position in updates {
+            block_updates.send(BlockUpdateEvent {
+                position,
+                layer: layer_id.0,
+                entity_layer: layer_id,
+            });
+        }
+        if used {
+            cancel.send(CancelPlacingEvent { client: event.client });
+        }
+    }
+}
+
+/// Wooden doors open and close both halves together.
+struct Door;
+
+impl BlockBehavior for Door {
+    fn on_use(&self, block_use: &mut BlockUse) -> bool {
+        let (position, kind) = (block_use.position, block_use.state.to_kind());
+        let state = toggle(block_use.state, PropName::Open);
+        block_use.set_block(position, state);
+        if let Some(other) = linked_half(state, position) {
+            if let Some(other_state) = block_use.layer.block(other).map(|b| b.state).filter(|s| s.to_kind() == kind) {
+                block_use.set_block(other, other_state.set(PropName::Open, state.get(PropName::Open).unwrap()));
+            }
+        }
+        let sound = if state.get(PropName::Open) == Some(PropValue::True) {
+            Sound::BlockWoodenDoorOpen
+        } else {
+            Sound::BlockWoodenDoorClose
+        };
+        block_use.layer.play_sound(sound, SoundCategory::Block, block_center(position), 1.0, 1.0);
+        true
+    }
+}
+
+struct Trapdoor;
+
+impl BlockBehavior for Trapdoor {
+    fn on_use(&self, block_use: &mut BlockUse) -> bool {
+        let position = block_use.position;
+        let state = toggle(block_use.state, PropName::Open);
+        block_use.set_block(position, state);
+        let sound = if state.get(PropName::Open) == Some(PropValue::True) {
+            Sound::BlockWoodenTrapdoorOpen
+        } else {
+            Sound::BlockWoodenTrapdoorClose
+        };
+        block_use.layer.play_sound(sound, SoundCategory::Block, block_center(position), 1.0, 1.0);
+        true
+    }
+}
+
+/// Fence gates swing open away from whoever opens them, and sit lower when
+/// they're set in a wall.
+struct FenceGate;
+
+impl BlockBehavior for FenceGate {
+    fn on_neighbor_update(&self, update: &mut NeighborUpdate) {
+        let is_wall = |state: Option<BlockState>| state.is_some_and(|s| s.get(PropName::Up).is_some());
+        let in_wall = match update.block.state.get(PropName::Facing) {
+            Some(PropValue::North) | Some(PropValue::South) => {
+                is_wall(update.neighbor(Direction::East)) || is_wall(update.neighbor(Direction::West))
+            }
+            _ => is_wall(update.neighbor(Direction::North)) || is_wall(update.neighbor(Direction::South)),
+        };
+        update.block.state = update.block.state.set(
+            PropName::InWall,
+            if in_wall {
+                PropValue::True
+            } else {
+                PropValue::False
+            },
+        );
+    }
+
+    fn on_use(&self, block_use: &mut BlockUse) -> bool {
+        let position = block_use.position;
+        let mut state = toggle(block_use.state, PropName::Open);
+        if state.get(PropName::Open) == Some(PropValue::True) {
+            // Gates always swing away from the player opening them.
+            let (facing, behind) = match (block_use.look.yaw.floor() as i32).rem_euclid(360) {
+                45..135 => (PropValue::West, PropValue::East),
+                135..225 => (PropValue::North, PropValue::South),
+                225..315 => (PropValue::East, PropValue::West),
+                _ => (PropValue::South, PropValue::North),
+            };
+            if state.get(PropName::Facing) == Some(behind) {
+                state = state.set(PropName::Facing, facing);
+            }
+        }
+        block_use.set_block(position, state);
+        let sound = if state.get(PropName::Open) == Some(PropValue::True) {
+            Sound::BlockFenceGateOpen
+        } else {
+            Sound::BlockFenceGateClose
+        };
+        block_use.layer.play_sound(sound, SoundCategory::Block, block_center(position), 1.0, 1.0);
+        true
+    }
+}
+
+struct Lever;
+
+impl BlockBehavior for Lever {
+    fn on_use(&self, block_use: &mut BlockUse) -> bool {
+        let position = block_use.position;
+        let state = toggle(block_use.state, PropName::Powered);
+        block_use.set_block(position, state);
+        let pitch = if state.get(PropName::Powered) == Some(PropValue::True) { 0.6 } else { 0.5 };
+        block_use.layer.play_sound(Sound::BlockLeverClick, SoundCategory::Block, block_center(position), 0.3, pitch);
+        true
+    }
+}
+
+/// Buttons stay pressed in for a moment, then pop back out on a scheduled
+/// tick.
+struct Button;
+
+impl BlockBehavior for Button {
+    fn on_use(&self, block_use: &mut BlockUse) -> bool {
+        // Pressing a button that is already in does nothing, but still
+        // doesn't place anything against it.
+        if block_use.state.get(PropName::Powered) == Some(PropValue::True) {
+            return true;
+        }
+        let (position, kind) = (block_use.position, block_use.state.to_kind());
+        block_use.set_block(position, block_use.state.set(PropName::Powered, PropValue::True));
+        let (sound, ticks) = if is_stone_button(kind) {
+            (Sound::BlockStoneButtonClickOn, STONE_BUTTON_TICKS)
+        } else {
+            (Sound::BlockWoodenButtonClickOn, WOODEN_BUTTON_TICKS)
+        };
+        block_use.layer.play_sound(sound, SoundCategory::Block, block_center(position), 0.3, 0.6);
+        block_use.schedule(position, kind, ticks, TickPriority::Normal);
+        true
+    }
+
+    fn on_scheduled_tick(&self, tick: &mut ScheduledTick) {
+        // The button may have been broken while it was pressed.
+        if !tick.state.to_kind().to_str().ends_with("_button")
+            || tick.state.get(PropName::Powered) != Some(PropValue::True)
+        {
+            return;
+        }
+
+        tick.set_block(tick.position, tick.state.set(PropName::Powered, PropValue::False));
+        let sound = if is_stone_button(tick.state.to_kind()) {
+            Sound::BlockStoneButtonClickOff
+        } else {
+            Sound::BlockWoodenButtonClickOff
+        };
+        tick.layer.play_sound(sound, SoundCategory::Block, block_center(tick.position), 0.3, 0.5);
+    }
+}
@@ -0,0 +1,137 @@
+use valence::nbt::{List, Value};
+use valence::prelude::*;
+use valence::rand::Rng;
+
+/// Returns the level of the enchantment with the given id on the stack, or 0 if
+/// the stack doesn't have it.
+///
+/// The id is namespaced, as in `"minecraft:protection"`.
+pub fn enchantment_level(stack: &ItemStack, id: &str) -> i32 {
+    let Some(Value::List(List::Compound(enchantments))) = stack.nbt.as_ref().and_then(|nbt| nbt.get("Enchantments")) else {
+        return 0;
+    };
+
+    enchantments
+        .iter()
+        .filter(|enchantment| matches!(enchantment.get("id"), Some(Value::String(s)) if s == id))
+        .filter_map(|enchantment| match enchantment.get("lvl") {
+            Some(&Value::Short(level)) => Some(i32::from(level)),
+            Some(&Value::Int(level)) => Some(level),
+            Some(&Value::Byte(level)) => Some(i32::from(level)),
+            _ => None,
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// Returns the name given to the stack in an anvil, if it has one.
+pub fn custom_name(stack: &ItemStack) -> Option<Text> {
+    let Some(Value::Compound(display)) = stack.nbt.as_ref()?.get("display") else {
+        return None;
+    };
+    let Some(Value::String(name)) = display.get("Name") else {
+        return None;
+    };
+
+    Some(name.parse().unwrap_or_else(|_| Text::text(name.clone())))
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ToolKind {
+    Pickaxe,
+    Axe,
+    Shovel,
+    Hoe,
+    Sword,
+    Shears,
+}
+
+/// How good a tool's material is. Blocks that need a tool also need at least a
+/// certain tier of it, such as diamond ore needing an iron pickaxe.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ToolTier {
+    /// Wood and gold.
+    Wood,
+    Stone,
+    Iron,
+    Diamond,
+    Netherite,
+}
+
+/// Returns what kind of tool an item is, and its tier.
+pub fn tool(item: ItemKind) -> Option<(ToolKind, ToolTier)> {
+    Some(match item {
+        ItemKind::WoodenPickaxe | ItemKind::GoldenPickaxe => (ToolKind::Pickaxe, ToolTier::Wood),
+        ItemKind::StonePickaxe => (ToolKind::Pickaxe, ToolTier::Stone),
+        ItemKind::IronPickaxe => (ToolKind::Pickaxe, ToolTier::Iron),
+        ItemKind::DiamondPickaxe => (ToolKind::Pickaxe, ToolTier::Diamond),
+        ItemKind::NetheritePickaxe => (ToolKind::Pickaxe, ToolTier::Netherite),
+
+        ItemKind::WoodenAxe | ItemKind::GoldenAxe => (ToolKind::Axe, ToolTier::Wood),
+        ItemKind::StoneAxe => (ToolKind::Axe, ToolTier::Stone),
+        ItemKind::IronAxe => (ToolKind::Axe, ToolTier::Iron),
+        ItemKind::DiamondAxe => (ToolKind::Axe, ToolTier::Diamond),
+        ItemKind::NetheriteAxe => (ToolKind::Axe, ToolTier::Netherite),
+
+        ItemKind::WoodenShovel | ItemKind::GoldenShovel => (ToolKind::Shovel, ToolTier::Wood),
+        ItemKind::StoneShovel => (ToolKind::Shovel, ToolTier::Stone),
+        ItemKind::IronShovel => (ToolKind::Shovel, ToolTier::Iron),
+        ItemKind::DiamondShovel => (ToolKind::Shovel, ToolTier::Diamond),
+        ItemKind::NetheriteShovel => (ToolKind::Shovel, ToolTier::Netherite),
+
+        ItemKind::WoodenHoe | ItemKind::GoldenHoe => (ToolKind::Hoe, ToolTier::Wood),
+        ItemKind::StoneHoe => (ToolKind::Hoe, ToolTier::Stone),
+        ItemKind::IronHoe => (ToolKind::Hoe, ToolTier::Iron),
+        ItemKind::DiamondHoe => (ToolKind::Hoe, ToolTier::Diamond),
+        ItemKind::NetheriteHoe => (ToolKind::Hoe, ToolTier::Netherite),
+
+        ItemKind::WoodenSword | ItemKind::GoldenSword => (ToolKind::Sword, ToolTier::Wood),
+        ItemKind::StoneSword => (ToolKind::Sword, ToolTier::Stone),
+        ItemKind::IronSword => (ToolKind::Sword, ToolTier::Iron),
+        ItemKind::DiamondSword => (ToolKind::Sword, ToolTier::Diamond),
+        ItemKind::NetheriteSword => (ToolKind::Sword, ToolTier::Netherite),
+
+        ItemKind::Shears => (ToolKind::Shears, ToolTier::Wood),
+
+        _ => return None,
+    })
+}
+
+/// Returns how much durability the stack has used up, from its `Damage` tag.
+pub fn damage(stack: &ItemStack) -> i32 {
+    match stack.nbt.as_ref().and_then(|nbt| nbt.get("Damage")) {
+        Some(&Value::Int(damage)) => damage,
+        _ => 0,
+    }
+}
+
+/// Uses up `amount` durability of the stack, taking Unbreaking into account.
+/// The stack is emptied if it breaks. Items without durability are left alone.
+///
+/// Returns whether the stack broke.
+pub fn damage_item(stack: &mut ItemStack, amount: i32, rng: &mut impl Rng) -> bool {
+    let max = i32::from(stack.item.max_durability());
+    if max == 0 || stack.is_empty() {
+        return false;
+    }
+
+    // Each point of damage is ignored with a chance of `level / (level + 1)`.
+    let unbreaking = enchantment_level(stack, "minecraft:unbreaking");
+    let amount = (0..amount)
+        .filter(|_| unbreaking == 0 || rng.gen_range(0..=unbreaking) == 0)
+        .count() as i32;
+    if amount == 0 {
+        return false;
+    }
+
+    let damage = damage(stack) + amount;
+    if damage >= max {
+        *stack = ItemStack::EMPTY;
+        return true;
+    }
+
+    let mut nbt = stack.nbt.take().unwrap_or_default();
+    nbt.insert("Damage", Value::Int(damage));
+    stack.nbt = Some(nbt);
+    false
+}
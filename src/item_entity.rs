@@ -0,0 +1,323 @@
+use std::f32::consts::TAU;
+
+use valence::entity::item::{ItemEntityBundle, Stack};
+use valence::entity::living::Health;
+use valence::entity::{EntityId, Velocity};
+use valence::event_loop::PacketEvent;
+use valence::inventory::{CursorItem, DropItemStackEvent};
+use valence::math::Aabb;
+use valence::prelude::*;
+use valence::protocol::packets::play::{CloseHandledScreenC2s, ItemPickupAnimationS2c};
+use valence::protocol::{VarInt, WritePacket};
+use valence::rand::{thread_rng, Rng};
+
+use crate::players::EYE_HEIGHT;
+
+/// Dropped items: spawning them when players throw items away, letting players
+/// pick them up, merging nearby stacks and despawning old ones.
+pub struct ItemEntities;
+
+impl Plugin for ItemEntities {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                (drop_item_stacks, drop_cursor_on_close),
+                init_items,
+                age_items,
+                merge_items,
+                pick_up_items,
+            )
+                .chain(),
+        );
+    }
+}
+
+/// Ticks a dropped item lasts for before it despawns.
+pub const DESPAWN_AGE: i32 = 6000;
+
+/// Pickup delay of items dropped by players, so they don't pick them straight
+/// back up.
+pub const PLAYER_DROP_PICKUP_DELAY: i32 = 40;
+
+/// Pickup delay of items dropped by blocks and everything else.
+pub const DEFAULT_PICKUP_DELAY: i32 = 10;
+
+/// State of a dropped item entity.
+#[derive(Component, Debug, Copy, Clone)]
+pub struct DroppedItem {
+    /// Ticks left until the item can be picked up.
+    pub pickup_delay: i32,
+    /// Ticks since the item was dropped.
+    pub age: i32,
+}
+
+impl Default for DroppedItem {
+    fn default() -> Self {
+        Self {
+            pickup_delay: DEFAULT_PICKUP_DELAY,
+            age: 0,
+        }
+    }
+}
+
+/// Spawns a dropped item entity. `velocity` is in blocks per second.
+pub fn spawn_item(
+    commands: &mut Commands,
+    layer: EntityLayerId,
+    position: DVec3,
+    stack: ItemStack,
+    velocity: Vec3,
+    pickup_delay: i32,
+) -> Entity {
+    commands
+        .spawn((
+            ItemEntityBundle {
+                item_stack: Stack(stack),
+                layer,
+                position: Position(position),
+                velocity: Velocity(velocity),
+                ..Default::default()
+            },
+            DroppedItem {
+                pickup_delay,
+                age: 0,
+            },
+        ))
+        .id()
+}
+
+/// Throws an item from a player in the direction they're looking, with a
+/// little randomness.
+pub fn throw_item(commands: &mut Commands, position: DVec3, look: Look, layer: EntityLayerId, stack: ItemStack) {
+    let mut rng = thread_rng();
+
+    let (pitch_sin, pitch_cos) = look.pitch.to_radians().sin_cos();
+    let (yaw_sin, yaw_cos) = look.yaw.to_radians().sin_cos();
+    let angle = rng.gen_range(0.0..TAU);
+    let spread = 0.02 * rng.gen::<f32>();
+
+    let velocity = Vec3::new(
+        -yaw_sin * pitch_cos * 0.3 + angle.cos() * spread,
+        -pitch_sin * 0.3 + 0.1 + (rng.gen::<f32>() - rng.gen::<f32>()) * 0.1,
+        yaw_cos * pitch_cos * 0.3 + angle.sin() * spread,
+    ) * 20.0;
+
+    spawn_item(
+        commands,
+        layer,
+        position + DVec3::new(0.0, EYE_HEIGHT - 0.3, 0.0),
+        stack,
+        velocity,
+        PLAYER_DROP_PICKUP_DELAY,
+    );
+}
+
+/// Spawns the items players drop with Q, or by clicking outside of an open
+/// inventory. The stacks have already been taken out of the inventory.
+fn drop_item_stacks(
+    clients: Query<(&Position, &Look, &EntityLayerId)>,
+    mut events: EventReader<DropItemStackEvent>,
+    mut commands: Commands,
+) {
+    for event in events.read() {
+        let Ok((position, &look, &layer)) = clients.get(event.client) else {
+            continue;
+        };
+        if event.stack.is_empty() {
+            continue;
+        }
+
+        throw_item(&mut commands, position.0, look, layer, event.stack.clone());
+    }
+}
+
+/// Throws out whatever the player was holding on their cursor when they close
+/// an inventory.
+fn drop_cursor_on_close(
+    mut clients: Query<(&Position, &Look, &EntityLayerId, &mut CursorItem)>,
+    mut packets: EventReader<PacketEvent>,
+    mut commands: Commands,
+) {
+    for packet in packets.read() {
+        if packet.decode::<CloseHandledScreenC2s>().is_none() {
+            continue;
+        }
+        let Ok((position, &look, &layer, mut cursor)) = clients.get_mut(packet.client) else {
+            continue;
+        };
+        if cursor.0.is_empty() {
+            continue;
+        }
+
+        let stack = std::mem::replace(&mut cursor.0, ItemStack::EMPTY);
+        throw_item(&mut commands, position.0, look, layer, stack);
+    }
+}
+
+/// Gives item entities spawned elsewhere, like from spawn eggs or other
+/// plugins, the default item state.
+fn init_items(items: Query<Entity, (Added<Stack>, Without<DroppedItem>)>, mut commands: Commands) {
+    for item in &items {
+        commands.entity(item).insert(DroppedItem::default());
+    }
+}
+
+fn age_items(mut items: Query<(Entity, &mut DroppedItem)>, mut commands: Commands) {
+    for (entity, mut item) in &mut items {
+        if item.pickup_delay > 0 {
+            item.pickup_delay -= 1;
+        }
+
+        item.age += 1;
+        if item.age >= DESPAWN_AGE {
+            commands.entity(entity).insert(Despawned);
+        }
+    }
+}
+
+/// Whether two stacks are the same item and can be merged into one.
+fn stackable(a: &ItemStack, b: &ItemStack) -> bool {
+    a.item == b.item && a.nbt == b.nbt
+}
+
+/// Merges identical item stacks lying close together, so that piles of items
+/// don't become piles of entities.
+fn merge_items(
+    mut items: Query<(Entity, &Position, &EntityLayerId, &mut Stack, &mut DroppedItem), Without<Despawned>>,
+    mut commands: Commands,
+) {
+    let candidates = items
+        .iter()
+        .filter(|(.., stack, _)| stack.0.count < stack.0.item.max_stack())
+        .map(|(entity, position, &layer, ..)| (entity, position.0, layer))
+        .collect::<Vec<_>>();
+
+    let mut merged = Vec::new();
+
+    for (i, &(a, a_pos, a_layer)) in candidates.iter().enumerate() {
+        if merged.contains(&a) {
+            continue;
+        }
+
+        for &(b, b_pos, b_layer) in &candidates[i + 1..] {
+            if merged.contains(&b) || a_layer != b_layer {
+                continue;
+            }
+            // Items merge when they are within half a block of each other
+            // horizontally and at about the same height.
+            if (a_pos.x - b_pos.x).abs() > 0.5 || (a_pos.z - b_pos.z).abs() > 0.5 || (a_pos.y - b_pos.y).abs() > 0.25 {
+                continue;
+            }
+
+            let Ok([(_, _, _, mut a_stack, mut a_item), (_, _, _, mut b_stack, b_item)]) = items.get_many_mut([a, b]) else {
+                continue;
+            };
+            if !stackable(&a_stack.0, &b_stack.0)
+                || i32::from(a_stack.0.count) + i32::from(b_stack.0.count) > i32::from(a_stack.0.item.max_stack())
+            {
+                continue;
+            }
+
+            // The bigger stack swallows the smaller one.
+            if a_stack.0.count < b_stack.0.count {
+                std::mem::swap(&mut a_stack.0, &mut b_stack.0);
+            }
+            a_stack.0.count += b_stack.0.count;
+            b_stack.0 = ItemStack::EMPTY;
+            a_item.pickup_delay = a_item.pickup_delay.max(b_item.pickup_delay);
+            a_item.age = a_item.age.min(b_item.age);
+
+            commands.entity(b).insert(Despawned);
+            merged.push(b);
+
+            if a_stack.0.count >= a_stack.0.item.max_stack() {
+                break;
+            }
+        }
+    }
+}
+
+/// Puts as much of `stack` into the player's inventory as fits, topping up
+/// existing stacks before using empty slots, and the hotbar before the rest.
+/// Returns how many items were taken.
+pub fn insert_into_inventory(inventory: &mut Inventory, stack: &ItemStack) -> i8 {
+    let mut remaining = stack.count;
+    let max_stack = stack.item.max_stack();
+
+    for slot in (36..45).chain(9..36).chain(45..46) {
+        if remaining == 0 {
+            break;
+        }
+        let existing = inventory.slot(slot);
+        if existing.is_empty() || !stackable(existing, stack) || existing.count >= max_stack {
+            continue;
+        }
+        let added = remaining.min(max_stack - existing.count);
+        let count = existing.count + added;
+        inventory.set_slot_amount(slot, count);
+        remaining -= added;
+    }
+
+    for slot in (36..45).chain(9..36) {
+        if remaining == 0 {
+            break;
+        }
+        if !inventory.slot(slot).is_empty() {
+            continue;
+        }
+        let added = remaining.min(max_stack);
+        inventory.set_slot(slot, ItemStack::new(stack.item, added, stack.nbt.clone()));
+        remaining -= added;
+    }
+
+    stack.count - remaining
+}
+
+fn pick_up_items(
+    mut clients: Query<(&EntityId, &Hitbox, &EntityLayerId, &mut Inventory, &GameMode, &Health), With<Client>>,
+    mut items: Query<(Entity, &EntityId, &Position, &EntityLayerId, &mut Stack, &DroppedItem), Without<Despawned>>,
+    mut layers: Query<&mut EntityLayer>,
+    mut commands: Commands,
+) {
+    for (collector_id, hitbox, layer_id, mut inventory, game_mode, health) in &mut clients {
+        if *game_mode == GameMode::Spectator || health.0 <= 0.0 {
+            continue;
+        }
+
+        // Players pick up items a block to either side of them, and half a
+        // block above and below.
+        let hitbox = hitbox.get();
+        let reach = Aabb::new(
+            hitbox.min() - DVec3::new(1.0, 0.5, 1.0),
+            hitbox.max() + DVec3::new(1.0, 0.5, 1.0),
+        );
+
+        for (item, item_id, item_position, item_layer, mut stack, state) in &mut items {
+            if state.pickup_delay > 0 || item_layer != layer_id || stack.0.is_empty() {
+                continue;
+            }
+            if !reach.contains_point(item_position.0) {
+                continue;
+            }
+
+            let taken = insert_into_inventory(&mut inventory, &stack.0);
+            if taken == 0 {
+                continue;
+            }
+
+            if let Ok(mut layer) = layers.get_mut(layer_id.0) {
+                layer.write_packet(&ItemPickupAnimationS2c {
+                    collected_entity_id: VarInt(item_id.get()),
+                    collector_entity_id: VarInt(collector_id.get()),
+                    pickup_item_count: VarInt(i32::from(taken)),
+                });
+            }
+
+            stack.0.count -= taken;
+            if stack.0.is_empty() {
+                commands.entity(item).insert(Despawned);
+            }
+        }
+    }
+}
@@ -2,6 +2,7 @@
 
 pub mod building;
 pub mod block_update;
+pub mod block_behavior;
 pub mod players;
 pub mod terrain;
 pub mod save;
@@ -14,6 +15,23 @@ pub mod physics;
 pub mod command;
 pub mod combat;
 pub mod death;
+pub mod damage;
+pub mod hunger;
+pub mod environment;
+pub mod respawn;
+pub mod rules;
+pub mod item;
+pub mod item_entity;
+pub mod loot;
+pub mod mining;
+pub mod placement;
+pub mod interact;
+pub mod container;
+pub mod smelting;
+pub mod crafting;
+pub mod random_tick;
+pub mod scheduled_tick;
+pub mod farming;
 
 pub const SPAWN_POS: DVec3 = DVec3::new(0., 70., 0.);
 
@@ -28,16 +46,35 @@ impl Plugin for Vanilla {
     fn build(&self, app: &mut App) {
         app.add_plugins(
             (
-                server_list::ServerList,
-                DefaultPlugins,
-                building::Building,
-                players::Players,
-                exit::Exit,
-                explosion::Explosion,
-                physics::Physics,
-                command::Command,
-                combat::Combat,
-                death::Death,
+                (
+                    server_list::ServerList,
+                    DefaultPlugins,
+                    building::Building,
+                    players::Players,
+                    exit::Exit,
+                    explosion::Explosion,
+                    physics::Physics,
+                    command::Command,
+                    random_tick::RandomTicks,
+                    scheduled_tick::TickScheduler,
+                    farming::Farming,
+                ),
+                (
+                    combat::Combat,
+                    death::Death,
+                    damage::Damage,
+                    hunger::Hunger,
+                    environment::Environment,
+                    respawn::Respawn,
+                    loot::Loot,
+                    item_entity::ItemEntities,
+                    mining::Mining,
+                    placement::Placement,
+                    interact::Interact,
+                    container::Containers,
+                    smelting::Smelting,
+                    crafting::Crafting,
+                ),
             )
         );
     }
@@ -59,4 +96,20 @@ pub mod prelude {
     pub use command::Command;
     pub use combat::Combat;
     pub use death::Death;
+    pub use damage::Damage;
+    pub use hunger::Hunger;
+    pub use environment::Environment;
+    pub use respawn::Respawn;
+    pub use loot::Loot;
+    pub use item_entity::ItemEntities;
+    pub use mining::Mining;
+    pub use placement::Placement;
+    pub use interact::Interact;
+    pub use container::Containers;
+    pub use smelting::Smelting;
+    pub use crafting::Crafting;
+    pub use random_tick::RandomTicks;
+    pub use scheduled_tick::TickScheduler;
+    pub use farming::Farming;
+    pub use block_behavior::{BlockBehavior, BlockBehaviors};
 }
@@ -0,0 +1,633 @@
+use std::collections::HashMap;
+
+use valence::inventory::HeldItem;
+use valence::prelude::*;
+use valence::rand::{thread_rng, Rng};
+
+use crate::building::{digging, BlockBreakEvent};
+use crate::item::{damage_item, enchantment_level, tool, ToolKind, ToolTier};
+use crate::item_entity::{spawn_item, DEFAULT_PICKUP_DELAY};
+use crate::mining::{hardness, Hardness};
+
+/// Drops items when blocks are broken in survival, according to the
+/// [`LootTables`] resource.
+pub struct Loot;
+
+impl Plugin for Loot {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LootTables>()
+            .add_systems(Update, drop_block_loot.after(digging));
+    }
+}
+
+/// What each kind of block drops when broken. Blocks without an entry drop
+/// their own item, if they have one.
+///
+/// Insert entries with [`LootTables::set`] to change what a block drops.
+#[derive(Resource, Debug, Clone)]
+pub struct LootTables {
+    blocks: HashMap<BlockKind, BlockLoot>,
+}
+
+impl LootTables {
+    pub fn get(&self, kind: BlockKind) -> BlockLoot {
+        self.blocks.get(&kind).cloned().unwrap_or_else(BlockLoot::itself)
+    }
+
+    pub fn set(&mut self, kind: BlockKind, loot: BlockLoot) {
+        self.blocks.insert(kind, loot);
+    }
+}
+
+/// The loot table of a single kind of block.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockLoot {
+    /// The tool, and minimum tier of it, the block must be broken with to drop
+    /// anything.
+    pub requires: Option<(ToolKind, ToolTier)>,
+    /// Whether the block drops itself instead of `drops` when broken with a
+    /// Silk Touch tool.
+    pub silk_touch: bool,
+    /// Whether the block drops itself instead of `drops` when broken with
+    /// shears.
+    pub shears: bool,
+    /// Whether only the first entry in `drops` that succeeds its chance roll
+    /// drops, rather than every one.
+    pub first_only: bool,
+    pub drops: Vec<LootEntry>,
+}
+
+impl BlockLoot {
+    pub fn new(drops: Vec<LootEntry>) -> Self {
+        Self {
+            requires: None,
+            silk_touch: false,
+            shears: false,
+            first_only: false,
+            drops,
+        }
+    }
+
+    /// Drops a single copy of the block's own item.
+    pub fn itself() -> Self {
+        Self::new(vec![LootEntry::itself()])
+    }
+
+    /// Drops nothing, unless mined with Silk Touch or shears if enabled.
+    pub fn none() -> Self {
+        Self::new(Vec::new())
+    }
+
+    pub fn requires(mut self, tool: ToolKind, tier: ToolTier) -> Self {
+        self.requires = Some((tool, tier));
+        self
+    }
+
+    pub fn silk_touch(mut self) -> Self {
+        self.silk_touch = true;
+        self
+    }
+
+    pub fn shears(mut self) -> Self {
+        self.shears = true;
+        self
+    }
+
+    pub fn first_only(mut self) -> Self {
+        self.first_only = true;
+        self
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum LootItem {
+    /// The item of the block that was broken.
+    Itself,
+    Item(ItemKind),
+}
+
+/// How the Fortune enchantment changes what an entry drops.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Fortune {
+    None,
+    /// Multiplies the count by a random bonus, like ores.
+    Ore,
+    /// Adds up to `multiplier * level` extra items.
+    UniformBonus(i32),
+    /// Replaces the entry's chance with one per Fortune level, from no Fortune
+    /// up to level 3.
+    Chances([f32; 4]),
+}
+
+/// A single item a block can drop.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LootEntry {
+    pub item: LootItem,
+    pub min: i32,
+    pub max: i32,
+    /// The chance of the entry dropping at all, between 0 and 1.
+    pub chance: f32,
+    pub fortune: Fortune,
+    /// The most items the entry can drop, even with Fortune.
+    pub limit: i32,
+}
+
+impl LootEntry {
+    pub const fn itself() -> Self {
+        Self {
+            item: LootItem::Itself,
+            min: 1,
+            max: 1,
+            chance: 1.0,
+            fortune: Fortune::None,
+            limit: 64,
+        }
+    }
+
+    pub const fn item(item: ItemKind) -> Self {
+        Self {
+            item: LootItem::Item(item),
+            ..Self::itself()
+        }
+    }
+
+    pub const fn count(mut self, min: i32, max: i32) -> Self {
+        self.min = min;
+        self.max = max;
+        self
+    }
+
+    pub const fn chance(mut self, chance: f32) -> Self {
+        self.chance = chance;
+        self
+    }
+
+    pub const fn fortune(mut self, fortune: Fortune) -> Self {
+        self.fortune = fortune;
+        self
+    }
+
+    pub const fn limit(mut self, limit: i32) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Rolls how many items this entry drops, which may be none.
+    fn roll(&self, fortune: i32, rng: &mut impl Rng) -> i32 {
+        let chance = match self.fortune {
+            Fortune::Chances(chances) => chances[fortune.clamp(0, 3) as usize],
+            _ => self.chance,
+        };
+        if chance < 1.0 && rng.gen::<f32>() >= chance {
+            return 0;
+        }
+
+        let mut count = rng.gen_range(self.min..=self.max);
+        match self.fortune {
+            Fortune::Ore if fortune > 0 => {
+                count *= rng.gen_range(0..fortune + 2).max(1);
+            }
+            Fortune::UniformBonus(multiplier) if fortune > 0 => {
+                count += rng.gen_range(0..=multiplier * fortune);
+            }
+            _ => {}
+        }
+
+        count.min(self.limit)
+    }
+}
+
+/// Blocks that need any pickaxe to drop.
+const PICKAXE_BLOCKS: &[BlockKind] = &[
+    BlockKind::Cobblestone,
+    BlockKind::MossyCobblestone,
+    BlockKind::Granite,
+    BlockKind::PolishedGranite,
+    BlockKind::Diorite,
+    BlockKind::PolishedDiorite,
+    BlockKind::Andesite,
+    BlockKind::PolishedAndesite,
+    BlockKind::CobbledDeepslate,
+    BlockKind::PolishedDeepslate,
+    BlockKind::DeepslateBricks,
+    BlockKind::CrackedDeepslateBricks,
+    BlockKind::DeepslateTiles,
+    BlockKind::CrackedDeepslateTiles,
+    BlockKind::ChiseledDeepslate,
+    BlockKind::Tuff,
+    BlockKind::Calcite,
+    BlockKind::DripstoneBlock,
+    BlockKind::PointedDripstone,
+    BlockKind::StoneBricks,
+    BlockKind::MossyStoneBricks,
+    BlockKind::CrackedStoneBricks,
+    BlockKind::ChiseledStoneBricks,
+    BlockKind::SmoothStone,
+    BlockKind::Bricks,
+    BlockKind::MudBricks,
+    BlockKind::Sandstone,
+    BlockKind::ChiseledSandstone,
+    BlockKind::CutSandstone,
+    BlockKind::SmoothSandstone,
+    BlockKind::RedSandstone,
+    BlockKind::ChiseledRedSandstone,
+    BlockKind::CutRedSandstone,
+    BlockKind::SmoothRedSandstone,
+    BlockKind::Netherrack,
+    BlockKind::NetherBricks,
+    BlockKind::RedNetherBricks,
+    BlockKind::CrackedNetherBricks,
+    BlockKind::ChiseledNetherBricks,
+    BlockKind::NetherBrickFence,
+    BlockKind::Basalt,
+    BlockKind::PolishedBasalt,
+    BlockKind::SmoothBasalt,
+    BlockKind::Blackstone,
+    BlockKind::GildedBlackstone,
+    BlockKind::PolishedBlackstone,
+    BlockKind::ChiseledPolishedBlackstone,
+    BlockKind::PolishedBlackstoneBricks,
+    BlockKind::CrackedPolishedBlackstoneBricks,
+    BlockKind::EndStone,
+    BlockKind::EndStoneBricks,
+    BlockKind::PurpurBlock,
+    BlockKind::PurpurPillar,
+    BlockKind::Prismarine,
+    BlockKind::PrismarineBricks,
+    BlockKind::DarkPrismarine,
+    BlockKind::QuartzBlock,
+    BlockKind::ChiseledQuartzBlock,
+    BlockKind::QuartzPillar,
+    BlockKind::QuartzBricks,
+    BlockKind::SmoothQuartz,
+    BlockKind::MagmaBlock,
+    BlockKind::AmethystBlock,
+    BlockKind::CoalBlock,
+    BlockKind::RedstoneBlock,
+    BlockKind::Terracotta,
+    BlockKind::WhiteTerracotta,
+    BlockKind::OrangeTerracotta,
+    BlockKind::MagentaTerracotta,
+    BlockKind::LightBlueTerracotta,
+    BlockKind::YellowTerracotta,
+    BlockKind::LimeTerracotta,
+    BlockKind::PinkTerracotta,
+    BlockKind::GrayTerracotta,
+    BlockKind::LightGrayTerracotta,
+    BlockKind::CyanTerracotta,
+    BlockKind::PurpleTerracotta,
+    BlockKind::BlueTerracotta,
+    BlockKind::BrownTerracotta,
+    BlockKind::GreenTerracotta,
+    BlockKind::RedTerracotta,
+    BlockKind::BlackTerracotta,
+    BlockKind::WhiteConcrete,
+    BlockKind::OrangeConcrete,
+    BlockKind::MagentaConcrete,
+    BlockKind::LightBlueConcrete,
+    BlockKind::YellowConcrete,
+    BlockKind::LimeConcrete,
+    BlockKind::PinkConcrete,
+    BlockKind::GrayConcrete,
+    BlockKind::LightGrayConcrete,
+    BlockKind::CyanConcrete,
+    BlockKind::PurpleConcrete,
+    BlockKind::BlueConcrete,
+    BlockKind::BrownConcrete,
+    BlockKind::GreenConcrete,
+    BlockKind::RedConcrete,
+    BlockKind::BlackConcrete,
+    BlockKind::Furnace,
+    BlockKind::BlastFurnace,
+    BlockKind::Smoker,
+    BlockKind::Stonecutter,
+    BlockKind::Grindstone,
+    BlockKind::Dispenser,
+    BlockKind::Dropper,
+    BlockKind::Observer,
+    BlockKind::BrewingStand,
+    BlockKind::Cauldron,
+    BlockKind::Hopper,
+    BlockKind::Anvil,
+    BlockKind::ChippedAnvil,
+    BlockKind::DamagedAnvil,
+    BlockKind::IronBars,
+    BlockKind::IronDoor,
+    BlockKind::IronTrapdoor,
+    BlockKind::Chain,
+    BlockKind::Lantern,
+    BlockKind::SoulLantern,
+    BlockKind::Bell,
+    BlockKind::EnchantingTable,
+    BlockKind::Lodestone,
+];
+
+/// Blocks that need at least a stone pickaxe to drop.
+const STONE_PICKAXE_BLOCKS: &[BlockKind] = &[
+    BlockKind::IronBlock,
+    BlockKind::RawIronBlock,
+    BlockKind::CopperBlock,
+    BlockKind::RawCopperBlock,
+    BlockKind::CutCopper,
+    BlockKind::LapisBlock,
+    BlockKind::LightningRod,
+];
+
+/// Blocks that need at least an iron pickaxe to drop.
+const IRON_PICKAXE_BLOCKS: &[BlockKind] = &[
+    BlockKind::GoldBlock,
+    BlockKind::RawGoldBlock,
+    BlockKind::DiamondBlock,
+    BlockKind::EmeraldBlock,
+];
+
+/// Blocks that need at least a diamond pickaxe to drop.
+const DIAMOND_PICKAXE_BLOCKS: &[BlockKind] = &[
+    BlockKind::Obsidian,
+    BlockKind::CryingObsidian,
+    BlockKind::RespawnAnchor,
+    BlockKind::NetheriteBlock,
+    BlockKind::AncientDebris,
+];
+
+/// Leaves, the sapling they drop, and whether they drop apples.
+const LEAVES: &[(BlockKind, ItemKind, bool)] = &[
+    (BlockKind::OakLeaves, ItemKind::OakSapling, true),
+    (BlockKind::SpruceLeaves, ItemKind::SpruceSapling, false),
+    (BlockKind::BirchLeaves, ItemKind::BirchSapling, false),
+    (BlockKind::JungleLeaves, ItemKind::JungleSapling, false),
+    (BlockKind::AcaciaLeaves, ItemKind::AcaciaSapling, false),
+    (BlockKind::DarkOakLeaves, ItemKind::DarkOakSapling, true),
+    (BlockKind::CherryLeaves, ItemKind::CherrySapling, false),
+    (BlockKind::AzaleaLeaves, ItemKind::Azalea, false),
+    (BlockKind::FloweringAzaleaLeaves, ItemKind::FloweringAzalea, false),
+];
+
+/// Glass blocks and panes drop nothing without Silk Touch.
+const GLASS: &[BlockKind] = &[
+    BlockKind::Glass,
+    BlockKind::GlassPane,
+    BlockKind::WhiteStainedGlass,
+    BlockKind::OrangeStainedGlass,
+    BlockKind::MagentaStainedGlass,
+    BlockKind::LightBlueStainedGlass,
+    BlockKind::YellowStainedGlass,
+    BlockKind::LimeStainedGlass,
+    BlockKind::PinkStainedGlass,
+    BlockKind::GrayStainedGlass,
+    BlockKind::LightGrayStainedGlass,
+    BlockKind::CyanStainedGlass,
+    BlockKind::PurpleStainedGlass,
+    BlockKind::BlueStainedGlass,
+    BlockKind::BrownStainedGlass,
+    BlockKind::GreenStainedGlass,
+    BlockKind::RedStainedGlass,
+    BlockKind::BlackStainedGlass,
+    BlockKind::WhiteStainedGlassPane,
+    BlockKind::OrangeStainedGlassPane,
+    BlockKind::MagentaStainedGlassPane,
+    BlockKind::LightBlueStainedGlassPane,
+    BlockKind::YellowStainedGlassPane,
+    BlockKind::LimeStainedGlassPane,
+    BlockKind::PinkStainedGlassPane,
+    BlockKind::GrayStainedGlassPane,
+    BlockKind::LightGrayStainedGlassPane,
+    BlockKind::CyanStainedGlassPane,
+    BlockKind::PurpleStainedGlassPane,
+    BlockKind::BlueStainedGlassPane,
+    BlockKind::BrownStainedGlassPane,
+    BlockKind::GreenStainedGlassPane,
+    BlockKind::RedStainedGlassPane,
+    BlockKind::BlackStainedGlassPane,
+    BlockKind::Ice,
+    BlockKind::PackedIce,
+    BlockKind::BlueIce,
+];
+
+/// An ore that drops `item` and needs a pickaxe of at least `tier`.
+fn ore(item: LootEntry, tier: ToolTier) -> BlockLoot {
+    BlockLoot::new(vec![item]).requires(ToolKind::Pickaxe, tier).silk_touch()
+}
+
+impl Default for LootTables {
+    fn default() -> Self {
+        let mut tables = Self {
+            blocks: HashMap::new(),
+        };
+
+        for &kind in PICKAXE_BLOCKS {
+            tables.set(kind, BlockLoot::itself().requires(ToolKind::Pickaxe, ToolTier::Wood));
+        }
+        for &kind in STONE_PICKAXE_BLOCKS {
+            tables.set(kind, BlockLoot::itself().requires(ToolKind::Pickaxe, ToolTier::Stone));
+        }
+        for &kind in IRON_PICKAXE_BLOCKS {
+            tables.set(kind, BlockLoot::itself().requires(ToolKind::Pickaxe, ToolTier::Iron));
+        }
+        for &kind in DIAMOND_PICKAXE_BLOCKS {
+            tables.set(kind, BlockLoot::itself().requires(ToolKind::Pickaxe, ToolTier::Diamond));
+        }
+        for &kind in GLASS {
+            tables.set(kind, BlockLoot::none().silk_touch());
+        }
+
+        for &(kind, sapling, apples) in LEAVES {
+            let sapling_chance = if kind == BlockKind::JungleLeaves { 0.025 } else { 0.05 };
+            let mut drops = vec![
+                LootEntry::item(sapling).fortune(Fortune::Chances([sapling_chance, 0.0625, 0.083333336, 0.1])),
+                LootEntry::item(ItemKind::Stick).count(1, 2).chance(0.02),
+            ];
+            if apples {
+                drops.push(LootEntry::item(ItemKind::Apple).fortune(Fortune::Chances([0.005, 0.0055555557, 0.00625, 0.008333334])));
+            }
+            tables.set(kind, BlockLoot::new(drops).silk_touch().shears());
+        }
+
+        tables.set(BlockKind::Stone, ore(LootEntry::item(ItemKind::Cobblestone), ToolTier::Wood));
+        tables.set(BlockKind::Deepslate, ore(LootEntry::item(ItemKind::CobbledDeepslate), ToolTier::Wood));
+
+        tables.set(BlockKind::CoalOre, ore(LootEntry::item(ItemKind::Coal).fortune(Fortune::Ore), ToolTier::Wood));
+        tables.set(BlockKind::DeepslateCoalOre, ore(LootEntry::item(ItemKind::Coal).fortune(Fortune::Ore), ToolTier::Wood));
+        tables.set(BlockKind::IronOre, ore(LootEntry::item(ItemKind::RawIron).fortune(Fortune::Ore), ToolTier::Stone));
+        tables.set(BlockKind::DeepslateIronOre, ore(LootEntry::item(ItemKind::RawIron).fortune(Fortune::Ore), ToolTier::Stone));
+        tables.set(BlockKind::CopperOre, ore(LootEntry::item(ItemKind::RawCopper).count(2, 5).fortune(Fortune::Ore), ToolTier::Stone));
+        tables.set(BlockKind::DeepslateCopperOre, ore(LootEntry::item(ItemKind::RawCopper).count(2, 5).fortune(Fortune::Ore), ToolTier::Stone));
+        tables.set(BlockKind::GoldOre, ore(LootEntry::item(ItemKind::RawGold).fortune(Fortune::Ore), ToolTier::Iron));
+        tables.set(BlockKind::DeepslateGoldOre, ore(LootEntry::item(ItemKind::RawGold).fortune(Fortune::Ore), ToolTier::Iron));
+        tables.set(BlockKind::NetherGoldOre, ore(LootEntry::item(ItemKind::GoldNugget).count(2, 6).fortune(Fortune::Ore), ToolTier::Wood));
+        tables.set(BlockKind::RedstoneOre, ore(LootEntry::item(ItemKind::Redstone).count(4, 5).fortune(Fortune::UniformBonus(1)), ToolTier::Iron));
+        tables.set(BlockKind::DeepslateRedstoneOre, ore(LootEntry::item(ItemKind::Redstone).count(4, 5).fortune(Fortune::UniformBonus(1)), ToolTier::Iron));
+        tables.set(BlockKind::LapisOre, ore(LootEntry::item(ItemKind::LapisLazuli).count(4, 9).fortune(Fortune::Ore), ToolTier::Stone));
+        tables.set(BlockKind::DeepslateLapisOre, ore(LootEntry::item(ItemKind::LapisLazuli).count(4, 9).fortune(Fortune::Ore), ToolTier::Stone));
+        tables.set(BlockKind::DiamondOre, ore(LootEntry::item(ItemKind::Diamond).fortune(Fortune::Ore), ToolTier::Iron));
+        tables.set(BlockKind::DeepslateDiamondOre, ore(LootEntry::item(ItemKind::Diamond).fortune(Fortune::Ore), ToolTier::Iron));
+        tables.set(BlockKind::EmeraldOre, ore(LootEntry::item(ItemKind::Emerald).fortune(Fortune::Ore), ToolTier::Iron));
+        tables.set(BlockKind::DeepslateEmeraldOre, ore(LootEntry::item(ItemKind::Emerald).fortune(Fortune::Ore), ToolTier::Iron));
+        tables.set(BlockKind::NetherQuartzOre, ore(LootEntry::item(ItemKind::Quartz).fortune(Fortune::Ore), ToolTier::Wood));
+
+        tables.set(BlockKind::GrassBlock, BlockLoot::new(vec![LootEntry::item(ItemKind::Dirt)]).silk_touch());
+        tables.set(BlockKind::Mycelium, BlockLoot::new(vec![LootEntry::item(ItemKind::Dirt)]).silk_touch());
+        tables.set(BlockKind::Podzol, BlockLoot::new(vec![LootEntry::item(ItemKind::Dirt)]).silk_touch());
+        tables.set(BlockKind::DirtPath, BlockLoot::new(vec![LootEntry::item(ItemKind::Dirt)]));
+        tables.set(BlockKind::Farmland, BlockLoot::new(vec![LootEntry::item(ItemKind::Dirt)]));
+        tables.set(
+            BlockKind::Gravel,
+            BlockLoot::new(vec![
+                LootEntry::item(ItemKind::Flint).fortune(Fortune::Chances([0.1, 0.14285715, 0.25, 1.0])),
+                LootEntry::itself(),
+            ])
+            .silk_touch()
+            .first_only(),
+        );
+        tables.set(
+            BlockKind::Glowstone,
+            BlockLoot::new(vec![LootEntry::item(ItemKind::GlowstoneDust).count(2, 4).fortune(Fortune::UniformBonus(1)).limit(4)]).silk_touch(),
+        );
+        tables.set(
+            BlockKind::Melon,
+            BlockLoot::new(vec![LootEntry::item(ItemKind::MelonSlice).count(3, 7).fortune(Fortune::UniformBonus(1)).limit(9)]).silk_touch(),
+        );
+        tables.set(BlockKind::Bookshelf, BlockLoot::new(vec![LootEntry::item(ItemKind::Book).count(3, 3)]).silk_touch());
+        tables.set(BlockKind::Clay, BlockLoot::new(vec![LootEntry::item(ItemKind::ClayBall).count(4, 4)]).silk_touch());
+        tables.set(
+            BlockKind::SnowBlock,
+            BlockLoot::new(vec![LootEntry::item(ItemKind::Snowball).count(4, 4)])
+                .requires(ToolKind::Shovel, ToolTier::Wood)
+                .silk_touch(),
+        );
+        tables.set(
+            BlockKind::EnderChest,
+            BlockLoot::new(vec![LootEntry::item(ItemKind::Obsidian).count(8, 8)])
+                .requires(ToolKind::Pickaxe, ToolTier::Wood)
+                .silk_touch(),
+        );
+        tables.set(BlockKind::Spawner, BlockLoot::none());
+        tables.set(BlockKind::BuddingAmethyst, BlockLoot::none());
+        tables.set(BlockKind::Cobweb, BlockLoot::new(vec![LootEntry::item(ItemKind::String)]).silk_touch().shears());
+        tables.set(
+            BlockKind::Grass,
+            BlockLoot::new(vec![LootEntry::item(ItemKind::WheatSeeds).chance(0.125).fortune(Fortune::UniformBonus(2))]).shears(),
+        );
+        tables.set(
+            BlockKind::Fern,
+            BlockLoot::new(vec![LootEntry::item(ItemKind::WheatSeeds).chance(0.125).fortune(Fortune::UniformBonus(2))]).shears(),
+        );
+        tables.set(BlockKind::DeadBush, BlockLoot::new(vec![LootEntry::item(ItemKind::Stick).count(0, 2)]).shears());
+        tables.set(BlockKind::Vine, BlockLoot::none().shears());
+        tables.set(BlockKind::Seagrass, BlockLoot::none().shears());
+
+        tables
+    }
+}
+
+/// Works out what a block drops when broken with `tool_stack`.
+pub fn block_drops(loot: &BlockLoot, state: BlockState, tool_stack: &ItemStack, rng: &mut impl Rng) -> Vec<ItemStack> {
+    let itself = state.to_kind().to_item_kind();
+    let held_tool = tool(tool_stack.item);
+
+    if let Some((kind, tier)) = loot.requires {
+        if !held_tool.is_some_and(|(held, held_tier)| held == kind && held_tier >= tier) {
+            return Vec::new();
+        }
+    }
+
+    let silk_touch = enchantment_level(tool_stack, "minecraft:silk_touch") > 0;
+    let shears = held_tool.is_some_and(|(kind, _)| kind == ToolKind::Shears);
+
+    if (loot.silk_touch && silk_touch) || (loot.shears && shears) {
+        if itself == ItemKind::Air {
+            return Vec::new();
+        }
+        return vec![ItemStack::new(itself, 1, None)];
+    }
+
+    let fortune = enchantment_level(tool_stack, "minecraft:fortune");
+
+    let mut drops = Vec::new();
+    for entry in &loot.drops {
+        let count = entry.roll(fortune, rng);
+        if count <= 0 {
+            continue;
+        }
+
+        let item = match entry.item {
+            LootItem::Itself => itself,
+            LootItem::Item(item) => item,
+        };
+        if item == ItemKind::Air {
+            continue;
+        }
+
+        // Split counts larger than a stack over several stacks.
+        let max_stack = i32::from(item.max_stack()).max(1);
+        let mut remaining = count;
+        while remaining > 0 {
+            let stack_count = remaining.min(max_stack);
+            drops.push(ItemStack::new(item, stack_count as i8, None));
+            remaining -= stack_count;
+        }
+
+        if loot.first_only {
+            break;
+        }
+    }
+
+    drops
+}
+
+fn drop_block_loot(
+    mut clients: Query<(&GameMode, &mut Inventory, &HeldItem)>,
+    tables: Res<LootTables>,
+    mut breaks: EventReader<BlockBreakEvent>,
+    mut commands: Commands,
+) {
+    let mut rng = thread_rng();
+
+    for event in breaks.read() {
+        let Ok((game_mode, mut inventory, held)) = clients.get_mut(event.client) else {
+            continue;
+        };
+        if *game_mode != GameMode::Survival {
+            continue;
+        }
+
+        let mut tool_stack = inventory.slot(held.slot()).clone();
+        let drops = block_drops(&tables.get(event.state.to_kind()), event.state, &tool_stack, &mut rng);
+
+        for stack in drops {
+            // Items pop out of a random spot near the middle of the block.
+            let position = DVec3::new(
+                f64::from(event.position.x) + 0.5 + rng.gen_range(-0.25..0.25),
+                f64::from(event.position.y) + 0.5 + rng.gen_range(-0.25..0.25) - 0.125,
+                f64::from(event.position.z) + 0.5 + rng.gen_range(-0.25..0.25),
+            );
+            let velocity = Vec3::new(
+                rng.gen_range(-0.1..0.1) * 20.0,
+                0.2 * 20.0,
+                rng.gen_range(-0.1..0.1) * 20.0,
+            );
+
+            spawn_item(&mut commands, event.layer, position, stack, velocity, DEFAULT_PICKUP_DELAY);
+        }
+
+        // Blocks that break instantly, like plants and torches, don't wear
+        // tools down.
+        let instant = match hardness(event.state.to_kind()) {
+            Hardness::Breakable(hardness) => hardness == 0.0,
+            Hardness::Unbreakable => true,
+            Hardness::Unknown => false,
+        };
+        if instant {
+            continue;
+        }
+
+        let amount = match tool(tool_stack.item) {
+            Some((ToolKind::Sword, _)) => 2,
+            Some(_) => 1,
+            None => continue,
+        };
+
+        damage_item(&mut tool_stack, amount, &mut rng);
+        inventory.set_slot(held.slot(), tool_stack);
+    }
+}
@@ -0,0 +1,650 @@
+use std::collections::HashSet;
+
+use valence::entity::active_status_effects::ActiveStatusEffects;
+use valence::entity::OnGround;
+use valence::inventory::HeldItem;
+use valence::prelude::*;
+use valence::protocol::packets::play::BlockUpdateS2c;
+use valence::protocol::status_effects::StatusEffect;
+use valence::protocol::WritePacket;
+
+use crate::block_behavior::block_pos;
+use crate::building::{digging, CancelDiggingEvent};
+use crate::item::{enchantment_level, tool, ToolKind, ToolTier};
+use crate::loot::LootTables;
+use crate::players::EYE_HEIGHT;
+
+/// Checks on the server that players aren't breaking blocks faster or further
+/// away than they should be able to.
+pub struct Mining;
+
+impl Plugin for Mining {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LootTables>()
+            .add_systems(Update, (init_clients, validate_digging.before(digging)));
+    }
+}
+
+/// Furthest a player can be from the middle of a block they are breaking,
+/// measured from their eyes.
+pub const MAX_REACH: f64 = 6.0;
+
+/// Fraction of the expected break time a dig has to last. Some leeway is
+/// needed because of latency, and vanilla allows the same.
+const MIN_PROGRESS: f64 = 0.7;
+
+/// The block a player is currently breaking.
+#[derive(Component, Debug, Default, Copy, Clone)]
+pub struct DiggingProgress {
+    /// The block being broken, if any.
+    pub position: Option<BlockPos>,
+    /// Tick the player started breaking the block on.
+    pub start_tick: i64,
+    /// Ticks the block takes to break. Zero for blocks that break instantly,
+    /// which the client never sends a `Stop` for.
+    pub ticks: i64,
+}
+
+impl DiggingProgress {
+    /// Whether the block at `position` breaks as soon as it is hit.
+    pub fn is_instant(&self, position: BlockPos) -> bool {
+        self.position == Some(position) && self.ticks == 0
+    }
+}
+
+fn init_clients(clients: Query<Entity, Added<Client>>, mut commands: Commands) {
+    for client in &clients {
+        commands.entity(client).insert(DiggingProgress::default());
+    }
+}
+
+/// How hard a block is to break.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Hardness {
+    Unbreakable,
+    Breakable(f32),
+    /// The block isn't covered by [`hardness`], so how long it takes to break
+    /// can't be checked.
+    Unknown,
+}
+
+/// Returns how hard a block is to break.
+///
+/// Covers the common blocks and block families. Anything else is
+/// [`Hardness::Unknown`].
+pub fn hardness(kind: BlockKind) -> Hardness {
+    let name = kind.to_str();
+
+    let hardness = match kind {
+        BlockKind::Bedrock
+        | BlockKind::Barrier
+        | BlockKind::Light
+        | BlockKind::EndPortal
+        | BlockKind::EndPortalFrame
+        | BlockKind::EndGateway
+        | BlockKind::NetherPortal
+        | BlockKind::CommandBlock
+        | BlockKind::ChainCommandBlock
+        | BlockKind::RepeatingCommandBlock
+        | BlockKind::StructureBlock
+        | BlockKind::Jigsaw
+        | BlockKind::ReinforcedDeepslate
+        | BlockKind::Water
+        | BlockKind::Lava
+        | BlockKind::Air
+        | BlockKind::CaveAir
+        | BlockKind::VoidAir => return Hardness::Unbreakable,
+
+        BlockKind::Obsidian | BlockKind::CryingObsidian | BlockKind::RespawnAnchor | BlockKind::NetheriteBlock => 50.0,
+        BlockKind::AncientDebris => 30.0,
+        BlockKind::EnderChest => 22.5,
+        BlockKind::IronBlock
+        | BlockKind::DiamondBlock
+        | BlockKind::EmeraldBlock
+        | BlockKind::RedstoneBlock
+        | BlockKind::CoalBlock
+        | BlockKind::RawIronBlock
+        | BlockKind::RawCopperBlock
+        | BlockKind::RawGoldBlock
+        | BlockKind::Anvil
+        | BlockKind::ChippedAnvil
+        | BlockKind::DamagedAnvil
+        | BlockKind::IronBars
+        | BlockKind::IronDoor
+        | BlockKind::IronTrapdoor
+        | BlockKind::Spawner
+        | BlockKind::EnchantingTable
+        | BlockKind::Bell
+        | BlockKind::Chain
+        | BlockKind::Hopper => 5.0,
+        BlockKind::Cobweb => 4.0,
+        BlockKind::Furnace | BlockKind::BlastFurnace | BlockKind::Smoker | BlockKind::Dispenser | BlockKind::Dropper => 3.5,
+        BlockKind::Deepslate | BlockKind::CobbledDeepslate | BlockKind::PolishedDeepslate => 3.0,
+        BlockKind::GoldBlock
+        | BlockKind::LapisBlock
+        | BlockKind::CopperBlock
+        | BlockKind::EndStone
+        | BlockKind::EndStoneBricks
+        | BlockKind::Observer
+        | BlockKind::Beacon
+        | BlockKind::Conduit
+        | BlockKind::Lodestone => 3.0,
+        BlockKind::BlueIce => 2.8,
+        BlockKind::Chest | BlockKind::TrappedChest | BlockKind::Barrel | BlockKind::CraftingTable => 2.5,
+        BlockKind::Cobblestone
+        | BlockKind::MossyCobblestone
+        | BlockKind::Bricks
+        | BlockKind::NetherBricks
+        | BlockKind::RedNetherBricks
+        | BlockKind::Campfire
+        | BlockKind::SoulCampfire
+        | BlockKind::Grindstone
+        | BlockKind::Cauldron
+        | BlockKind::Composter
+        | BlockKind::Jukebox => 2.0,
+        BlockKind::Stone
+        | BlockKind::Granite
+        | BlockKind::PolishedGranite
+        | BlockKind::Diorite
+        | BlockKind::PolishedDiorite
+        | BlockKind::Andesite
+        | BlockKind::PolishedAndesite
+        | BlockKind::StoneBricks
+        | BlockKind::MossyStoneBricks
+        | BlockKind::CrackedStoneBricks
+        | BlockKind::ChiseledStoneBricks
+        | BlockKind::Blackstone
+        | BlockKind::PolishedBlackstone
+        | BlockKind::PurpurBlock
+        | BlockKind::PurpurPillar
+        | BlockKind::PurpurStairs
+        | BlockKind::PurpurSlab
+        | BlockKind::Prismarine
+        | BlockKind::PrismarineBricks
+        | BlockKind::DarkPrismarine
+        | BlockKind::AmethystBlock
+        | BlockKind::Tuff
+        | BlockKind::DripstoneBlock
+        | BlockKind::Bookshelf
+        | BlockKind::Stonecutter => 1.5,
+        BlockKind::Terracotta | BlockKind::Basalt | BlockKind::PolishedBasalt => 1.25,
+        BlockKind::Melon | BlockKind::Pumpkin | BlockKind::CarvedPumpkin | BlockKind::JackOLantern | BlockKind::NoteBlock => 1.0,
+        BlockKind::Sandstone | BlockKind::RedSandstone | BlockKind::QuartzBlock | BlockKind::Calcite => 0.8,
+        BlockKind::GrassBlock
+        | BlockKind::Mycelium
+        | BlockKind::Podzol
+        | BlockKind::Farmland
+        | BlockKind::DirtPath
+        | BlockKind::Gravel
+        | BlockKind::Clay
+        | BlockKind::Sponge
+        | BlockKind::WetSponge => 0.6,
+        BlockKind::Dirt
+        | BlockKind::CoarseDirt
+        | BlockKind::RootedDirt
+        | BlockKind::Mud
+        | BlockKind::Sand
+        | BlockKind::RedSand
+        | BlockKind::SoulSand
+        | BlockKind::SoulSoil
+        | BlockKind::Ice
+        | BlockKind::PackedIce
+        | BlockKind::FrostedIce
+        | BlockKind::MagmaBlock
+        | BlockKind::HayBlock
+        | BlockKind::Lever => 0.5,
+        BlockKind::Netherrack | BlockKind::Cactus | BlockKind::Ladder => 0.4,
+        BlockKind::Glass | BlockKind::GlassPane | BlockKind::Glowstone | BlockKind::SeaLantern | BlockKind::TintedGlass => 0.3,
+        BlockKind::SnowBlock => 0.2,
+        BlockKind::Snow | BlockKind::MossBlock | BlockKind::MossCarpet => 0.1,
+        BlockKind::Torch
+        | BlockKind::WallTorch
+        | BlockKind::SoulTorch
+        | BlockKind::SoulWallTorch
+        | BlockKind::RedstoneTorch
+        | BlockKind::RedstoneWallTorch
+        | BlockKind::RedstoneWire
+        | BlockKind::Repeater
+        | BlockKind::Comparator
+        | BlockKind::Tripwire
+        | BlockKind::TripwireHook
+        | BlockKind::Grass
+        | BlockKind::Fern
+        | BlockKind::TallGrass
+        | BlockKind::LargeFern
+        | BlockKind::DeadBush
+        | BlockKind::Seagrass
+        | BlockKind::TallSeagrass
+        | BlockKind::Kelp
+        | BlockKind::KelpPlant
+        | BlockKind::SugarCane
+        | BlockKind::LilyPad
+        | BlockKind::Wheat
+        | BlockKind::Carrots
+        | BlockKind::Potatoes
+        | BlockKind::Beetroots
+        | BlockKind::MelonStem
+        | BlockKind::PumpkinStem
+        | BlockKind::NetherWart
+        | BlockKind::SweetBerryBush
+        | BlockKind::BrownMushroom
+        | BlockKind::RedMushroom
+        | BlockKind::Fire
+        | BlockKind::SoulFire
+        | BlockKind::Tnt
+        | BlockKind::SlimeBlock
+        | BlockKind::HoneyBlock
+        | BlockKind::FlowerPot
+        | BlockKind::Scaffolding
+        | BlockKind::Dandelion
+        | BlockKind::Poppy
+        | BlockKind::BlueOrchid
+        | BlockKind::Allium
+        | BlockKind::AzureBluet
+        | BlockKind::OxeyeDaisy
+        | BlockKind::Cornflower
+        | BlockKind::LilyOfTheValley
+        | BlockKind::WitherRose
+        | BlockKind::Sunflower
+        | BlockKind::Lilac
+        | BlockKind::RoseBush
+        | BlockKind::Peony => 0.0,
+
+        BlockKind::Lantern | BlockKind::SoulLantern => 3.5,
+        BlockKind::SculkCatalyst | BlockKind::SculkShrieker | BlockKind::DragonEgg => 3.0,
+        BlockKind::Lectern
+        | BlockKind::Loom
+        | BlockKind::CartographyTable
+        | BlockKind::FletchingTable
+        | BlockKind::SmithingTable => 2.5,
+        BlockKind::SmoothStone | BlockKind::SmoothSandstone | BlockKind::SmoothRedSandstone | BlockKind::BoneBlock => 2.0,
+        BlockKind::MudBricks
+        | BlockKind::Piston
+        | BlockKind::StickyPiston
+        | BlockKind::PistonHead
+        | BlockKind::SculkSensor
+        | BlockKind::CalibratedSculkSensor
+        | BlockKind::PointedDripstone
+        | BlockKind::BuddingAmethyst
+        | BlockKind::AmethystCluster
+        | BlockKind::ChiseledBookshelf => 1.5,
+        BlockKind::PackedMud
+        | BlockKind::NetherWartBlock
+        | BlockKind::WarpedWartBlock
+        | BlockKind::Shroomlight
+        | BlockKind::Bamboo => 1.0,
+        BlockKind::QuartzBricks
+        | BlockKind::QuartzPillar
+        | BlockKind::ChiseledQuartzBlock
+        | BlockKind::CutSandstone
+        | BlockKind::ChiseledSandstone
+        | BlockKind::CutRedSandstone
+        | BlockKind::ChiseledRedSandstone => 0.8,
+        BlockKind::Rail
+        | BlockKind::PoweredRail
+        | BlockKind::DetectorRail
+        | BlockKind::ActivatorRail
+        | BlockKind::MangroveRoots
+        | BlockKind::MuddyMangroveRoots => 0.7,
+        BlockKind::HoneycombBlock | BlockKind::Beehive => 0.6,
+        BlockKind::Target
+        | BlockKind::DriedKelpBlock
+        | BlockKind::BrewingStand
+        | BlockKind::Cake
+        | BlockKind::TurtleEgg
+        | BlockKind::SnifferEgg => 0.5,
+        BlockKind::CrimsonNylium | BlockKind::WarpedNylium | BlockKind::ChorusPlant | BlockKind::ChorusFlower => 0.4,
+        BlockKind::BeeNest
+        | BlockKind::RedstoneLamp
+        | BlockKind::OchreFroglight
+        | BlockKind::VerdantFroglight
+        | BlockKind::PearlescentFroglight => 0.3,
+        BlockKind::PowderSnow | BlockKind::SuspiciousSand | BlockKind::SuspiciousGravel => 0.25,
+        BlockKind::Sculk
+        | BlockKind::SculkVein
+        | BlockKind::Vine
+        | BlockKind::GlowLichen
+        | BlockKind::Cocoa
+        | BlockKind::DaylightDetector => 0.2,
+        BlockKind::BigDripleaf | BlockKind::BigDripleafStem => 0.1,
+        BlockKind::EndRod
+        | BlockKind::SeaPickle
+        | BlockKind::CaveVines
+        | BlockKind::CaveVinesPlant
+        | BlockKind::Azalea
+        | BlockKind::FloweringAzalea
+        | BlockKind::SmallDripleaf
+        | BlockKind::SporeBlossom
+        | BlockKind::HangingRoots
+        | BlockKind::Frogspawn
+        | BlockKind::NetherSprouts
+        | BlockKind::CrimsonRoots
+        | BlockKind::WarpedRoots
+        | BlockKind::CrimsonFungus
+        | BlockKind::WarpedFungus
+        | BlockKind::WeepingVines
+        | BlockKind::WeepingVinesPlant
+        | BlockKind::TwistingVines
+        | BlockKind::TwistingVinesPlant
+        | BlockKind::DecoratedPot
+        | BlockKind::Torchflower
+        | BlockKind::TorchflowerCrop
+        | BlockKind::PitcherPlant
+        | BlockKind::PitcherCrop
+        | BlockKind::PinkPetals => 0.0,
+
+        _ if name.starts_with("potted_") || name.ends_with("_sapling") || name.ends_with("_tulip") => 0.0,
+        _ if name.ends_with("_coral") || name.ends_with("_coral_fan") || name.ends_with("_coral_wall_fan") => 0.0,
+        _ if name.starts_with("deepslate_") && name.ends_with("_ore") => 4.5,
+        _ if name.ends_with("_ore") => 3.0,
+        _ if name.ends_with("_planks") || name.ends_with("_log") || name.ends_with("_wood") => 2.0,
+        _ if name.ends_with("_stem") || name.ends_with("_hyphae") => 2.0,
+        _ if name.ends_with("_fence") || name.ends_with("_fence_gate") => 2.0,
+        _ if name.ends_with("_door") || name.ends_with("_trapdoor") => 3.0,
+        _ if name.ends_with("_shulker_box") || name == "shulker_box" => 2.0,
+        _ if name.ends_with("_concrete_powder") => 0.5,
+        _ if name.ends_with("_concrete") => 1.8,
+        _ if name.ends_with("_glazed_terracotta") => 1.4,
+        _ if name.ends_with("_terracotta") => 1.25,
+        _ if name.ends_with("_coral_block") => 1.5,
+        _ if name.ends_with("_sign") || name.ends_with("_banner") => 1.0,
+        _ if name.ends_with("_wool") => 0.8,
+        _ if name.ends_with("_button") || name.ends_with("_pressure_plate") => 0.5,
+        _ if name.ends_with("_glass") || name.ends_with("_glass_pane") => 0.3,
+        _ if name.ends_with("_leaves") => 0.2,
+        _ if name.ends_with("_bed") => 0.2,
+        _ if name.ends_with("_carpet") || name.ends_with("_candle") || name == "candle" => 0.1,
+        _ if name.ends_with("_head") || name.ends_with("_skull") => 1.0,
+        _ if name.ends_with("candle_cake") => 0.5,
+        _ if name.ends_with("_amethyst_bud") => 1.5,
+        _ if name.starts_with("infested_") => 0.75,
+        _ if name.contains("copper") => 3.0,
+        _ if name.contains("deepslate") => 3.5,
+        _ if name.contains("blackstone") => 1.5,
+        _ if name.ends_with("_stairs") || name.ends_with("_slab") || name.ends_with("_wall") => 2.0,
+
+        _ => return Hardness::Unknown,
+    };
+
+    Hardness::Breakable(hardness)
+}
+
+/// Returns the kind of tool that breaks a block faster.
+pub fn preferred_tool(kind: BlockKind, tables: &LootTables) -> Option<ToolKind> {
+    if let Some((tool, _)) = tables.get(kind).requires {
+        return Some(tool);
+    }
+
+    let name = kind.to_str();
+
+    if kind == BlockKind::Cobweb {
+        Some(ToolKind::Sword)
+    } else if name.ends_with("_leaves")
+        || kind == BlockKind::HayBlock
+        || kind == BlockKind::Sponge
+        || kind == BlockKind::WetSponge
+        || kind == BlockKind::MossBlock
+        || kind == BlockKind::NetherWartBlock
+        || kind == BlockKind::WarpedWartBlock
+        || kind == BlockKind::Shroomlight
+        || kind == BlockKind::Target
+        || kind == BlockKind::DriedKelpBlock
+    {
+        Some(ToolKind::Hoe)
+    } else if kind == BlockKind::Dirt
+        || kind == BlockKind::CoarseDirt
+        || kind == BlockKind::RootedDirt
+        || kind == BlockKind::GrassBlock
+        || kind == BlockKind::Mycelium
+        || kind == BlockKind::Podzol
+        || kind == BlockKind::Farmland
+        || kind == BlockKind::DirtPath
+        || kind == BlockKind::Mud
+        || kind == BlockKind::Sand
+        || kind == BlockKind::RedSand
+        || kind == BlockKind::Gravel
+        || kind == BlockKind::Clay
+        || kind == BlockKind::Snow
+        || kind == BlockKind::SnowBlock
+        || kind == BlockKind::SoulSand
+        || kind == BlockKind::SoulSoil
+        || name.ends_with("_concrete_powder")
+    {
+        Some(ToolKind::Shovel)
+    } else if name.ends_with("_planks")
+        || name.ends_with("_log")
+        || name.ends_with("_wood")
+        || name.ends_with("_stem")
+        || name.ends_with("_hyphae")
+        || name.ends_with("_sign")
+        || name.ends_with("_banner")
+        || name.ends_with("_fence")
+        || name.ends_with("_fence_gate")
+        || (name.ends_with("_door") && kind != BlockKind::IronDoor)
+        || (name.ends_with("_trapdoor") && kind != BlockKind::IronTrapdoor)
+        || kind == BlockKind::Chest
+        || kind == BlockKind::TrappedChest
+        || kind == BlockKind::Barrel
+        || kind == BlockKind::CraftingTable
+        || kind == BlockKind::Bookshelf
+        || kind == BlockKind::Ladder
+        || kind == BlockKind::NoteBlock
+        || kind == BlockKind::Jukebox
+        || kind == BlockKind::Composter
+        || kind == BlockKind::Lectern
+        || kind == BlockKind::Campfire
+        || kind == BlockKind::SoulCampfire
+        || kind == BlockKind::Pumpkin
+        || kind == BlockKind::CarvedPumpkin
+        || kind == BlockKind::JackOLantern
+        || kind == BlockKind::Melon
+    {
+        Some(ToolKind::Axe)
+    } else if name.contains("stone")
+        || name.contains("brick")
+        || name.contains("deepslate")
+        || name.contains("terracotta")
+        || (name.contains("concrete") && !name.ends_with("_powder"))
+        || name.contains("copper")
+        || name.ends_with("_ice")
+        || kind == BlockKind::Ice
+        || name.contains("rail")
+    {
+        Some(ToolKind::Pickaxe)
+    } else {
+        None
+    }
+}
+
+/// How much faster than a bare hand a tool of this tier is.
+fn tier_speed(item: ItemKind, tier: ToolTier) -> f32 {
+    if item == ItemKind::GoldenPickaxe
+        || item == ItemKind::GoldenAxe
+        || item == ItemKind::GoldenShovel
+        || item == ItemKind::GoldenHoe
+    {
+        return 12.0;
+    }
+
+    match tier {
+        ToolTier::Wood => 2.0,
+        ToolTier::Stone => 4.0,
+        ToolTier::Iron => 6.0,
+        ToolTier::Diamond => 8.0,
+        ToolTier::Netherite => 9.0,
+    }
+}
+
+/// What a player is breaking a block with, and the state they are in while
+/// doing so.
+pub struct Digger<'a> {
+    pub tool: &'a ItemStack,
+    pub helmet: &'a ItemStack,
+    pub effects: Option<&'a ActiveStatusEffects>,
+    pub on_ground: bool,
+    pub underwater: bool,
+}
+
+/// Returns the number of ticks it takes to break a block, following vanilla's
+/// formula. Returns `None` if the block can't be broken or its hardness isn't
+/// known.
+pub fn break_ticks(state: BlockState, digger: &Digger, tables: &LootTables) -> Option<i64> {
+    let kind = state.to_kind();
+    let Hardness::Breakable(hardness) = hardness(kind) else {
+        return None;
+    };
+    if hardness == 0.0 {
+        return Some(0);
+    }
+
+    let held = tool(digger.tool.item);
+    let preferred = preferred_tool(kind, tables);
+
+    let mut speed = match held {
+        Some((ToolKind::Shears, _)) if kind == BlockKind::Cobweb || kind.to_str().ends_with("_leaves") => 15.0,
+        Some((ToolKind::Shears, _)) if kind.to_str().ends_with("_wool") => 5.0,
+        Some((ToolKind::Sword, _)) if kind == BlockKind::Cobweb => 15.0,
+        Some((ToolKind::Sword, _)) => 1.5,
+        Some((held_kind, tier)) if Some(held_kind) == preferred => tier_speed(digger.tool.item, tier),
+        _ => 1.0,
+    };
+
+    if speed > 1.0 {
+        let efficiency = enchantment_level(digger.tool, "minecraft:efficiency");
+        if efficiency > 0 {
+            speed += (efficiency * efficiency + 1) as f32;
+        }
+    }
+
+    if let Some(effects) = digger.effects {
+        if let Some(haste) = effects.get_current_effect(StatusEffect::Haste) {
+            speed *= 1.0 + 0.2 * (f32::from(haste.amplifier()) + 1.0);
+        }
+        if let Some(fatigue) = effects.get_current_effect(StatusEffect::MiningFatigue) {
+            speed *= match fatigue.amplifier() {
+                0 => 0.3,
+                1 => 0.09,
+                2 => 0.0027,
+                _ => 0.00081,
+            };
+        }
+    }
+
+    if digger.underwater && enchantment_level(digger.helmet, "minecraft:aqua_affinity") == 0 {
+        speed /= 5.0;
+    }
+    if !digger.on_ground {
+        speed /= 5.0;
+    }
+
+    let can_harvest = match tables.get(kind).requires {
+        Some((kind, tier)) => held.is_some_and(|(held, held_tier)| held == kind && held_tier >= tier),
+        None => true,
+    };
+
+    let progress_per_tick = speed / hardness / if can_harvest { 30.0 } else { 100.0 };
+    if progress_per_tick >= 1.0 {
+        return Some(0);
+    }
+
+    Some((1.0 / progress_per_tick).ceil() as i64)
+}
+
+/// Rejects digs that are out of reach or finish sooner than the block could
+/// have been broken, and puts the block back on the client.
+fn validate_digging(
+    server: Res<Server>,
+    tables: Res<LootTables>,
+    mut clients: Query<(
+        &mut Client,
+        &mut DiggingProgress,
+        &Position,
+        &Inventory,
+        &HeldItem,
+        &GameMode,
+        &OnGround,
+        Option<&ActiveStatusEffects>,
+        &EntityLayerId,
+    )>,
+    layers: Query<&ChunkLayer>,
+    mut events: EventReader<DiggingEvent>,
+    mut cancel: EventWriter<CancelDiggingEvent>,
+    mut unknown: Local<HashSet<BlockKind>>,
+) {
+    for event in events.read() {
+        let Ok((mut client, mut progress, position, inventory, held, game_mode, on_ground, effects, layer_id)) =
+            clients.get_mut(event.client)
+        else {
+            continue;
+        };
+        let Ok(layer) = layers.get(layer_id.0) else {
+            continue;
+        };
+        let Some(state) = layer.block(event.position).map(|b| b.state) else {
+            continue;
+        };
+
+        let eyes = position.0 + DVec3::new(0.0, EYE_HEIGHT, 0.0);
+        let center = DVec3::new(
+            f64::from(event.position.x) + 0.5,
+            f64::from(event.position.y) + 0.5,
+            f64::from(event.position.z) + 0.5,
+        );
+
+        let mut reject = eyes.distance_squared(center) > MAX_REACH * MAX_REACH;
+
+        // Blocks missing from the hardness table can't be timed, so only
+        // their reach is checked rather than rejecting every break.
+        let kind = state.to_kind();
+        let timed = hardness(kind) != Hardness::Unknown;
+        if !timed && unknown.insert(kind) {
+            tracing::warn!("no hardness known for `{}`, not checking how fast it's broken", kind.to_str());
+        }
+
+        if !reject && timed && *game_mode == GameMode::Survival {
+            match event.state {
+                DiggingState::Start => {
+                    let underwater = layer.block(block_pos(eyes)).is_some_and(|b| {
+                        b.state.to_kind() == BlockKind::Water
+                            || b.state.get(PropName::Waterlogged) == Some(PropValue::True)
+                    });
+                    let digger = Digger {
+                        tool: inventory.slot(held.slot()),
+                        helmet: inventory.slot(5),
+                        effects,
+                        on_ground: on_ground.0,
+                        underwater,
+                    };
+
+                    match break_ticks(state, &digger, &tables) {
+                        Some(ticks) => {
+                            *progress = DiggingProgress {
+                                position: Some(event.position),
+                                start_tick: server.current_tick(),
+                                ticks,
+                            };
+                        }
+                        None => reject = true,
+                    }
+                }
+                DiggingState::Abort => {
+                    *progress = DiggingProgress::default();
+                }
+                DiggingState::Stop => {
+                    let elapsed = server.current_tick() - progress.start_tick + 1;
+                    reject = progress.position != Some(event.position)
+                        || (elapsed as f64) < progress.ticks as f64 * MIN_PROGRESS;
+                    *progress = DiggingProgress::default();
+                }
+            }
+        }
+
+        if reject {
+            cancel.send(CancelDiggingEvent { client: event.client });
+            client.write_packet(&BlockUpdateS2c {
+                position: event.position,
+                block_id: state,
+            });
+        }
+    }
+}
@@ -3,12 +3,16 @@ use std::{collections::VecDeque, time::{Duration, Instant}};
 
 use valence::prelude::*;
 use bevy::prelude::*;
+use bevy_time::TimePlugin;
 use tracing::info;
 
 pub struct Perf;
 
 impl Plugin for Perf {
     fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<TimePlugin>() {
+            app.add_plugins(TimePlugin);
+        }
         app
             .insert_resource(PrintTimer::new())
             .insert_resource(TickTimer(Instant::now()))
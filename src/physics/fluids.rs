@@ -1,707 +1,616 @@
-use std::time::Duration;
-
-use bevy_time::{Time, TimePlugin, Timer, TimerMode};
-use valence::interact_block::InteractBlockEvent;
+use valence::entity::axolotl::AxolotlEntityBundle;
+use valence::entity::cod::CodEntityBundle;
+use valence::entity::pufferfish::PufferfishEntityBundle;
+use valence::entity::salmon::SalmonEntityBundle;
+use valence::entity::tadpole::TadpoleEntityBundle;
+use valence::entity::tropical_fish::TropicalFishEntityBundle;
+use valence::interact_item::InteractItemEvent;
 use valence::inventory::HeldItem;
 use valence::layer::chunk::IntoBlock;
 use valence::prelude::*;
-
-use crate::block_update::BlockUpdateEvent;
+use valence::protocol::Hand;
+use valence::rand::thread_rng;
+use valence::sound::{Sound, SoundCategory};
+
+use crate::block_behavior::{
+    block_pos, with_neighbors, BlockBehavior, BlockBehaviors, BlockView, NeighborUpdate, OnScheduledTick,
+};
+use crate::block_update::{send_updates, BlockUpdateEvent};
+use crate::farming::number;
+use crate::item_entity::{insert_into_inventory, spawn_item, throw_item, DEFAULT_PICKUP_DELAY};
+use crate::loot::{block_drops, LootTables};
+use crate::players::EYE_HEIGHT;
+use crate::scheduled_tick::{ScheduledTick, ScheduledTicks, TickPriority};
 
 pub struct Fluids;
 
 impl Plugin for Fluids {
     fn build(&self, app: &mut App) {
-        app.add_plugins(TimePlugin).add_systems(
-            Update,
-            (
-                placing,
-                start_water_flow,
-                flowing_water,
-                start_lava_flow,
-                flowing_lava,
-            )
-                .chain(),
-        );
+        app.init_resource::<BlockBehaviors>()
+            .init_resource::<LootTables>()
+            .add_event::<WashedAwayEvent>()
+            .add_systems(Update, ((buckets, schedule_flow).chain(), drop_washed_away));
+
+        let mut behaviors = app.world_mut().resource_mut::<BlockBehaviors>();
+        behaviors.insert(BlockKind::Water, OnScheduledTick(flow_water));
+        behaviors.insert(BlockKind::Lava, Lava);
     }
 }
 
-fn placing(
-    mut clients: Query<(&mut Inventory, &GameMode, &HeldItem, &EntityLayerId)>,
+/// Game ticks between each step of water spreading.
+const WATER_FLOW_DELAY: i64 = 5;
+/// Game ticks between each step of lava spreading.
+const LAVA_FLOW_DELAY: i64 = 30;
+/// Lava in the Nether is a lot quicker.
+const NETHER_LAVA_FLOW_DELAY: i64 = 10;
+
+/// A block that flowing water washed away, like a torch or a flower. Its
+/// drops are spawned where it was.
+#[derive(Event, Debug, Copy, Clone)]
+pub struct WashedAwayEvent {
+    pub position: BlockPos,
+    pub state: BlockState,
+    pub layer: EntityLayerId,
+}
+
+/// How far players can reach with a bucket.
+const BUCKET_REACH: f64 = 5.0;
+
+/// What using a bucket does: the block it changes, what that block becomes
+/// and the bucket the player is left with.
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct BucketUse {
+    position: BlockPos,
+    state: BlockState,
+    bucket: ItemKind,
+    sound: Sound,
+}
+
+/// Empties and fills buckets.
+///
+/// Clients don't target fluids themselves, so this works from the item use
+/// and finds the block on the server, the way vanilla does. The block
+/// interaction sent for the same click is left alone, since handling both
+/// would empty a bucket and fill it straight back up.
+fn buckets(
+    mut clients: Query<(&mut Inventory, &GameMode, &HeldItem, &Position, &Look, &EntityLayerId)>,
     mut layers: Query<&mut ChunkLayer>,
-    mut events: EventReader<InteractBlockEvent>,
+    mut events: EventReader<InteractItemEvent>,
+    mut commands: Commands,
     mut block_updates: EventWriter<BlockUpdateEvent>,
 ) {
     for event in events.read() {
-        let Ok((mut inventory, game_mode, held, &layer_id)) = clients.get_mut(event.client) else {
+        if event.hand != Hand::Main {
             continue;
-        };
+        }
 
-        let Ok(mut layer) = layers.get_mut(layer_id.0) else {
+        let Ok((mut inventory, game_mode, held, position, look, &layer_id)) = clients.get_mut(event.client) else {
             continue;
         };
-
-        if event.head_inside_block {
+        let Ok(mut layer) = layers.get_mut(layer_id.0) else {
             continue;
-        }
+        };
 
-        // get the held item
         let slot_id = held.slot();
-        let stack = inventory.slot(slot_id);
-        if stack.is_empty() {
-            // no item in the slot
+        let stack = inventory.slot(slot_id).clone();
+
+        let eyes = position.0 + DVec3::new(0.0, EYE_HEIGHT, 0.0);
+        let Some(used) = use_bucket(&*layer, eyes, *look, stack.item) else {
             continue;
         };
 
-        let state = match stack.item {
-            ItemKind::WaterBucket => BlockState::WATER,
-            ItemKind::LavaBucket => BlockState::LAVA,
-            _ => {
-                continue;
-            }
-        };
+        layer.set_block(used.position, used.state);
 
-        let real_pos = event.position.get_in_direction(event.face);
+        let center = DVec3::new(
+            used.position.x as f64 + 0.5,
+            used.position.y as f64 + 0.5,
+            used.position.z as f64 + 0.5,
+        );
+        layer.play_sound(used.sound, SoundCategory::Block, center, 1.0, 1.0);
+
+        if stack.item == ItemKind::Bucket {
+            let filled = ItemStack::new(used.bucket, 1, None);
+            match *game_mode {
+                GameMode::Survival | GameMode::Adventure if stack.count == 1 => {
+                    inventory.set_slot(slot_id, filled);
+                }
+                GameMode::Survival | GameMode::Adventure => {
+                    inventory.set_slot(slot_id, ItemStack::new(stack.item, stack.count - 1, stack.nbt));
+                    if insert_into_inventory(&mut inventory, &filled) == 0 {
+                        throw_item(&mut commands, position.0, *look, layer_id, filled);
+                    }
+                }
+                // Creative players keep their empty bucket, and get a full
+                // one if they don't have one already.
+                _ => {
+                    if !(0..inventory.slot_count()).any(|slot| inventory.slot(slot).item == filled.item) {
+                        insert_into_inventory(&mut inventory, &filled);
+                    }
+                }
+            }
+        } else {
+            if *game_mode == GameMode::Survival {
+                inventory.set_slot(slot_id, ItemStack::new(used.bucket, 1, None));
+            }
 
-        if *game_mode == GameMode::Survival {
-            // check if the player has the item in their inventory and remove
-            // it.
-            inventory.set_slot(slot_id, ItemStack::new(ItemKind::Bucket, 1, None));
+            // The fish come out with the water.
+            let position = Position(center);
+            let layer = layer_id;
+            match stack.item {
+                ItemKind::PufferfishBucket => { commands.spawn(PufferfishEntityBundle { position, layer, ..Default::default() }); }
+                ItemKind::SalmonBucket => { commands.spawn(SalmonEntityBundle { position, layer, ..Default::default() }); }
+                ItemKind::CodBucket => { commands.spawn(CodEntityBundle { position, layer, ..Default::default() }); }
+                ItemKind::TropicalFishBucket => { commands.spawn(TropicalFishEntityBundle { position, layer, ..Default::default() }); }
+                ItemKind::AxolotlBucket => { commands.spawn(AxolotlEntityBundle { position, layer, ..Default::default() }); }
+                ItemKind::TadpoleBucket => { commands.spawn(TadpoleEntityBundle { position, layer, ..Default::default() }); }
+                _ => {}
+            }
         }
 
-        // if let Some(waterloggable) = layer
-        //     .block(real_pos)
-        //     .filter(|b| b.state.get(PropName::Waterlogged) == Some(PropValue::False))
-        // {
-        //     state = waterloggable.set(PropName::Waterlogged, PropValue::True);
-        // }
-
-        // client.send_chat_message(format!("{:?}", state));
-        layer.set_block(real_pos, state);
-
-        block_updates.send(BlockUpdateEvent {
-            position: real_pos,
-            layer: layer_id.0,
-            entity_layer: layer_id,
-        });
-        block_updates.send(BlockUpdateEvent {
-            position: real_pos.get_in_direction(Direction::Up),
-            layer: layer_id.0,
-            entity_layer: layer_id,
-        });
-        block_updates.send(BlockUpdateEvent {
-            position: real_pos.get_in_direction(Direction::Down),
-            layer: layer_id.0,
-            entity_layer: layer_id,
-        });
-        block_updates.send(BlockUpdateEvent {
-            position: real_pos.get_in_direction(Direction::North),
-            layer: layer_id.0,
-            entity_layer: layer_id,
-        });
-        block_updates.send(BlockUpdateEvent {
-            position: real_pos.get_in_direction(Direction::East),
-            layer: layer_id.0,
-            entity_layer: layer_id,
-        });
-        block_updates.send(BlockUpdateEvent {
-            position: real_pos.get_in_direction(Direction::South),
-            layer: layer_id.0,
-            entity_layer: layer_id,
-        });
-        block_updates.send(BlockUpdateEvent {
-            position: real_pos.get_in_direction(Direction::West),
-            layer: layer_id.0,
-            entity_layer: layer_id,
-        });
+        send_updates(&mut block_updates, layer_id, with_neighbors(used.position));
     }
 }
 
-#[derive(Component)]
-struct WaterFlowTimer(Timer);
-
-#[derive(Component)]
-struct WaterBlockPos(BlockPos);
+/// Works out what using `item` from `eyes` does, if it's a bucket that can
+/// be used there.
+///
+/// Empty buckets fill from the first fluid source, waterlogged block or
+/// powder snow in the player's line of sight. Full ones look straight
+/// through fluids and empty into the block they hit if it can be
+/// waterlogged, otherwise in front of it.
+fn use_bucket(view: &impl BlockView, eyes: DVec3, look: Look, item: ItemKind) -> Option<BucketUse> {
+    let (state, sound) = match item {
+        ItemKind::Bucket => {
+            let (position, _, state) = bucket_target(view, eyes, look, true)?;
+            let (bucket, remaining, sound) = if state.get(PropName::Waterlogged) == Some(PropValue::True) {
+                (
+                    ItemKind::WaterBucket,
+                    state.set(PropName::Waterlogged, PropValue::False),
+                    Sound::ItemBucketFill,
+                )
+            } else if state.to_kind() == BlockKind::Water {
+                (ItemKind::WaterBucket, BlockState::AIR, Sound::ItemBucketFill)
+            } else if state.to_kind() == BlockKind::Lava {
+                (ItemKind::LavaBucket, BlockState::AIR, Sound::ItemBucketFillLava)
+            } else if state.to_kind() == BlockKind::PowderSnow {
+                (ItemKind::PowderSnowBucket, BlockState::AIR, Sound::ItemBucketFillPowderSnow)
+            } else {
+                return None;
+            };
+            return Some(BucketUse {
+                position,
+                state: remaining,
+                bucket,
+                sound,
+            });
+        }
+        ItemKind::WaterBucket => (BlockState::WATER, Sound::ItemBucketEmpty),
+        ItemKind::LavaBucket => (BlockState::LAVA, Sound::ItemBucketEmptyLava),
+        ItemKind::PowderSnowBucket => (BlockState::POWDER_SNOW, Sound::ItemBucketEmptyPowderSnow),
+        ItemKind::AxolotlBucket => (BlockState::WATER, Sound::ItemBucketEmptyAxolotl),
+        ItemKind::TadpoleBucket => (BlockState::WATER, Sound::ItemBucketEmptyTadpole),
+        ItemKind::PufferfishBucket | ItemKind::SalmonBucket | ItemKind::CodBucket | ItemKind::TropicalFishBucket => {
+            (BlockState::WATER, Sound::ItemBucketEmptyFish)
+        }
+        _ => return None,
+    };
+
+    let (hit, front, _) = bucket_target(view, eyes, look, false)?;
+
+    // Water goes into the block it hits if it can hold it, otherwise in
+    // front of it.
+    let position = if state == BlockState::WATER && waterloggable(view, hit) {
+        hit
+    } else {
+        front
+    };
+    let target = view.state_at(position)?;
+
+    let new_state = if state == BlockState::WATER && waterloggable(view, position) {
+        target.set(PropName::Waterlogged, PropValue::True)
+    } else if target.is_air() || target.is_replaceable() {
+        state
+    } else {
+        return None;
+    };
+
+    Some(BucketUse {
+        position,
+        state: new_state,
+        bucket: ItemKind::Bucket,
+        sound,
+    })
+}
 
-#[derive(Bundle)]
-struct WaterFlowBundle {
-    timer: WaterFlowTimer,
-    position: WaterBlockPos,
-    layer: EntityLayerId,
+/// Whether the block can be filled with water without replacing it.
+fn waterloggable(view: &impl BlockView, position: BlockPos) -> bool {
+    view.state_at(position)
+        .is_some_and(|state| state.get(PropName::Waterlogged) == Some(PropValue::False))
 }
 
-impl WaterFlowBundle {
-    fn new(pos: BlockPos, layer: Entity) -> Self {
-        Self {
-            timer: WaterFlowTimer(Timer::new(Duration::from_millis(250), TimerMode::Once)),
-            position: WaterBlockPos(pos),
-            layer: EntityLayerId(layer),
+/// Walks along the player's line of sight and returns the first block it
+/// hits, along with the block just before it. Air and flowing fluids are
+/// always looked through, and fluid sources too unless `sources` is set.
+fn bucket_target(
+    view: &impl BlockView,
+    eyes: DVec3,
+    look: Look,
+    sources: bool,
+) -> Option<(BlockPos, BlockPos, BlockState)> {
+    let (pitch_sin, pitch_cos) = (look.pitch as f64).to_radians().sin_cos();
+    let (yaw_sin, yaw_cos) = (look.yaw as f64).to_radians().sin_cos();
+    let direction = DVec3::new(-yaw_sin * pitch_cos, -pitch_sin, yaw_cos * pitch_cos);
+
+    let mut last = block_pos(eyes);
+    for step in 0..=(BUCKET_REACH * 20.0) as i32 {
+        let point = eyes + direction * (step as f64 / 20.0);
+        let pos = block_pos(point);
+        if step > 0 && last == pos {
+            continue;
+        }
+
+        let state = view.state_at(pos)?;
+        let fluid = state.to_kind() == BlockKind::Water || state.to_kind() == BlockKind::Lava;
+        let source = fluid && state.get(PropName::Level) == Some(PropValue::_0);
+        if state.is_air() || (fluid && !(sources && source)) {
+            last = pos;
+            continue;
         }
+        return Some((pos, last, state));
     }
+    None
 }
 
-fn start_water_flow(
-    flow_entities: Query<&WaterBlockPos, With<WaterFlowTimer>>,
-    layers: Query<&ChunkLayer>,
-    mut commands: Commands,
+/// Schedules fluids to flow when something next to them changes.
+fn schedule_flow(
+    server: Res<Server>,
+    mut layers: Query<(&ChunkLayer, &mut ScheduledTicks)>,
     mut block_updates: EventReader<BlockUpdateEvent>,
 ) {
-    let mut to_spawn = Vec::<WaterFlowBundle>::new();
+    let now = server.current_tick();
 
     for event in block_updates.read() {
-        let Ok(layer) = layers.get(event.layer) else {
+        let Ok((layer, mut ticks)) = layers.get_mut(event.layer) else {
             continue;
         };
-
-        if !layer
-            .block(event.position)
-            .is_some_and(|b| b.state.to_kind() == BlockKind::Water)
-        {
+        let Some(state) = layer.block(event.position).map(|b| b.state) else {
             continue;
-        }
+        };
 
-        if flow_entities.iter().any(|e| e.0 == event.position)
-            || to_spawn.iter().any(|b| b.position.0 == event.position)
-        {
-            continue;
+        if is_water(state) {
+            ticks.schedule(now + WATER_FLOW_DELAY, event.position, BlockKind::Water, TickPriority::Normal);
+        } else if state.to_kind() == BlockKind::Lava {
+            let delay = if is_nether(layer) { NETHER_LAVA_FLOW_DELAY } else { LAVA_FLOW_DELAY };
+            ticks.schedule(now + delay, event.position, BlockKind::Lava, TickPriority::Normal);
         }
-
-        to_spawn.push(WaterFlowBundle::new(event.position, event.layer));
     }
-    commands.spawn_batch(to_spawn);
 }
 
-fn flowing_water(
-    mut flow_entities: Query<(Entity, &WaterBlockPos, &mut WaterFlowTimer, &EntityLayerId)>,
-    mut layers: Query<&mut ChunkLayer>,
-    time: Res<Time>,
-    mut commands: Commands,
-    mut block_updates: EventWriter<BlockUpdateEvent>,
-) {
-    for (entity, position, mut timer, &layer_id) in &mut flow_entities {
-        if !timer.0.tick(time.delta()).finished() {
-            continue;
-        }
+fn drop_washed_away(tables: Res<LootTables>, mut events: EventReader<WashedAwayEvent>, mut commands: Commands) {
+    let mut rng = thread_rng();
 
-        commands.entity(entity).despawn();
+    for event in events.read() {
+        let center = DVec3::new(
+            f64::from(event.position.x) + 0.5,
+            f64::from(event.position.y) + 0.25,
+            f64::from(event.position.z) + 0.5,
+        );
+        for stack in block_drops(&tables.get(event.state.to_kind()), event.state, &ItemStack::EMPTY, &mut rng) {
+            spawn_item(&mut commands, event.layer, center, stack, Vec3::new(0.0, 4.0, 0.0), DEFAULT_PICKUP_DELAY);
+        }
+    }
+}
 
-        let Ok(mut layer) = layers.get_mut(layer_id.0) else {
-            continue;
-        };
+fn flow_water(tick: &mut ScheduledTick) {
+    let position = tick.position;
+    let layer_id = tick.layer_id;
+    let layer = &mut *tick.layer;
+    let commands = &mut *tick.commands;
+    let updates = &mut *tick.updates;
+
+    let Some(mut block) = layer
+        .block(position)
+        .filter(|b| is_water(b.state))
+        .map(IntoBlock::into_block)
+    else {
+        return;
+    };
+    // Waterlogged blocks spread like a source, but are never changed by
+    // the water around them.
+    let waterlogged = block.state.to_kind() != BlockKind::Water;
+    if waterlogged {
+        block.state = BlockState::WATER;
+    }
+    let original_state = block.state;
+
+    if !waterlogged
+        && !layer
+            .block(position.get_in_direction(Direction::Up))
+            .is_some_and(|b| is_water(b.state))
+    {
+        block.state = block.state.set(
+            PropName::Level,
+            match block.state.get(PropName::Level) {
+                Some(PropValue::_8) => PropValue::_1,
+                Some(PropValue::_9) => PropValue::_2,
+                Some(PropValue::_10) => PropValue::_3,
+                Some(PropValue::_11) => PropValue::_4,
+                Some(PropValue::_12) => PropValue::_5,
+                Some(PropValue::_13) => PropValue::_6,
+                Some(PropValue::_14) => PropValue::_7,
+                Some(PropValue::_15) | None => {
+                    return;
+                }
+                Some(level) => level,
+            },
+        );
+        layer.set_block(position, block.state);
+    }
 
-        let Some(mut block) = layer
-            .block(position.0)
-            .filter(|b| b.state.to_kind() == BlockKind::Water)
-            .map(IntoBlock::into_block)
-        else {
-            continue;
-        };
-        let original_state = block.state;
+    let adjacent_sources = [
+        Direction::North,
+        Direction::East,
+        Direction::South,
+        Direction::West,
+    ]
+    .into_iter()
+    .filter(|d| {
+        layer
+            .block(position.get_in_direction(*d))
+            .is_some_and(|b| is_water_source(b.state))
+    })
+    .count();
+
+    // Water between two sources becomes a source itself, as long as there's
+    // something under it to hold it up.
+    let held_up = layer
+        .block(position.get_in_direction(Direction::Down))
+        .is_some_and(|b| is_water_source(b.state) || b.state.collision_shapes().next().is_some());
+    if !waterlogged && adjacent_sources >= 2 && held_up {
+        block.state = block.state.set(PropName::Level, PropValue::_0);
+        layer.set_block(position, block.state);
+    }
 
-        if !layer
-            .block(position.0.get_in_direction(Direction::Up))
-            .is_some_and(|b| b.state.to_kind() == BlockKind::Water)
-        {
-            block.state = block.state.set(
+    if !layer
+        .block(position.get_in_direction(Direction::Up))
+        .is_some_and(|b| is_water(b.state))
+        && !layer
+            .block(position.get_in_direction(Direction::North))
+            .is_some_and(|b| {
+                is_water(b.state)
+                    && level(b.state)
+                        .zip(level(block.state))
+                        .is_some_and(|(a, b)| a < b)
+            })
+        && !layer
+            .block(position.get_in_direction(Direction::East))
+            .is_some_and(|b| {
+                is_water(b.state)
+                    && level(b.state)
+                        .zip(level(block.state))
+                        .is_some_and(|(a, b)| a < b)
+            })
+        && !layer
+            .block(position.get_in_direction(Direction::South))
+            .is_some_and(|b| {
+                is_water(b.state)
+                    && level(b.state)
+                        .zip(level(block.state))
+                        .is_some_and(|(a, b)| a < b)
+            })
+        && !layer
+            .block(position.get_in_direction(Direction::West))
+            .is_some_and(|b| {
+                is_water(b.state)
+                    && level(b.state)
+                        .zip(level(block.state))
+                        .is_some_and(|(a, b)| a < b)
+            })
+        && block.state.get(PropName::Level) != Some(PropValue::_0)
+    {
+        block.state = if block.state.get(PropName::Level) == Some(PropValue::_7) {
+            BlockState::AIR
+        } else {
+            block.state.set(
                 PropName::Level,
                 match block.state.get(PropName::Level) {
-                    Some(PropValue::_8) => PropValue::_1,
-                    Some(PropValue::_9) => PropValue::_2,
-                    Some(PropValue::_10) => PropValue::_3,
-                    Some(PropValue::_11) => PropValue::_4,
-                    Some(PropValue::_12) => PropValue::_5,
-                    Some(PropValue::_13) => PropValue::_6,
-                    Some(PropValue::_14) => PropValue::_7,
-                    Some(PropValue::_15) | None => {
-                        continue;
+                    Some(PropValue::_0) => PropValue::_1,
+                    Some(PropValue::_1) => PropValue::_2,
+                    Some(PropValue::_2) => PropValue::_3,
+                    Some(PropValue::_3) => PropValue::_4,
+                    Some(PropValue::_4) => PropValue::_5,
+                    Some(PropValue::_5) => PropValue::_6,
+                    Some(PropValue::_6) => PropValue::_7,
+                    _ => {
+                        return;
                     }
-                    Some(level) => level,
                 },
-            );
-            layer.set_block(position.0, block.state);
-        }
-
-        let adjacent_sources = [
-            Direction::North,
-            Direction::East,
-            Direction::South,
-            Direction::West,
-        ]
-        .into_iter()
-        .filter(|d| {
-            layer
-                .block(position.0.get_in_direction(*d))
-                .is_some_and(|b| {
-                    b.state.to_kind() == BlockKind::Water
-                        && b.state.get(PropName::Level) == Some(PropValue::_0)
-                })
+            )
+        };
+        layer.set_block(position, block.state);
+    }
+    if layer
+        .block(position.get_in_direction(Direction::Down))
+        .is_some_and(|b| {
+            b.state.is_air()
+                || washable(b.state)
+                || (b.state.to_kind() == BlockKind::Water
+                    && b.state.get(PropName::Level) != Some(PropValue::_0))
         })
-        .count();
-
-        if adjacent_sources >= 2 {
-            block.state = block.state.set(
+    {
+        wash_away(layer, commands, layer_id, position.get_in_direction(Direction::Down));
+        layer.set_block(
+            position.get_in_direction(Direction::Down),
+            block.state.set(
                 PropName::Level,
                 match block.state.get(PropName::Level) {
-                    Some(PropValue::_8) | Some(PropValue::_9) | Some(PropValue::_10)
-                    | Some(PropValue::_11) | Some(PropValue::_12) | Some(PropValue::_13)
-                    | Some(PropValue::_14) | Some(PropValue::_15) => PropValue::_8,
-                    _ => PropValue::_0,
+                    Some(PropValue::_0) | // => PropValue::_8,
+                    Some(PropValue::_1) | // => PropValue::_9,
+                    Some(PropValue::_2) | // => PropValue::_10,
+                    Some(PropValue::_3) | // => PropValue::_11,
+                    Some(PropValue::_4) | // => PropValue::_12,
+                    Some(PropValue::_5) | // => PropValue::_13,
+                    Some(PropValue::_6) | // => PropValue::_14,
+                    Some(PropValue::_7) => PropValue::_8,
+                    Some(level) => level,
+                    _ => {
+                        return;
+                    }
                 },
+            ),
+        );
+        updates.push(position.get_in_direction(Direction::Down));
+        updates.push(position.get_in_direction(Direction::Down).get_in_direction(Direction::North));
+        updates.push(position.get_in_direction(Direction::Down).get_in_direction(Direction::East));
+        updates.push(position.get_in_direction(Direction::Down).get_in_direction(Direction::South));
+        updates.push(position.get_in_direction(Direction::Down).get_in_direction(Direction::West));
+        updates.push(position.get_in_direction(Direction::Down).get_in_direction(Direction::Down));
+    } else if !layer
+        .block(position.get_in_direction(Direction::Down))
+        .is_some_and(|b| b.state.to_kind() == BlockKind::Water
+                    && b.state.get(PropName::Level) != Some(PropValue::_0))
+    {
+        // Water at the end of its reach doesn't spread any further.
+        if level(block.state) == Some(7) {
+            return;
+        }
+        if layer
+            .block(position.get_in_direction(Direction::North))
+            .is_some_and(|b| b.state.is_air() || washable(b.state))
+        {
+            wash_away(layer, commands, layer_id, position.get_in_direction(Direction::North));
+            layer.set_block(
+                position.get_in_direction(Direction::North),
+                block.state.set(
+                    PropName::Level,
+                    match block.state.get(PropName::Level) {
+                        Some(PropValue::_7) | Some(PropValue::_15) => {
+                            return;
+                        }
+                        Some(PropValue::_6) | Some(PropValue::_14) => PropValue::_7,
+                        Some(PropValue::_5) | Some(PropValue::_13) => PropValue::_6,
+                        Some(PropValue::_4) | Some(PropValue::_12) => PropValue::_5,
+                        Some(PropValue::_3) | Some(PropValue::_11) => PropValue::_4,
+                        Some(PropValue::_2) | Some(PropValue::_10) => PropValue::_3,
+                        Some(PropValue::_1) | Some(PropValue::_9) => PropValue::_2,
+                        _ => PropValue::_1,
+                    },
+                ),
             );
-            layer.set_block(position.0, block.state);
+            updates.push(position.get_in_direction(Direction::North));
+            updates.push(position.get_in_direction(Direction::North).get_in_direction(Direction::North));
+            updates.push(position.get_in_direction(Direction::North).get_in_direction(Direction::East));
+            updates.push(position.get_in_direction(Direction::North).get_in_direction(Direction::Up));
+            updates.push(position.get_in_direction(Direction::North).get_in_direction(Direction::West));
+            updates.push(position.get_in_direction(Direction::North).get_in_direction(Direction::Down));
         }
-
-        if !layer
-            .block(position.0.get_in_direction(Direction::Up))
-            .is_some_and(|b| b.state.to_kind() == BlockKind::Water)
-            && !layer
-                .block(position.0.get_in_direction(Direction::North))
-                .is_some_and(|b| {
-                    b.state.to_kind() == BlockKind::Water
-                        && level(b.state)
-                            .zip(level(block.state))
-                            .is_some_and(|(a, b)| a < b)
-                })
-            && !layer
-                .block(position.0.get_in_direction(Direction::East))
-                .is_some_and(|b| {
-                    b.state.to_kind() == BlockKind::Water
-                        && level(b.state)
-                            .zip(level(block.state))
-                            .is_some_and(|(a, b)| a < b)
-                })
-            && !layer
-                .block(position.0.get_in_direction(Direction::South))
-                .is_some_and(|b| {
-                    b.state.to_kind() == BlockKind::Water
-                        && level(b.state)
-                            .zip(level(block.state))
-                            .is_some_and(|(a, b)| a < b)
-                })
-            && !layer
-                .block(position.0.get_in_direction(Direction::West))
-                .is_some_and(|b| {
-                    b.state.to_kind() == BlockKind::Water
-                        && level(b.state)
-                            .zip(level(block.state))
-                            .is_some_and(|(a, b)| a < b)
-                })
-            && block.state.get(PropName::Level) != Some(PropValue::_0)
+        if layer
+            .block(position.get_in_direction(Direction::East))
+            .is_some_and(|b| b.state.is_air() || washable(b.state))
         {
-            block.state = if block.state.get(PropName::Level) == Some(PropValue::_7) {
-                BlockState::AIR
-            } else {
+            wash_away(layer, commands, layer_id, position.get_in_direction(Direction::East));
+            layer.set_block(
+                position.get_in_direction(Direction::East),
                 block.state.set(
                     PropName::Level,
                     match block.state.get(PropName::Level) {
-                        Some(PropValue::_0) => PropValue::_1,
-                        Some(PropValue::_1) => PropValue::_2,
-                        Some(PropValue::_2) => PropValue::_3,
-                        Some(PropValue::_3) => PropValue::_4,
-                        Some(PropValue::_4) => PropValue::_5,
-                        Some(PropValue::_5) => PropValue::_6,
-                        Some(PropValue::_6) => PropValue::_7,
-                        _ => {
-                            continue;
+                        Some(PropValue::_7) | Some(PropValue::_15) => {
+                            return;
                         }
+                        Some(PropValue::_6) | Some(PropValue::_14) => PropValue::_7,
+                        Some(PropValue::_5) | Some(PropValue::_13) => PropValue::_6,
+                        Some(PropValue::_4) | Some(PropValue::_12) => PropValue::_5,
+                        Some(PropValue::_3) | Some(PropValue::_11) => PropValue::_4,
+                        Some(PropValue::_2) | Some(PropValue::_10) => PropValue::_3,
+                        Some(PropValue::_1) | Some(PropValue::_9) => PropValue::_2,
+                        _ => PropValue::_1,
                     },
-                )
-            };
-            layer.set_block(position.0, block.state);
+                ),
+            );
+            updates.push(position.get_in_direction(Direction::East));
+            updates.push(position.get_in_direction(Direction::East).get_in_direction(Direction::North));
+            updates.push(position.get_in_direction(Direction::East).get_in_direction(Direction::East));
+            updates.push(position.get_in_direction(Direction::East).get_in_direction(Direction::South));
+            updates.push(position.get_in_direction(Direction::East).get_in_direction(Direction::Up));
+            updates.push(position.get_in_direction(Direction::East).get_in_direction(Direction::Down));
         }
         if layer
-            .block(position.0.get_in_direction(Direction::Down))
-            .is_some_and(|b| {
-                b.state.is_air()
-                    || (b.state.to_kind() == BlockKind::Water
-                        && b.state.get(PropName::Level) != Some(PropValue::_0))
-            })
+            .block(position.get_in_direction(Direction::South))
+            .is_some_and(|b| b.state.is_air() || washable(b.state))
         {
+            wash_away(layer, commands, layer_id, position.get_in_direction(Direction::South));
             layer.set_block(
-                position.0.get_in_direction(Direction::Down),
+                position.get_in_direction(Direction::South),
                 block.state.set(
                     PropName::Level,
                     match block.state.get(PropName::Level) {
-                        Some(PropValue::_0) | // => PropValue::_8,
-                        Some(PropValue::_1) | // => PropValue::_9,
-                        Some(PropValue::_2) | // => PropValue::_10,
-                        Some(PropValue::_3) | // => PropValue::_11,
-                        Some(PropValue::_4) | // => PropValue::_12,
-                        Some(PropValue::_5) | // => PropValue::_13,
-                        Some(PropValue::_6) | // => PropValue::_14,
-                        Some(PropValue::_7) => PropValue::_8,
-                        Some(level) => level,
-                        _ => {
-                            continue;
+                        Some(PropValue::_7) | Some(PropValue::_15) => {
+                            return;
                         }
+                        Some(PropValue::_6) | Some(PropValue::_14) => PropValue::_7,
+                        Some(PropValue::_5) | Some(PropValue::_13) => PropValue::_6,
+                        Some(PropValue::_4) | Some(PropValue::_12) => PropValue::_5,
+                        Some(PropValue::_3) | Some(PropValue::_11) => PropValue::_4,
+                        Some(PropValue::_2) | Some(PropValue::_10) => PropValue::_3,
+                        Some(PropValue::_1) | Some(PropValue::_9) => PropValue::_2,
+                        _ => PropValue::_1,
                     },
                 ),
             );
-            block_updates.send(BlockUpdateEvent {
-                position: position.0.get_in_direction(Direction::Down),
-                layer: layer_id.0,
-                entity_layer: layer_id,
-            });
-            block_updates.send(BlockUpdateEvent {
-                position: position
-                    .0
-                    .get_in_direction(Direction::Down)
-                    .get_in_direction(Direction::North),
-                layer: layer_id.0,
-                entity_layer: layer_id,
-            });
-            block_updates.send(BlockUpdateEvent {
-                position: position
-                    .0
-                    .get_in_direction(Direction::Down)
-                    .get_in_direction(Direction::East),
-                layer: layer_id.0,
-                entity_layer: layer_id,
-            });
-            block_updates.send(BlockUpdateEvent {
-                position: position
-                    .0
-                    .get_in_direction(Direction::Down)
-                    .get_in_direction(Direction::South),
-                layer: layer_id.0,
-                entity_layer: layer_id,
-            });
-            block_updates.send(BlockUpdateEvent {
-                position: position
-                    .0
-                    .get_in_direction(Direction::Down)
-                    .get_in_direction(Direction::West),
-                layer: layer_id.0,
-                entity_layer: layer_id,
-            });
-            block_updates.send(BlockUpdateEvent {
-                position: position
-                    .0
-                    .get_in_direction(Direction::Down)
-                    .get_in_direction(Direction::Down),
-                layer: layer_id.0,
-                entity_layer: layer_id,
-            });
-        } else if !layer
-            .block(position.0.get_in_direction(Direction::Down))
-            .is_some_and(|b| b.state.to_kind() == BlockKind::Water
-                        && b.state.get(PropName::Level) != Some(PropValue::_0))
+            updates.push(position.get_in_direction(Direction::South));
+            updates.push(position.get_in_direction(Direction::South).get_in_direction(Direction::Up));
+            updates.push(position.get_in_direction(Direction::South).get_in_direction(Direction::East));
+            updates.push(position.get_in_direction(Direction::South).get_in_direction(Direction::South));
+            updates.push(position.get_in_direction(Direction::South).get_in_direction(Direction::West));
+            updates.push(position.get_in_direction(Direction::South).get_in_direction(Direction::Down));
+        }
+        if layer
+            .block(position.get_in_direction(Direction::West))
+            .is_some_and(|b| b.state.is_air() || washable(b.state))
         {
-            if layer
-                .block(position.0.get_in_direction(Direction::North))
-                .is_some_and(|b| b.state.is_air())
-            {
-                layer.set_block(
-                    position.0.get_in_direction(Direction::North),
-                    block.state.set(
-                        PropName::Level,
-                        match block.state.get(PropName::Level) {
-                            Some(PropValue::_7) | Some(PropValue::_15) => {
-                                continue;
-                            }
-                            Some(PropValue::_6) | Some(PropValue::_14) => PropValue::_7,
-                            Some(PropValue::_5) | Some(PropValue::_13) => PropValue::_6,
-                            Some(PropValue::_4) | Some(PropValue::_12) => PropValue::_5,
-                            Some(PropValue::_3) | Some(PropValue::_11) => PropValue::_4,
-                            Some(PropValue::_2) | Some(PropValue::_10) => PropValue::_3,
-                            Some(PropValue::_1) | Some(PropValue::_9) => PropValue::_2,
-                            _ => PropValue::_1,
-                        },
-                    ),
-                );
-                block_updates.send(BlockUpdateEvent {
-                    position: position.0.get_in_direction(Direction::North),
-                    layer: layer_id.0,
-                    entity_layer: layer_id,
-                });
-                block_updates.send(BlockUpdateEvent {
-                    position: position
-                        .0
-                        .get_in_direction(Direction::North)
-                        .get_in_direction(Direction::North),
-                    layer: layer_id.0,
-                    entity_layer: layer_id,
-                });
-                block_updates.send(BlockUpdateEvent {
-                    position: position
-                        .0
-                        .get_in_direction(Direction::North)
-                        .get_in_direction(Direction::East),
-                    layer: layer_id.0,
-                    entity_layer: layer_id,
-                });
-                block_updates.send(BlockUpdateEvent {
-                    position: position
-                        .0
-                        .get_in_direction(Direction::North)
-                        .get_in_direction(Direction::Up),
-                    layer: layer_id.0,
-                    entity_layer: layer_id,
-                });
-                block_updates.send(BlockUpdateEvent {
-                    position: position
-                        .0
-                        .get_in_direction(Direction::North)
-                        .get_in_direction(Direction::West),
-                    layer: layer_id.0,
-                    entity_layer: layer_id,
-                });
-                block_updates.send(BlockUpdateEvent {
-                    position: position
-                        .0
-                        .get_in_direction(Direction::North)
-                        .get_in_direction(Direction::Down),
-                    layer: layer_id.0,
-                    entity_layer: layer_id,
-                });
-            }
-            if layer
-                .block(position.0.get_in_direction(Direction::East))
-                .is_some_and(|b| b.state.is_air())
-            {
-                layer.set_block(
-                    position.0.get_in_direction(Direction::East),
-                    block.state.set(
-                        PropName::Level,
-                        match block.state.get(PropName::Level) {
-                            Some(PropValue::_7) | Some(PropValue::_15) => {
-                                continue;
-                            }
-                            Some(PropValue::_6) | Some(PropValue::_14) => PropValue::_7,
-                            Some(PropValue::_5) | Some(PropValue::_13) => PropValue::_6,
-                            Some(PropValue::_4) | Some(PropValue::_12) => PropValue::_5,
-                            Some(PropValue::_3) | Some(PropValue::_11) => PropValue::_4,
-                            Some(PropValue::_2) | Some(PropValue::_10) => PropValue::_3,
-                            Some(PropValue::_1) | Some(PropValue::_9) => PropValue::_2,
-                            _ => PropValue::_1,
-                        },
-                    ),
-                );
-                block_updates.send(BlockUpdateEvent {
-                    position: position.0.get_in_direction(Direction::East),
-                    layer: layer_id.0,
-                    entity_layer: layer_id,
-                });
-                block_updates.send(BlockUpdateEvent {
-                    position: position
-                        .0
-                        .get_in_direction(Direction::East)
-                        .get_in_direction(Direction::North),
-                    layer: layer_id.0,
-                    entity_layer: layer_id,
-                });
-                block_updates.send(BlockUpdateEvent {
-                    position: position
-                        .0
-                        .get_in_direction(Direction::East)
-                        .get_in_direction(Direction::East),
-                    layer: layer_id.0,
-                    entity_layer: layer_id,
-                });
-                block_updates.send(BlockUpdateEvent {
-                    position: position
-                        .0
-                        .get_in_direction(Direction::East)
-                        .get_in_direction(Direction::South),
-                    layer: layer_id.0,
-                    entity_layer: layer_id,
-                });
-                block_updates.send(BlockUpdateEvent {
-                    position: position
-                        .0
-                        .get_in_direction(Direction::East)
-                        .get_in_direction(Direction::Up),
-                    layer: layer_id.0,
-                    entity_layer: layer_id,
-                });
-                block_updates.send(BlockUpdateEvent {
-                    position: position
-                        .0
-                        .get_in_direction(Direction::East)
-                        .get_in_direction(Direction::Down),
-                    layer: layer_id.0,
-                    entity_layer: layer_id,
-                });
-            }
-            if layer
-                .block(position.0.get_in_direction(Direction::South))
-                .is_some_and(|b| b.state.is_air())
-            {
-                layer.set_block(
-                    position.0.get_in_direction(Direction::South),
-                    block.state.set(
-                        PropName::Level,
-                        match block.state.get(PropName::Level) {
-                            Some(PropValue::_7) | Some(PropValue::_15) => {
-                                continue;
-                            }
-                            Some(PropValue::_6) | Some(PropValue::_14) => PropValue::_7,
-                            Some(PropValue::_5) | Some(PropValue::_13) => PropValue::_6,
-                            Some(PropValue::_4) | Some(PropValue::_12) => PropValue::_5,
-                            Some(PropValue::_3) | Some(PropValue::_11) => PropValue::_4,
-                            Some(PropValue::_2) | Some(PropValue::_10) => PropValue::_3,
-                            Some(PropValue::_1) | Some(PropValue::_9) => PropValue::_2,
-                            _ => PropValue::_1,
-                        },
-                    ),
-                );
-                block_updates.send(BlockUpdateEvent {
-                    position: position.0.get_in_direction(Direction::South),
-                    layer: layer_id.0,
-                    entity_layer: layer_id,
-                });
-                block_updates.send(BlockUpdateEvent {
-                    position: position
-                        .0
-                        .get_in_direction(Direction::South)
-                        .get_in_direction(Direction::Up),
-                    layer: layer_id.0,
-                    entity_layer: layer_id,
-                });
-                block_updates.send(BlockUpdateEvent {
-                    position: position
-                        .0
-                        .get_in_direction(Direction::South)
-                        .get_in_direction(Direction::East),
-                    layer: layer_id.0,
-                    entity_layer: layer_id,
-                });
-                block_updates.send(BlockUpdateEvent {
-                    position: position
-                        .0
-                        .get_in_direction(Direction::South)
-                        .get_in_direction(Direction::South),
-                    layer: layer_id.0,
-                    entity_layer: layer_id,
-                });
-                block_updates.send(BlockUpdateEvent {
-                    position: position
-                        .0
-                        .get_in_direction(Direction::South)
-                        .get_in_direction(Direction::West),
-                    layer: layer_id.0,
-                    entity_layer: layer_id,
-                });
-                block_updates.send(BlockUpdateEvent {
-                    position: position
-                        .0
-                        .get_in_direction(Direction::South)
-                        .get_in_direction(Direction::Down),
-                    layer: layer_id.0,
-                    entity_layer: layer_id,
-                });
-            }
-            if layer
-                .block(position.0.get_in_direction(Direction::West))
-                .is_some_and(|b| b.state.is_air())
-            {
-                layer.set_block(
-                    position.0.get_in_direction(Direction::West),
-                    block.state.set(
-                        PropName::Level,
-                        match block.state.get(PropName::Level) {
-                            Some(PropValue::_7) | Some(PropValue::_15) => {
-                                continue;
-                            }
-                            Some(PropValue::_6) | Some(PropValue::_14) => PropValue::_7,
-                            Some(PropValue::_5) | Some(PropValue::_13) => PropValue::_6,
-                            Some(PropValue::_4) | Some(PropValue::_12) => PropValue::_5,
-                            Some(PropValue::_3) | Some(PropValue::_11) => PropValue::_4,
-                            Some(PropValue::_2) | Some(PropValue::_10) => PropValue::_3,
-                            Some(PropValue::_1) | Some(PropValue::_9) => PropValue::_2,
-                            _ => PropValue::_1,
-                        },
-                    ),
-                );
-                block_updates.send(BlockUpdateEvent {
-                    position: position.0.get_in_direction(Direction::West),
-                    layer: layer_id.0,
-                    entity_layer: layer_id,
-                });
-                block_updates.send(BlockUpdateEvent {
-                    position: position
-                        .0
-                        .get_in_direction(Direction::West)
-                        .get_in_direction(Direction::North),
-                    layer: layer_id.0,
-                    entity_layer: layer_id,
-                });
-                block_updates.send(BlockUpdateEvent {
-                    position: position
-                        .0
-                        .get_in_direction(Direction::West)
-                        .get_in_direction(Direction::Up),
-                    layer: layer_id.0,
-                    entity_layer: layer_id,
-                });
-                block_updates.send(BlockUpdateEvent {
-                    position: position
-                        .0
-                        .get_in_direction(Direction::West)
-                        .get_in_direction(Direction::South),
-                    layer: layer_id.0,
-                    entity_layer: layer_id,
-                });
-                block_updates.send(BlockUpdateEvent {
-                    position: position
-                        .0
-                        .get_in_direction(Direction::West)
-                        .get_in_direction(Direction::West),
-                    layer: layer_id.0,
-                    entity_layer: layer_id,
-                });
-                block_updates.send(BlockUpdateEvent {
-                    position: position
-                        .0
-                        .get_in_direction(Direction::West)
-                        .get_in_direction(Direction::Down),
-                    layer: layer_id.0,
-                    entity_layer: layer_id,
-                });
-            }
+            wash_away(layer, commands, layer_id, position.get_in_direction(Direction::West));
+            layer.set_block(
+                position.get_in_direction(Direction::West),
+                block.state.set(
+                    PropName::Level,
+                    match block.state.get(PropName::Level) {
+                        Some(PropValue::_7) | Some(PropValue::_15) => {
+                            return;
+                        }
+                        Some(PropValue::_6) | Some(PropValue::_14) => PropValue::_7,
+                        Some(PropValue::_5) | Some(PropValue::_13) => PropValue::_6,
+                        Some(PropValue::_4) | Some(PropValue::_12) => PropValue::_5,
+                        Some(PropValue::_3) | Some(PropValue::_11) => PropValue::_4,
+                        Some(PropValue::_2) | Some(PropValue::_10) => PropValue::_3,
+                        Some(PropValue::_1) | Some(PropValue::_9) => PropValue::_2,
+                        _ => PropValue::_1,
+                    },
+                ),
+            );
+            updates.push(position.get_in_direction(Direction::West));
+            updates.push(position.get_in_direction(Direction::West).get_in_direction(Direction::North));
+            updates.push(position.get_in_direction(Direction::West).get_in_direction(Direction::Up));
+            updates.push(position.get_in_direction(Direction::West).get_in_direction(Direction::South));
+            updates.push(position.get_in_direction(Direction::West).get_in_direction(Direction::West));
+            updates.push(position.get_in_direction(Direction::West).get_in_direction(Direction::Down));
         }
+    }
 
-        if block.state != original_state {
-            block_updates.send(BlockUpdateEvent {
-                position: position.0,
-                layer: layer_id.0,
-                entity_layer: layer_id,
-            });
-            block_updates.send(BlockUpdateEvent {
-                position: position.0.get_in_direction(Direction::Up),
-                layer: layer_id.0,
-                entity_layer: layer_id,
-            });
-            block_updates.send(BlockUpdateEvent {
-                position: position.0.get_in_direction(Direction::North),
-                layer: layer_id.0,
-                entity_layer: layer_id,
-            });
-            block_updates.send(BlockUpdateEvent {
-                position: position.0.get_in_direction(Direction::East),
-                layer: layer_id.0,
-                entity_layer: layer_id,
-            });
-            block_updates.send(BlockUpdateEvent {
-                position: position.0.get_in_direction(Direction::South),
-                layer: layer_id.0,
-                entity_layer: layer_id,
-            });
-            block_updates.send(BlockUpdateEvent {
-                position: position.0.get_in_direction(Direction::West),
-                layer: layer_id.0,
-                entity_layer: layer_id,
-            });
-            block_updates.send(BlockUpdateEvent {
-                position: position.0.get_in_direction(Direction::Down),
-                layer: layer_id.0,
-                entity_layer: layer_id,
-            });
-        }
+    if block.state != original_state {
+        updates.push(position);
+        updates.push(position.get_in_direction(Direction::Up));
+        updates.push(position.get_in_direction(Direction::North));
+        updates.push(position.get_in_direction(Direction::East));
+        updates.push(position.get_in_direction(Direction::South));
+        updates.push(position.get_in_direction(Direction::West));
+        updates.push(position.get_in_direction(Direction::Down));
     }
 }
 
 fn level(state: BlockState) -> Option<u8> {
+    if state.get(PropName::Waterlogged) == Some(PropValue::True) {
+        return Some(0);
+    }
     match state.get(PropName::Level) {
         Some(PropValue::_0) | Some(PropValue::_8) => Some(0),
         Some(PropValue::_1) | Some(PropValue::_9) => Some(1),
@@ -715,556 +624,342 @@ fn level(state: BlockState) -> Option<u8> {
     }
 }
 
-#[derive(Component)]
-struct LavaFlowTimer(Timer);
-
-#[derive(Component)]
-struct LavaBlockPos(BlockPos);
-
-#[derive(Bundle)]
-struct LavaFlowBundle {
-    timer: LavaFlowTimer,
-    position: LavaBlockPos,
-    layer: EntityLayerId,
+/// Whether the block is water or holds some.
+fn is_water(state: BlockState) -> bool {
+    state.to_kind() == BlockKind::Water || state.get(PropName::Waterlogged) == Some(PropValue::True)
 }
 
-impl LavaFlowBundle {
-    fn new(pos: BlockPos, layer: Entity) -> Self {
-        Self {
-            timer: LavaFlowTimer(Timer::new(Duration::from_millis(1500), TimerMode::Once)),
-            position: LavaBlockPos(pos),
-            layer: EntityLayerId(layer),
-        }
+/// Which fluid is in the block, if any. Waterlogged blocks count as water.
+pub fn fluid(state: BlockState) -> Option<BlockKind> {
+    if is_water(state) {
+        Some(BlockKind::Water)
+    } else if state.to_kind() == BlockKind::Lava {
+        Some(BlockKind::Lava)
+    } else {
+        None
     }
 }
 
-fn start_lava_flow(
-    flow_entities: Query<&LavaBlockPos, With<LavaFlowTimer>>,
-    layers: Query<&ChunkLayer>,
-    mut commands: Commands,
-    mut block_updates: EventReader<BlockUpdateEvent>,
-) {
-    let mut to_spawn = Vec::<LavaFlowBundle>::new();
+/// How far up the block the fluid in it comes, from 0 to 1. Sources are
+/// 8/9 of a block high, and each level they spread out drops it by a ninth.
+pub fn fluid_height(state: BlockState) -> f64 {
+    if fluid(state).is_none() {
+        return 0.0;
+    }
+    level(state).map_or(0.0, |l| f64::from(8 - l) / 9.0)
+}
 
-    for event in block_updates.read() {
-        let Ok(layer) = layers.get(event.layer) else {
+/// The way the fluid at `position` is flowing, as a unit vector. It runs from
+/// higher fluid to lower, and over edges where it can fall. Still fluid gives
+/// zero.
+pub fn flow(layer: &ChunkLayer, position: BlockPos) -> DVec3 {
+    let Some(state) = layer.block(position).map(|b| b.state) else {
+        return DVec3::ZERO;
+    };
+    let Some(kind) = fluid(state) else {
+        return DVec3::ZERO;
+    };
+    let height = fluid_height(state);
+
+    let mut flow = DVec3::ZERO;
+    for (direction, offset) in [
+        (Direction::North, DVec3::NEG_Z),
+        (Direction::East, DVec3::X),
+        (Direction::South, DVec3::Z),
+        (Direction::West, DVec3::NEG_X),
+    ] {
+        let side = position.get_in_direction(direction);
+        let Some(side_state) = layer.block(side).map(|b| b.state) else {
             continue;
         };
 
-        if !layer
-            .block(event.position)
-            .is_some_and(|b| b.state.to_kind() == BlockKind::Lava)
-        {
+        let difference = if fluid(side_state) == Some(kind) {
+            height - fluid_height(side_state)
+        } else if side_state.collision_shapes().next().is_none() {
+            // Fluid pours over the edge into the same fluid below.
+            let Some(below) = layer
+                .block(side.get_in_direction(Direction::Down))
+                .map(|b| b.state)
+                .filter(|s| fluid(*s) == Some(kind))
+            else {
+                continue;
+            };
+            height - (fluid_height(below) - 8.0 / 9.0)
+        } else {
             continue;
-        }
+        };
+        flow += offset * difference;
+    }
 
-        if flow_entities.iter().any(|e| e.0 == event.position)
-            || to_spawn.iter().any(|b| b.position.0 == event.position)
-        {
-            continue;
-        }
+    flow.normalize_or_zero()
+}
 
-        to_spawn.push(LavaFlowBundle::new(event.position, event.layer));
-    }
-    commands.spawn_batch(to_spawn);
+fn is_water_source(state: BlockState) -> bool {
+    (state.to_kind() == BlockKind::Water && state.get(PropName::Level) == Some(PropValue::_0))
+        || state.get(PropName::Waterlogged) == Some(PropValue::True)
 }
 
-fn flowing_lava(
-    mut flow_entities: Query<(Entity, &LavaBlockPos, &mut LavaFlowTimer, &EntityLayerId)>,
-    mut layers: Query<&mut ChunkLayer>,
-    time: Res<Time>,
-    mut commands: Commands,
-    mut block_updates: EventWriter<BlockUpdateEvent>,
-) {
-    for (entity, position, mut timer, &layer_id) in &mut flow_entities {
-        if !timer.0.tick(time.delta()).finished() {
-            continue;
-        }
+/// Lava spreads on its scheduled ticks, but hardens as soon as water touches
+/// it rather than waiting for the next one.
+struct Lava;
 
-        commands.entity(entity).despawn();
+impl BlockBehavior for Lava {
+    /// Lava that touches water hardens: sources into obsidian and flowing lava
+    /// into cobblestone.
+    fn on_neighbor_update(&self, update: &mut NeighborUpdate) {
+        let wet = [
+            Direction::Up,
+            Direction::North,
+            Direction::East,
+            Direction::South,
+            Direction::West,
+        ]
+        .into_iter()
+        .any(|d| update.neighbor(d).is_some_and(is_water));
+        if !wet {
+            return;
+        }
 
-        let Ok(mut layer) = layers.get_mut(layer_id.0) else {
-            continue;
+        let hardened = if update.block.state.get(PropName::Level) == Some(PropValue::_0) {
+            BlockState::OBSIDIAN
+        } else {
+            BlockState::COBBLESTONE
         };
+        update.block = hardened.into_block();
+        update.play_sound(Sound::BlockLavaExtinguish, 0.5, 2.6);
+    }
 
-        let Some(mut block) = layer
-            .block(position.0)
-            .filter(|b| b.state.to_kind() == BlockKind::Lava)
-            .map(IntoBlock::into_block)
-        else {
-            continue;
+    fn on_scheduled_tick(&self, tick: &mut ScheduledTick) {
+        flow_lava(tick);
+    }
+}
+
+fn flow_lava(tick: &mut ScheduledTick) {
+    let position = tick.position;
+    let layer = &mut *tick.layer;
+    let updates = &mut *tick.updates;
+
+    let Some(mut block) = layer
+        .block(position)
+        .filter(|b| b.state.to_kind() == BlockKind::Lava)
+        .map(IntoBlock::into_block)
+    else {
+        return;
+    };
+    let original_state = block.state;
+    // How much the level goes up for each block lava spreads. Lava reaches
+    // twice as far in the Nether.
+    let drop = if is_nether(layer) { 1 } else { 2 };
+
+    let fed_from_above = layer
+        .block(position.get_in_direction(Direction::Up))
+        .is_some_and(|b| b.state.to_kind() == BlockKind::Lava);
+
+    // Falling lava with nothing above it any more spreads out like the lava
+    // it fell from.
+    if !fed_from_above && falling(block.state) {
+        let Some(next) = level(block.state).map(|l| l + drop).filter(|&l| l <= 7) else {
+            return;
         };
-        let original_state = block.state;
+        block.state = block.state.set(PropName::Level, number(next));
+        layer.set_block(position, block.state);
+    }
 
-        if !layer
-            .block(position.0.get_in_direction(Direction::Up))
-            .is_some_and(|b| b.state.to_kind() == BlockKind::Water)
-        {
-            block.state = block.state.set(
-                PropName::Level,
-                match block.state.get(PropName::Level) {
-                    Some(PropValue::_8) => PropValue::_2,
-                    Some(PropValue::_9) => PropValue::_3,
-                    Some(PropValue::_10) => PropValue::_4,
-                    Some(PropValue::_11) => PropValue::_5,
-                    Some(PropValue::_12) => PropValue::_6,
-                    Some(PropValue::_13) => PropValue::_7,
-                    Some(PropValue::_14) | Some(PropValue::_15) | None => {
-                        continue;
-                    }
-                    Some(level) => level,
-                },
-            );
-            layer.set_block(position.0, block.state);
-        }
+    let fed_from_side = [
+        Direction::North,
+        Direction::East,
+        Direction::South,
+        Direction::West,
+    ]
+    .into_iter()
+    .any(|d| {
+        layer.block(position.get_in_direction(d)).is_some_and(|b| {
+            b.state.to_kind() == BlockKind::Lava
+                && level(b.state)
+                    .zip(level(block.state))
+                    .is_some_and(|(a, b)| a < b)
+        })
+    });
 
-        if !layer
-            .block(position.0.get_in_direction(Direction::Up))
-            .is_some_and(|b| b.state.to_kind() == BlockKind::Water)
-            && !layer
-                .block(position.0.get_in_direction(Direction::North))
-                .is_some_and(|b| {
-                    b.state.to_kind() == BlockKind::Water
-                        && level(b.state)
-                            .zip(level(block.state))
-                            .is_some_and(|(a, b)| a < b)
-                })
-            && !layer
-                .block(position.0.get_in_direction(Direction::East))
-                .is_some_and(|b| {
-                    b.state.to_kind() == BlockKind::Water
-                        && level(b.state)
-                            .zip(level(block.state))
-                            .is_some_and(|(a, b)| a < b)
-                })
-            && !layer
-                .block(position.0.get_in_direction(Direction::South))
-                .is_some_and(|b| {
-                    b.state.to_kind() == BlockKind::Water
-                        && level(b.state)
-                            .zip(level(block.state))
-                            .is_some_and(|(a, b)| a < b)
-                })
-            && !layer
-                .block(position.0.get_in_direction(Direction::West))
-                .is_some_and(|b| {
-                    b.state.to_kind() == BlockKind::Water
-                        && level(b.state)
-                            .zip(level(block.state))
-                            .is_some_and(|(a, b)| a < b)
-                })
-            && block.state.get(PropName::Level) != Some(PropValue::_0)
-        {
-            block.state = if block.state.get(PropName::Level) == Some(PropValue::_6)
-                || block.state.get(PropName::Level) == Some(PropValue::_7)
-            {
-                BlockState::AIR
-            } else {
-                block.state.set(
-                    PropName::Level,
-                    match block.state.get(PropName::Level) {
-                        Some(PropValue::_0) => PropValue::_2,
-                        Some(PropValue::_1) => PropValue::_3,
-                        Some(PropValue::_2) => PropValue::_4,
-                        Some(PropValue::_3) => PropValue::_5,
-                        Some(PropValue::_4) => PropValue::_6,
-                        Some(PropValue::_5) => PropValue::_7,
-                        _ => {
-                            continue;
-                        }
-                    },
-                )
+    if !fed_from_above && !fed_from_side && block.state.get(PropName::Level) != Some(PropValue::_0) {
+        block.state = match level(block.state).map(|l| l + drop) {
+            Some(next) if next <= 7 => block.state.set(PropName::Level, number(next)),
+            _ => BlockState::AIR,
+        };
+        layer.set_block(position, block.state);
+    }
+
+    if !block.state.is_air() {
+        let below = position.get_in_direction(Direction::Down);
+        let below_state = layer.block(below).map(|b| b.state);
+
+        if below_state.is_some_and(|s| s.to_kind() == BlockKind::Water) {
+            // Lava running down into water turns it to stone.
+            layer.set_block(below, BlockState::STONE);
+            fizz(layer, below);
+            updates.extend(with_neighbors(below));
+        } else if below_state.is_some_and(|s| {
+            s.is_air() || washable(s) || (s.to_kind() == BlockKind::Lava && s.get(PropName::Level) != Some(PropValue::_0))
+        }) {
+            burn_away(layer, below);
+            layer.set_block(below, block.state.set(PropName::Level, PropValue::_8));
+            updates.extend(with_neighbors(below));
+        } else if !below_state.is_some_and(|s| s.to_kind() == BlockKind::Lava) {
+            let Some(next) = level(block.state).map(|l| l + drop).filter(|&l| l <= 7) else {
+                return;
             };
-            layer.set_block(position.0, block.state);
-        }
-        if layer
-            .block(position.0.get_in_direction(Direction::Down))
-            .is_some_and(|b| {
-                b.state.is_air()
-                    || (b.state.to_kind() == BlockKind::Water
-                        && b.state.get(PropName::Level) != Some(PropValue::_0))
-            })
-        {
-            layer.set_block(
-                position.0.get_in_direction(Direction::Down),
-                block.state.set(
-                    PropName::Level,
-                    match block.state.get(PropName::Level) {
-                        Some(PropValue::_0) | // => PropValue::_8,
-                        Some(PropValue::_1) | // => PropValue::_9,
-                        Some(PropValue::_2) | // => PropValue::_10,
-                        Some(PropValue::_3) | // => PropValue::_11,
-                        Some(PropValue::_4) | // => PropValue::_12,
-                        Some(PropValue::_5) | // => PropValue::_13,
-                        Some(PropValue::_6) | // => PropValue::_14,
-                        Some(PropValue::_7) => PropValue::_8,
-                        Some(level) => level,
-                        _ => {
-                            continue;
-                        }
-                    },
-                ),
-            );
-            block_updates.send(BlockUpdateEvent {
-                position: position.0.get_in_direction(Direction::Down),
-                layer: layer_id.0,
-                entity_layer: layer_id,
-            });
-            block_updates.send(BlockUpdateEvent {
-                position: position
-                    .0
-                    .get_in_direction(Direction::Down)
-                    .get_in_direction(Direction::North),
-                layer: layer_id.0,
-                entity_layer: layer_id,
-            });
-            block_updates.send(BlockUpdateEvent {
-                position: position
-                    .0
-                    .get_in_direction(Direction::Down)
-                    .get_in_direction(Direction::East),
-                layer: layer_id.0,
-                entity_layer: layer_id,
-            });
-            block_updates.send(BlockUpdateEvent {
-                position: position
-                    .0
-                    .get_in_direction(Direction::Down)
-                    .get_in_direction(Direction::South),
-                layer: layer_id.0,
-                entity_layer: layer_id,
-            });
-            block_updates.send(BlockUpdateEvent {
-                position: position
-                    .0
-                    .get_in_direction(Direction::Down)
-                    .get_in_direction(Direction::West),
-                layer: layer_id.0,
-                entity_layer: layer_id,
-            });
-            block_updates.send(BlockUpdateEvent {
-                position: position
-                    .0
-                    .get_in_direction(Direction::Down)
-                    .get_in_direction(Direction::Down),
-                layer: layer_id.0,
-                entity_layer: layer_id,
-            });
-        } else if !layer
-            .block(position.0.get_in_direction(Direction::Down))
-            .is_some_and(|b| b.state.to_kind() == BlockKind::Lava
-                        && b.state.get(PropName::Level) != Some(PropValue::_0))
-        {
-            if layer
-                .block(position.0.get_in_direction(Direction::North))
-                .is_some_and(|b| b.state.is_air())
-            {
-                layer.set_block(
-                    position.0.get_in_direction(Direction::North),
-                    block.state.set(
-                        PropName::Level,
-                        match block.state.get(PropName::Level) {
-                            Some(PropValue::_7) | Some(PropValue::_6) | Some(PropValue::_15)
-                            | Some(PropValue::_14) => {
-                                continue;
-                            }
-                            Some(PropValue::_5) | Some(PropValue::_13) => PropValue::_7,
-                            Some(PropValue::_4) | Some(PropValue::_12) => PropValue::_6,
-                            Some(PropValue::_3) | Some(PropValue::_11) => PropValue::_5,
-                            Some(PropValue::_2) | Some(PropValue::_10) => PropValue::_4,
-                            Some(PropValue::_1) | Some(PropValue::_9) => PropValue::_3,
-                            _ => PropValue::_2,
-                        },
-                    ),
-                );
-                block_updates.send(BlockUpdateEvent {
-                    position: position.0.get_in_direction(Direction::North),
-                    layer: layer_id.0,
-                    entity_layer: layer_id,
-                });
-                block_updates.send(BlockUpdateEvent {
-                    position: position
-                        .0
-                        .get_in_direction(Direction::North)
-                        .get_in_direction(Direction::North),
-                    layer: layer_id.0,
-                    entity_layer: layer_id,
-                });
-                block_updates.send(BlockUpdateEvent {
-                    position: position
-                        .0
-                        .get_in_direction(Direction::North)
-                        .get_in_direction(Direction::East),
-                    layer: layer_id.0,
-                    entity_layer: layer_id,
-                });
-                block_updates.send(BlockUpdateEvent {
-                    position: position
-                        .0
-                        .get_in_direction(Direction::North)
-                        .get_in_direction(Direction::Up),
-                    layer: layer_id.0,
-                    entity_layer: layer_id,
-                });
-                block_updates.send(BlockUpdateEvent {
-                    position: position
-                        .0
-                        .get_in_direction(Direction::North)
-                        .get_in_direction(Direction::West),
-                    layer: layer_id.0,
-                    entity_layer: layer_id,
-                });
-                block_updates.send(BlockUpdateEvent {
-                    position: position
-                        .0
-                        .get_in_direction(Direction::North)
-                        .get_in_direction(Direction::Down),
-                    layer: layer_id.0,
-                    entity_layer: layer_id,
-                });
-            }
-            if layer
-                .block(position.0.get_in_direction(Direction::East))
-                .is_some_and(|b| b.state.is_air())
-            {
-                layer.set_block(
-                    position.0.get_in_direction(Direction::East),
-                    block.state.set(
-                        PropName::Level,
-                        match block.state.get(PropName::Level) {
-                            Some(PropValue::_7) | Some(PropValue::_6) | Some(PropValue::_15)
-                            | Some(PropValue::_14) => {
-                                continue;
-                            }
-                            Some(PropValue::_5) | Some(PropValue::_13) => PropValue::_7,
-                            Some(PropValue::_4) | Some(PropValue::_12) => PropValue::_6,
-                            Some(PropValue::_3) | Some(PropValue::_11) => PropValue::_5,
-                            Some(PropValue::_2) | Some(PropValue::_10) => PropValue::_4,
-                            Some(PropValue::_1) | Some(PropValue::_9) => PropValue::_3,
-                            _ => PropValue::_2,
-                        },
-                    ),
-                );
-                block_updates.send(BlockUpdateEvent {
-                    position: position.0.get_in_direction(Direction::East),
-                    layer: layer_id.0,
-                    entity_layer: layer_id,
-                });
-                block_updates.send(BlockUpdateEvent {
-                    position: position
-                        .0
-                        .get_in_direction(Direction::East)
-                        .get_in_direction(Direction::North),
-                    layer: layer_id.0,
-                    entity_layer: layer_id,
-                });
-                block_updates.send(BlockUpdateEvent {
-                    position: position
-                        .0
-                        .get_in_direction(Direction::East)
-                        .get_in_direction(Direction::East),
-                    layer: layer_id.0,
-                    entity_layer: layer_id,
-                });
-                block_updates.send(BlockUpdateEvent {
-                    position: position
-                        .0
-                        .get_in_direction(Direction::East)
-                        .get_in_direction(Direction::South),
-                    layer: layer_id.0,
-                    entity_layer: layer_id,
-                });
-                block_updates.send(BlockUpdateEvent {
-                    position: position
-                        .0
-                        .get_in_direction(Direction::East)
-                        .get_in_direction(Direction::Up),
-                    layer: layer_id.0,
-                    entity_layer: layer_id,
-                });
-                block_updates.send(BlockUpdateEvent {
-                    position: position
-                        .0
-                        .get_in_direction(Direction::East)
-                        .get_in_direction(Direction::Down),
-                    layer: layer_id.0,
-                    entity_layer: layer_id,
-                });
-            }
-            if layer
-                .block(position.0.get_in_direction(Direction::South))
-                .is_some_and(|b| b.state.is_air())
-            {
-                layer.set_block(
-                    position.0.get_in_direction(Direction::South),
-                    block.state.set(
-                        PropName::Level,
-                        match block.state.get(PropName::Level) {
-                            Some(PropValue::_7) | Some(PropValue::_6) | Some(PropValue::_15)
-                            | Some(PropValue::_14) => {
-                                continue;
-                            }
-                            Some(PropValue::_5) | Some(PropValue::_13) => PropValue::_7,
-                            Some(PropValue::_4) | Some(PropValue::_12) => PropValue::_6,
-                            Some(PropValue::_3) | Some(PropValue::_11) => PropValue::_5,
-                            Some(PropValue::_2) | Some(PropValue::_10) => PropValue::_4,
-                            Some(PropValue::_1) | Some(PropValue::_9) => PropValue::_3,
-                            _ => PropValue::_2,
-                        },
-                    ),
-                );
-                block_updates.send(BlockUpdateEvent {
-                    position: position.0.get_in_direction(Direction::South),
-                    layer: layer_id.0,
-                    entity_layer: layer_id,
-                });
-                block_updates.send(BlockUpdateEvent {
-                    position: position
-                        .0
-                        .get_in_direction(Direction::South)
-                        .get_in_direction(Direction::Up),
-                    layer: layer_id.0,
-                    entity_layer: layer_id,
-                });
-                block_updates.send(BlockUpdateEvent {
-                    position: position
-                        .0
-                        .get_in_direction(Direction::South)
-                        .get_in_direction(Direction::East),
-                    layer: layer_id.0,
-                    entity_layer: layer_id,
-                });
-                block_updates.send(BlockUpdateEvent {
-                    position: position
-                        .0
-                        .get_in_direction(Direction::South)
-                        .get_in_direction(Direction::South),
-                    layer: layer_id.0,
-                    entity_layer: layer_id,
-                });
-                block_updates.send(BlockUpdateEvent {
-                    position: position
-                        .0
-                        .get_in_direction(Direction::South)
-                        .get_in_direction(Direction::West),
-                    layer: layer_id.0,
-                    entity_layer: layer_id,
-                });
-                block_updates.send(BlockUpdateEvent {
-                    position: position
-                        .0
-                        .get_in_direction(Direction::South)
-                        .get_in_direction(Direction::Down),
-                    layer: layer_id.0,
-                    entity_layer: layer_id,
-                });
-            }
-            if layer
-                .block(position.0.get_in_direction(Direction::West))
-                .is_some_and(|b| b.state.is_air())
-            {
-                layer.set_block(
-                    position.0.get_in_direction(Direction::West),
-                    block.state.set(
-                        PropName::Level,
-                        match block.state.get(PropName::Level) {
-                            Some(PropValue::_7) | Some(PropValue::_6) | Some(PropValue::_15)
-                            | Some(PropValue::_14) => {
-                                continue;
-                            }
-                            Some(PropValue::_5) | Some(PropValue::_13) => PropValue::_7,
-                            Some(PropValue::_4) | Some(PropValue::_12) => PropValue::_6,
-                            Some(PropValue::_3) | Some(PropValue::_11) => PropValue::_5,
-                            Some(PropValue::_2) | Some(PropValue::_10) => PropValue::_4,
-                            Some(PropValue::_1) | Some(PropValue::_9) => PropValue::_3,
-                            _ => PropValue::_2,
-                        },
-                    ),
-                );
-                block_updates.send(BlockUpdateEvent {
-                    position: position.0.get_in_direction(Direction::West),
-                    layer: layer_id.0,
-                    entity_layer: layer_id,
-                });
-                block_updates.send(BlockUpdateEvent {
-                    position: position
-                        .0
-                        .get_in_direction(Direction::West)
-                        .get_in_direction(Direction::North),
-                    layer: layer_id.0,
-                    entity_layer: layer_id,
-                });
-                block_updates.send(BlockUpdateEvent {
-                    position: position
-                        .0
-                        .get_in_direction(Direction::West)
-                        .get_in_direction(Direction::Up),
-                    layer: layer_id.0,
-                    entity_layer: layer_id,
-                });
-                block_updates.send(BlockUpdateEvent {
-                    position: position
-                        .0
-                        .get_in_direction(Direction::West)
-                        .get_in_direction(Direction::South),
-                    layer: layer_id.0,
-                    entity_layer: layer_id,
-                });
-                block_updates.send(BlockUpdateEvent {
-                    position: position
-                        .0
-                        .get_in_direction(Direction::West)
-                        .get_in_direction(Direction::West),
-                    layer: layer_id.0,
-                    entity_layer: layer_id,
-                });
-                block_updates.send(BlockUpdateEvent {
-                    position: position
-                        .0
-                        .get_in_direction(Direction::West)
-                        .get_in_direction(Direction::Down),
-                    layer: layer_id.0,
-                    entity_layer: layer_id,
-                });
+            for direction in [
+                Direction::North,
+                Direction::East,
+                Direction::South,
+                Direction::West,
+            ] {
+                let target = position.get_in_direction(direction);
+                if layer.block(target).is_some_and(|b| b.state.is_air() || washable(b.state)) {
+                    burn_away(layer, target);
+                    layer.set_block(target, block.state.set(PropName::Level, number(next)));
+                    updates.extend(with_neighbors(target));
+                }
             }
         }
+    }
 
-        if block.state != original_state {
-            block_updates.send(BlockUpdateEvent {
-                position: position.0,
-                layer: layer_id.0,
-                entity_layer: layer_id,
-            });
-            block_updates.send(BlockUpdateEvent {
-                position: position.0.get_in_direction(Direction::Up),
-                layer: layer_id.0,
-                entity_layer: layer_id,
-            });
-            block_updates.send(BlockUpdateEvent {
-                position: position.0.get_in_direction(Direction::North),
-                layer: layer_id.0,
-                entity_layer: layer_id,
-            });
-            block_updates.send(BlockUpdateEvent {
-                position: position.0.get_in_direction(Direction::East),
-                layer: layer_id.0,
-                entity_layer: layer_id,
-            });
-            block_updates.send(BlockUpdateEvent {
-                position: position.0.get_in_direction(Direction::South),
-                layer: layer_id.0,
-                entity_layer: layer_id,
-            });
-            block_updates.send(BlockUpdateEvent {
-                position: position.0.get_in_direction(Direction::West),
-                layer: layer_id.0,
-                entity_layer: layer_id,
-            });
-            block_updates.send(BlockUpdateEvent {
-                position: position.0.get_in_direction(Direction::Down),
-                layer: layer_id.0,
-                entity_layer: layer_id,
-            });
-        }
+    if block.state != original_state {
+        updates.extend(with_neighbors(position));
+    }
+}
+
+/// Whether the fluid is falling rather than spreading out.
+fn falling(state: BlockState) -> bool {
+    matches!(
+        state.get(PropName::Level),
+        Some(
+            PropValue::_8
+                | PropValue::_9
+                | PropValue::_10
+                | PropValue::_11
+                | PropValue::_12
+                | PropValue::_13
+                | PropValue::_14
+                | PropValue::_15
+        )
+    )
+}
+
+/// Whether flowing fluid breaks the block. That's anything without a
+/// collision box that can't hold water itself, like torches, flowers and
+/// redstone dust.
+fn washable(state: BlockState) -> bool {
+    let kind = state.to_kind();
+    !state.is_air()
+        && !state.is_liquid()
+        && state.get(PropName::Waterlogged).is_none()
+        && state.collision_shapes().next().is_none()
+        && kind != BlockKind::SugarCane
+        && kind != BlockKind::BubbleColumn
+        && kind != BlockKind::Kelp
+        && kind != BlockKind::KelpPlant
+        && kind != BlockKind::Seagrass
+        && kind != BlockKind::TallSeagrass
+        && kind != BlockKind::NetherPortal
+        && kind != BlockKind::EndPortal
+        && kind != BlockKind::EndGateway
+        && kind != BlockKind::StructureVoid
+}
+
+/// Breaks a block water is about to flow into, dropping it as items.
+fn wash_away(layer: &ChunkLayer, commands: &mut Commands, layer_id: EntityLayerId, position: BlockPos) {
+    let Some(state) = layer.block(position).map(|b| b.state).filter(|s| washable(*s)) else {
+        return;
+    };
+    commands.add(move |world: &mut World| {
+        world.send_event(WashedAwayEvent {
+            position,
+            state,
+            layer: layer_id,
+        });
+    });
+}
+
+/// Burns up a block lava is about to flow into. Nothing is dropped.
+fn burn_away(layer: &mut ChunkLayer, position: BlockPos) {
+    if layer.block(position).is_some_and(|b| washable(b.state)) {
+        fizz(layer, position);
+    }
+}
+
+fn fizz(layer: &mut ChunkLayer, position: BlockPos) {
+    let center = DVec3::new(
+        f64::from(position.x) + 0.5,
+        f64::from(position.y) + 0.5,
+        f64::from(position.z) + 0.5,
+    );
+    layer.play_sound(Sound::BlockLavaExtinguish, SoundCategory::Block, center, 0.5, 2.6);
+}
+
+pub fn is_nether(layer: &ChunkLayer) -> bool {
+    layer.dimension_type_name() == ident!("the_nether")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    const EYES: DVec3 = DVec3::new(0.5, 3.62, 0.5);
+    const DOWN: Look = Look { yaw: 0.0, pitch: 90.0 };
+
+    /// A column of blocks going up from the origin, for looking straight
+    /// down at from [`EYES`].
+    fn column(states: [BlockState; 4]) -> HashMap<BlockPos, BlockState> {
+        (0..).zip(states).map(|(y, state)| (BlockPos::new(0, y, 0), state)).collect()
+    }
+
+    /// Uses `item` looking down the column, and puts what it did in the world.
+    fn use_on(blocks: &mut HashMap<BlockPos, BlockState>, item: ItemKind) -> Option<(BlockPos, ItemKind)> {
+        let used = use_bucket(&*blocks, EYES, DOWN, item)?;
+        blocks.insert(used.position, used.state);
+        Some((used.position, used.bucket))
+    }
+
+    #[test]
+    fn one_click_only_empties_the_bucket() {
+        let mut blocks = column([BlockState::STONE, BlockState::AIR, BlockState::AIR, BlockState::AIR]);
+
+        // A click both interacts with the block and uses the item, but only
+        // the item use is handled, so the water stays put.
+        assert_eq!(use_on(&mut blocks, ItemKind::WaterBucket), Some((BlockPos::new(0, 1, 0), ItemKind::Bucket)));
+        assert_eq!(blocks[&BlockPos::new(0, 1, 0)], BlockState::WATER);
+
+        // The next click picks it up again.
+        assert_eq!(use_on(&mut blocks, ItemKind::Bucket), Some((BlockPos::new(0, 1, 0), ItemKind::WaterBucket)));
+        assert_eq!(blocks[&BlockPos::new(0, 1, 0)], BlockState::AIR);
+        assert_eq!(use_on(&mut blocks, ItemKind::Bucket), None);
+    }
+
+    #[test]
+    fn water_goes_into_blocks_that_hold_it() {
+        let slab = BlockState::OAK_SLAB.set(PropName::Waterlogged, PropValue::False);
+        let mut blocks = column([slab, BlockState::AIR, BlockState::AIR, BlockState::AIR]);
+
+        assert_eq!(use_on(&mut blocks, ItemKind::WaterBucket), Some((BlockPos::new(0, 0, 0), ItemKind::Bucket)));
+        assert_eq!(blocks[&BlockPos::new(0, 0, 0)], slab.set(PropName::Waterlogged, PropValue::True));
+
+        // Lava can't, so it goes on top.
+        assert_eq!(use_on(&mut blocks, ItemKind::LavaBucket), Some((BlockPos::new(0, 1, 0), ItemKind::Bucket)));
+
+        assert_eq!(use_on(&mut blocks, ItemKind::Bucket), Some((BlockPos::new(0, 1, 0), ItemKind::LavaBucket)));
+        assert_eq!(use_on(&mut blocks, ItemKind::Bucket), Some((BlockPos::new(0, 0, 0), ItemKind::WaterBucket)));
+        assert_eq!(blocks[&BlockPos::new(0, 0, 0)], slab);
+    }
+
+    #[test]
+    fn empty_buckets_look_through_flowing_fluids() {
+        let flowing = BlockState::WATER.set(PropName::Level, PropValue::_1);
+        let mut blocks = column([BlockState::WATER, flowing, BlockState::AIR, BlockState::AIR]);
+
+        assert_eq!(use_on(&mut blocks, ItemKind::Bucket), Some((BlockPos::new(0, 0, 0), ItemKind::WaterBucket)));
+        assert_eq!(use_on(&mut blocks, ItemKind::Stone), None);
     }
 }
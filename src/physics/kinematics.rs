@@ -1,27 +1,51 @@
+use std::collections::HashMap;
+
+use valence::abilities::PlayerAbilitiesFlags;
+use valence::entity::entity::Flags;
 use valence::entity::falling_block::FallingBlockEntity;
+use valence::entity::living::Health;
 use valence::entity::ObjectData;
 use valence::entity::{entity::NoGravity, OnGround, Velocity};
 use valence::math::Aabb;
 use valence::prelude::*;
 use valence::prelude::Position;
 
+use super::fluids::{flow, fluid, fluid_height, is_nether};
+use crate::block_behavior::{block_pos, with_neighbors};
+use crate::block_update::{send_updates, BlockUpdateEvent};
+use crate::environment::FireTicks;
+
 pub struct Kinematics;
 
 impl Plugin for Kinematics {
     fn build(&self, app: &mut App) {
-        app.add_systems(
+        app.init_resource::<PlayerPushes>()
+            .add_systems(
                 Update,
                 (
+                    swim.after(gravity).after(drag).before(collide),
                     collide.before(update),
                     update,
                     gravity,
                     drag,
                     land,
+                    push_players.before(send_pushes),
+                    send_pushes,
                 ),
             );
     }
 }
 
+/// How high walking mobs can climb without jumping, enough for slabs and
+/// stairs.
+const STEP_HEIGHT: f64 = 0.6;
+
+/// Gaps smaller than this count as touching.
+const EPSILON: f64 = 1.0e-7;
+
+/// How far under an entity to look for something to stand on.
+const GROUND_PROBE: f64 = 1.0e-3;
+
 fn collide(
     mut entities: Query<(
         &mut OnGround,
@@ -29,6 +53,7 @@ fn collide(
         &mut Velocity,
         &EntityLayerId,
         &Hitbox,
+        Has<Health>,
     ), Without<Client>>,
     layers: Query<&ChunkLayer>,
 ) {
@@ -38,86 +63,135 @@ fn collide(
         mut velocity,
         layer_id,
         hitbox,
+        living,
     ) in &mut entities {
         let Ok(layer) = layers.get(layer_id.0) else {
             continue;
         };
 
-        let colliders = {
-            let position = *position;
-            (-1..=1)
-                .flat_map(move |y| {
-                    (-1..=1).flat_map(move |z| {
-                        (-1..=1).flat_map(move |x| {
-                            let block_pos = BlockPos {
-                                x: position.0.x.floor() as i32 + x,
-                                y: position.0.y.floor() as i32 + y,
-                                z: position.0.z.floor() as i32 + z,
-                            };
-                            layer.block(block_pos).into_iter().flat_map(move |b| {
-                                b.state.collision_shapes().map(move |c| {
-                                    c + DVec3::new(
-                                        (position.0.x.floor() as i32 + x) as f64,
-                                        (position.0.y.floor() as i32 + y) as f64,
-                                        (position.0.z.floor() as i32 + z) as f64,
-                                    )
-                                })
-                            })
-                        })
-                    })
-                })
-                .collect::<Vec<Aabb>>()
-        };
+        let hitbox = hitbox.get();
+        let motion = velocity.0.as_dvec3() / 20.0;
+        let step_height = if living { STEP_HEIGHT } else { 0.0 };
+
+        let colliders = colliders(layer, hitbox, motion, step_height);
+        let moved = collide_with_step(hitbox, &colliders, motion, step_height, on_ground.0);
 
-        const STEP_COUNT: u32 = 8;
+        if moved.x != motion.x {
+            velocity.x = 0.0;
+        }
+        if moved.y != motion.y {
+            velocity.y = 0.0;
+        }
+        if moved.z != motion.z {
+            velocity.z = 0.0;
+        }
+        on_ground.0 = collide_axis(hitbox + moved, &colliders, 1, -GROUND_PROBE) > -GROUND_PROBE;
 
-        on_ground.0 = false;
+        position.0 += moved;
+    }
+}
 
-        let mut step_offset = DVec3::ZERO;
-        let mut new_offset = DVec3::ZERO;
-        for _ in 0..STEP_COUNT {
-            step_offset += velocity.0.as_dvec3() / (20.0 * STEP_COUNT as f64);
-            new_offset = offset(hitbox.get(), &colliders, step_offset);
+/// The collision boxes of every block a hitbox could touch while moving by
+/// `motion`, including anything it could step up onto.
+fn colliders(layer: &ChunkLayer, hitbox: Aabb, motion: DVec3, step_height: f64) -> Vec<Aabb> {
+    // Fences and walls stick up half a block into the space above them, so
+    // start a block lower.
+    let min = (hitbox.min().min(hitbox.min() + motion) - DVec3::Y).floor();
+    let max = (hitbox.max().max(hitbox.max() + motion) + DVec3::Y * step_height).floor();
 
-            if new_offset.x != step_offset.x {
-                velocity.x = 0.0;
-            }
-            if new_offset.y != step_offset.y {
-                velocity.y = 0.0;
-                on_ground.0 = true;
-            }
-            if new_offset.z != step_offset.z {
-                velocity.z = 0.0;
+    let mut colliders = Vec::new();
+    for y in min.y as i32..=max.y as i32 {
+        for z in min.z as i32..=max.z as i32 {
+            for x in min.x as i32..=max.x as i32 {
+                let Some(block) = layer.block(BlockPos { x, y, z }) else {
+                    continue;
+                };
+                let corner = DVec3::new(f64::from(x), f64::from(y), f64::from(z));
+                colliders.extend(block.state.collision_shapes().map(|c| c + corner));
             }
         }
-
-        position.0 += new_offset;
     }
+    colliders
 }
 
-fn offset(a: Aabb, bs: &[Aabb], mut offset: DVec3) -> DVec3 {
-    for b in bs {
-        if offset.y > 0.0 && (a + offset * DVec3::Y).intersects(*b) {
-            offset.y = offset.y.min(b.min().y - a.max().y);
-        } else if offset.y < 0.0 && (a + offset * DVec3::Y).intersects(*b) {
-            offset.y = offset.y.max(b.max().y - a.min().y);
-        }
+/// How far `hitbox` can move along one axis (0 for X, 1 for Y, 2 for Z)
+/// before it runs into one of `colliders`.
+fn collide_axis(hitbox: Aabb, colliders: &[Aabb], axis: usize, mut distance: f64) -> f64 {
+    if distance == 0.0 {
+        return 0.0;
     }
-    for b in bs {
-        if offset.x > 0.0 && (a + offset * DVec3::X).intersects(*b) {
-            offset.x = offset.x.min(b.min().x - a.max().x);
-        } else if offset.x > 0.0 && (a + offset * DVec3::X).intersects(*b) {
-            offset.x = offset.x.max(b.max().x - a.min().x);
+    let (min, max) = (hitbox.min(), hitbox.max());
+
+    for collider in colliders {
+        let (collider_min, collider_max) = (collider.min(), collider.max());
+        // Only colliders that overlap the hitbox on the other two axes are in
+        // the way.
+        let in_the_way = (0..3)
+            .filter(|&other| other != axis)
+            .all(|other| collider_max[other] > min[other] + EPSILON && collider_min[other] < max[other] - EPSILON);
+        if !in_the_way {
+            continue;
         }
-    }
-    for b in bs {
-        if offset.z > 0.0 && (a + offset * DVec3::Z).intersects(*b) {
-            offset.z = offset.z.min(b.min().z - a.max().z);
-        } else if offset.z > 0.0 && (a + offset * DVec3::Z).intersects(*b) {
-            offset.z = offset.z.max(b.max().z - a.min().z);
+
+        if distance > 0.0 && collider_min[axis] >= max[axis] - EPSILON {
+            distance = distance.min((collider_min[axis] - max[axis]).max(0.0));
+        } else if distance < 0.0 && collider_max[axis] <= min[axis] + EPSILON {
+            distance = distance.max((collider_max[axis] - min[axis]).min(0.0));
         }
     }
-    offset
+    distance
+}
+
+/// How far `hitbox` actually moves when it tries to move by `motion`. Each
+/// axis stops at the first collider in the way, however far it goes, so fast
+/// things can't pass through walls.
+///
+/// Vertical motion goes first, then the larger of the horizontal ones.
+pub fn collide_motion(hitbox: Aabb, colliders: &[Aabb], motion: DVec3) -> DVec3 {
+    let mut moved = DVec3::ZERO;
+    let mut hitbox = hitbox;
+
+    let axes = if motion.x.abs() < motion.z.abs() { [1, 2, 0] } else { [1, 0, 2] };
+    for axis in axes {
+        let distance = collide_axis(hitbox, colliders, axis, motion[axis]);
+        let mut step = DVec3::ZERO;
+        step[axis] = distance;
+        hitbox = hitbox + step;
+        moved[axis] = distance;
+    }
+    moved
+}
+
+/// Like [`collide_motion`], but something on the ground that walks into a
+/// ledge up to `step_height` high climbs onto it instead of stopping.
+pub fn collide_with_step(
+    hitbox: Aabb,
+    colliders: &[Aabb],
+    motion: DVec3,
+    step_height: f64,
+    on_ground: bool,
+) -> DVec3 {
+    let moved = collide_motion(hitbox, colliders, motion);
+
+    let blocked = moved.x != motion.x || moved.z != motion.z;
+    let grounded = on_ground || (motion.y < 0.0 && moved.y != motion.y);
+    if step_height <= 0.0 || !blocked || !grounded {
+        return moved;
+    }
+
+    // Make the same move from higher up, then drop back down onto whatever
+    // is there.
+    let up = collide_axis(hitbox, colliders, 1, step_height);
+    let raised = hitbox + DVec3::Y * up;
+    let across = collide_motion(raised, colliders, DVec3::new(motion.x, 0.0, motion.z));
+    let down = collide_axis(raised + across, colliders, 1, -up);
+    let stepped = DVec3::new(across.x, up + down, across.z);
+
+    if stepped.x * stepped.x + stepped.z * stepped.z > moved.x * moved.x + moved.z * moved.z {
+        stepped
+    } else {
+        moved
+    }
 }
 
 #[allow(dead_code)]
@@ -181,7 +255,9 @@ fn ground(
     }
 }
 
-fn update(mut entities: Query<(&mut Position, &Velocity), Without<Client>>) {
+/// Moves entities that have no hitbox to collide with. [`collide`] moves the
+/// rest.
+fn update(mut entities: Query<(&mut Position, &Velocity), (Without<Client>, Without<Hitbox>)>) {
     for (mut position, velocity) in &mut entities {
         position.0 += DVec3::from(velocity.0 / 20.);
     }
@@ -325,6 +401,7 @@ fn land(
         With<FallingBlockEntity>,
     >,
     mut commands: Commands,
+    mut block_updates: EventWriter<BlockUpdateEvent>,
 ) {
     for (entity, position, data, on_ground, layer_id) in &mut falling_blocks {
         if !on_ground.0 {
@@ -341,12 +418,330 @@ fn land(
 
         commands.entity(entity).insert(Despawned);
 
-        let block_pos = BlockPos {
-            x: position.0.x.floor() as i32,
-            y: position.0.y.floor() as i32,
-            z: position.0.z.floor() as i32,
+        let position = block_pos(position.0);
+        layer.set_block(position, block);
+        // The blocks around need to know it landed, so that concrete powder
+        // that fell into water sets.
+        send_updates(&mut block_updates, *layer_id, with_neighbors(position));
+    }
+}
+
+/// How much flowing water speeds things up each tick, in blocks per tick.
+const WATER_PUSH: f64 = 0.014;
+/// Lava pushes much more weakly, except in the Nether.
+const LAVA_PUSH: f64 = 0.0023333;
+const NETHER_LAVA_PUSH: f64 = 0.007;
+
+/// How fast things that float rise through fluid, in blocks per tick per
+/// tick.
+const FLOAT_ACCELERATION: f32 = 5.0e-4;
+/// Floating things stop speeding up once they rise this fast, in blocks per
+/// tick.
+const MAX_FLOAT_SPEED: f32 = 0.06;
+
+/// Ticks something keeps burning for after it leaves lava.
+const LAVA_FIRE_TICKS: i32 = 300;
+
+/// The fluids touching a hitbox, and how they push it.
+#[derive(Debug, Default)]
+struct Immersion {
+    water: bool,
+    lava: bool,
+    /// The average push of every fluid block touching the hitbox, in blocks
+    /// per tick.
+    push: DVec3,
+}
+
+fn immersion(layer: &ChunkLayer, hitbox: Aabb) -> Immersion {
+    let lava_push = if is_nether(layer) { NETHER_LAVA_PUSH } else { LAVA_PUSH };
+    let mut immersion = Immersion::default();
+    let mut touching = 0;
+
+    let (min, max) = (hitbox.min().floor(), hitbox.max().floor());
+    for y in min.y as i32..=max.y as i32 {
+        for z in min.z as i32..=max.z as i32 {
+            for x in min.x as i32..=max.x as i32 {
+                let position = BlockPos { x, y, z };
+                let Some(state) = layer.block(position).map(|b| b.state) else {
+                    continue;
+                };
+                let Some(kind) = fluid(state) else {
+                    continue;
+                };
+                // Shallow fluid at the bottom of the block may not come up as
+                // far as the hitbox.
+                if f64::from(y) + fluid_height(state) < hitbox.min().y {
+                    continue;
+                }
+
+                let strength = if kind == BlockKind::Water {
+                    immersion.water = true;
+                    WATER_PUSH
+                } else {
+                    immersion.lava = true;
+                    lava_push
+                };
+                immersion.push += flow(layer, position) * strength;
+                touching += 1;
+            }
+        }
+    }
+
+    if touching > 0 {
+        immersion.push /= f64::from(touching);
+    }
+    immersion
+}
+
+/// Entities that lava doesn't set on fire.
+fn fire_immune(kind: EntityKind) -> bool {
+    matches!(
+        kind,
+        EntityKind::BLAZE
+            | EntityKind::GHAST
+            | EntityKind::MAGMA_CUBE
+            | EntityKind::STRIDER
+            | EntityKind::WITHER
+            | EntityKind::WITHER_SKELETON
+            | EntityKind::ZOMBIFIED_PIGLIN
+            | EntityKind::ZOGLIN
+            | EntityKind::ENDER_DRAGON
+    )
+}
+
+/// Fluid currents, buoyancy and drag for everything but players, and setting
+/// things alight in lava.
+///
+/// Items, experience and boats float. Mobs sink slowly through water and
+/// wade through lava at half speed.
+fn swim(
+    mut entities: Query<
+        (
+            Entity,
+            &mut Velocity,
+            &mut Flags,
+            Option<&mut FireTicks>,
+            &OnGround,
+            &NoGravity,
+            &EntityKind,
+            &Hitbox,
+            &EntityLayerId,
+            Has<Health>,
+        ),
+        Without<Client>,
+    >,
+    layers: Query<&ChunkLayer>,
+    mut commands: Commands,
+) {
+    for (entity, mut velocity, mut flags, fire, on_ground, no_gravity, &kind, hitbox, layer_id, living) in &mut entities {
+        let Ok(layer) = layers.get(layer_id.0) else {
+            continue;
+        };
+        let immersion = immersion(layer, hitbox.get());
+
+        if immersion.water || immersion.lava {
+            velocity.0 += (immersion.push * 20.0).as_vec3();
+
+            // Only what `gravity` took off this tick gets undone.
+            let (acceleration, ..) = if no_gravity.0 || on_ground.0 {
+                (0.0, 0.0, 0.0, false)
+            } else {
+                entity_physics_properties(kind, on_ground.0)
+            };
+            let floats = matches!(
+                kind,
+                EntityKind::ITEM | EntityKind::EXPERIENCE_ORB | EntityKind::BOAT | EntityKind::CHEST_BOAT
+            );
+
+            if floats {
+                // Undo gravity and drift up instead.
+                velocity.0.y += acceleration * 20.0;
+                if velocity.0.y < MAX_FLOAT_SPEED * 20.0 {
+                    velocity.0.y += FLOAT_ACCELERATION * 20.0;
+                }
+                let drag = if immersion.lava { 0.95 } else { 0.99 };
+                velocity.0.x *= drag;
+                velocity.0.z *= drag;
+            } else if living {
+                let (drag, sinking) = if immersion.lava { (0.5, 0.25) } else { (0.8, 0.0625) };
+                velocity.0.y += acceleration * 20.0 * (1.0 - sinking);
+                velocity.0 *= drag;
+            }
+        }
+
+        let mut fire_ticks = fire.as_deref().map_or(0, |fire| fire.0);
+        if immersion.lava && !fire_immune(kind) {
+            fire_ticks = fire_ticks.max(LAVA_FIRE_TICKS);
+        } else if immersion.water {
+            fire_ticks = 0;
+        } else {
+            fire_ticks = (fire_ticks - 1).max(0);
+        }
+
+        match fire {
+            Some(mut fire) => fire.0 = fire_ticks,
+            None if fire_ticks > 0 => {
+                commands.entity(entity).insert(FireTicks(fire_ticks));
+            }
+            None => {}
+        }
+
+        let on_fire = fire_ticks > 0;
+        if flags.on_fire() != on_fire {
+            flags.set_on_fire(on_fire);
+        }
+    }
+}
+
+/// How hard players are being pushed this tick, by currents and by the
+/// entities around them, in blocks per tick. Everything that pushes players
+/// adds to this, and [`send_pushes`] sends each player the total at once.
+#[derive(Resource, Debug, Default)]
+pub struct PlayerPushes(HashMap<Entity, DVec3>);
+
+impl PlayerPushes {
+    pub fn add(&mut self, player: Entity, push: DVec3) {
+        *self.0.entry(player).or_default() += push;
+    }
+}
+
+/// Clients move themselves, so currents push players through their client.
+fn push_players(
+    clients: Query<(Entity, &Hitbox, &GameMode, &PlayerAbilitiesFlags, &EntityLayerId), With<Client>>,
+    layers: Query<&ChunkLayer>,
+    mut pushes: ResMut<PlayerPushes>,
+) {
+    for (player, hitbox, game_mode, abilities, layer_id) in &clients {
+        if *game_mode == GameMode::Spectator || abilities.flying() {
+            continue;
+        }
+        let Ok(layer) = layers.get(layer_id.0) else {
+            continue;
+        };
+
+        let immersion = immersion(layer, hitbox.get());
+        if immersion.push != DVec3::ZERO {
+            pushes.add(player, immersion.push);
+        }
+    }
+}
+
+/// Sends players everything that pushed them this tick, in one packet each.
+///
+/// Clients can only be told what their velocity is, not to speed up, so this
+/// replaces the client's velocity with how fast it moved last tick plus the
+/// push. That loses whatever the player's own movement and friction would
+/// have done that tick, which is why players that weren't pushed are left
+/// alone.
+pub fn send_pushes(
+    mut pushes: ResMut<PlayerPushes>,
+    mut clients: Query<(&mut Client, &Position, &OldPosition)>,
+) {
+    for (player, push) in pushes.0.drain() {
+        if push == DVec3::ZERO {
+            continue;
+        }
+        let Ok((mut client, position, old_position)) = clients.get_mut(player) else {
+            continue;
         };
 
-        layer.set_block(block_pos, block);
+        let motion = (position.0 - old_position.get()) * 20.0;
+        client.set_velocity((motion + push * 20.0).as_vec3());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(x: i32, y: i32, z: i32) -> Aabb {
+        let min = DVec3::new(f64::from(x), f64::from(y), f64::from(z));
+        Aabb::new(min, min + DVec3::ONE)
+    }
+
+    /// A 7x7 floor at y = 0 with a wall block two blocks away in each
+    /// direction, a ceiling block three blocks up and a slab to the north
+    /// east.
+    fn layout() -> Vec<Aabb> {
+        let mut colliders = Vec::new();
+        for z in -3..=3 {
+            for x in -3..=3 {
+                colliders.push(block(x, 0, z));
+            }
+        }
+        colliders.push(block(2, 1, 0));
+        colliders.push(block(-2, 1, 0));
+        colliders.push(block(0, 1, 2));
+        colliders.push(block(0, 1, -2));
+        colliders.push(block(0, 4, 0));
+        colliders.push(Aabb::new(DVec3::new(1.0, 1.0, -1.0), DVec3::new(2.0, 1.5, 0.0)));
+        colliders
+    }
+
+    /// A player-sized hitbox standing in the middle of the block at (0, 1, 0).
+    fn standing() -> Aabb {
+        Aabb::new(DVec3::new(0.2, 1.0, 0.2), DVec3::new(0.8, 2.8, 0.8))
+    }
+
+    fn assert_close(actual: DVec3, expected: DVec3) {
+        assert!(
+            (actual - expected).abs().max_element() < 1.0e-9,
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn lands_on_the_floor() {
+        let falling = standing() + DVec3::Y * 0.5;
+        let moved = collide_motion(falling, &layout(), DVec3::new(0.0, -1.0, 0.0));
+        assert_close(moved, DVec3::new(0.0, -0.5, 0.0));
+    }
+
+    #[test]
+    fn stops_at_walls_in_every_direction() {
+        let colliders = layout();
+        let cases = [
+            (DVec3::new(2.0, 0.0, 0.0), DVec3::new(1.2, 0.0, 0.0)),
+            (DVec3::new(-2.0, 0.0, 0.0), DVec3::new(-1.2, 0.0, 0.0)),
+            (DVec3::new(0.0, 0.0, 2.0), DVec3::new(0.0, 0.0, 1.2)),
+            (DVec3::new(0.0, 0.0, -2.0), DVec3::new(0.0, 0.0, -1.2)),
+            (DVec3::new(0.0, 3.0, 0.0), DVec3::new(0.0, 1.2, 0.0)),
+            (DVec3::new(0.0, -3.0, 0.0), DVec3::ZERO),
+        ];
+        for (motion, expected) in cases {
+            assert_close(collide_motion(standing(), &colliders, motion), expected);
+        }
+    }
+
+    #[test]
+    fn fast_motion_does_not_tunnel() {
+        let moved = collide_motion(standing(), &layout(), DVec3::new(-50.0, 0.0, 0.0));
+        assert_close(moved, DVec3::new(-1.2, 0.0, 0.0));
+    }
+
+    #[test]
+    fn slides_along_walls() {
+        let moved = collide_motion(standing(), &layout(), DVec3::new(2.0, 0.0, 1.0));
+        assert_close(moved, DVec3::new(1.2, 0.0, 1.0));
+    }
+
+    #[test]
+    fn steps_up_onto_slabs() {
+        let beside_slab = standing() + DVec3::new(0.0, 0.0, -0.9);
+        let moved = collide_with_step(beside_slab, &layout(), DVec3::new(1.0, 0.0, 0.0), STEP_HEIGHT, true);
+        assert_close(moved, DVec3::new(1.0, 0.5, 0.0));
+    }
+
+    #[test]
+    fn does_not_step_up_full_blocks() {
+        let moved = collide_with_step(standing(), &layout(), DVec3::new(2.0, 0.0, 0.0), STEP_HEIGHT, true);
+        assert_close(moved, DVec3::new(1.2, 0.0, 0.0));
+    }
+
+    #[test]
+    fn does_not_step_in_the_air() {
+        let beside_slab = standing() + DVec3::new(0.0, 0.0, -0.9);
+        let moved = collide_with_step(beside_slab, &layout(), DVec3::new(1.0, 0.0, 0.0), STEP_HEIGHT, false);
+        assert_close(moved, DVec3::new(0.2, 0.0, 0.0));
     }
 }
@@ -4,10 +4,12 @@ use valence::prelude::*;
 pub mod kinematics;
 pub mod fluids;
 pub mod redstone;
+pub mod pushing;
 
 use kinematics::Kinematics;
 use fluids::Fluids;
 use redstone::Redstone;
+use pushing::Pushing;
 
 pub struct Physics;
 
@@ -16,6 +18,7 @@ impl Plugin for Physics {
         app
             .add_plugins(Kinematics)
             .add_plugins(Fluids)
-            .add_plugins(Redstone);
+            .add_plugins(Redstone)
+            .add_plugins(Pushing);
     }
 }
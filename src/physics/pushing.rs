@@ -0,0 +1,178 @@
+use std::collections::HashMap;
+
+use valence::entity::living::Health;
+use valence::entity::Velocity;
+use valence::math::{Aabb, IVec3};
+use valence::prelude::*;
+use valence::rand::{thread_rng, Rng};
+
+use super::kinematics::{send_pushes, PlayerPushes};
+use crate::damage::{DamageCause, DamageEvent};
+use crate::rules::GameRules;
+
+/// Mobs, players, boats and minecarts shoving each other apart when they
+/// overlap, and getting crushed when too many are crammed into one place.
+pub struct Pushing;
+
+impl Plugin for Pushing {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EntityGrid>()
+            .init_resource::<GameRules>()
+            .init_resource::<PlayerPushes>()
+            .add_systems(Update, (fill_grid, push_entities).chain().before(send_pushes));
+    }
+}
+
+/// Width of the cells in [`EntityGrid`], in blocks. Most hitboxes fit in one
+/// or two.
+const CELL_SIZE: f64 = 2.0;
+
+/// How hard two overlapping entities push each other apart, in blocks per
+/// tick.
+const PUSH_STRENGTH: f64 = 0.05;
+
+/// Damage dealt each time cramming hurts something.
+const CRAMMING_DAMAGE: f32 = 6.0;
+
+/// The hitboxes of every pushable entity, sorted into a grid of cells in each
+/// entity layer, so that finding what's near something doesn't mean looking
+/// at everything. It's filled again at the start of every tick.
+#[derive(Resource, Debug, Default)]
+pub struct EntityGrid {
+    cells: HashMap<(Entity, IVec3), Vec<(Entity, Aabb)>>,
+}
+
+impl EntityGrid {
+    fn cells(area: Aabb) -> impl Iterator<Item = IVec3> {
+        let min = (area.min() / CELL_SIZE).floor().as_ivec3();
+        let max = (area.max() / CELL_SIZE).floor().as_ivec3();
+        (min.y..=max.y).flat_map(move |y| {
+            (min.z..=max.z).flat_map(move |z| (min.x..=max.x).map(move |x| IVec3::new(x, y, z)))
+        })
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.clear();
+    }
+
+    pub fn insert(&mut self, layer: EntityLayerId, entity: Entity, hitbox: Aabb) {
+        for cell in Self::cells(hitbox) {
+            self.cells.entry((layer.0, cell)).or_default().push((entity, hitbox));
+        }
+    }
+
+    /// Every entity in the layer with a hitbox that overlaps `area`.
+    pub fn overlapping(&self, layer: EntityLayerId, area: Aabb) -> Vec<(Entity, Aabb)> {
+        let mut found: Vec<(Entity, Aabb)> = Vec::new();
+        for cell in Self::cells(area) {
+            let Some(entities) = self.cells.get(&(layer.0, cell)) else {
+                continue;
+            };
+            for &(entity, hitbox) in entities {
+                // Hitboxes in more than one cell show up more than once.
+                if hitbox.intersects(area) && !found.iter().any(|&(e, _)| e == entity) {
+                    found.push((entity, hitbox));
+                }
+            }
+        }
+        found
+    }
+}
+
+/// Whether the entity pushes and gets pushed by others.
+fn pushable(kind: EntityKind, living: bool, game_mode: Option<&GameMode>) -> bool {
+    if game_mode == Some(&GameMode::Spectator) {
+        return false;
+    }
+    match kind {
+        EntityKind::BOAT
+        | EntityKind::CHEST_BOAT
+        | EntityKind::MINECART
+        | EntityKind::CHEST_MINECART
+        | EntityKind::COMMAND_BLOCK_MINECART
+        | EntityKind::FURNACE_MINECART
+        | EntityKind::HOPPER_MINECART
+        | EntityKind::SPAWNER_MINECART
+        | EntityKind::TNT_MINECART => true,
+        EntityKind::ARMOR_STAND | EntityKind::BAT => false,
+        _ => living,
+    }
+}
+
+fn fill_grid(
+    mut grid: ResMut<EntityGrid>,
+    entities: Query<(Entity, &EntityKind, &Hitbox, &EntityLayerId, Option<&GameMode>, Has<Health>)>,
+) {
+    grid.clear();
+
+    for (entity, &kind, hitbox, &layer_id, game_mode, living) in &entities {
+        if pushable(kind, living, game_mode) {
+            grid.insert(layer_id, entity, hitbox.get());
+        }
+    }
+}
+
+fn push_entities(
+    grid: Res<EntityGrid>,
+    rules: Res<GameRules>,
+    mut entities: Query<(Entity, &Position, &Hitbox, &EntityLayerId, &mut Velocity, Has<Client>, Has<Health>)>,
+    mut player_pushes: ResMut<PlayerPushes>,
+    mut damage_events: EventWriter<DamageEvent>,
+) {
+    let mut rng = thread_rng();
+    let mut pushes = Vec::new();
+
+    for (entity, position, hitbox, &layer_id, .., living) in &entities {
+        let others = grid.overlapping(layer_id, hitbox.get());
+        if !others.iter().any(|&(e, _)| e == entity) {
+            continue;
+        }
+
+        let mut push = DVec3::ZERO;
+        for &(other, other_hitbox) in &others {
+            if other == entity {
+                continue;
+            }
+            let mut away = position.0 - (other_hitbox.min() + other_hitbox.max()) / 2.0;
+            away.y = 0.0;
+            let distance = away.x.abs().max(away.z.abs());
+            if distance < 0.01 {
+                continue;
+            }
+            // Entities right next to each other push hardest.
+            let distance = distance.sqrt();
+            push += away / distance * (1.0 / distance).min(1.0) * PUSH_STRENGTH;
+        }
+        if push != DVec3::ZERO {
+            pushes.push((entity, push));
+        }
+
+        // Counting itself, more than `max_entity_cramming` in one place
+        // hurts, though only now and then.
+        let crammed = others.len() - 1;
+        if living
+            && rules.max_entity_cramming > 0
+            && crammed >= rules.max_entity_cramming as usize
+            && rng.gen_range(0..4) == 0
+        {
+            damage_events.send(DamageEvent {
+                target: entity,
+                amount: CRAMMING_DAMAGE,
+                source: DamageCause::Cramming.into(),
+            });
+        }
+    }
+
+    for (entity, push) in pushes {
+        let Ok((.., mut velocity, player, _)) = entities.get_mut(entity) else {
+            continue;
+        };
+        // Clients move themselves, so players are pushed through their
+        // client, along with anything else pushing them.
+        if player {
+            player_pushes.add(entity, push);
+        } else {
+            velocity.0 += (push * 20.0).as_vec3();
+        }
+    }
+}
@@ -1,27 +1,109 @@
 
-use std::collections::VecDeque;
+use std::collections::HashMap;
 
 use valence::{layer::chunk::IntoBlock, prelude::*};
 
-use crate::block_update::BlockUpdateEvent;
+use crate::anvil::{ChunkLoadEvent, ChunkLoadStatus};
+use crate::block_behavior::{BlockBehavior, BlockBehaviors, BlockView, NeighborUpdate, OnScheduledTick};
+use crate::block_update::{BlockUpdateEvent, UpdateQueue};
+use crate::rules::GameRules;
+use crate::scheduled_tick::{ScheduledTick, ScheduledTicks, TickPriority};
 
 pub struct Redstone;
 
 impl Plugin for Redstone {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, handle_block_update);
+        app.init_resource::<BlockBehaviors>()
+            .init_resource::<ObservedBlocks>()
+            .init_resource::<GameRules>()
+            .add_systems(Update, (watch_loaded_observers, handle_block_update).chain());
+
+        let mut behaviors = app.world_mut().resource_mut::<BlockBehaviors>();
+        behaviors.insert(BlockKind::Repeater, Repeater);
+        behaviors.insert(BlockKind::Observer, OnScheduledTick(observer_tick));
+    }
+}
+
+/// The last state seen of each block an observer is looking at, so that it
+/// only fires when that block actually changes rather than on every update
+/// near it.
+///
+/// Blocks are watched from when the observer is placed or its chunk loads,
+/// since by the time an update arrives the block has already changed.
+#[derive(Resource, Debug, Default)]
+struct ObservedBlocks(HashMap<(Entity, BlockPos), BlockState>);
+
+/// Starts watching the block in front of the observer at `observer`, unless
+/// it's already being watched.
+fn watch(observed: &mut ObservedBlocks, layer: &ChunkLayer, layer_id: Entity, observer: BlockPos) {
+    let Some(direction) = layer.block(observer).and_then(|b| facing(b.state)) else {
+        return;
+    };
+    let watched = observer.get_in_direction(direction);
+    if let Some(block) = layer.block(watched) {
+        observed.0.entry((layer_id, watched)).or_insert(block.state);
+    }
+}
+
+/// Watches the blocks in front of observers in chunks as they load. Observers
+/// just outside the chunk are included, since what they look at may only
+/// have loaded now.
+fn watch_loaded_observers(
+    mut observed: ResMut<ObservedBlocks>,
+    layers: Query<&ChunkLayer>,
+    mut events: EventReader<ChunkLoadEvent>,
+) {
+    for event in events.read() {
+        let ChunkLoadStatus::Success { .. } = &event.status else {
+            continue;
+        };
+        let Ok(layer) = layers.get(event.chunk_layer) else {
+            continue;
+        };
+        let Some(chunk) = layer.chunk(event.pos) else {
+            continue;
+        };
+        let min_y = layer.min_y();
+
+        for y in 0..chunk.height() {
+            for z in -1..17 {
+                for x in -1..17 {
+                    let inside = (0..16).contains(&x) && (0..16).contains(&z);
+                    let position = BlockPos::new(event.pos.x * 16 + x, min_y + y as i32, event.pos.z * 16 + z);
+                    let kind = if inside {
+                        chunk.block_state(x as u32, y, z as u32).to_kind()
+                    } else {
+                        match layer.block(position) {
+                            Some(block) => block.state.to_kind(),
+                            None => continue,
+                        }
+                    };
+                    if kind == BlockKind::Observer {
+                        watch(&mut observed, layer, event.chunk_layer, position);
+                    }
+                }
+            }
+        }
     }
 }
 
+/// Game ticks an observer waits before pulsing, and how long the pulse lasts.
+const OBSERVER_DELAY: i64 = 2;
+
 fn handle_block_update(
+    server: Res<Server>,
+    rules: Res<GameRules>,
+    mut observed: ResMut<ObservedBlocks>,
     mut layers: Query<&mut ChunkLayer>,
+    mut scheduled: Query<&mut ScheduledTicks>,
     mut events: EventReader<BlockUpdateEvent>,
+    mut queue: Local<UpdateQueue>,
 ) {
-    let mut queue = VecDeque::new();
+    let now = server.current_tick();
 
-    queue.extend(events.read().cloned());
+    queue.begin(events.read().cloned());
 
-    while let Some(event) = queue.pop_front() {
+    while let Some(event) = queue.pop(rules.max_block_updates) {
 
         let Ok(mut layer) = layers.get_mut(event.layer) else {
             continue;
@@ -32,50 +114,194 @@ fn handle_block_update(
         let mut block = original_block.into_block();
         let original_block = block.clone();
 
+        if block.state.to_kind() == BlockKind::Observer {
+            watch(&mut observed, &layer, event.layer, event.position);
+        }
+
         let cardinal = [
             Direction::North,
             Direction::East,
             Direction::South,
             Direction::West,
         ];
-        let surrounding = cardinal.into_iter().map(|d| layer.block(event.position.get_in_direction(d)));
         if block.state.to_kind() == BlockKind::RedstoneWire {
-            if surrounding.clone().flatten().any(|b| b.state.to_kind() == BlockKind::RedstoneBlock) {
+            if cardinal.into_iter().any(|d| {
+                layer
+                    .block(event.position.get_in_direction(d))
+                    .is_some_and(|b| is_power_source(b.state) || gate_powers(b.state, d))
+            }) {
                 block.state = block.state.set(PropName::Power, PropValue::_15);
             } else {
                 let max_strength = cardinal.into_iter().filter_map(|d| layer.block(event.position.get_in_direction(d))).filter(|b| b.state.to_kind() == BlockKind::RedstoneWire).filter_map(|b| to_power(b.state)).max().unwrap_or(0);
                 block.state = from_power(block.state, max_strength.saturating_sub(1));
             }
         }
+
+        if let Ok(mut ticks) = scheduled.get_mut(event.layer) {
+            // Wire power spreads through this queue rather than as block
+            // update events, so repeaters it reaches are checked here too.
+            if block.state.to_kind() == BlockKind::Repeater {
+                let mut update = NeighborUpdate::new(&*layer, event.position, block.state);
+                Repeater.on_neighbor_update(&mut update);
+                for &(delay, priority) in update.scheduled_ticks() {
+                    ticks.schedule(now + delay, event.position, BlockKind::Repeater, priority);
+                }
+            }
+
+            // Observers looking at this block notice if it's different from
+            // last time.
+            let state = block.state;
+            let last = observed.0.get(&(event.layer, event.position)).copied();
+            let mut watched = false;
+            for direction in [
+                Direction::Up,
+                Direction::Down,
+                Direction::North,
+                Direction::East,
+                Direction::South,
+                Direction::West,
+            ] {
+                let observer = event.position.get_in_direction(direction);
+                let Some(observer_state) = layer
+                    .block(observer)
+                    .map(|b| b.state)
+                    .filter(|s| s.to_kind() == BlockKind::Observer && facing(*s) == Some(opposite(direction)))
+                else {
+                    continue;
+                };
+                watched = true;
+                if last.is_some_and(|last| last != state)
+                    && observer_state.get(PropName::Powered) != Some(PropValue::True)
+                {
+                    ticks.schedule(now + OBSERVER_DELAY, observer, BlockKind::Observer, TickPriority::Normal);
+                }
+            }
+            if watched {
+                observed.0.insert((event.layer, event.position), state);
+            } else {
+                observed.0.remove(&(event.layer, event.position));
+            }
+        }
+
         if block != original_block {
+            if !queue.record_change(event) {
+                continue;
+            }
             layer.set_block(event.position, block.state);
-            
-            queue.push_back(BlockUpdateEvent {
-                position: event.position.get_in_direction(Direction::Up),
-                ..event
-            });
-            queue.push_back(BlockUpdateEvent {
-                position: event.position.get_in_direction(Direction::Down),
-                ..event
-            });
-            queue.push_back(BlockUpdateEvent {
-                position: event.position.get_in_direction(Direction::North),
-                ..event
-            });
-            queue.push_back(BlockUpdateEvent {
-                position: event.position.get_in_direction(Direction::East),
-                ..event
-            });
-            queue.push_back(BlockUpdateEvent {
-                position: event.position.get_in_direction(Direction::South),
-                ..event
-            });
-            queue.push_back(BlockUpdateEvent {
-                position: event.position.get_in_direction(Direction::West),
-                ..event
-            });
+            queue.push_neighbors(event);
         }
     }
+
+    queue.end("redstone");
+}
+
+/// Repeaters follow their input after a delay set by the player.
+struct Repeater;
+
+impl BlockBehavior for Repeater {
+    fn on_neighbor_update(&self, update: &mut NeighborUpdate) {
+        let state = update.block.state;
+        let powered = state.get(PropName::Powered) == Some(PropValue::True);
+        if powered != repeater_input(update.view, update.position, state) {
+            let priority = if powered { TickPriority::VeryHigh } else { TickPriority::High };
+            update.schedule_tick(repeater_delay(state), priority);
+        }
+    }
+
+    fn on_scheduled_tick(&self, tick: &mut ScheduledTick) {
+        if tick.state.to_kind() != BlockKind::Repeater {
+            return;
+        }
+        let powered = tick.state.get(PropName::Powered) == Some(PropValue::True);
+        let input = repeater_input(&*tick.layer, tick.position, tick.state);
+
+        if powered && !input {
+            tick.set_block(tick.position, tick.state.set(PropName::Powered, PropValue::False));
+        } else if !powered {
+            tick.set_block(tick.position, tick.state.set(PropName::Powered, PropValue::True));
+            // Pulses shorter than the delay still come out the full length.
+            if !input {
+                let delay = repeater_delay(tick.state);
+                tick.schedule(tick.position, BlockKind::Repeater, delay, TickPriority::VeryHigh);
+            }
+        }
+    }
+}
+
+/// Turns an observer on when it sees something change, and off again after a
+/// short pulse.
+fn observer_tick(tick: &mut ScheduledTick) {
+    if tick.state.to_kind() != BlockKind::Observer {
+        return;
+    }
+    if tick.state.get(PropName::Powered) == Some(PropValue::True) {
+        tick.set_block(tick.position, tick.state.set(PropName::Powered, PropValue::False));
+    } else {
+        tick.set_block(tick.position, tick.state.set(PropName::Powered, PropValue::True));
+        tick.schedule(tick.position, BlockKind::Observer, OBSERVER_DELAY, TickPriority::Normal);
+    }
+}
+
+/// Game ticks between a repeater's input changing and its output following.
+fn repeater_delay(state: BlockState) -> i64 {
+    match state.get(PropName::Delay) {
+        Some(PropValue::_2) => 4,
+        Some(PropValue::_3) => 6,
+        Some(PropValue::_4) => 8,
+        _ => 2,
+    }
+}
+
+/// Whether the block behind a repeater is powering it.
+fn repeater_input(view: &dyn BlockView, position: BlockPos, state: BlockState) -> bool {
+    let Some(direction) = facing(state) else {
+        return false;
+    };
+    view.state_at(position.get_in_direction(direction)).is_some_and(|s| {
+        is_power_source(s)
+            || gate_powers(s, direction)
+            || (s.to_kind() == BlockKind::RedstoneWire && to_power(s).is_some_and(|p| p > 0))
+    })
+}
+
+/// Whether a repeater or observer that is in `direction` from a block is
+/// powering it. Both give out power from their back, opposite the way they
+/// face.
+fn gate_powers(state: BlockState, direction: Direction) -> bool {
+    (state.to_kind() == BlockKind::Repeater || state.to_kind() == BlockKind::Observer)
+        && state.get(PropName::Powered) == Some(PropValue::True)
+        && facing(state) == Some(direction)
+}
+
+fn facing(state: BlockState) -> Option<Direction> {
+    match state.get(PropName::Facing) {
+        Some(PropValue::Up) => Some(Direction::Up),
+        Some(PropValue::Down) => Some(Direction::Down),
+        Some(PropValue::North) => Some(Direction::North),
+        Some(PropValue::East) => Some(Direction::East),
+        Some(PropValue::South) => Some(Direction::South),
+        Some(PropValue::West) => Some(Direction::West),
+        _ => None,
+    }
+}
+
+fn opposite(direction: Direction) -> Direction {
+    match direction {
+        Direction::Up => Direction::Down,
+        Direction::Down => Direction::Up,
+        Direction::North => Direction::South,
+        Direction::East => Direction::West,
+        Direction::South => Direction::North,
+        Direction::West => Direction::East,
+    }
+}
+
+/// Whether the block powers redstone wire next to it at full strength.
+fn is_power_source(state: BlockState) -> bool {
+    let kind = state.to_kind();
+    kind == BlockKind::RedstoneBlock
+        || ((kind == BlockKind::Lever || kind.to_str().ends_with("_button"))
+            && state.get(PropName::Powered) == Some(PropValue::True))
 }
 
 fn to_power(state: BlockState) -> Option<u8> {
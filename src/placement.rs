@@ -0,0 +1,230 @@
+use std::borrow::Cow;
+
+use valence::interact_block::InteractBlockEvent;
+use valence::inventory::{ClientInventoryState, HeldItem};
+use valence::prelude::*;
+use valence::protocol::packets::play::{BlockUpdateS2c, ScreenHandlerSlotUpdateS2c};
+use valence::protocol::{VarInt, WritePacket};
+use valence::Direction;
+
+use crate::building::{building, BlockPlaceEvent, CancelPlacingEvent};
+use crate::mining::MAX_REACH;
+use crate::players::EYE_HEIGHT;
+
+/// Checks on the server that players only place blocks they are allowed to,
+/// and undoes the client's prediction when a placement is refused so that
+/// ghost blocks don't linger.
+pub struct Placement;
+
+impl Plugin for Placement {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (validate_placing.before(building), resync_rejected.after(building)),
+        );
+    }
+}
+
+/// Dirt and the blocks like it that plants grow on.
+pub fn is_soil(kind: BlockKind) -> bool {
+    kind == BlockKind::GrassBlock
+        || kind == BlockKind::Dirt
+        || kind == BlockKind::CoarseDirt
+        || kind == BlockKind::Podzol
+        || kind == BlockKind::RootedDirt
+        || kind == BlockKind::Mycelium
+        || kind == BlockKind::Farmland
+        || kind == BlockKind::MossBlock
+        || kind == BlockKind::Mud
+        || kind == BlockKind::MuddyMangroveRoots
+}
+
+fn is_sand(kind: BlockKind) -> bool {
+    kind == BlockKind::Sand || kind == BlockKind::RedSand || kind == BlockKind::SuspiciousSand
+}
+
+/// Whether a block of this kind can stay at `position`, given what is around
+/// it. Blocks that don't depend on their neighbours always can.
+pub fn can_survive(kind: BlockKind, layer: &ChunkLayer, position: BlockPos) -> bool {
+    let name = kind.to_str();
+    let Some(below) = layer.block(position.get_in_direction(Direction::Down)).map(|b| b.state) else {
+        return false;
+    };
+    let below_kind = below.to_kind();
+
+    if kind == BlockKind::Dandelion
+        || kind == BlockKind::Poppy
+        || kind == BlockKind::BlueOrchid
+        || kind == BlockKind::Allium
+        || kind == BlockKind::AzureBluet
+        || kind == BlockKind::OxeyeDaisy
+        || kind == BlockKind::Cornflower
+        || kind == BlockKind::LilyOfTheValley
+        || kind == BlockKind::Torchflower
+        || kind == BlockKind::Sunflower
+        || kind == BlockKind::Lilac
+        || kind == BlockKind::RoseBush
+        || kind == BlockKind::Peony
+        || kind == BlockKind::Grass
+        || kind == BlockKind::Fern
+        || kind == BlockKind::TallGrass
+        || kind == BlockKind::LargeFern
+        || kind == BlockKind::SweetBerryBush
+        || kind == BlockKind::PinkPetals
+        || name.ends_with("_tulip")
+        || name.ends_with("_sapling")
+    {
+        is_soil(below_kind)
+    } else if kind == BlockKind::WitherRose {
+        is_soil(below_kind)
+            || below_kind == BlockKind::Netherrack
+            || below_kind == BlockKind::SoulSand
+            || below_kind == BlockKind::SoulSoil
+    } else if kind == BlockKind::DeadBush {
+        is_soil(below_kind) || is_sand(below_kind) || below_kind.to_str().ends_with("terracotta")
+    } else if kind == BlockKind::CrimsonFungus
+        || kind == BlockKind::WarpedFungus
+        || kind == BlockKind::CrimsonRoots
+        || kind == BlockKind::WarpedRoots
+        || kind == BlockKind::NetherSprouts
+    {
+        is_soil(below_kind)
+            || below_kind == BlockKind::CrimsonNylium
+            || below_kind == BlockKind::WarpedNylium
+            || below_kind == BlockKind::SoulSoil
+    } else if kind == BlockKind::Wheat
+        || kind == BlockKind::Carrots
+        || kind == BlockKind::Potatoes
+        || kind == BlockKind::Beetroots
+        || kind == BlockKind::MelonStem
+        || kind == BlockKind::PumpkinStem
+        || kind == BlockKind::TorchflowerCrop
+        || kind == BlockKind::PitcherCrop
+    {
+        below_kind == BlockKind::Farmland
+    } else if kind == BlockKind::NetherWart {
+        below_kind == BlockKind::SoulSand
+    } else if kind == BlockKind::Cactus {
+        below_kind == BlockKind::Cactus || is_sand(below_kind)
+    } else if kind == BlockKind::SugarCane {
+        below_kind == BlockKind::SugarCane
+            || ((is_soil(below_kind) || is_sand(below_kind))
+                && [Direction::North, Direction::East, Direction::South, Direction::West]
+                    .into_iter()
+                    .any(|dir| {
+                        layer
+                            .block(position.get_in_direction(Direction::Down).get_in_direction(dir))
+                            .is_some_and(|b| {
+                                b.state.to_kind() == BlockKind::Water
+                                    || b.state.get(PropName::Waterlogged) == Some(PropValue::True)
+                            })
+                    }))
+    } else if kind == BlockKind::BrownMushroom || kind == BlockKind::RedMushroom {
+        below.is_opaque()
+    } else if kind == BlockKind::Torch
+        || kind == BlockKind::SoulTorch
+        || kind == BlockKind::RedstoneTorch
+        || kind == BlockKind::RedstoneWire
+        || kind == BlockKind::Repeater
+        || kind == BlockKind::Comparator
+        || name.ends_with("_pressure_plate")
+        || name.ends_with("rail")
+    {
+        below.is_opaque()
+    } else if name.ends_with("_carpet") {
+        !below.is_air()
+    } else {
+        true
+    }
+}
+
+/// Rejects placements from players who can't build, are out of reach, or
+/// are placing a block somewhere it can't stay.
+fn validate_placing(
+    clients: Query<(&Position, &GameMode, &Inventory, &HeldItem, &EntityLayerId)>,
+    layers: Query<&ChunkLayer>,
+    mut events: EventReader<InteractBlockEvent>,
+    mut cancel: EventWriter<CancelPlacingEvent>,
+) {
+    for event in events.read() {
+        let Ok((position, game_mode, inventory, held, layer_id)) = clients.get(event.client) else {
+            continue;
+        };
+        let Ok(layer) = layers.get(layer_id.0) else {
+            continue;
+        };
+        let Some(kind) = BlockKind::from_item_kind(inventory.slot(held.slot()).item) else {
+            continue;
+        };
+
+        let eyes = position.0 + DVec3::new(0.0, EYE_HEIGHT, 0.0);
+        let center = DVec3::new(
+            f64::from(event.position.x) + 0.5,
+            f64::from(event.position.y) + 0.5,
+            f64::from(event.position.z) + 0.5,
+        );
+
+        // The block goes in place of the clicked one if it can be replaced,
+        // and next to it otherwise.
+        let target = if layer
+            .block(event.position)
+            .is_some_and(|b| !b.state.is_replaceable() || b.state.to_kind() == kind)
+        {
+            event.position.get_in_direction(event.face)
+        } else {
+            event.position
+        };
+
+        if *game_mode == GameMode::Adventure
+            || *game_mode == GameMode::Spectator
+            || eyes.distance_squared(center) > MAX_REACH * MAX_REACH
+            || !can_survive(kind, layer, target)
+        {
+            cancel.send(CancelPlacingEvent { client: event.client });
+        }
+    }
+}
+
+/// The client shows a block as soon as it places one and takes the item out
+/// of its hand. When the server didn't place anything, both are sent back as
+/// they really are.
+fn resync_rejected(
+    mut clients: Query<(&mut Client, &Inventory, &HeldItem, &ClientInventoryState, &EntityLayerId)>,
+    layers: Query<&ChunkLayer>,
+    mut events: EventReader<InteractBlockEvent>,
+    mut places: EventReader<BlockPlaceEvent>,
+) {
+    let placed = places.read().map(|e| e.client).collect::<Vec<_>>();
+
+    for event in events.read() {
+        if placed.contains(&event.client) {
+            continue;
+        }
+        let Ok((mut client, inventory, held, inventory_state, layer_id)) = clients.get_mut(event.client) else {
+            continue;
+        };
+        let stack = inventory.slot(held.slot());
+        if BlockKind::from_item_kind(stack.item).is_none() {
+            continue;
+        }
+        let Ok(layer) = layers.get(layer_id.0) else {
+            continue;
+        };
+
+        for position in [event.position, event.position.get_in_direction(event.face)] {
+            if let Some(block) = layer.block(position) {
+                client.write_packet(&BlockUpdateS2c {
+                    position,
+                    block_id: block.state,
+                });
+            }
+        }
+
+        client.write_packet(&ScreenHandlerSlotUpdateS2c {
+            window_id: 0,
+            state_id: VarInt(inventory_state.state_id().0),
+            slot_idx: held.slot() as i16,
+            slot_data: Cow::Borrowed(stack),
+        });
+    }
+}
@@ -1,10 +1,16 @@
 use std::borrow::Cow;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::Path;
+
+use serde::Deserialize;
+use thiserror::Error;
 use valence::client::DisconnectClient;
 use valence::abilities::PlayerAbilitiesFlags;
-use valence::entity::player::PlayerEntityBundle;
+use valence::entity::player::{Food, PlayerEntityBundle, Saturation};
 use valence::message::SendMessage;
-use valence::protocol::packets::play::DisconnectS2c;
-use valence::protocol::WritePacket;
+use valence::protocol::packets::play::{DisconnectS2c, ExperienceBarUpdateS2c};
+use valence::protocol::{VarInt, WritePacket};
 use valence::inventory::HeldItem;
 use valence::prelude::*;
 
@@ -16,19 +22,210 @@ pub struct Players;
 
 impl Plugin for Players {
     fn build(&self, app: &mut App) {
+        if !app.world().contains_resource::<JoinSettings>() {
+            let settings = match JoinSettings::load(JOIN_SETTINGS_PATH) {
+                Ok(settings) => settings,
+                Err(LoadJoinSettingsError::Io(err)) if err.kind() == ErrorKind::NotFound => {
+                    JoinSettings::default()
+                }
+                Err(err) => {
+                    tracing::warn!("failed to load `{JOIN_SETTINGS_PATH}`, using defaults: {err}");
+                    JoinSettings::default()
+                }
+            };
+            app.insert_resource(settings);
+        }
+
         app.add_systems(
             Update,
-            (handle_loaded_clients, despawn_disconnected_clients),
+            (handle_loaded_clients, broadcast_leave_messages, despawn_disconnected_clients, update_xp_bar),
         ).add_systems(Update, disconnect_on_shutdown.after(handle_exit).before(autosave));
     }
 }
 
+/// The file [`JoinSettings`] are read from when the [`Players`] plugin is
+/// added, relative to the directory the server is run in.
+pub const JOIN_SETTINGS_PATH: &str = "join.toml";
+
+/// Height of a standing player's eyes above their feet.
+pub const EYE_HEIGHT: f64 = 1.62;
+
+/// Controls what players experience when they join and leave the server.
+///
+/// Insert this resource before adding the [`Players`] plugin to configure it
+/// in code, otherwise it is loaded from [`JOIN_SETTINGS_PATH`] if that file
+/// exists. Messages may contain the placeholders `{player}` and `{online}`.
+///
+/// ```toml
+/// default-game-mode = "survival"
+/// welcome-message = "Welcome to the server, {player}!"
+/// join-message = "{player} joined the game"
+/// leave-message = "{player} left the game"
+///
+/// [[starter-kit]]
+/// item = "minecraft:stone_sword"
+///
+/// [[starter-kit]]
+/// item = "minecraft:bread"
+/// count = 8
+/// ```
+#[derive(Resource, Debug, Clone)]
+pub struct JoinSettings {
+    /// The game mode of players joining for the first time.
+    pub default_game_mode: GameMode,
+    /// If set, every player is put in this game mode when they join,
+    /// regardless of the game mode they were saved with.
+    pub forced_game_mode: Option<GameMode>,
+    /// Sent to players joining for the first time.
+    pub welcome_message: Option<String>,
+    /// Items given to players joining for the first time.
+    pub starter_kit: Vec<ItemStack>,
+    /// Broadcast to the player's layer when they join.
+    pub join_message: Option<String>,
+    /// Broadcast to the player's layer when they leave.
+    pub leave_message: Option<String>,
+}
+
+impl Default for JoinSettings {
+    fn default() -> Self {
+        Self {
+            default_game_mode: GameMode::Survival,
+            forced_game_mode: None,
+            welcome_message: Some("Welcome to the server, {player}!".into()),
+            starter_kit: Vec::new(),
+            join_message: Some("{player} joined the game".into()),
+            leave_message: Some("{player} left the game".into()),
+        }
+    }
+}
+
+impl JoinSettings {
+    /// Reads join settings from a TOML file. Any settings missing from the
+    /// file take their default values.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, LoadJoinSettingsError> {
+        let contents = fs::read_to_string(path)?;
+        let raw: RawJoinSettings = toml::from_str(&contents)?;
+        let defaults = Self::default();
+
+        let starter_kit = raw
+            .starter_kit
+            .into_iter()
+            .map(|kit_item| {
+                let Some(item) = ItemKind::from_str(kit_item.item.trim_start_matches("minecraft:")) else {
+                    return Err(LoadJoinSettingsError::InvalidItem(kit_item.item));
+                };
+                if !(1..=item.max_stack()).contains(&kit_item.count) {
+                    return Err(LoadJoinSettingsError::InvalidItemCount(kit_item.item, kit_item.count));
+                }
+                Ok(ItemStack::new(item, kit_item.count, None))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            default_game_mode: raw
+                .default_game_mode
+                .map(parse_game_mode)
+                .transpose()?
+                .unwrap_or(defaults.default_game_mode),
+            forced_game_mode: raw.forced_game_mode.map(parse_game_mode).transpose()?,
+            welcome_message: raw.welcome_message.or(defaults.welcome_message),
+            starter_kit,
+            join_message: raw.join_message.or(defaults.join_message),
+            leave_message: raw.leave_message.or(defaults.leave_message),
+        })
+    }
+}
+
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum LoadJoinSettingsError {
+    #[error("Error reading join settings: {0}")]
+    Io(#[from] io::Error),
+    #[error("Invalid join settings: {0}")]
+    Toml(#[from] toml::de::Error),
+    #[error("`{0}` is not a valid game mode")]
+    InvalidGameMode(String),
+    #[error("`{0}` is not a valid item")]
+    InvalidItem(String),
+    #[error("`{0}` cannot be given in a stack of {1}")]
+    InvalidItemCount(String, i8),
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+struct RawJoinSettings {
+    default_game_mode: Option<String>,
+    forced_game_mode: Option<String>,
+    welcome_message: Option<String>,
+    starter_kit: Vec<RawKitItem>,
+    join_message: Option<String>,
+    leave_message: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawKitItem {
+    item: String,
+    #[serde(default = "default_kit_count")]
+    count: i8,
+}
+
+fn default_kit_count() -> i8 {
+    1
+}
+
+fn parse_game_mode(name: String) -> Result<GameMode, LoadJoinSettingsError> {
+    match name.as_str() {
+        "survival" => Ok(GameMode::Survival),
+        "creative" => Ok(GameMode::Creative),
+        "adventure" => Ok(GameMode::Adventure),
+        "spectator" => Ok(GameMode::Spectator),
+        _ => Err(LoadJoinSettingsError::InvalidGameMode(name)),
+    }
+}
+
 #[derive(Component, Debug, Copy, Clone)]
 pub struct Xp {
     pub level: i32,
     pub bar: f32,
 }
 
+impl Xp {
+    /// Experience points it takes to go from `level` to the next level.
+    pub fn points_for_level(level: i32) -> i32 {
+        if level >= 30 {
+            112 + (level - 30) * 9
+        } else if level >= 15 {
+            37 + (level - 15) * 5
+        } else {
+            7 + level * 2
+        }
+    }
+
+    /// Total experience points collected to reach this level and bar.
+    pub fn total_points(&self) -> i32 {
+        let level = self.level as f32;
+        let levels = if self.level <= 16 {
+            level * level + 6.0 * level
+        } else if self.level <= 31 {
+            2.5 * level * level - 40.5 * level + 360.0
+        } else {
+            4.5 * level * level - 162.5 * level + 2220.0
+        };
+        levels as i32 + (self.bar * Self::points_for_level(self.level) as f32) as i32
+    }
+
+    /// Adds experience points, levelling up as the bar fills.
+    pub fn add_points(&mut self, points: i32) {
+        self.bar += points as f32 / Self::points_for_level(self.level) as f32;
+        while self.bar >= 1.0 {
+            self.bar = (self.bar - 1.0) * Self::points_for_level(self.level) as f32;
+            self.level += 1;
+            self.bar /= Self::points_for_level(self.level) as f32;
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct PlayerData {
     pub entity: PlayerEntityBundle,
@@ -96,15 +293,22 @@ fn handle_loaded_clients(
             &mut HeldItem,
             &mut GameMode,
             &mut PlayerAbilitiesFlags,
+            &mut Food,
+            &mut Saturation,
             &Username,
             &UniqueId,
         ),
         Added<Client>,
     >,
+    online: Query<(), With<Client>>,
     layers: Query<(Entity, &ChunkLayer)>,
+    mut entity_layers: Query<&mut EntityLayer>,
     mut anvils: Query<&mut AnvilLevel>,
+    settings: Res<JoinSettings>,
     mut commands: Commands,
 ) {
+    let online = online.iter().count();
+
     for (
         entity,
         mut client,
@@ -117,7 +321,9 @@ fn handle_loaded_clients(
         mut held_item,
         mut game_mode,
         mut flags,
-        _username,
+        mut food,
+        mut saturation,
+        username,
         &uuid,
     ) in &mut clients {
 
@@ -158,8 +364,10 @@ fn handle_loaded_clients(
                 layer_id.0 = layer;
                 visible_chunk_layer.0 = layer;
                 visible_entity_layers.0.insert(layer);
-                *game_mode = saved.game_mode;
+                *game_mode = settings.forced_game_mode.unwrap_or(saved.game_mode);
                 flags.set_flying(saved.flying);
+                *food = saved.entity.player_food;
+                *saturation = saved.entity.player_saturation;
                 *look = saved.entity.look;
                 commands.entity(entity).insert(saved.xp);
             }
@@ -175,32 +383,24 @@ fn handle_loaded_clients(
                 layer_id.0 = layer;
                 visible_chunk_layer.0 = layer;
                 visible_entity_layers.0.insert(layer);
-                *game_mode = GameMode::Creative;
+                *game_mode = settings.forced_game_mode.unwrap_or(settings.default_game_mode);
                 commands.entity(entity).insert(Xp { level: 0, bar: 0. });
-        
-                let rust_text = "Rust"
-                    .color(Color::AQUA)
-                    .underlined()
-                    .on_click_open_url("https://rust-lang.org")
-                    .on_hover_show_text("https://rust-lang.org".color(Color::AQUA));
-                let bevy_text = "Bevy ECS"
-                    .color(Color::AQUA)
-                    .underlined()
-                    .on_click_open_url("https://bevyengine.org")
-                    .on_hover_show_text("https://bevyengine.org".color(Color::AQUA));
-                let valence_text = "Valence"
-                    .color(Color::AQUA)
-                    .underlined()
-                    .on_click_open_url("https://valence.rs")
-                    .on_hover_show_text("https://valence.rs".color(Color::AQUA));
-                let message = "Welcome to BongoThirteen's experimental server!\n\n".color(Color::AQUA)
-                    + "This server is written from scratch in ".color(Color::DARK_AQUA) + rust_text + " and contains no code from Mojang. It is in an early stage of development, so expect lots of bugs.\n\n".color(Color::DARK_AQUA)
-                    + "Thank you to all the projects that make this server possible, including but not limited to:\n\n".color(Color::DARK_AQUA)
-                    + "    • ".color(Color::DARK_AQUA) + bevy_text
-                    + "\n    • ".color(Color::DARK_AQUA) + valence_text
-                    + "\n";
-        
-                client.send_chat_message(message);
+
+                for stack in &settings.starter_kit {
+                    // Fill the hotbar first, then the main inventory.
+                    let Some(slot) = inventory
+                        .first_empty_slot_in(36..45)
+                        .or_else(|| inventory.first_empty_slot_in(9..36))
+                    else {
+                        tracing::warn!("no room for starter kit item {:?} in the inventory of {}", stack.item, username.0);
+                        break;
+                    };
+                    inventory.set_slot(slot, stack.clone());
+                }
+
+                if let Some(welcome) = &settings.welcome_message {
+                    client.send_chat_message(fill_placeholders(welcome, &username.0, online).color(Color::DARK_AQUA));
+                }
             }
             Err(err) => {
                 tracing::warn!("failed to load player data: {err:?}");
@@ -215,16 +415,54 @@ fn handle_loaded_clients(
                 layer_id.0 = layer;
                 visible_chunk_layer.0 = layer;
                 visible_entity_layers.0.insert(layer);
-                *game_mode = GameMode::Creative;
+                *game_mode = settings.forced_game_mode.unwrap_or(settings.default_game_mode);
+                commands.entity(entity).insert(Xp { level: 0, bar: 0. });
                 
                 client.send_chat_message("Unfortunately, we couldn't load your player data from the world save due to corruption. Please notify the server administration of this issue.".color(Color::RED));
             }
         }
 
-        // entity_layer.send_chat_message(username.0.clone().color(Color::DARK_AQUA) + " joined the game");
+        if let Some(join) = &settings.join_message {
+            if let Ok(mut entity_layer) = entity_layers.get_mut(layer_id.0) {
+                entity_layer.send_chat_message(fill_placeholders(join, &username.0, online).color(Color::YELLOW));
+            }
+        }
+    }
+}
+
+fn broadcast_leave_messages(
+    mut disconnected_clients: RemovedComponents<Client>,
+    players: Query<(&Username, &EntityLayerId)>,
+    online: Query<(), With<Client>>,
+    mut entity_layers: Query<&mut EntityLayer>,
+    settings: Res<JoinSettings>,
+) {
+    let Some(leave) = &settings.leave_message else {
+        disconnected_clients.clear();
+        return;
+    };
+
+    let online = online.iter().count();
+
+    for disconnected_client in disconnected_clients.read() {
+        let Ok((username, layer_id)) = players.get(disconnected_client) else {
+            continue;
+        };
+        if let Ok(mut entity_layer) = entity_layers.get_mut(layer_id.0) {
+            entity_layer.send_chat_message(fill_placeholders(leave, &username.0, online).color(Color::YELLOW));
+        }
     }
 }
 
+/// Replaces the placeholders in a join settings message template.
+///
+/// `{player}` is replaced with the player's username and `{online}` with the
+/// number of players currently connected.
+fn fill_placeholders(template: &str, username: &str, online: usize) -> String {
+    template
+        .replace("{player}", username)
+        .replace("{online}", &online.to_string())
+}
 
 // fn save_player_data(
 //     mut disconnected_clients: RemovedComponents<Client>,
@@ -264,3 +502,13 @@ fn disconnect_on_shutdown(
         }
     }
 }
+
+fn update_xp_bar(mut clients: Query<(&mut Client, &Xp), Changed<Xp>>) {
+    for (mut client, xp) in &mut clients {
+        client.write_packet(&ExperienceBarUpdateS2c {
+            bar: xp.bar,
+            level: VarInt(xp.level),
+            total_xp: VarInt(xp.total_points()),
+        });
+    }
+}
@@ -0,0 +1,285 @@
+use std::collections::HashMap;
+
+use valence::layer::chunk::IntoBlock;
+use valence::prelude::*;
+use valence::rand::rngs::StdRng;
+use valence::rand::{Rng, SeedableRng};
+
+use crate::block_behavior::{with_neighbors, BlockBehaviors};
+use crate::block_update::BlockUpdateEvent;
+use crate::rules::GameRules;
+
+/// Picks random blocks in loaded chunks every tick and runs the random tick
+/// hook of their [`BlockBehaviors`]. This is what slow, random changes like
+/// crops growing and ice melting are built on.
+pub struct RandomTicks;
+
+impl Plugin for RandomTicks {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameRules>()
+            .init_resource::<BlockBehaviors>()
+            .init_resource::<RandomTickRng>()
+            .init_resource::<AirSections>()
+            .add_systems(Update, (forget_changed_sections, random_tick).chain());
+    }
+}
+
+/// Where random ticks and everything behaviours do with them get their
+/// randomness from. Insert one made with [`RandomTickRng::seeded`] to make
+/// them repeat exactly, such as in tests.
+#[derive(Resource, Debug, Clone)]
+pub struct RandomTickRng(pub StdRng);
+
+impl RandomTickRng {
+    pub fn seeded(seed: u64) -> Self {
+        Self(StdRng::seed_from_u64(seed))
+    }
+}
+
+impl Default for RandomTickRng {
+    fn default() -> Self {
+        Self(StdRng::from_entropy())
+    }
+}
+
+/// Remembers which chunk sections have nothing but air in them, so that
+/// random ticks don't keep looking there. Sections are looked at again once
+/// a block in them changes.
+#[derive(Resource, Debug, Default)]
+struct AirSections(HashMap<(Entity, ChunkPos, i32), bool>);
+
+/// Ticks between clearing out sections of chunks that were unloaded.
+const PRUNE_INTERVAL: i64 = 1200;
+
+/// A block that was picked for a random tick, and what its behaviour can use
+/// to change it and the blocks around it.
+pub struct RandomTick<'a> {
+    pub layer: &'a mut ChunkLayer,
+    pub layer_id: EntityLayerId,
+    pub position: BlockPos,
+    pub state: BlockState,
+    pub rng: &'a mut StdRng,
+    pub rules: &'a GameRules,
+    changed: &'a mut Vec<BlockPos>,
+}
+
+impl RandomTick<'_> {
+    /// Sets a block and lets the blocks around it know it changed.
+    pub fn set_block(&mut self, position: BlockPos, block: impl IntoBlock) {
+        self.layer.set_block(position, block);
+        self.changed.push(position);
+    }
+}
+
+fn forget_changed_sections(mut sections: ResMut<AirSections>, mut events: EventReader<BlockUpdateEvent>) {
+    for event in events.read() {
+        let chunk = ChunkPos::new(event.position.x.div_euclid(16), event.position.z.div_euclid(16));
+        sections.0.remove(&(event.layer, chunk, event.position.y.div_euclid(16)));
+    }
+}
+
+/// Whether the 16 blocks tall section starting at `min_y` within the chunk
+/// has only air in it.
+fn only_air(chunk: &impl Chunk, min_y: u32) -> bool {
+    for y in min_y..min_y + 16 {
+        for z in 0..16 {
+            for x in 0..16 {
+                if !chunk.block_state(x, y, z).is_air() {
+                    return false;
+                }
+            }
+        }
+    }
+    true
+}
+
+/// Picks `speed` random blocks in each section of the chunk, skipping the
+/// sections `air` says are empty, and returns the positions of those that
+/// `ticks` says have random ticks.
+fn pick_blocks(
+    chunk: &impl Chunk,
+    pos: ChunkPos,
+    min_y: i32,
+    speed: u32,
+    rng: &mut StdRng,
+    mut air: impl FnMut(u32) -> bool,
+    ticks: impl Fn(BlockKind) -> bool,
+) -> Vec<BlockPos> {
+    let mut picked = Vec::new();
+
+    for section in 0..chunk.height() / 16 {
+        if air(section) {
+            continue;
+        }
+
+        for _ in 0..speed {
+            let (x, y, z) = (rng.gen_range(0..16), rng.gen_range(0..16), rng.gen_range(0..16));
+            if !ticks(chunk.block_state(x, section * 16 + y, z).to_kind()) {
+                continue;
+            }
+            picked.push(BlockPos::new(
+                pos.x * 16 + x as i32,
+                min_y + (section * 16 + y) as i32,
+                pos.z * 16 + z as i32,
+            ));
+        }
+    }
+
+    picked
+}
+
+fn random_tick(
+    server: Res<Server>,
+    rules: Res<GameRules>,
+    behaviors: Res<BlockBehaviors>,
+    mut rng: ResMut<RandomTickRng>,
+    mut sections: ResMut<AirSections>,
+    mut layers: Query<(Entity, &mut ChunkLayer)>,
+    mut block_updates: EventWriter<BlockUpdateEvent>,
+) {
+    if server.current_tick() % PRUNE_INTERVAL == 0 {
+        sections
+            .0
+            .retain(|(layer, chunk, _), _| layers.get(*layer).is_ok_and(|(_, l)| l.chunk(*chunk).is_some()));
+    }
+    if rules.random_tick_speed == 0 || !behaviors.any_random_ticks() {
+        return;
+    }
+
+    let rng = &mut rng.0;
+
+    for (layer_entity, mut layer) in &mut layers {
+        let min_y = layer.min_y();
+
+        // Chunks are visited in a fixed order, so that a seeded generator
+        // always picks the same blocks.
+        let mut chunks = layer.chunks().map(|(pos, _)| pos).collect::<Vec<_>>();
+        chunks.sort_unstable_by_key(|pos| (pos.x, pos.z));
+
+        let mut picked = Vec::new();
+
+        for pos in chunks {
+            let Some(chunk) = layer.chunk(pos) else {
+                continue;
+            };
+
+            picked.extend(pick_blocks(
+                chunk,
+                pos,
+                min_y,
+                rules.random_tick_speed,
+                rng,
+                |section| {
+                    let section_y = min_y.div_euclid(16) + section as i32;
+                    *sections
+                        .0
+                        .entry((layer_entity, pos, section_y))
+                        .or_insert_with(|| only_air(chunk, section * 16))
+                },
+                |kind| behaviors.get(kind).is_some_and(|b| b.has_random_ticks()),
+            ));
+        }
+
+        let layer_id = EntityLayerId(layer_entity);
+        let mut changed = Vec::new();
+
+        for position in picked {
+            // An earlier behaviour this tick may have changed the block.
+            let Some(state) = layer.block(position).map(|b| b.state) else {
+                continue;
+            };
+            let Some(behavior) = behaviors.get(state.to_kind()).filter(|b| b.has_random_ticks()) else {
+                continue;
+            };
+
+            behavior.on_random_tick(&mut RandomTick {
+                layer: &mut layer,
+                layer_id,
+                position,
+                state,
+                rng,
+                rules: &rules,
+                changed: &mut changed,
+            });
+        }
+
+        for position in changed {
+            for position in with_neighbors(position) {
+                block_updates.send(BlockUpdateEvent {
+                    position,
+                    layer: layer_entity,
+                    entity_layer: layer_id,
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use valence::layer::chunk::UnloadedChunk;
+
+    use super::*;
+
+    /// Three sections: wheat at the bottom, stone in the middle and nothing
+    /// but air at the top.
+    fn chunk() -> UnloadedChunk {
+        let mut chunk = UnloadedChunk::with_height(48);
+        chunk.fill_block_state_section(0, BlockState::WHEAT);
+        chunk.fill_block_state_section(1, BlockState::STONE);
+        chunk
+    }
+
+    fn pick(chunk: &UnloadedChunk, seed: u64, ticks: impl Fn(BlockKind) -> bool) -> Vec<BlockPos> {
+        let mut rng = RandomTickRng::seeded(seed).0;
+        pick_blocks(chunk, ChunkPos::new(2, -1), -16, 3, &mut rng, |section| only_air(chunk, section * 16), ticks)
+    }
+
+    #[test]
+    fn seeded_picks_repeat() {
+        let chunk = chunk();
+        let ticks = |kind| kind == BlockKind::Wheat;
+
+        let first = pick(&chunk, 42, ticks);
+        assert_eq!(first.len(), 3);
+        assert_eq!(first, pick(&chunk, 42, ticks));
+        assert_ne!(first, pick(&chunk, 43, ticks));
+
+        for position in first {
+            assert!((32..48).contains(&position.x));
+            assert!((-16..0).contains(&position.y));
+            assert!((-16..0).contains(&position.z));
+        }
+    }
+
+    #[test]
+    fn air_sections_are_skipped() {
+        let chunk = chunk();
+        assert!(!only_air(&chunk, 0));
+        assert!(!only_air(&chunk, 16));
+        assert!(only_air(&chunk, 32));
+
+        // Even when every block would take random ticks, nothing is picked
+        // in the section with only air.
+        let picked = pick(&chunk, 7, |_| true);
+        assert_eq!(picked.len(), 6);
+        assert!(picked.iter().all(|position| position.y < 16));
+
+        let mut asked = Vec::new();
+        let mut rng = RandomTickRng::seeded(7).0;
+        let picked = pick_blocks(
+            &chunk,
+            ChunkPos::new(0, 0),
+            0,
+            3,
+            &mut rng,
+            |section| {
+                asked.push(section);
+                section == 2
+            },
+            |_| true,
+        );
+        assert_eq!(asked, [0, 1, 2]);
+        assert!(picked.iter().all(|position| position.y < 32));
+    }
+}
@@ -0,0 +1,356 @@
+use std::f32::consts::TAU;
+
+use valence::entity::active_status_effects::ActiveStatusEffects;
+use valence::entity::entity::{Air, Flags};
+use valence::entity::experience_orb::ExperienceOrbEntityBundle;
+use valence::entity::living::Health;
+use valence::entity::player::{Food, Saturation};
+use valence::entity::ObjectData;
+use valence::interact_block::InteractBlockEvent;
+use valence::inventory::HeldItem;
+use valence::message::SendMessage;
+use valence::prelude::*;
+use valence::protocol::Hand;
+use valence::rand::{thread_rng, Rng};
+use valence::spawn::{DeathLocation, HasRespawnScreen, RespawnPosition};
+use valence::status::RequestRespawnEvent;
+
+use crate::block_behavior::block_pos;
+use crate::building::{building, CancelPlacingEvent};
+use crate::death::DeathEvent;
+use crate::environment::{FallDistance, FireTicks, MAX_AIR};
+use crate::hunger::HungerState;
+use crate::interact::sneak_bypasses;
+use crate::item::enchantment_level;
+use crate::item_entity::{spawn_item, PLAYER_DROP_PICKUP_DELAY};
+use crate::players::Xp;
+use crate::rules::GameRules;
+use crate::SPAWN_POS;
+
+/// Handles what happens between a player dying and coming back: dropping their
+/// items, the respawn screen, and sending them back to their bed, respawn
+/// anchor or the world spawn.
+pub struct Respawn;
+
+impl Plugin for Respawn {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameRules>().add_systems(
+            Update,
+            (init_clients, set_spawn_point.before(building), drop_on_death, respawn),
+        );
+    }
+}
+
+/// Where a player respawns instead of the world spawn.
+#[derive(Component, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SpawnPoint {
+    /// The layer the spawn block is in.
+    pub layer: Entity,
+    pub position: BlockPos,
+    pub kind: SpawnPointKind,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SpawnPointKind {
+    Bed,
+    RespawnAnchor,
+}
+
+fn init_clients(mut clients: Query<&mut HasRespawnScreen, Added<Client>>) {
+    for mut respawn_screen in &mut clients {
+        respawn_screen.0 = true;
+    }
+}
+
+fn is_bed(kind: BlockKind) -> bool {
+    kind == BlockKind::WhiteBed
+        || kind == BlockKind::LightGrayBed
+        || kind == BlockKind::GrayBed
+        || kind == BlockKind::BlackBed
+        || kind == BlockKind::BrownBed
+        || kind == BlockKind::RedBed
+        || kind == BlockKind::OrangeBed
+        || kind == BlockKind::YellowBed
+        || kind == BlockKind::LimeBed
+        || kind == BlockKind::GreenBed
+        || kind == BlockKind::CyanBed
+        || kind == BlockKind::LightBlueBed
+        || kind == BlockKind::BlueBed
+        || kind == BlockKind::PurpleBed
+        || kind == BlockKind::MagentaBed
+        || kind == BlockKind::PinkBed
+}
+
+fn anchor_charges(state: BlockState) -> u8 {
+    match state.get(PropName::Charges) {
+        Some(PropValue::_1) => 1,
+        Some(PropValue::_2) => 2,
+        Some(PropValue::_3) => 3,
+        Some(PropValue::_4) => 4,
+        _ => 0,
+    }
+}
+
+fn with_anchor_charges(state: BlockState, charges: u8) -> BlockState {
+    let value = match charges {
+        0 => PropValue::_0,
+        1 => PropValue::_1,
+        2 => PropValue::_2,
+        3 => PropValue::_3,
+        _ => PropValue::_4,
+    };
+    state.set(PropName::Charges, value)
+}
+
+/// Using a bed sets the player's spawn point. Respawn anchors are charged with
+/// glowstone and set the spawn point once charged, but only in the nether.
+fn set_spawn_point(
+    mut clients: Query<(
+        &mut Client,
+        &mut Inventory,
+        &HeldItem,
+        &GameMode,
+        &Flags,
+        &mut RespawnPosition,
+        &EntityLayerId,
+    )>,
+    mut layers: Query<&mut ChunkLayer>,
+    mut events: EventReader<InteractBlockEvent>,
+    mut cancel: EventWriter<CancelPlacingEvent>,
+    mut commands: Commands,
+) {
+    for event in events.read() {
+        if event.hand != Hand::Main {
+            continue;
+        }
+        let Ok((mut client, mut inventory, held, game_mode, flags, mut respawn_position, layer_id)) =
+            clients.get_mut(event.client)
+        else {
+            continue;
+        };
+        if sneak_bypasses(flags, &inventory, held) {
+            continue;
+        }
+        let Ok(mut layer) = layers.get_mut(layer_id.0) else {
+            continue;
+        };
+        let Some(state) = layer.block(event.position).map(|b| b.state) else {
+            continue;
+        };
+
+        let kind = if is_bed(state.to_kind()) {
+            SpawnPointKind::Bed
+        } else if state.to_kind() == BlockKind::RespawnAnchor {
+            let charges = anchor_charges(state);
+            let stack = inventory.slot(held.slot()).clone();
+
+            if stack.item == ItemKind::Glowstone && charges < 4 {
+                cancel.send(CancelPlacingEvent { client: event.client });
+                layer.set_block(event.position, with_anchor_charges(state, charges + 1));
+                if *game_mode != GameMode::Creative {
+                    inventory.set_slot_amount(held.slot(), stack.count - 1);
+                }
+                continue;
+            }
+
+            if charges == 0 || layer.dimension_type_name() != ident!("the_nether") {
+                continue;
+            }
+
+            SpawnPointKind::RespawnAnchor
+        } else {
+            continue;
+        };
+
+        // The bed or anchor was used, so whatever is held isn't placed
+        // against it.
+        cancel.send(CancelPlacingEvent { client: event.client });
+
+        let spawn_point = SpawnPoint {
+            layer: layer_id.0,
+            position: event.position,
+            kind,
+        };
+
+        respawn_position.pos = event.position;
+        commands.entity(event.client).insert(spawn_point);
+        client.send_chat_message(Text::translate("block.minecraft.set_spawn", []));
+    }
+}
+
+/// Player inventory slots that are dropped on death. Slot 0 is the crafting
+/// result, which doesn't hold a real item.
+const DROPPED_SLOTS: std::ops::Range<u16> = 1..46;
+
+fn drop_on_death(
+    rules: Res<GameRules>,
+    mut clients: Query<(&Position, &EntityLayerId, &mut Inventory, &mut Xp, &mut DeathLocation), With<Client>>,
+    layers: Query<&ChunkLayer>,
+    mut deaths: EventReader<DeathEvent>,
+    mut commands: Commands,
+) {
+    for event in deaths.read() {
+        let Ok((position, &layer_id, mut inventory, mut xp, mut death_location)) = clients.get_mut(event.entity) else {
+            continue;
+        };
+
+        if let Ok(layer) = layers.get(layer_id.0) {
+            death_location.0 = Some((layer.dimension_type_name().to_string_ident(), block_pos(position.0)));
+        }
+
+        if rules.keep_inventory {
+            continue;
+        }
+
+        let mut rng = thread_rng();
+
+        for slot in DROPPED_SLOTS {
+            let stack = inventory.replace_slot(slot, ItemStack::EMPTY);
+            if stack.is_empty() || enchantment_level(&stack, "minecraft:vanishing_curse") > 0 {
+                continue;
+            }
+
+            // Scatter the items around the player, like vanilla does.
+            let speed = rng.gen_range(0.0..0.5) * 20.0;
+            let angle = rng.gen_range(0.0..TAU);
+            let velocity = Vec3::new(-angle.sin() * speed, 4.0, angle.cos() * speed);
+
+            spawn_item(&mut commands, layer_id, position.0 + DVec3::new(0.0, 1.32, 0.0), stack, velocity, PLAYER_DROP_PICKUP_DELAY);
+        }
+
+        let experience = (xp.level * 7).min(100);
+        if experience > 0 {
+            commands.spawn(ExperienceOrbEntityBundle {
+                layer: layer_id,
+                position: *position,
+                object_data: ObjectData(experience),
+                ..Default::default()
+            });
+        }
+        *xp = Xp { level: 0, bar: 0.0 };
+    }
+}
+
+/// Finds where to stand when respawning at a spawn point, using up an anchor
+/// charge if needed. Returns `None` if the bed or anchor is gone.
+fn use_spawn_point(layer: &mut ChunkLayer, spawn_point: SpawnPoint) -> Option<DVec3> {
+    let state = layer.block(spawn_point.position)?.state;
+    let standing = DVec3::new(
+        f64::from(spawn_point.position.x) + 0.5,
+        f64::from(spawn_point.position.y),
+        f64::from(spawn_point.position.z) + 0.5,
+    );
+
+    match spawn_point.kind {
+        SpawnPointKind::Bed if is_bed(state.to_kind()) => Some(standing + DVec3::new(0.0, 0.5625, 0.0)),
+        SpawnPointKind::RespawnAnchor if state.to_kind() == BlockKind::RespawnAnchor => {
+            let charges = anchor_charges(state);
+            if charges == 0 {
+                return None;
+            }
+            layer.set_block(spawn_point.position, with_anchor_charges(state, charges - 1));
+            Some(standing + DVec3::new(0.0, 1.0, 0.0))
+        }
+        _ => None,
+    }
+}
+
+fn respawn(
+    mut clients: Query<(
+        &mut Client,
+        &mut Position,
+        &mut EntityLayerId,
+        &mut VisibleChunkLayer,
+        &mut VisibleEntityLayers,
+        &mut Health,
+        &mut Food,
+        &mut Saturation,
+        &mut Air,
+        &mut Flags,
+        Option<&SpawnPoint>,
+        Option<&mut FallDistance>,
+        Option<&mut FireTicks>,
+        Option<&mut HungerState>,
+        Option<&mut ActiveStatusEffects>,
+    )>,
+    mut layers: Query<(Entity, &mut ChunkLayer)>,
+    mut events: EventReader<RequestRespawnEvent>,
+    mut commands: Commands,
+) {
+    for event in events.read() {
+        let Ok((
+            mut client,
+            mut position,
+            mut layer_id,
+            mut visible_chunk_layer,
+            mut visible_entity_layers,
+            mut health,
+            mut food,
+            mut saturation,
+            mut air,
+            mut flags,
+            spawn_point,
+            fall,
+            fire,
+            hunger,
+            effects,
+        )) = clients.get_mut(event.client) else {
+            continue;
+        };
+
+        if health.0 > 0.0 {
+            continue;
+        }
+
+        let mut destination = None;
+
+        if let Some(&spawn_point) = spawn_point {
+            destination = layers
+                .get_mut(spawn_point.layer)
+                .ok()
+                .and_then(|(layer, mut chunks)| Some((layer, use_spawn_point(&mut chunks, spawn_point)?)));
+
+            if destination.is_none() {
+                commands.entity(event.client).remove::<SpawnPoint>();
+                client.send_chat_message(Text::translate("block.minecraft.spawn.not_valid", []));
+            }
+        }
+
+        let Some((layer, pos)) = destination.or_else(|| {
+            layers
+                .iter()
+                .find(|(_, l)| l.dimension_type_name() == ident!("overworld"))
+                .map(|(layer, _)| (layer, SPAWN_POS))
+        }) else {
+            continue;
+        };
+
+        visible_entity_layers.0.remove(&layer_id.0);
+        layer_id.0 = layer;
+        visible_chunk_layer.0 = layer;
+        visible_entity_layers.0.insert(layer);
+        // The client only leaves the death screen when it is sent a respawn
+        // packet, which happens when its chunk layer changes.
+        visible_chunk_layer.set_changed();
+        position.set(pos);
+
+        health.0 = 20.0;
+        food.0 = 20;
+        saturation.0 = 5.0;
+        air.0 = MAX_AIR;
+        flags.set_on_fire(false);
+
+        if let Some(mut fall) = fall {
+            fall.0 = 0.0;
+        }
+        if let Some(mut fire) = fire {
+            fire.0 = 0;
+        }
+        if let Some(mut hunger) = hunger {
+            *hunger = HungerState::default();
+        }
+        if let Some(mut effects) = effects {
+            effects.remove_all();
+        }
+    }
+}
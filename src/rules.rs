@@ -0,0 +1,49 @@
+use valence::prelude::*;
+
+/// World settings that change how the vanilla plugins behave.
+///
+/// Plugins that read this call `init_resource`, so insert it before adding
+/// them to use anything other than the defaults.
+#[derive(Resource, Debug, Clone)]
+pub struct GameRules {
+    pub difficulty: Difficulty,
+    /// Whether players heal over time when they have enough food.
+    pub natural_regeneration: bool,
+    /// Whether players keep their items and experience when they die.
+    pub keep_inventory: bool,
+    /// Whether death messages are broadcast in chat. The player who died
+    /// always sees theirs on the death screen.
+    pub show_death_messages: bool,
+    /// How many blocks in each 16x16x16 section of loaded chunks are picked
+    /// for a random tick every game tick. 0 turns random ticks off.
+    pub random_tick_speed: u32,
+    /// How many block updates each update queue handles in a game tick.
+    /// Updates past this wait for the next tick, so huge contraptions slow
+    /// down instead of freezing the server.
+    pub max_block_updates: u32,
+    /// How many entities can share a space before they start hurting each
+    /// other. 0 turns cramming damage off.
+    pub max_entity_cramming: u32,
+}
+
+impl Default for GameRules {
+    fn default() -> Self {
+        Self {
+            difficulty: Difficulty::Normal,
+            natural_regeneration: true,
+            keep_inventory: false,
+            show_death_messages: true,
+            random_tick_speed: 3,
+            max_block_updates: 65536,
+            max_entity_cramming: 24,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Difficulty {
+    Peaceful,
+    Easy,
+    Normal,
+    Hard,
+}
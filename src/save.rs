@@ -1,5 +1,5 @@
 
-use valence::{abilities::PlayerAbilitiesFlags, entity::player::PlayerEntityBundle, inventory::HeldItem, prelude::*};
+use valence::{abilities::PlayerAbilitiesFlags, entity::player::{Food, PlayerEntityBundle, Saturation}, inventory::HeldItem, prelude::*};
 use crate::{anvil::{AnvilLevel, AnvilPlugin, ChunkLoadEvent, ChunkLoadStatus}, players::{PlayerData, Xp}};
 
 pub struct Save;
@@ -82,6 +82,8 @@ fn save_players(
             &Xp,
             &GameMode,
             &PlayerAbilitiesFlags,
+            &Food,
+            &Saturation,
         )
     >,
     mut layers: Query<(&mut AnvilLevel, &ChunkLayer)>,
@@ -98,6 +100,8 @@ fn save_players(
                 xp,
                 game_mode,
                 flags,
+                food,
+                saturation,
             )
         ) = players.get(disconnected_client) else {
             continue;
@@ -120,6 +124,8 @@ fn save_players(
                 look: *look,
                 position: *position,
                 uuid: *uuid,
+                player_food: *food,
+                player_saturation: *saturation,
                 ..Default::default()
             },
         };
@@ -0,0 +1,249 @@
+use std::collections::{BTreeMap, HashSet};
+
+use valence::layer::chunk::IntoBlock;
+use valence::prelude::*;
+
+use crate::block_behavior::{with_neighbors, BlockBehaviors};
+use crate::block_update::BlockUpdateEvent;
+
+/// Runs blocks' scheduled ticks when they are due. Fluids spreading, repeaters
+/// switching and sand starting to fall all wait for these rather than keeping
+/// timers of their own.
+pub struct TickScheduler;
+
+impl Plugin for TickScheduler {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BlockBehaviors>()
+            .add_systems(PreUpdate, add_scheduled_ticks)
+            .add_systems(Update, run_scheduled_ticks);
+    }
+}
+
+/// Which ticks go first when several are due on the same game tick. Ticks
+/// with the same priority run in the order they were scheduled.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum TickPriority {
+    ExtremelyHigh,
+    VeryHigh,
+    High,
+    #[default]
+    Normal,
+    Low,
+    VeryLow,
+    ExtremelyLow,
+}
+
+/// The ticks waiting to happen in a chunk layer. Each layer gets one of these
+/// as soon as it's spawned.
+///
+/// A block can only have one tick scheduled for each kind at a time. Scheduling
+/// another before the first has run does nothing, so blocks that get lots of
+/// updates don't pile up ticks.
+#[derive(Component, Debug, Default)]
+pub struct ScheduledTicks {
+    queue: BTreeMap<(i64, TickPriority, u64), (BlockPos, BlockKind)>,
+    scheduled: HashSet<(BlockPos, BlockKind)>,
+    next_order: u64,
+}
+
+impl ScheduledTicks {
+    /// Schedules a tick for the block at `position` on game tick `tick`.
+    /// Returns `false` if one was already waiting.
+    pub fn schedule(&mut self, tick: i64, position: BlockPos, kind: BlockKind, priority: TickPriority) -> bool {
+        if !self.scheduled.insert((position, kind)) {
+            return false;
+        }
+        self.queue.insert((tick, priority, self.next_order), (position, kind));
+        self.next_order += 1;
+        true
+    }
+
+    pub fn is_scheduled(&self, position: BlockPos, kind: BlockKind) -> bool {
+        self.scheduled.contains(&(position, kind))
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// Takes every tick that is due by `now`, in the order they should run.
+    fn take_due(&mut self, now: i64) -> Vec<(BlockPos, BlockKind)> {
+        let later = self.queue.split_off(&(now + 1, TickPriority::ExtremelyHigh, 0));
+        let due = std::mem::replace(&mut self.queue, later);
+        due.into_values()
+            .inspect(|key| {
+                self.scheduled.remove(key);
+            })
+            .collect()
+    }
+}
+
+/// A scheduled tick that is due, and what a block's behaviour can use to act
+/// on it.
+///
+/// Ticks only run if the block is still the kind they were scheduled for, but
+/// its other properties may have changed since.
+pub struct ScheduledTick<'a, 'w, 's> {
+    pub layer: &'a mut ChunkLayer,
+    pub layer_id: EntityLayerId,
+    pub position: BlockPos,
+    pub state: BlockState,
+    /// The game tick it is now.
+    pub now: i64,
+    pub ticks: &'a mut ScheduledTicks,
+    pub commands: &'a mut Commands<'w, 's>,
+    /// Positions that get a block update once the tick has run.
+    pub updates: &'a mut Vec<BlockPos>,
+}
+
+impl ScheduledTick<'_, '_, '_> {
+    /// Sets a block and lets the blocks around it know it changed.
+    pub fn set_block(&mut self, position: BlockPos, block: impl IntoBlock) {
+        self.layer.set_block(position, block);
+        self.updates.extend(with_neighbors(position));
+    }
+
+    /// Schedules another tick `delay` game ticks from now.
+    pub fn schedule(&mut self, position: BlockPos, kind: BlockKind, delay: i64, priority: TickPriority) -> bool {
+        self.ticks.schedule(self.now + delay, position, kind, priority)
+    }
+}
+
+/// Whether a tick scheduled for `kind` still belongs to the block. Water ticks
+/// also belong to waterlogged blocks, which spread water without being it.
+fn ticks_for(state: BlockState, kind: BlockKind) -> bool {
+    state.to_kind() == kind || (kind == BlockKind::Water && state.get(PropName::Waterlogged) == Some(PropValue::True))
+}
+
+fn add_scheduled_ticks(layers: Query<Entity, (With<ChunkLayer>, Without<ScheduledTicks>)>, mut commands: Commands) {
+    for layer in &layers {
+        commands.entity(layer).insert(ScheduledTicks::default());
+    }
+}
+
+fn run_scheduled_ticks(
+    server: Res<Server>,
+    behaviors: Res<BlockBehaviors>,
+    mut layers: Query<(Entity, &mut ChunkLayer, &mut ScheduledTicks)>,
+    mut commands: Commands,
+    mut block_updates: EventWriter<BlockUpdateEvent>,
+) {
+    let now = server.current_tick();
+
+    for (layer_entity, mut layer, mut ticks) in &mut layers {
+        let due = ticks.take_due(now);
+        if due.is_empty() {
+            continue;
+        }
+
+        let layer_id = EntityLayerId(layer_entity);
+        let mut updates = Vec::new();
+
+        for (position, kind) in due {
+            let Some(behavior) = behaviors.get(kind) else {
+                continue;
+            };
+            // Ticks in chunks that have since been unloaded are dropped, as
+            // are ticks for blocks that have been replaced.
+            let Some(state) = layer.block(position).map(|b| b.state) else {
+                continue;
+            };
+            if !ticks_for(state, kind) {
+                continue;
+            }
+
+            behavior.on_scheduled_tick(&mut ScheduledTick {
+                layer: &mut layer,
+                layer_id,
+                position,
+                state,
+                now,
+                ticks: &mut ticks,
+                commands: &mut commands,
+                updates: &mut updates,
+            });
+        }
+
+        for position in updates {
+            block_updates.send(BlockUpdateEvent {
+                position,
+                layer: layer_entity,
+                entity_layer: layer_id,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pos(x: i32) -> BlockPos {
+        BlockPos::new(x, 0, 0)
+    }
+
+    #[test]
+    fn due_ticks_run_by_tick_then_priority_then_order() {
+        let mut ticks = ScheduledTicks::default();
+        ticks.schedule(5, pos(0), BlockKind::Stone, TickPriority::Normal);
+        ticks.schedule(3, pos(1), BlockKind::Stone, TickPriority::Low);
+        ticks.schedule(5, pos(2), BlockKind::Stone, TickPriority::High);
+        ticks.schedule(3, pos(3), BlockKind::Stone, TickPriority::Low);
+        ticks.schedule(3, pos(4), BlockKind::Stone, TickPriority::ExtremelyHigh);
+        ticks.schedule(5, pos(5), BlockKind::Stone, TickPriority::Normal);
+
+        let order = ticks.take_due(5).into_iter().map(|(position, _)| position.x).collect::<Vec<_>>();
+        assert_eq!(order, [4, 1, 3, 2, 0, 5]);
+        assert!(ticks.is_empty());
+    }
+
+    #[test]
+    fn scheduling_twice_does_nothing() {
+        let mut ticks = ScheduledTicks::default();
+        assert!(ticks.schedule(10, pos(0), BlockKind::Sand, TickPriority::Normal));
+        assert!(!ticks.schedule(2, pos(0), BlockKind::Sand, TickPriority::High));
+        assert!(ticks.schedule(10, pos(0), BlockKind::Water, TickPriority::Normal));
+        assert!(ticks.schedule(10, pos(1), BlockKind::Sand, TickPriority::Normal));
+        assert_eq!(ticks.len(), 3);
+
+        // The first tick is kept, so nothing is due early.
+        assert!(ticks.take_due(2).is_empty());
+        assert_eq!(ticks.take_due(10).len(), 3);
+
+        // Once a tick has run, another can be scheduled.
+        assert!(!ticks.is_scheduled(pos(0), BlockKind::Sand));
+        assert!(ticks.schedule(12, pos(0), BlockKind::Sand, TickPriority::Normal));
+        assert!(ticks.is_scheduled(pos(0), BlockKind::Sand));
+    }
+
+    #[test]
+    fn take_due_includes_now_and_nothing_later() {
+        let mut ticks = ScheduledTicks::default();
+        ticks.schedule(-1, pos(0), BlockKind::Stone, TickPriority::ExtremelyLow);
+        ticks.schedule(4, pos(1), BlockKind::Stone, TickPriority::ExtremelyLow);
+        ticks.schedule(5, pos(2), BlockKind::Stone, TickPriority::ExtremelyHigh);
+        ticks.schedule(6, pos(3), BlockKind::Stone, TickPriority::ExtremelyHigh);
+
+        assert!(ticks.take_due(-2).is_empty());
+        assert_eq!(ticks.take_due(4), [(pos(0), BlockKind::Stone), (pos(1), BlockKind::Stone)]);
+        assert_eq!(ticks.take_due(5), [(pos(2), BlockKind::Stone)]);
+        assert!(ticks.is_scheduled(pos(3), BlockKind::Stone));
+        assert_eq!(ticks.len(), 1);
+        assert_eq!(ticks.take_due(100), [(pos(3), BlockKind::Stone)]);
+        assert!(ticks.take_due(100).is_empty());
+    }
+
+    #[test]
+    fn ticks_only_run_for_the_kind_they_were_scheduled_for() {
+        assert!(ticks_for(BlockState::SAND, BlockKind::Sand));
+        assert!(!ticks_for(BlockState::AIR, BlockKind::Sand));
+        assert!(ticks_for(BlockState::WATER, BlockKind::Water));
+        let waterlogged = BlockState::OAK_STAIRS.set(PropName::Waterlogged, PropValue::True);
+        assert!(ticks_for(waterlogged, BlockKind::Water));
+        assert!(!ticks_for(BlockState::OAK_STAIRS, BlockKind::Water));
+    }
+}
@@ -0,0 +1,594 @@
+use std::collections::HashSet;
+
+use valence::inventory::{ClickSlotEvent, ClientInventoryState, OpenInventory};
+use valence::nbt::{Compound, Value};
+use valence::prelude::*;
+use valence::protocol::packets::play::ScreenHandlerPropertyUpdateS2c;
+use valence::protocol::WritePacket;
+use valence::rand::{thread_rng, Rng};
+
+use crate::anvil::{ChunkLoadEvent, ChunkLoadStatus};
+use crate::container::{read_items, write_items, Container};
+use crate::crafting::Recipes;
+use crate::players::Xp;
+
+/// Furnaces, blast furnaces and smokers burning fuel to smelt items, whether
+/// or not anyone has them open.
+pub struct Smelting;
+
+impl Plugin for Smelting {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ActiveFurnaces>().add_systems(
+            Update,
+            (
+                (activate_loaded_furnaces, activate_changed_furnaces, tick_furnaces).chain(),
+                award_experience,
+            ),
+        );
+    }
+}
+
+/// Furnaces that are burning or have something to do, by chunk layer and
+/// position. Idle furnaces aren't ticked until their inventory changes.
+#[derive(Resource, Debug, Default)]
+pub struct ActiveFurnaces(pub HashSet<(Entity, BlockPos)>);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FurnaceKind {
+    Furnace,
+    BlastFurnace,
+    Smoker,
+}
+
+impl FurnaceKind {
+    pub fn from_block(kind: BlockKind) -> Option<Self> {
+        match kind {
+            BlockKind::Furnace => Some(Self::Furnace),
+            BlockKind::BlastFurnace => Some(Self::BlastFurnace),
+            BlockKind::Smoker => Some(Self::Smoker),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SmeltingRecipe {
+    pub output: ItemKind,
+    /// Experience awarded per item smelted.
+    pub experience: f32,
+    /// Ticks it takes to smelt one item.
+    pub cook_time: i32,
+}
+
+const INPUT_SLOT: u16 = 0;
+const FUEL_SLOT: u16 = 1;
+const OUTPUT_SLOT: u16 = 2;
+
+fn recipe(input: ItemKind) -> Option<(ItemKind, f32)> {
+    let name = input.to_str();
+
+    Some(match input {
+        ItemKind::IronOre | ItemKind::DeepslateIronOre | ItemKind::RawIron => (ItemKind::IronIngot, 0.7),
+        ItemKind::GoldOre | ItemKind::DeepslateGoldOre | ItemKind::NetherGoldOre | ItemKind::RawGold => {
+            (ItemKind::GoldIngot, 1.0)
+        }
+        ItemKind::CopperOre | ItemKind::DeepslateCopperOre | ItemKind::RawCopper => (ItemKind::CopperIngot, 0.7),
+        ItemKind::CoalOre | ItemKind::DeepslateCoalOre => (ItemKind::Coal, 0.1),
+        ItemKind::DiamondOre | ItemKind::DeepslateDiamondOre => (ItemKind::Diamond, 1.0),
+        ItemKind::EmeraldOre | ItemKind::DeepslateEmeraldOre => (ItemKind::Emerald, 1.0),
+        ItemKind::LapisOre | ItemKind::DeepslateLapisOre => (ItemKind::LapisLazuli, 0.2),
+        ItemKind::RedstoneOre | ItemKind::DeepslateRedstoneOre => (ItemKind::Redstone, 0.7),
+        ItemKind::NetherQuartzOre => (ItemKind::Quartz, 0.2),
+        ItemKind::AncientDebris => (ItemKind::NetheriteScrap, 2.0),
+
+        ItemKind::Beef => (ItemKind::CookedBeef, 0.35),
+        ItemKind::Porkchop => (ItemKind::CookedPorkchop, 0.35),
+        ItemKind::Chicken => (ItemKind::CookedChicken, 0.35),
+        ItemKind::Mutton => (ItemKind::CookedMutton, 0.35),
+        ItemKind::Rabbit => (ItemKind::CookedRabbit, 0.35),
+        ItemKind::Cod => (ItemKind::CookedCod, 0.35),
+        ItemKind::Salmon => (ItemKind::CookedSalmon, 0.35),
+        ItemKind::Potato => (ItemKind::BakedPotato, 0.35),
+        ItemKind::Kelp => (ItemKind::DriedKelp, 0.1),
+
+        ItemKind::Sand | ItemKind::RedSand => (ItemKind::Glass, 0.1),
+        ItemKind::Cobblestone => (ItemKind::Stone, 0.1),
+        ItemKind::Stone => (ItemKind::SmoothStone, 0.1),
+        ItemKind::CobbledDeepslate => (ItemKind::Deepslate, 0.1),
+        ItemKind::StoneBricks => (ItemKind::CrackedStoneBricks, 0.1),
+        ItemKind::Sandstone => (ItemKind::SmoothSandstone, 0.1),
+        ItemKind::RedSandstone => (ItemKind::SmoothRedSandstone, 0.1),
+        ItemKind::QuartzBlock => (ItemKind::SmoothQuartz, 0.1),
+        ItemKind::Basalt => (ItemKind::SmoothBasalt, 0.1),
+        ItemKind::ClayBall => (ItemKind::Brick, 0.3),
+        ItemKind::Clay => (ItemKind::Terracotta, 0.35),
+        ItemKind::Netherrack => (ItemKind::NetherBrick, 0.1),
+        ItemKind::Cactus => (ItemKind::GreenDye, 1.0),
+        ItemKind::SeaPickle => (ItemKind::LimeDye, 0.1),
+        ItemKind::WetSponge => (ItemKind::Sponge, 0.15),
+        ItemKind::ChorusFruit => (ItemKind::PoppedChorusFruit, 0.1),
+        _ if (name.ends_with("_log") || name.ends_with("_wood"))
+            && !name.contains("crimson")
+            && !name.contains("warped") =>
+        {
+            (ItemKind::Charcoal, 0.15)
+        }
+
+        _ => return None,
+    })
+}
+
+/// Ores and raw metals, which blast furnaces smelt too.
+fn is_ore(input: ItemKind) -> bool {
+    let name = input.to_str();
+    name.ends_with("_ore") || name.starts_with("raw_") || input == ItemKind::AncientDebris
+}
+
+/// Food, which smokers cook too.
+fn is_food(input: ItemKind) -> bool {
+    input == ItemKind::Beef
+        || input == ItemKind::Porkchop
+        || input == ItemKind::Chicken
+        || input == ItemKind::Mutton
+        || input == ItemKind::Rabbit
+        || input == ItemKind::Cod
+        || input == ItemKind::Salmon
+        || input == ItemKind::Potato
+        || input == ItemKind::Kelp
+}
+
+/// Returns what `furnace` turns `input` into. Blast furnaces only smelt ores
+/// and smokers only cook food, but both take half the time of a furnace.
+pub fn smelting_recipe(furnace: FurnaceKind, input: ItemKind) -> Option<SmeltingRecipe> {
+    let (output, experience) = recipe(input)?;
+
+    let cook_time = match furnace {
+        FurnaceKind::Furnace => 200,
+        FurnaceKind::BlastFurnace if is_ore(input) => 100,
+        FurnaceKind::Smoker if is_food(input) => 100,
+        _ => return None,
+    };
+
+    Some(SmeltingRecipe {
+        output,
+        experience,
+        cook_time,
+    })
+}
+
+/// The id of a built-in recipe, named like vanilla names its furnace
+/// recipes, e.g. `minecraft:iron_ingot_from_smelting_raw_iron`.
+pub fn smelting_recipe_id(furnace: FurnaceKind, input: ItemKind) -> Option<String> {
+    let recipe = smelting_recipe(furnace, input)?;
+    let method = match furnace {
+        FurnaceKind::Furnace => "smelting",
+        FurnaceKind::BlastFurnace => "blasting",
+        FurnaceKind::Smoker => "smoking",
+    };
+    Some(format!("minecraft:{}_from_{method}_{}", recipe.output.to_str(), input.to_str()))
+}
+
+/// Looks a built-in recipe up by the id [`smelting_recipe_id`] gave it.
+pub fn smelting_recipe_by_id(id: &str) -> Option<SmeltingRecipe> {
+    let (output, rest) = id.strip_prefix("minecraft:")?.split_once("_from_")?;
+    let (furnace, input) = if let Some(input) = rest.strip_prefix("smelting_") {
+        (FurnaceKind::Furnace, input)
+    } else if let Some(input) = rest.strip_prefix("blasting_") {
+        (FurnaceKind::BlastFurnace, input)
+    } else if let Some(input) = rest.strip_prefix("smoking_") {
+        (FurnaceKind::Smoker, input)
+    } else {
+        return None;
+    };
+    smelting_recipe(furnace, ItemKind::from_str(input)?).filter(|recipe| recipe.output.to_str() == output)
+}
+
+fn is_wooden(name: &str) -> bool {
+    name.starts_with("oak_")
+        || name.starts_with("spruce_")
+        || name.starts_with("birch_")
+        || name.starts_with("jungle_")
+        || name.starts_with("acacia_")
+        || name.starts_with("dark_oak_")
+        || name.starts_with("mangrove_")
+        || name.starts_with("cherry_")
+        || name.starts_with("bamboo_")
+        || name.starts_with("stripped_")
+}
+
+/// Returns how many ticks an item burns for in a furnace. Items that aren't
+/// fuel return 0.
+pub fn burn_time(item: ItemKind) -> i32 {
+    let name = item.to_str();
+
+    match item {
+        ItemKind::LavaBucket => 20000,
+        ItemKind::CoalBlock => 16000,
+        ItemKind::DriedKelpBlock => 4001,
+        ItemKind::BlazeRod => 2400,
+        ItemKind::Coal | ItemKind::Charcoal => 1600,
+        ItemKind::WoodenSword
+        | ItemKind::WoodenPickaxe
+        | ItemKind::WoodenAxe
+        | ItemKind::WoodenShovel
+        | ItemKind::WoodenHoe => 200,
+        ItemKind::Bow
+        | ItemKind::Crossbow
+        | ItemKind::FishingRod
+        | ItemKind::Ladder
+        | ItemKind::CraftingTable
+        | ItemKind::CartographyTable
+        | ItemKind::FletchingTable
+        | ItemKind::SmithingTable
+        | ItemKind::Loom
+        | ItemKind::Bookshelf
+        | ItemKind::ChiseledBookshelf
+        | ItemKind::Lectern
+        | ItemKind::Composter
+        | ItemKind::Chest
+        | ItemKind::TrappedChest
+        | ItemKind::Barrel
+        | ItemKind::Jukebox
+        | ItemKind::NoteBlock
+        | ItemKind::DaylightDetector
+        | ItemKind::MangroveRoots => 300,
+        ItemKind::Stick | ItemKind::Bowl | ItemKind::DeadBush | ItemKind::Azalea => 100,
+        ItemKind::Bamboo | ItemKind::Scaffolding => 50,
+        _ if name.ends_with("_boat") || name.ends_with("_raft") => 1200,
+        _ if name.ends_with("_banner") => 300,
+        _ if name.ends_with("_wool") || name.ends_with("_sapling") => 100,
+        _ if name.ends_with("_carpet") => 67,
+        _ if name.ends_with("_log")
+            || name.ends_with("_wood")
+            || name.ends_with("_planks") =>
+        {
+            if name.contains("crimson") || name.contains("warped") {
+                0
+            } else {
+                300
+            }
+        }
+        _ if !is_wooden(name) => 0,
+        _ if name.ends_with("_slab") => 150,
+        _ if name.ends_with("_stairs")
+            || name.ends_with("_fence")
+            || name.ends_with("_fence_gate")
+            || name.ends_with("_trapdoor")
+            || name.ends_with("_pressure_plate") =>
+        {
+            300
+        }
+        _ if name.ends_with("_door") || name.ends_with("_sign") => 200,
+        _ if name.ends_with("_button") => 100,
+        _ => 0,
+    }
+}
+
+/// Burn and cook progress of a furnace, kept in its block entity data.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+struct FurnaceProgress {
+    /// Ticks of fuel left.
+    burn_time: i32,
+    /// Ticks the current fuel lasts for in total.
+    burn_duration: i32,
+    cook_time: i32,
+    cook_total: i32,
+}
+
+fn get_int(nbt: &Compound, key: &str) -> i32 {
+    match nbt.get(key) {
+        Some(&Value::Short(n)) => i32::from(n),
+        Some(&Value::Int(n)) => n,
+        _ => 0,
+    }
+}
+
+/// Adds one use of the recipe `id` to the recipes the furnace has used,
+/// which is where the experience players get from it is kept.
+fn record_recipe(nbt: &mut Compound, id: String) {
+    let mut used = match nbt.remove("RecipesUsed") {
+        Some(Value::Compound(used)) => used,
+        _ => Compound::new(),
+    };
+    let count = get_int(&used, &id) + 1;
+    used.insert(id, Value::Int(count));
+    nbt.insert("RecipesUsed", Value::Compound(used));
+}
+
+/// Looks a recipe up in the loaded [`Recipes`], or the built-in ones when
+/// the [`Crafting`](crate::crafting::Crafting) plugin isn't used.
+fn find_recipe(recipes: Option<&Recipes>, furnace: FurnaceKind, input: ItemKind) -> Option<SmeltingRecipe> {
+    match recipes {
+        Some(recipes) => recipes.cooking(furnace, input),
+        None => smelting_recipe(furnace, input),
+    }
+}
+
+/// The id of the recipe [`find_recipe`] returns.
+fn find_recipe_id(recipes: Option<&Recipes>, furnace: FurnaceKind, input: ItemKind) -> Option<String> {
+    match recipes {
+        Some(recipes) => recipes.cooking_id(furnace, input),
+        None => smelting_recipe_id(furnace, input),
+    }
+}
+
+/// Looks a recipe up by the id [`find_recipe_id`] gave it.
+fn find_recipe_by_id(recipes: Option<&Recipes>, id: &str) -> Option<SmeltingRecipe> {
+    match recipes {
+        Some(recipes) => recipes.cooking_by_id(id),
+        None => smelting_recipe_by_id(id),
+    }
+}
+
+/// Runs one tick of a furnace. Returns whether it still has anything to do.
+fn tick(
+    recipes: Option<&Recipes>,
+    furnace: FurnaceKind,
+    items: &mut Inventory,
+    progress: &mut FurnaceProgress,
+    nbt: &mut Compound,
+) -> bool {
+    let input = items.slot(INPUT_SLOT).clone();
+    let fuel = items.slot(FUEL_SLOT).clone();
+    let output = items.slot(OUTPUT_SLOT).clone();
+
+    if progress.burn_time > 0 {
+        progress.burn_time -= 1;
+    }
+
+    let recipe = (!input.is_empty())
+        .then(|| find_recipe(recipes, furnace, input.item))
+        .flatten()
+        .filter(|recipe| {
+            output.is_empty()
+                || (output.item == recipe.output && output.nbt.is_none() && output.count < output.item.max_stack())
+        });
+
+    if progress.burn_time > 0 || (!fuel.is_empty() && !input.is_empty()) {
+        if progress.burn_time == 0 && recipe.is_some() {
+            // Blast furnaces and smokers burn through fuel twice as fast.
+            let duration = match furnace {
+                FurnaceKind::Furnace => burn_time(fuel.item),
+                FurnaceKind::BlastFurnace | FurnaceKind::Smoker => burn_time(fuel.item) / 2,
+            };
+            progress.burn_time = duration;
+            progress.burn_duration = duration;
+
+            if duration > 0 {
+                if fuel.item == ItemKind::LavaBucket {
+                    items.set_slot(FUEL_SLOT, ItemStack::new(ItemKind::Bucket, 1, None));
+                } else {
+                    items.set_slot_amount(FUEL_SLOT, fuel.count - 1);
+                }
+            }
+        }
+
+        match recipe {
+            Some(recipe) if progress.burn_time > 0 => {
+                progress.cook_total = recipe.cook_time;
+                progress.cook_time += 1;
+                if progress.cook_time >= progress.cook_total {
+                    progress.cook_time = 0;
+                    if output.is_empty() {
+                        items.set_slot(OUTPUT_SLOT, ItemStack::new(recipe.output, 1, None));
+                    } else {
+                        items.set_slot_amount(OUTPUT_SLOT, output.count + 1);
+                    }
+                    items.set_slot_amount(INPUT_SLOT, input.count - 1);
+                    if let Some(id) = find_recipe_id(recipes, furnace, input.item) {
+                        record_recipe(nbt, id);
+                    }
+                }
+            }
+            _ => progress.cook_time = 0,
+        }
+    } else if progress.cook_time > 0 {
+        // Without fuel, the progress slowly goes back down.
+        progress.cook_time = (progress.cook_time - 2).max(0);
+    }
+
+    let fuel = items.slot(FUEL_SLOT);
+    progress.burn_time > 0
+        || progress.cook_time > 0
+        || (recipe.is_some() && !fuel.is_empty() && burn_time(fuel.item) > 0)
+}
+
+/// Picks up furnaces that were still burning when their chunk was saved.
+fn activate_loaded_furnaces(
+    mut active: ResMut<ActiveFurnaces>,
+    layers: Query<&ChunkLayer>,
+    mut events: EventReader<ChunkLoadEvent>,
+) {
+    for event in events.read() {
+        let ChunkLoadStatus::Success { block_entities, .. } = &event.status else {
+            continue;
+        };
+        let Ok(layer) = layers.get(event.chunk_layer) else {
+            continue;
+        };
+
+        for &position in block_entities {
+            if layer
+                .block(position)
+                .is_some_and(|b| FurnaceKind::from_block(b.state.to_kind()).is_some())
+            {
+                active.0.insert((event.chunk_layer, position));
+            }
+        }
+    }
+}
+
+/// Wakes furnaces up when players put something in them.
+fn activate_changed_furnaces(
+    mut active: ResMut<ActiveFurnaces>,
+    containers: Query<&Container, Changed<Inventory>>,
+    layers: Query<&ChunkLayer>,
+) {
+    for container in &containers {
+        let Ok(layer) = layers.get(container.layer.0) else {
+            continue;
+        };
+        let position = container.blocks[0];
+        if layer
+            .block(position)
+            .is_some_and(|b| FurnaceKind::from_block(b.state.to_kind()).is_some())
+        {
+            active.0.insert((container.layer.0, position));
+        }
+    }
+}
+
+fn tick_furnaces(
+    recipes: Option<Res<Recipes>>,
+    mut active: ResMut<ActiveFurnaces>,
+    mut containers: Query<(Entity, &mut Inventory, &Container)>,
+    mut viewers: Query<(&mut Client, &OpenInventory, &ClientInventoryState)>,
+    mut layers: Query<&mut ChunkLayer>,
+) {
+    active.0.retain(|&(layer_entity, position)| {
+        let Ok(mut layer) = layers.get_mut(layer_entity) else {
+            return false;
+        };
+        // Furnaces in unloaded chunks are picked up again when they load.
+        let Some((state, nbt)) = layer.block(position).map(|b| (b.state, b.nbt.cloned())) else {
+            return false;
+        };
+        let Some(furnace) = FurnaceKind::from_block(state.to_kind()) else {
+            return false;
+        };
+
+        let mut nbt = nbt.unwrap_or_default();
+        let mut progress = FurnaceProgress {
+            burn_time: get_int(&nbt, "BurnTime"),
+            burn_duration: get_int(&nbt, "BurnDuration"),
+            cook_time: get_int(&nbt, "CookTime"),
+            cook_total: get_int(&nbt, "CookTimeTotal"),
+        };
+
+        // While someone has the furnace open, its open inventory has the
+        // latest items.
+        let open = containers
+            .iter_mut()
+            .find(|(_, _, c)| c.layer.0 == layer_entity && c.blocks.contains(&position));
+
+        let busy = match open {
+            Some((entity, mut items, _)) => {
+                let busy = tick(recipes.as_deref(), furnace, &mut items, &mut progress, &mut nbt);
+                nbt.insert("Items", write_items(&items, 0..3));
+
+                for (mut client, open, inventory_state) in &mut viewers {
+                    if open.entity != entity {
+                        continue;
+                    }
+                    for (property, value) in [
+                        progress.burn_time,
+                        progress.burn_duration,
+                        progress.cook_time,
+                        progress.cook_total,
+                    ]
+                    .into_iter()
+                    .enumerate()
+                    {
+                        client.write_packet(&ScreenHandlerPropertyUpdateS2c {
+                            window_id: inventory_state.window_id(),
+                            property: property as i16,
+                            value: value as i16,
+                        });
+                    }
+                }
+                busy
+            }
+            None => {
+                let mut items = Inventory::new(InventoryKind::Furnace);
+                read_items(Some(&nbt), &mut items, 0);
+                let busy = tick(recipes.as_deref(), furnace, &mut items, &mut progress, &mut nbt);
+                nbt.insert("Items", write_items(&items, 0..3));
+                busy
+            }
+        };
+
+        nbt.insert("BurnTime", Value::Short(progress.burn_time as i16));
+        nbt.insert("BurnDuration", Value::Short(progress.burn_duration as i16));
+        nbt.insert("CookTime", Value::Short(progress.cook_time as i16));
+        nbt.insert("CookTimeTotal", Value::Short(progress.cook_total as i16));
+
+        let lit = if progress.burn_time > 0 {
+            PropValue::True
+        } else {
+            PropValue::False
+        };
+        if state.get(PropName::Lit) != Some(lit) {
+            layer.set_block(
+                position,
+                Block {
+                    state: state.set(PropName::Lit, lit),
+                    nbt: Some(nbt),
+                },
+            );
+        } else if let Some(existing) = layer.block_entity_mut(position) {
+            *existing = nbt;
+        }
+
+        busy
+    });
+}
+
+/// Gives players the experience stored in a furnace when they take items out
+/// of its output slot.
+fn award_experience(
+    recipes: Option<Res<Recipes>>,
+    mut clients: Query<(&OpenInventory, &mut Xp)>,
+    containers: Query<&Container>,
+    mut layers: Query<&mut ChunkLayer>,
+    mut clicks: EventReader<ClickSlotEvent>,
+) {
+    let mut rng = thread_rng();
+
+    for click in clicks.read() {
+        if click.slot_id != OUTPUT_SLOT as i16 {
+            continue;
+        }
+        let Ok((open, mut xp)) = clients.get_mut(click.client) else {
+            continue;
+        };
+        let Ok(container) = containers.get(open.entity) else {
+            continue;
+        };
+        let Ok(mut layer) = layers.get_mut(container.layer.0) else {
+            continue;
+        };
+        let position = container.blocks[0];
+        let Some(state) = layer.block(position).map(|b| b.state) else {
+            continue;
+        };
+        if FurnaceKind::from_block(state.to_kind()).is_none() {
+            continue;
+        }
+        let Some(nbt) = layer.block_entity_mut(position) else {
+            continue;
+        };
+        let Some(Value::Compound(used)) = nbt.remove("RecipesUsed") else {
+            continue;
+        };
+
+        let mut experience = 0.0;
+        for (id, count) in used.iter() {
+            let Some(recipe) = find_recipe_by_id(recipes.as_deref(), id) else {
+                continue;
+            };
+            let count = match count {
+                &Value::Int(n) => n,
+                &Value::Short(n) => i32::from(n),
+                _ => 0,
+            };
+            experience += recipe.experience * count as f32;
+        }
+
+        // Fractions of a point are rounded up by chance.
+        let mut points = experience.floor() as i32;
+        if rng.gen::<f32>() < experience.fract() {
+            points += 1;
+        }
+        xp.add_points(points);
+    }
+}